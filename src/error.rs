@@ -2,13 +2,19 @@ use std::fmt;
 
 use value_trait::ValueType;
 
+use crate::StructuralIndex;
+use crate::stringparse::unescape;
+use crate::to_offset;
+
 /// Error types encountered while parsing
 #[derive(Debug)]
 pub enum ErrorType {
     /// A specific type was expected but another one encountered.
     Unexpected(Option<ValueType>, Option<ValueType>),
-    /// Simd-json only supports inputs of up to
-    /// 4GB in size.
+    /// The input is larger than
+    /// [`ParserOptions::with_max_input_len`](crate::ParserOptions::with_max_input_len)
+    /// allows, simd-json only supports inputs of up to 4GB in size unless the
+    /// `wide-index` feature is enabled.
     InputTooLarge,
     /// The key of a map isn't a string
     BadKeyType,
@@ -88,8 +94,38 @@ pub enum ErrorType {
     Overflow,
     /// The structure depth exceeds the limit (`1024` by default).
     DepthLimitExceeded,
+    /// A string or object key is longer than
+    /// [`ParserOptions::with_max_string_len`](crate::ParserOptions::with_max_string_len)
+    /// allows
+    StringTooLong,
+    /// An array or object has more members than
+    /// [`ParserOptions::with_max_members`](crate::ParserOptions::with_max_members)
+    /// allows
+    TooManyMembers,
+    /// The document has more nodes than
+    /// [`ParserOptions::with_max_nodes`](crate::ParserOptions::with_max_nodes)
+    /// allows
+    TooManyNodes,
     /// No SIMD support detected during runtime
     SimdUnsupported,
+    /// A `NaN` or infinite float can't be serialized with the
+    /// [`NonFinitePolicy::Error`](crate::NonFinitePolicy::Error) policy
+    NonFiniteFloat,
+    /// An object has the same key more than once with the
+    /// [`DuplicateKeyPolicy::Error`](crate::DuplicateKeyPolicy::Error) policy
+    DuplicateKey,
+    /// A binary tape is truncated or corrupt, see
+    /// [`Tape::from_binary`](crate::Tape::from_binary)
+    InvalidBinaryTape,
+    /// A binary tape was written in a format version this version of
+    /// simd-json doesn't support
+    UnsupportedBinaryTape(u16),
+    /// A JSON pointer is malformed or doesn't lead to an array or object a
+    /// value can be inserted into
+    InvalidPointer,
+    /// A `JSONPath` expression is malformed or not well-typed, see
+    /// [`Path::compile`](crate::path::Path::compile)
+    InvalidPath(String),
     /// IO error
     Io(std::io::Error),
 }
@@ -147,16 +183,59 @@ impl PartialEq for ErrorType {
             | (Self::ExpectedObjectKey, Self::ExpectedObjectKey)
             | (Self::Overflow, Self::Overflow)
             | (Self::DepthLimitExceeded, Self::DepthLimitExceeded)
+            | (Self::StringTooLong, Self::StringTooLong)
+            | (Self::TooManyMembers, Self::TooManyMembers)
+            | (Self::TooManyNodes, Self::TooManyNodes)
             | (Self::InputTooLarge, Self::InputTooLarge)
-            | (Self::SimdUnsupported, Self::SimdUnsupported) => true,
-            (Self::Serde(s1), Self::Serde(s2)) => s1 == s2,
+            | (Self::SimdUnsupported, Self::SimdUnsupported)
+            | (Self::NonFiniteFloat, Self::NonFiniteFloat)
+            | (Self::DuplicateKey, Self::DuplicateKey)
+            | (Self::InvalidBinaryTape, Self::InvalidBinaryTape)
+            | (Self::InvalidPointer, Self::InvalidPointer) => true,
+            (Self::UnsupportedBinaryTape(v1), Self::UnsupportedBinaryTape(v2)) => v1 == v2,
+            (Self::Serde(s1), Self::Serde(s2)) | (Self::InvalidPath(s1), Self::InvalidPath(s2)) => {
+                s1 == s2
+            }
             (Self::InternalError(e1), Self::InternalError(e2)) => e1 == e2,
             _ => false,
         }
     }
 }
+/// A 1-based line and column, the column counts bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Position {
+    line: usize,
+    column: usize,
+}
+
+impl Position {
+    /// The start of an input
+    pub(crate) const START: Self = Self { line: 1, column: 1 };
+
+    /// The position behind `bytes` if they start at `self`
+    #[must_use]
+    #[allow(clippy::naive_bytecount)]
+    pub(crate) fn advance(mut self, bytes: &[u8]) -> Self {
+        if let Some(last) = bytes.iter().rposition(|b| *b == b'\n') {
+            self.line += bytes[..=last].iter().filter(|b| **b == b'\n').count();
+            self.column = bytes.len() - last;
+        } else {
+            self.column += bytes.len();
+        }
+        self
+    }
+}
+
+/// Where in the input an error was encountered
+#[derive(Debug)]
+struct Location {
+    position: Position,
+    /// JSON Pointer to the value
+    path: Option<String>,
+}
+
 /// Parser error
-#[derive(Debug, PartialEq)]
+#[derive(Debug)]
 pub struct Error {
     /// Byte index it was encountered at
     index: usize,
@@ -164,6 +243,18 @@ pub struct Error {
     character: Option<char>,
     /// Type of error
     err_type: ErrorType,
+    /// Line, column and path if the error came from parsing an input
+    location: Option<Box<Location>>,
+}
+
+/// Errors are equal if they are of the same type at the same index, no
+/// matter how much is known about their location.
+impl PartialEq for Error {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+            && self.character == other.character
+            && self.err_type == other.err_type
+    }
 }
 
 impl Error {
@@ -174,6 +265,7 @@ impl Error {
             index,
             character,
             err_type,
+            location: None,
         }
     }
     #[cold]
@@ -191,15 +283,92 @@ impl Error {
             index: 0,
             character: None,
             err_type: t,
+            location: None,
+        }
+    }
+
+    /// Shifts the byte index by `offset` and the line and column by
+    /// `start`, used when the parsed slice is only a part of a larger input
+    /// and starts at `offset` / `start` in it.
+    #[must_use]
+    pub(crate) fn offset_by(mut self, offset: usize, start: Position) -> Self {
+        self.index += offset;
+        if let Some(location) = &mut self.location {
+            let position = &mut location.position;
+            if position.line == 1 {
+                position.column += start.column - 1;
+            }
+            position.line += start.line - 1;
+        }
+        self
+    }
+
+    /// Sets the line and column of the error to `position`
+    #[must_use]
+    pub(crate) fn at(mut self, position: Position) -> Self {
+        if let Some(location) = &mut self.location {
+            location.position = position;
+        } else {
+            self.location = Some(Box::new(Location {
+                position,
+                path: None,
+            }));
+        }
+        self
+    }
+
+    /// Adds the line and column of the error's byte index in `input`
+    #[cold]
+    #[must_use]
+    pub(crate) fn located(self, input: &[u8]) -> Self {
+        let end = self.index.min(input.len());
+        self.at(Position::START.advance(&input[..end]))
+    }
+
+    /// Adds the line and column of the error's byte index in `input` and
+    /// the JSON Pointer to the value it was encountered in, found by
+    /// walking the structural indexes in front of it. `input` has to be
+    /// the padded copy of the input the indexes were found in.
+    #[cold]
+    #[must_use]
+    pub(crate) fn located_in(self, input: &[u8], structural_indexes: &[StructuralIndex]) -> Self {
+        let path = pointer_to(input, structural_indexes, self.index);
+        let mut e = self.located(input);
+        if let Some(location) = &mut e.location {
+            location.path = path;
         }
+        e
     }
 
-    /// Returns the byte index the error occurred at.
+    /// Returns the byte index the error occurred at, this is the offset
+    /// into the original input for errors found while parsing it.
     #[must_use]
     pub fn index(&self) -> usize {
         self.index
     }
 
+    /// Returns the 1-based line the error occurred at, if the error was
+    /// found while parsing an input.
+    #[must_use]
+    pub fn line(&self) -> Option<usize> {
+        self.location.as_ref().map(|l| l.position.line)
+    }
+
+    /// Returns the 1-based column, counted in bytes, the error occurred
+    /// at, if the error was found while parsing an input.
+    #[must_use]
+    pub fn column(&self) -> Option<usize> {
+        self.location.as_ref().map(|l| l.position.column)
+    }
+
+    /// Returns the JSON Pointer ([RFC 6901](https://www.rfc-editor.org/rfc/rfc6901))
+    /// to the value the error occurred in, e.g. `/items/3/price`, if it is
+    /// known. The root value has the empty pointer `""`.
+    #[must_use]
+    pub fn path(&self) -> Option<&str> {
+        self.location.as_ref().and_then(|l| l.path.as_deref())
+    }
+
     /// Returns the current character the error occurred at.
     #[must_use]
     pub fn character(&self) -> Option<char> {
@@ -275,13 +444,227 @@ impl Error {
 }
 impl std::error::Error for Error {}
 
+/// A container on the way to an error
+enum Level {
+    /// An array and the index of the current element
+    Array(usize),
+    /// An object and the key of the current value, if a key was read
+    Object(Option<String>),
+}
+
+/// The JSON Pointer to the value that contains byte `offset`, walking the
+/// structural indexes in front of it. `input` has to be padded.
+#[cold]
+fn pointer_to(
+    input: &[u8],
+    structural_indexes: &[StructuralIndex],
+    offset: usize,
+) -> Option<String> {
+    let mut levels = Vec::new();
+    let mut expect_key = false;
+    for &idx in structural_indexes {
+        let idx = to_offset(idx);
+        if idx >= offset {
+            break;
+        }
+        match *input.get(idx)? {
+            b'[' => levels.push(Level::Array(0)),
+            b'{' => {
+                levels.push(Level::Object(None));
+                expect_key = true;
+            }
+            b']' | b'}' => {
+                levels.pop();
+                expect_key = false;
+            }
+            b',' => match levels.last_mut() {
+                Some(Level::Array(i)) => *i += 1,
+                Some(Level::Object(key)) => {
+                    *key = None;
+                    expect_key = true;
+                }
+                None => (),
+            },
+            b'"' if expect_key => {
+                let raw = input.get(idx + 1..)?;
+                let mut len = 0;
+                while *raw.get(len)? != b'"' {
+                    len += if raw[len] == b'\\' { 2 } else { 1 };
+                }
+                if let Some(Level::Object(key)) = levels.last_mut() {
+                    *key = Some(unescape(raw, len)?);
+                }
+                expect_key = false;
+            }
+            _ => (),
+        }
+    }
+
+    let mut path = String::new();
+    for level in levels {
+        match level {
+            Level::Array(i) => {
+                path.push('/');
+                path.push_str(&i.to_string());
+            }
+            Level::Object(Some(key)) => {
+                path.push('/');
+                path.push_str(&key.replace('~', "~0").replace('/', "~1"));
+            }
+            // the error is in the key itself or in between values
+            Level::Object(None) => break,
+        }
+    }
+    Some(path)
+}
+
+impl fmt::Display for ErrorType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Unexpected(Some(expected), Some(found)) => {
+                write!(f, "expected {expected} but found {found}")
+            }
+            Self::Unexpected(Some(expected), None) => write!(f, "expected {expected}"),
+            Self::Unexpected(None, Some(found)) => write!(f, "unexpected {found}"),
+            Self::Unexpected(None, None) => f.write_str("unexpected value"),
+            Self::InputTooLarge => f.write_str("the input is too large"),
+            Self::BadKeyType | Self::KeyMustBeAString => f.write_str("object keys must be strings"),
+            Self::ExpectedArray => f.write_str("expected an array"),
+            Self::ExpectedArrayComma => f.write_str("expected `,` or `]` in array"),
+            Self::ExpectedBoolean => f.write_str("expected a boolean"),
+            Self::ExpectedEnum => f.write_str("expected an enum"),
+            Self::ExpectedFloat => f.write_str("expected a float"),
+            Self::ExpectedInteger => f.write_str("expected an integer"),
+            Self::ExpectedMap => f.write_str("expected an object"),
+            Self::ExpectedObjectColon => f.write_str("expected `:` after object key"),
+            Self::ExpectedMapComma => f.write_str("expected `,` or `}` in object"),
+            Self::ExpectedMapEnd => f.write_str("expected the end of the object"),
+            Self::ExpectedNull => f.write_str("expected `null`"),
+            Self::ExpectedTrue => f.write_str("expected `true`"),
+            Self::ExpectedFalse => f.write_str("expected `false`"),
+            Self::ExpectedNumber => f.write_str("expected a number"),
+            Self::ExpectedSigned => f.write_str("expected a signed integer"),
+            Self::ExpectedString => f.write_str("expected a string"),
+            Self::ExpectedUnsigned => f.write_str("expected an unsigned integer"),
+            Self::InvalidEscape => f.write_str("invalid escape sequence in string"),
+            Self::InvalidExponent => f.write_str("invalid exponent in number"),
+            Self::InvalidNumber => f.write_str("invalid number"),
+            Self::InvalidUtf8 => f.write_str("invalid UTF-8"),
+            Self::InvalidUnicodeEscape => f.write_str("invalid unicode escape in string"),
+            Self::InvalidUnicodeCodepoint => f.write_str("invalid unicode codepoint in string"),
+            Self::NoStructure => f.write_str("the input contains no JSON value"),
+            Self::Parser => f.write_str("invalid JSON"),
+            Self::Eof => f.write_str("unexpected end of input"),
+            Self::Serde(msg) => f.write_str(msg),
+            Self::Syntax => f.write_str("syntax error"),
+            Self::TrailingData => f.write_str("trailing data after the JSON value"),
+            // a tape error is an unexpected character in stage 2
+            Self::UnexpectedCharacter | Self::InternalError(InternalError::TapeError) => {
+                f.write_str("unexpected character")
+            }
+            Self::UnterminatedString => f.write_str("unterminated string"),
+            Self::ExpectedArrayContent => f.write_str("expected a value or `]` in array"),
+            Self::ExpectedObjectContent => f.write_str("expected a key or `}` in object"),
+            Self::ExpectedObjectKey => f.write_str("expected an object key"),
+            Self::Overflow => f.write_str("a limited buffer overflowed"),
+            Self::DepthLimitExceeded => f.write_str("the nesting depth exceeds the limit"),
+            Self::StringTooLong => f.write_str("a string exceeds the length limit"),
+            Self::TooManyMembers => f.write_str("an array or object exceeds the member limit"),
+            Self::TooManyNodes => f.write_str("the document exceeds the node limit"),
+            Self::SimdUnsupported => f.write_str("no supported SIMD instruction set was detected"),
+            Self::NonFiniteFloat => f.write_str("NaN and infinite floats can't be serialized"),
+            Self::DuplicateKey => f.write_str("duplicate object key"),
+            Self::InvalidBinaryTape => f.write_str("the binary tape is truncated or corrupt"),
+            Self::UnsupportedBinaryTape(version) => {
+                write!(f, "unsupported binary tape format version {version}")
+            }
+            Self::InvalidPointer => {
+                f.write_str("the JSON pointer is malformed or doesn't lead to an array or object")
+            }
+            Self::InvalidPath(msg) => write!(f, "invalid JSONPath: {msg}"),
+            Self::Io(e) => write!(f, "IO error: {e}"),
+        }
+    }
+}
+
+impl Error {
+    /// Renders the error with the line of `input` it occurred on and a
+    /// caret under its column, like a compiler diagnostic:
+    ///
+    /// ```text
+    /// error: expected `:` after object key
+    ///  --> line 3, column 10 (at /server/port)
+    ///   |
+    /// 3 |   "port" 8080
+    ///   |          ^
+    /// ```
+    ///
+    /// `input` has to be the original text, parsing rewrites the bytes it
+    /// is given so render from a copy. Errors that weren't found in the
+    /// input are rendered without a snippet.
+    #[cold]
+    #[must_use]
+    pub fn render<I: AsRef<[u8]> + ?Sized>(&self, input: &I) -> String {
+        use std::fmt::Write;
+
+        let input = input.as_ref();
+        let mut out = format!("error: {}", self.err_type);
+        if self.location.is_none() && self.character.is_none() {
+            return out;
+        }
+        let index = self.index.min(input.len());
+        let start = input[..index]
+            .iter()
+            .rposition(|b| *b == b'\n')
+            .map_or(0, |i| i + 1);
+        let end = input[index..]
+            .iter()
+            .position(|b| *b == b'\n')
+            .map_or(input.len(), |i| index + i);
+        let position = Position::START.advance(&input[..index]);
+
+        let _ = write!(
+            out,
+            "\n --> line {}, column {}",
+            position.line, position.column
+        );
+        if let Some(path) = self.path().filter(|p| !p.is_empty()) {
+            let _ = write!(out, " (at {path})");
+        }
+        let line = String::from_utf8_lossy(&input[start..end]);
+        let line = line.trim_end_matches('\r');
+        // keep tabs so the caret lines up with the text above it
+        let pad: String = String::from_utf8_lossy(&input[start..index])
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let gutter = " ".repeat(position.line.to_string().len());
+        let _ = write!(
+            out,
+            "\n{gutter} |\n{} | {line}\n{gutter} | {pad}^",
+            position.line
+        );
+        out
+    }
+}
+
 #[cfg(not(tarpaulin_include))]
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        if let Some(c) = self.character {
-            write!(f, "{:?} at character {} ('{c}')", self.err_type, self.index)
+        write!(f, "{}", self.err_type)?;
+        if let Some(location) = &self.location {
+            let Position { line, column } = location.position;
+            write!(f, " at line {line} column {column}")?;
+            if let Some(path) = location.path.as_deref().filter(|p| !p.is_empty()) {
+                write!(f, " (at {path})")?;
+            }
+            Ok(())
+        } else if let Some(c) = self.character {
+            write!(f, " at byte {} ('{c}')", self.index)
+        } else if self.index > 0 {
+            write!(f, " at byte {}", self.index)
         } else {
-            write!(f, "{:?} at character {}", self.err_type, self.index)
+            Ok(())
         }
     }
 }
@@ -153,10 +153,16 @@ pub(crate) unsafe fn parse_str<'invoke, 'de>(
                         src.get_kinda_unchecked(src_i..),
                         buffer.get_kinda_unchecked_mut(dst_i..),
                     )
-                    .map_err(|_| Deserializer::error_c(src_i, 'u', InvalidUnicodeCodepoint))?;
+                    .map_err(|_| {
+                        Deserializer::error_c(idx + src_i, 'u', InvalidUnicodeCodepoint)
+                    })?;
 
                     if o == 0 {
-                        return Err(Deserializer::error_c(src_i, 'u', InvalidUnicodeCodepoint));
+                        return Err(Deserializer::error_c(
+                            idx + src_i,
+                            'u',
+                            InvalidUnicodeCodepoint,
+                        ));
                     }
                     // We moved o steps forward at the destination and 6 on the source
                     src_i += s;
@@ -169,7 +175,7 @@ pub(crate) unsafe fn parse_str<'invoke, 'de>(
                     let escape_result: u8 = *ESCAPE_MAP.get_kinda_unchecked(escape_char as usize);
                     if escape_result == 0 {
                         return Err(Deserializer::error_c(
-                            src_i,
+                            idx + src_i + bs_dist as usize,
                             escape_char as char,
                             InvalidEscape,
                         ));
@@ -198,6 +198,7 @@ impl Stage1Parse for SimdInput {
     #[cfg_attr(not(feature = "no-inline"), inline)]
     #[allow(clippy::cast_possible_wrap, clippy::cast_ptr_alignment)]
     #[target_feature(enable = "avx2")]
+    #[cfg(not(feature = "wide-index"))]
     unsafe fn flatten_bits(base: &mut Vec<u32>, idx: u32, mut bits: u64) {
         unsafe {
             let cnt: usize = bits.count_ones() as usize;
@@ -0,0 +1,172 @@
+#![allow(dead_code)]
+use crate::{
+    Stage1Parse,
+    macros::{static_cast_i32, static_cast_i64},
+};
+#[cfg(target_arch = "x86")]
+use std::arch::x86 as arch;
+
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64 as arch;
+
+use arch::{
+    __m512i, _mm_clmulepi64_si128, _mm_set_epi64x, _mm_set1_epi8, _mm_setr_epi8, _mm512_add_epi32,
+    _mm512_and_si512, _mm512_broadcast_i32x4, _mm512_cmpeq_epi8_mask, _mm512_cmple_epu8_mask,
+    _mm512_loadu_si512, _mm512_maskz_compress_epi32, _mm512_set1_epi8, _mm512_set1_epi32,
+    _mm512_setr_epi32, _mm512_shuffle_epi8, _mm512_srli_epi32, _mm512_storeu_si512,
+    _mm512_test_epi8_mask,
+};
+
+// `_mm512_shuffle_epi8` looks up within each 128 bit lane, so the nibble
+// tables are the AVX2 ones repeated in all four lanes.
+macro_rules! low_nibble_mask {
+    () => {
+        _mm512_broadcast_i32x4(_mm_setr_epi8(
+            16, 0, 0, 0, 0, 0, 0, 0, 0, 8, 12, 1, 2, 9, 0, 0,
+        ))
+    };
+}
+
+macro_rules! high_nibble_mask {
+    () => {
+        _mm512_broadcast_i32x4(_mm_setr_epi8(
+            8, 0, 18, 4, 0, 1, 0, 1, 0, 0, 0, 3, 2, 1, 0, 0,
+        ))
+    };
+}
+
+/// A whole 64 byte block in one register, the comparisons write their result
+/// straight into a 64 bit mask.
+#[derive(Debug)]
+pub(crate) struct SimdInput {
+    v: __m512i,
+}
+
+impl Stage1Parse for SimdInput {
+    // there is no AVX-512 validator, every CPU with AVX-512 has AVX2
+    type Utf8Validator = simdutf8::basic::imp::x86::avx2::ChunkedUtf8ValidatorImp;
+    type SimdRepresentation = __m512i;
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    // _mm512_loadu_si512 does not need alignment
+    #[allow(clippy::cast_ptr_alignment)]
+    #[target_feature(enable = "avx512f", enable = "avx512bw")]
+    unsafe fn new(ptr: &[u8]) -> Self {
+        unsafe {
+            Self {
+                v: _mm512_loadu_si512(ptr.as_ptr().cast::<__m512i>()),
+            }
+        }
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    #[allow(clippy::cast_sign_loss)]
+    #[target_feature(enable = "avx512f", enable = "pclmulqdq")]
+    #[cfg(target_arch = "x86_64")]
+    unsafe fn compute_quote_mask(quote_bits: u64) -> u64 {
+        std::arch::x86_64::_mm_cvtsi128_si64(_mm_clmulepi64_si128(
+            _mm_set_epi64x(0, static_cast_i64!(quote_bits)),
+            _mm_set1_epi8(-1_i8 /* 0xFF */),
+            0,
+        )) as u64
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    #[allow(clippy::cast_sign_loss)]
+    #[target_feature(enable = "avx512f")]
+    #[cfg(target_arch = "x86")]
+    unsafe fn compute_quote_mask(quote_bits: u64) -> u64 {
+        let mut quote_mask: u64 = quote_bits ^ (quote_bits << 1);
+        quote_mask = quote_mask ^ (quote_mask << 2);
+        quote_mask = quote_mask ^ (quote_mask << 4);
+        quote_mask = quote_mask ^ (quote_mask << 8);
+        quote_mask = quote_mask ^ (quote_mask << 16);
+        quote_mask = quote_mask ^ (quote_mask << 32);
+        quote_mask
+    }
+
+    /// a straightforward comparison of a mask against input
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    #[allow(clippy::cast_possible_wrap)]
+    #[target_feature(enable = "avx512f", enable = "avx512bw")]
+    unsafe fn cmp_mask_against_input(&self, m: u8) -> u64 {
+        _mm512_cmpeq_epi8_mask(self.v, _mm512_set1_epi8(m as i8))
+    }
+
+    // find all values less than or equal than the content of maxval (using unsigned arithmetic)
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    #[target_feature(enable = "avx512f", enable = "avx512bw")]
+    unsafe fn unsigned_lteq_against_input(&self, maxval: __m512i) -> u64 {
+        _mm512_cmple_epu8_mask(self.v, maxval)
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    #[target_feature(enable = "avx512f", enable = "avx512bw")]
+    unsafe fn find_whitespace_and_structurals(&self, whitespace: &mut u64, structurals: &mut u64) {
+        // the same 'shufti' as the AVX2 implementation, see there for the
+        // buckets: structurals go into 1/2/4 and whitespace into 8/16
+        let low_nibble_mask: __m512i = low_nibble_mask!();
+        let high_nibble_mask: __m512i = high_nibble_mask!();
+
+        let structural_shufti_mask: __m512i = _mm512_set1_epi8(0x7);
+        let whitespace_shufti_mask: __m512i = _mm512_set1_epi8(0x18);
+
+        let v: __m512i = _mm512_and_si512(
+            _mm512_shuffle_epi8(low_nibble_mask, self.v),
+            _mm512_shuffle_epi8(
+                high_nibble_mask,
+                _mm512_and_si512(_mm512_srli_epi32::<4>(self.v), _mm512_set1_epi8(0x7f)),
+            ),
+        );
+
+        // the test sets a bit for every byte that is in one of the buckets
+        *structurals = _mm512_test_epi8_mask(v, structural_shufti_mask);
+        *whitespace = _mm512_test_epi8_mask(v, whitespace_shufti_mask);
+    }
+
+    // flatten out values in 'bits' assuming that they are are to have values of idx
+    // plus their position in the bitvector, and store these indexes at
+    // base_ptr[base] incrementing base as we go
+    // will potentially store extra values beyond end of valid bits, so base_ptr
+    // needs to be large enough to handle this
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_ptr_alignment)]
+    #[target_feature(enable = "avx512f")]
+    #[cfg(not(feature = "wide-index"))]
+    unsafe fn flatten_bits(base: &mut Vec<u32>, idx: u32, mut bits: u64) {
+        unsafe {
+            let cnt: usize = bits.count_ones() as usize;
+            let mut l = base.len();
+            let idx_minus_64 = static_cast_i32!(idx.wrapping_sub(64));
+            // the index of each of the 16 bits in the lowest 16 bit of `bits`
+            let mut offsets = _mm512_add_epi32(
+                _mm512_set1_epi32(idx_minus_64),
+                _mm512_setr_epi32(0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15),
+            );
+            let sixteen = _mm512_set1_epi32(16);
+
+            // We reserve 64 extra entries, because we've at most 64 bit to set.
+            // Every store writes 16 entries but only advances by the number of
+            // bits in its 16 bit chunk, so the last one ends 64 entries past
+            // the start at most.
+            base.reserve(64);
+            let final_len = l + cnt;
+
+            while bits != 0 {
+                let chunk = bits as u16;
+                let v = _mm512_maskz_compress_epi32(chunk, offsets);
+                _mm512_storeu_si512(base.as_mut_ptr().add(l).cast::<__m512i>(), v);
+                l += chunk.count_ones() as usize;
+                bits >>= 16;
+                offsets = _mm512_add_epi32(offsets, sixteen);
+            }
+            // We have written all the data
+            base.set_len(final_len);
+        }
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    #[target_feature(enable = "avx512f")]
+    unsafe fn fill_s8(n: i8) -> __m512i {
+        _mm512_set1_epi8(n)
+    }
+}
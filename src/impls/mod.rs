@@ -10,6 +10,9 @@ pub(crate) mod native;
 /// rust native implementation
 pub(crate) mod portable;
 
+#[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+pub(crate) mod avx512;
+
 #[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
 pub(crate) mod avx2;
 
@@ -195,6 +195,7 @@ impl Stage1Parse for SimdInput {
     //TODO: usize was u32 here does this matter?
     #[cfg_attr(not(feature = "no-inline"), inline)]
     #[allow(clippy::cast_possible_wrap, clippy::cast_ptr_alignment)]
+    #[cfg(not(feature = "wide-index"))]
     unsafe fn flatten_bits(base: &mut Vec<u32>, idx: u32, mut bits: u64) {
         unsafe {
             let cnt: usize = bits.count_ones() as usize;
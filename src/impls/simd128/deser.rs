@@ -141,10 +141,18 @@ pub(crate) fn parse_str<'invoke, 'de>(
                         buffer.get_kinda_unchecked_mut(dst_i..)
                     })
                 else {
-                    return Err(Deserializer::error_c(src_i, 'u', InvalidUnicodeCodepoint));
+                    return Err(Deserializer::error_c(
+                        idx + src_i,
+                        'u',
+                        InvalidUnicodeCodepoint,
+                    ));
                 };
                 if o == 0 {
-                    return Err(Deserializer::error_c(src_i, 'u', InvalidUnicodeCodepoint));
+                    return Err(Deserializer::error_c(
+                        idx + src_i,
+                        'u',
+                        InvalidUnicodeCodepoint,
+                    ));
                 };
                 // We moved o steps forward at the destination and 6 on the source
                 src_i += s;
@@ -158,7 +166,7 @@ pub(crate) fn parse_str<'invoke, 'de>(
                     unsafe { *ESCAPE_MAP.get_kinda_unchecked(escape_char as usize) };
                 if escape_result == 0 {
                     return Err(Deserializer::error_c(
-                        src_i,
+                        idx + src_i + bs_dist as usize,
                         escape_char as char,
                         InvalidEscape,
                     ));
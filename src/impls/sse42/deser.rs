@@ -15,7 +15,11 @@ use arch::{
 };
 
 #[target_feature(enable = "sse4.2")]
-#[allow(clippy::if_not_else, clippy::cast_possible_wrap)]
+#[allow(
+    clippy::if_not_else,
+    clippy::cast_possible_wrap,
+    clippy::too_many_lines
+)]
 #[cfg_attr(not(feature = "no-inline"), inline)]
 pub(crate) unsafe fn parse_str<'invoke, 'de>(
     input: SillyWrapper<'de>,
@@ -144,10 +148,18 @@ pub(crate) unsafe fn parse_str<'invoke, 'de>(
                         src.get_kinda_unchecked(src_i..),
                         buffer.get_kinda_unchecked_mut(dst_i..),
                     ) else {
-                        return Err(Deserializer::error_c(src_i, 'u', InvalidUnicodeCodepoint));
+                        return Err(Deserializer::error_c(
+                            idx + src_i,
+                            'u',
+                            InvalidUnicodeCodepoint,
+                        ));
                     };
                     if o == 0 {
-                        return Err(Deserializer::error_c(src_i, 'u', InvalidUnicodeCodepoint));
+                        return Err(Deserializer::error_c(
+                            idx + src_i,
+                            'u',
+                            InvalidUnicodeCodepoint,
+                        ));
                     }
                     // We moved o steps forward at the destination and 6 on the source
                     src_i += s;
@@ -160,7 +172,7 @@ pub(crate) unsafe fn parse_str<'invoke, 'de>(
                     let escape_result: u8 = *ESCAPE_MAP.get_kinda_unchecked(escape_char as usize);
                     if escape_result == 0 {
                         return Err(Deserializer::error_c(
-                            src_i,
+                            idx + src_i + bs_dist as usize,
                             escape_char as char,
                             InvalidEscape,
                         ));
@@ -29,8 +29,9 @@ pub mod tests;
 use crate::error::InternalError;
 #[cfg(feature = "serde_impl")]
 pub use crate::serde::{
-    from_reader, from_slice, from_str, to_string, to_string_pretty, to_vec, to_vec_pretty,
-    to_writer, to_writer_pretty,
+    from_bytes, from_reader, from_slice, from_str, to_string, to_string_pretty, to_vec,
+    to_vec_pretty, to_writer, to_writer_pretty, to_writer_pretty_with_non_finite,
+    to_writer_with_non_finite,
 };
 
 /// Default trait imports;
@@ -47,6 +48,7 @@ mod stringparse;
 #[allow(unused_imports)]
 use macros::static_cast_u64;
 use safer_unchecked::GetSaferUnchecked;
+use stage1::Stage1State;
 use stage2::StackState;
 use tape::Value;
 
@@ -56,19 +58,57 @@ mod impls;
 pub mod cow;
 
 /// The maximum padding size required by any SIMD implementation
-pub(crate) const SIMDJSON_PADDING: usize = 32; // take upper limit mem::size_of::<__m256i>()
+pub(crate) const SIMDJSON_PADDING: usize = 64; // take upper limit mem::size_of::<__m512i>()
 /// It's 64 for all (Is this correct?)
 pub(crate) const SIMDINPUT_LENGTH: usize = 64;
 
+/// Offset of a structural character in the input, this limits the size of
+/// the input to 4 GiB unless the `wide-index` feature is enabled.
+#[cfg(not(feature = "wide-index"))]
+pub type StructuralIndex = u32;
+/// Offset of a structural character in the input, the `wide-index` feature
+/// makes it 64 bit so inputs larger than 4 GiB can be parsed.
+#[cfg(feature = "wide-index")]
+pub type StructuralIndex = u64;
+
+#[cfg(all(feature = "wide-index", not(target_pointer_width = "64")))]
+compile_error!("the `wide-index` feature requires a 64 bit target");
+
+/// The offset in the input a structural index stands for
+#[cfg_attr(not(feature = "no-inline"), inline)]
+// indexes are offsets into the input slice so they always fit into `usize`,
+// and `wide-index` is only allowed on 64 bit targets
+#[allow(clippy::cast_possible_truncation)]
+pub(crate) const fn to_offset(idx: StructuralIndex) -> usize {
+    idx as usize
+}
+
+/// The largest input the parser accepts
+#[allow(clippy::cast_possible_truncation)]
+pub(crate) const MAX_INPUT_LEN: usize = StructuralIndex::MAX as usize;
+
 /// The default maximum nesting depth of objects and arrays, mirroring
 /// simdjson's `DEFAULT_MAX_DEPTH`.
 pub const DEFAULT_MAX_DEPTH: usize = 1024;
 
+mod minify;
+mod stage1;
 mod stage2;
+mod validate;
 /// simd-json JSON-DOM value
 pub mod value;
 
-use std::{alloc::dealloc, mem};
+pub mod lines;
+pub use crate::lines::{JsonLines, JsonLinesReader, LineError};
+
+pub mod many;
+pub use crate::many::{Documents, parse_many};
+
+pub mod ondemand;
+
+pub mod path;
+
+use std::{alloc::dealloc, marker::PhantomData, mem};
 pub use value_trait::StaticNode;
 
 pub use crate::error::{Error, ErrorType};
@@ -91,13 +131,132 @@ use std::ptr::NonNull;
 
 use simdutf8::basic::imp::ChunkedUtf8Validator;
 
+/// Resource limits for parsing untrusted input.
+///
+/// The options are used through the [`Buffers`] they are turned into, so
+/// they apply to every parser that takes buffers such as
+/// [`to_tape_with_buffers`], [`to_borrowed_value_with_buffers`] or
+/// `serde::from_slice_with_buffers`. Every limit fails with its own
+/// [`ErrorType`]. [`validate_with_buffers`] only checks the input size and
+/// the depth.
+///
+/// ```rust
+/// use simd_json::{Buffers, ErrorType, ParserOptions};
+///
+/// let options = ParserOptions::default()
+///     .with_max_input_len(1 << 20)
+///     .with_max_depth(32)
+///     .with_max_members(2);
+/// let mut buffers = Buffers::from(options);
+/// let mut input = br#"[1, 2, 3]"#.to_vec();
+/// let err = simd_json::to_tape_with_buffers(&mut input, &mut buffers).unwrap_err();
+/// assert_eq!(err.error(), &ErrorType::TooManyMembers);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParserOptions {
+    input_len: usize,
+    depth: usize,
+    string_len: usize,
+    members: usize,
+    nodes: usize,
+}
+
+impl Default for ParserOptions {
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn default() -> Self {
+        Self {
+            input_len: MAX_INPUT_LEN,
+            depth: DEFAULT_MAX_DEPTH,
+            string_len: usize::MAX,
+            members: usize::MAX,
+            nodes: usize::MAX,
+        }
+    }
+}
+
+impl ParserOptions {
+    /// Limits the size of the input in bytes, larger inputs fail with
+    /// [`ErrorType::InputTooLarge`]. It can't be raised above the 4GB
+    /// simd-json supports without the `wide-index` feature.
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    #[must_use]
+    #[allow(clippy::unnecessary_min_or_max)] // `MAX_INPUT_LEN` is `usize::MAX` with `wide-index`
+    pub fn with_max_input_len(mut self, max_input_len: usize) -> Self {
+        self.input_len = max_input_len.min(MAX_INPUT_LEN);
+        self
+    }
+
+    /// Limits the nesting depth of objects and arrays, deeper inputs fail
+    /// with [`ErrorType::DepthLimitExceeded`]. The default is
+    /// [`DEFAULT_MAX_DEPTH`].
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    #[must_use]
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.depth = max_depth;
+        self
+    }
+
+    /// Limits the length in bytes of strings and object keys after
+    /// unescaping, longer ones fail with [`ErrorType::StringTooLong`].
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    #[must_use]
+    pub fn with_max_string_len(mut self, max_string_len: usize) -> Self {
+        self.string_len = max_string_len;
+        self
+    }
+
+    /// Limits the number of elements of an array and of keys of an object,
+    /// larger ones fail with [`ErrorType::TooManyMembers`].
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    #[must_use]
+    pub fn with_max_members(mut self, max_members: usize) -> Self {
+        self.members = max_members;
+        self
+    }
+
+    /// Limits the number of nodes of the tape, that is every value and
+    /// every object key, documents with more fail with
+    /// [`ErrorType::TooManyNodes`].
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    #[must_use]
+    pub fn with_max_nodes(mut self, max_nodes: usize) -> Self {
+        self.nodes = max_nodes;
+        self
+    }
+
+    /// Whether any limit stage 2 has to check is set
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    pub(crate) fn limits_stage2(&self) -> bool {
+        self.string_len != usize::MAX || self.members != usize::MAX || self.nodes != usize::MAX
+    }
+}
+
 /// A struct to hold the buffers for the parser.
+#[allow(clippy::struct_excessive_bools)]
 pub struct Buffers {
     string_buffer: Vec<u8>,
-    structural_indexes: Vec<u32>,
+    structural_indexes: Vec<StructuralIndex>,
+    /// `u32` copy of the structural indexes, made by `structural_indexes`
+    #[cfg(feature = "wide-index")]
+    narrow_structural_indexes: std::sync::OnceLock<Vec<u32>>,
     input_buffer: AlignedBuf,
     stage2_stack: Vec<StackState>,
-    max_depth: usize,
+    options: ParserOptions,
+    /// copy of the input for parsers that must not modify it
+    scratch: Vec<u8>,
+    /// the closing characters and member counts of the open containers when
+    /// validating
+    validate_stack: Vec<(u8, usize)>,
+    stage1_threads: usize,
+    relaxed: bool,
+    non_finite: bool,
+    arbitrary_precision: bool,
+    /// the spans of the last tape, if they are recorded
+    spans: Option<tape::Spans>,
+    #[cfg(feature = "serde_impl")]
+    raw_values: bool,
+    /// the policy set with `with_duplicate_keys`, if any
+    duplicate_keys: Option<DuplicateKeyPolicy>,
 }
 
 impl Default for Buffers {
@@ -107,6 +266,13 @@ impl Default for Buffers {
     }
 }
 
+impl From<ParserOptions> for Buffers {
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn from(options: ParserOptions) -> Self {
+        Self::default().with_options(options)
+    }
+}
+
 impl Buffers {
     /// Borrow the byte offsets of every JSON structural character produced by
     /// stage-1 SIMD scanning.  Populated as a side effect of any parse path
@@ -117,12 +283,96 @@ impl Buffers {
     /// these buffers.  Useful for downstream tooling that wants to align
     /// its own column indices against simd-json's structural decisions
     /// without running stage-1 a second time.
+    ///
+    /// With the `wide-index` feature the indexes are stored as `u64` and
+    /// copied into `u32` on the first call after a parse, use
+    /// `Buffers::wide_structural_indexes` to borrow them directly.
+    ///
+    /// # Panics
+    ///
+    /// With the `wide-index` feature, if the input was larger than 4 GiB.
     #[cfg_attr(not(feature = "no-inline"), inline)]
     #[must_use]
     pub fn structural_indexes(&self) -> &[u32] {
+        #[cfg(not(feature = "wide-index"))]
+        {
+            &self.structural_indexes
+        }
+        #[cfg(feature = "wide-index")]
+        {
+            self.narrow_structural_indexes.get_or_init(|| {
+                self.structural_indexes
+                    .iter()
+                    .map(|&i| u32::try_from(i).expect("structural index larger than 4 GiB"))
+                    .collect()
+            })
+        }
+    }
+
+    /// Borrow the `u64` byte offsets of every JSON structural character, see
+    /// [`Buffers::structural_indexes`].
+    #[cfg(feature = "wide-index")]
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    #[must_use]
+    pub fn wide_structural_indexes(&self) -> &[u64] {
         &self.structural_indexes
     }
 
+    /// The structural indexes for stage 1 to fill, drops the `u32` copy of
+    /// the previous ones.
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    pub(crate) fn structural_indexes_mut(&mut self) -> &mut Vec<StructuralIndex> {
+        #[cfg(feature = "wide-index")]
+        self.narrow_structural_indexes.take();
+        &mut self.structural_indexes
+    }
+
+    /// Copies `input` into the input buffer and sizes the string buffer for
+    /// it, see [`Buffers::copy_input`].
+    #[allow(clippy::uninit_vec)]
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    pub(crate) fn load_input(&mut self, input: &[u8]) {
+        let len = input.len();
+        self.string_buffer.clear();
+        self.string_buffer.reserve(len + SIMDJSON_PADDING);
+
+        unsafe {
+            self.string_buffer.set_len(len + SIMDJSON_PADDING);
+        };
+
+        self.copy_input(input);
+    }
+
+    /// Copies `input` into the input buffer, padded with whitespace so SIMD
+    /// loads can run past its end.
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    pub(crate) fn copy_input(&mut self, input: &[u8]) {
+        const LOTS_OF_SPACES: [u8; SIMDINPUT_LENGTH] = [b' '; SIMDINPUT_LENGTH];
+        let len = input.len();
+        let simd_safe_len = len + SIMDINPUT_LENGTH;
+
+        let input_buffer = &mut self.input_buffer;
+        if input_buffer.capacity() < simd_safe_len {
+            *input_buffer = AlignedBuf::with_capacity(simd_safe_len);
+        }
+
+        unsafe {
+            input_buffer
+                .as_mut_ptr()
+                .copy_from_nonoverlapping(input.as_ptr(), len);
+
+            // initialize all remaining bytes
+            // this also ensures we have whitespace to terminate the buffer
+            input_buffer
+                .as_mut_ptr()
+                .add(len)
+                .copy_from_nonoverlapping(LOTS_OF_SPACES.as_ptr(), SIMDINPUT_LENGTH);
+
+            // safety: all bytes are initialized
+            input_buffer.set_len(simd_safe_len);
+        };
+    }
+
     /// Create new buffer for input length.
     /// If this is too small a new buffer will be allocated, if needed during parsing.
     #[cfg_attr(not(feature = "no-inline"), inline)]
@@ -141,11 +391,129 @@ impl Buffers {
         Self {
             string_buffer: Vec::with_capacity(input_len + SIMDJSON_PADDING),
             structural_indexes: Vec::with_capacity(heuristic_index_cout),
+            #[cfg(feature = "wide-index")]
+            narrow_structural_indexes: std::sync::OnceLock::new(),
             input_buffer: AlignedBuf::with_capacity(input_len + SIMDJSON_PADDING * 2),
             stage2_stack: Vec::with_capacity(heuristic_index_cout),
-            max_depth,
+            options: ParserOptions::default().with_max_depth(max_depth),
+            scratch: Vec::new(),
+            validate_stack: Vec::new(),
+            stage1_threads: 1,
+            relaxed: false,
+            non_finite: false,
+            arbitrary_precision: false,
+            spans: None,
+            #[cfg(feature = "serde_impl")]
+            raw_values: false,
+            duplicate_keys: None,
         }
     }
+
+    /// Allows stage 1 to run on up to `threads` threads.
+    ///
+    /// The input is only split up if every thread gets at least 1 MiB of it,
+    /// so this only benefits very large documents. The default is a single
+    /// thread.
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    #[must_use]
+    pub fn with_stage1_threads(mut self, threads: usize) -> Self {
+        self.stage1_threads = threads;
+        self
+    }
+
+    /// Enables the relaxed mode, which accepts `//` line and `/* */` block
+    /// comments as whitespace and trailing commas in arrays and objects, like
+    /// "JSON with comments" config files do. Everything else RFC 8259 forbids
+    /// is still rejected.
+    ///
+    /// Comments are only supported by parsers that get the whole document at
+    /// once and not by [`parse_many`] or the [`ondemand`] API.
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    #[must_use]
+    pub fn with_relaxed(mut self, relaxed: bool) -> Self {
+        self.relaxed = relaxed;
+        self
+    }
+
+    /// Accepts the `NaN`, `Infinity` and `-Infinity` literals some encoders
+    /// write for non-finite floats and parses them as `f64`s. They are not
+    /// valid JSON so this is disabled by default.
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    #[must_use]
+    pub fn with_non_finite_literals(mut self, non_finite: bool) -> Self {
+        self.non_finite = non_finite;
+        self
+    }
+
+    /// Keeps numbers as their source text instead of converting them, so
+    /// integers of any size and decimals of any precision survive parsing
+    /// and are written back byte for byte. They are checked against the
+    /// JSON grammar but not range checked. Disabled by default.
+    ///
+    /// Numbers end up in the tape as [`tape::Node::Number`] and in the
+    /// values as `Value::Number`, serde gets them in the same form as
+    /// `serde_json`'s `arbitrary_precision` `Number`, see `serde::Number`.
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    #[must_use]
+    pub fn with_arbitrary_precision(mut self, arbitrary_precision: bool) -> Self {
+        self.arbitrary_precision = arbitrary_precision;
+        self
+    }
+
+    /// Records the byte range in the input every node was parsed from, they
+    /// are available through [`Buffers::spans`] until the next parse.
+    /// Disabled by default.
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    #[must_use]
+    pub fn with_spans(mut self, spans: bool) -> Self {
+        self.spans = spans.then(tape::Spans::default);
+        self
+    }
+
+    /// The spans of the tape parsed last, if they are recorded, see
+    /// [`Buffers::with_spans`]
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    #[must_use]
+    pub fn spans(&self) -> Option<&tape::Spans> {
+        self.spans.as_ref()
+    }
+
+    /// Hands the copy of the input the parser works on over to the serde
+    /// [`Deserializer`], so [`serde::RawValue`]s can capture the original
+    /// text of a value. The copy has to be allocated again for the next
+    /// parse. Disabled by default, raw values are then encoded from the
+    /// tape.
+    #[cfg(feature = "serde_impl")]
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    #[must_use]
+    pub fn with_raw_values(mut self, raw_values: bool) -> Self {
+        self.raw_values = raw_values;
+        self
+    }
+
+    /// Replaces the resource limits, see [`ParserOptions`].
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    #[must_use]
+    pub fn with_options(mut self, options: ParserOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// The resource limits the buffers parse with
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    #[must_use]
+    pub fn options(&self) -> &ParserOptions {
+        &self.options
+    }
+
+    /// Sets how objects with the same key more than once are handled, see
+    /// [`DuplicateKeyPolicy`].
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    #[must_use]
+    pub fn with_duplicate_keys(mut self, policy: DuplicateKeyPolicy) -> Self {
+        self.duplicate_keys = Some(policy);
+        self
+    }
 }
 
 /// Creates a tape from the input for later consumption
@@ -166,6 +534,45 @@ pub fn to_tape_with_buffers<'de>(s: &'de mut [u8], buffers: &mut Buffers) -> Res
     Deserializer::from_slice_with_buffers(s, buffers).map(Deserializer::into_tape)
 }
 
+/// Creates a tape from the input without modifying it
+///
+/// The input is copied into `copy` before parsing. Strings and numbers that
+/// are unchanged by parsing borrow from `s`, a tape can't own strings so the
+/// ones that had to be unescaped point into `copy`.
+/// # Errors
+///
+/// Will return `Err` if `s` is invalid JSON.
+#[cfg_attr(not(feature = "no-inline"), inline)]
+pub fn to_tape_from_bytes<'tape>(s: &'tape [u8], copy: &'tape mut Vec<u8>) -> Result<Tape<'tape>> {
+    let mut buffers = Buffers::new(s.len());
+    to_tape_from_bytes_with_buffers(s, copy, &mut buffers)
+}
+
+/// Creates a tape from the input without modifying it, see
+/// [`to_tape_from_bytes`]
+/// # Errors
+///
+/// Will return `Err` if `s` is invalid JSON.
+#[cfg_attr(not(feature = "no-inline"), inline)]
+pub fn to_tape_from_bytes_with_buffers<'tape>(
+    s: &'tape [u8],
+    copy: &'tape mut Vec<u8>,
+    buffers: &mut Buffers,
+) -> Result<Tape<'tape>> {
+    copy.clear();
+    copy.extend_from_slice(s);
+    let base = copy.as_ptr() as usize;
+    let reborrow = |text: &'tape str| value::borrowed::reborrow(s, base, text).unwrap_or(text);
+    let mut tape = stry!(to_tape_with_buffers(copy, buffers));
+    for node in &mut tape.0 {
+        match node {
+            Node::String(text) | Node::Number(text, _) => *text = reborrow(text),
+            Node::Static(_) | Node::Array { .. } | Node::Object { .. } => {}
+        }
+    }
+    Ok(tape)
+}
+
 /// Fills a already existing tape from the input for later consumption
 /// # Errors
 ///
@@ -173,7 +580,48 @@ pub fn to_tape_with_buffers<'de>(s: &'de mut [u8], buffers: &mut Buffers) -> Res
 #[cfg_attr(not(feature = "no-inline"), inline)]
 pub fn fill_tape<'de>(s: &'de mut [u8], buffers: &mut Buffers, tape: &mut Tape<'de>) -> Result<()> {
     tape.0.clear();
-    Deserializer::fill_tape(s, buffers, &mut tape.0)
+    Deserializer::fill_tape(s, buffers, &mut tape.0)?;
+    Deserializer::fill_spans(buffers, tape.0.len());
+    Ok(())
+}
+
+/// Checks that the input is valid JSON without building a tape
+///
+/// This runs the same checks as [`to_tape`], including the parser limits, but
+/// doesn't write any nodes or unescape any strings. The input is not modified.
+/// # Errors
+///
+/// Will return `Err` if `s` is invalid JSON.
+#[cfg_attr(not(feature = "no-inline"), inline)]
+pub fn validate(s: &[u8]) -> Result<()> {
+    let mut buffers = Buffers::new(s.len());
+    Deserializer::validate(s, &mut buffers)
+}
+
+/// Checks that the input is valid JSON without building a tape, see
+/// [`validate`]. The limits of the [`ParserOptions`] of `buffers` are
+/// enforced as [`to_tape_with_buffers`] does.
+/// # Errors
+///
+/// Will return `Err` if `s` is invalid JSON.
+#[cfg_attr(not(feature = "no-inline"), inline)]
+pub fn validate_with_buffers(s: &[u8], buffers: &mut Buffers) -> Result<()> {
+    Deserializer::validate(s, buffers)
+}
+
+/// Removes all insignificant whitespace from the input and appends the
+/// result to `out`
+///
+/// Every other byte is copied unchanged, so keys keep their order and numbers
+/// and strings their exact spelling. Apart from strings being terminated the
+/// input is not validated.
+/// # Errors
+///
+/// Will return `Err` if `s` ends inside of a string, `out` is left unchanged
+/// in that case.
+#[cfg_attr(not(feature = "no-inline"), inline)]
+pub fn minify(s: &[u8], out: &mut Vec<u8>) -> Result<()> {
+    Deserializer::minify(s, out).map_err(Error::generic)
 }
 
 pub(crate) trait Stage1Parse {
@@ -190,7 +638,21 @@ pub(crate) trait Stage1Parse {
 
     unsafe fn find_whitespace_and_structurals(&self, whitespace: &mut u64, structurals: &mut u64);
 
-    unsafe fn flatten_bits(base: &mut Vec<u32>, idx: u32, bits: u64);
+    #[cfg(not(feature = "wide-index"))]
+    unsafe fn flatten_bits(base: &mut Vec<StructuralIndex>, idx: StructuralIndex, bits: u64);
+
+    // The SIMD implementations write 32 bit lanes, wide indexes are flattened
+    // one bit at a time.
+    #[cfg(feature = "wide-index")]
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    unsafe fn flatten_bits(base: &mut Vec<StructuralIndex>, idx: StructuralIndex, mut bits: u64) {
+        let idx_minus_64 = idx.wrapping_sub(64);
+        base.reserve(bits.count_ones() as usize);
+        while bits != 0 {
+            base.push(idx_minus_64 + StructuralIndex::from(bits.trailing_zeros()));
+            bits &= bits.wrapping_sub(1);
+        }
+    }
 
     // return both the quote mask (which is a half-open mask that covers the first
     // quote in an unescaped quote pair and everything in the quote pair) and the
@@ -319,12 +781,52 @@ pub(crate) trait Stage1Parse {
 }
 
 /// Deserializer struct to deserialize a JSON
+///
+/// The tape is a `Vec` when parsing and a slice of an existing tape when
+/// deserializing one of its values.
 #[derive(Debug)]
-pub struct Deserializer<'de> {
+pub struct Deserializer<'de, T = Vec<Node<'de>>> {
     // Note: we use the 2nd part as both index and length since only one is ever
     // used (array / object use len) everything else uses idx
-    pub(crate) tape: Vec<Node<'de>>,
+    pub(crate) tape: T,
     idx: usize,
+    /// how the value builders and serde handle duplicate keys, `None` when
+    /// no policy was set
+    pub(crate) duplicate_keys: Option<DuplicateKeyPolicy>,
+    /// the original input, kept for raw values
+    #[cfg(feature = "serde_impl")]
+    pub(crate) raw_source: Option<serde::RawSource<'de>>,
+    /// the addresses of strings that only live as long as the deserializer,
+    /// they are not borrowed by serde, see [`serde::from_bytes`]
+    #[cfg(feature = "serde_impl")]
+    pub(crate) transient: std::ops::Range<usize>,
+    /// the input the nodes borrow from, `T` does not have to name it
+    pub(crate) _input: PhantomData<&'de [u8]>,
+}
+
+impl<'de, T> Deserializer<'de, T>
+where
+    T: AsRef<[Node<'de>]>,
+{
+    #[cfg(feature = "serde_impl")]
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn skip(&mut self) {
+        self.idx += 1;
+    }
+
+    /// Same as `next()` but we pull out the check so we don't need to
+    /// stry every time. Use this only if you know the next element exists!
+    ///
+    /// # Safety
+    ///
+    /// This function is not safe to use, it is meant for internal use
+    /// where it's know the tape isn't finished.
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    pub unsafe fn next_(&mut self) -> Node<'de> {
+        let r = *unsafe { self.tape.as_ref().get_kinda_unchecked(self.idx) };
+        self.idx += 1;
+        r
+    }
 }
 
 // architecture dependant parse_str
@@ -345,11 +847,6 @@ impl From<*mut u8> for SillyWrapper<'_> {
     }
 }
 
-#[cfg(all(
-    feature = "runtime-detection",
-    any(target_arch = "x86_64", target_arch = "x86"),
-))] // The runtime detection code is inspired from simdutf8's implementation
-type FnRaw = *mut ();
 #[cfg(all(
     feature = "runtime-detection",
     any(target_arch = "x86_64", target_arch = "x86"),
@@ -360,15 +857,6 @@ type ParseStrFn = for<'invoke, 'de> unsafe fn(
     &'invoke mut [u8],
     usize,
 ) -> std::result::Result<&'de str, error::Error>;
-#[cfg(all(
-    feature = "runtime-detection",
-    any(target_arch = "x86_64", target_arch = "x86"),
-))]
-type FindStructuralBitsFn = unsafe fn(
-    input: &[u8],
-    structural_indexes: &mut Vec<u32>,
-) -> std::result::Result<(), ErrorType>;
-
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 /// Supported implementations
 pub enum Implementation {
@@ -380,12 +868,102 @@ pub enum Implementation {
     SSE42,
     /// AVX2 implementation
     AVX2,
+    /// AVX-512 (F and BW) implementation
+    AVX512,
     /// ARM NEON implementation
     NEON,
     /// WEBASM SIMD128 implementation
     SIMD128,
 }
 
+/// How serializers write floats that are `NaN` or infinite, JSON has no
+/// representation for them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NonFinitePolicy {
+    /// Fail with [`ErrorType::NonFiniteFloat`]
+    Error,
+    /// Write `null` in their place, like `serde_json` does
+    #[default]
+    Null,
+    /// Write the `NaN`, `Infinity` and `-Infinity` literals, which can be
+    /// read back with [`Buffers::with_non_finite_literals`]
+    Literal,
+}
+
+impl NonFinitePolicy {
+    /// Writes the non-finite `num` according to the policy
+    pub(crate) fn write<W: std::io::Write>(self, w: &mut W, num: f64) -> std::io::Result<()> {
+        match self {
+            Self::Error => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "non-finite float",
+            )),
+            Self::Null => w.write_all(b"null"),
+            Self::Literal if num.is_nan() => w.write_all(b"NaN"),
+            Self::Literal if num.is_sign_positive() => w.write_all(b"Infinity"),
+            Self::Literal => w.write_all(b"-Infinity"),
+        }
+    }
+    /// Writes `num` if it is finite, otherwise according to the policy
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    pub(crate) fn write_f64<G: value_trait::generator::BaseGenerator + ?Sized>(
+        self,
+        g: &mut G,
+        num: f64,
+    ) -> std::io::Result<()> {
+        if num.is_finite() {
+            g.write_float(num)
+        } else {
+            self.write(g.get_writer(), num)
+        }
+    }
+}
+
+/// How objects that contain the same key more than once are handled.
+///
+/// The policy is set with [`Buffers::with_duplicate_keys`] and applies to
+/// the values and to serde deserialization, tapes always hold every entry.
+/// Without a policy values use the default, `KeepAll` or `LastWins` with the
+/// `value-no-dup-keys` feature, while serde keeps every entry so derived
+/// structs still report duplicate fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicateKeyPolicy {
+    /// Fail parsing with [`ErrorType::DuplicateKey`]
+    Error,
+    /// Keep the first value of a key and ignore the ones after it
+    FirstWins,
+    /// Keep the last value of a key, like `serde_json` does
+    #[cfg_attr(feature = "value-no-dup-keys", default)]
+    LastWins,
+    /// Keep every entry, this is the fastest as keys are not compared.
+    /// `owned::Value` and `borrowed::Value` objects then hold the key more
+    /// than once and which value a lookup finds is unspecified, serde
+    /// visitors see every entry.
+    #[cfg_attr(not(feature = "value-no-dup-keys"), default)]
+    KeepAll,
+}
+
+impl DuplicateKeyPolicy {
+    /// Inserts `key` into an object under construction
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    pub(crate) fn insert<K, V, S>(self, map: &mut halfbrown::HashMap<K, V, S>, key: K, value: V)
+    where
+        K: std::hash::Hash + Eq,
+        S: std::hash::BuildHasher + Default,
+    {
+        match self {
+            // with `Error` duplicates were rejected while parsing
+            Self::KeepAll | Self::Error => unsafe { map.insert_nocheck(key, value) },
+            Self::LastWins => {
+                map.insert(key, value);
+            }
+            Self::FirstWins => {
+                map.entry(key).or_insert(value);
+            }
+        }
+    }
+}
+
 impl std::fmt::Display for Implementation {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -393,13 +971,195 @@ impl std::fmt::Display for Implementation {
             Implementation::StdSimd => write!(f, "std::simd"),
             Implementation::SSE42 => write!(f, "SSE42"),
             Implementation::AVX2 => write!(f, "AVX2"),
+            Implementation::AVX512 => write!(f, "AVX-512"),
             Implementation::NEON => write!(f, "NEON"),
             Implementation::SIMD128 => write!(f, "SIMD128"),
         }
     }
 }
 
+/// The environment variable that selects the implementation, it is read
+/// once before the first parse. It takes the same names as
+/// [`Implementation::from_str`](std::str::FromStr), unknown or unsupported
+/// implementations are ignored.
+pub const IMPLEMENTATION_ENV: &str = "SIMD_JSON_IMPLEMENTATION";
+
+/// The implementation picked on `x86`, `0` until it is selected and the
+/// position in `Implementation::ALL` plus one after
+#[cfg(all(
+    feature = "runtime-detection",
+    any(target_arch = "x86_64", target_arch = "x86"),
+))]
+static SELECTED_IMPLEMENTATION: std::sync::atomic::AtomicU8 = std::sync::atomic::AtomicU8::new(0);
+
+impl Implementation {
+    /// Every implementation, fastest first
+    const ALL: [Self; 7] = [
+        Self::AVX512,
+        Self::AVX2,
+        Self::SSE42,
+        Self::NEON,
+        Self::SIMD128,
+        Self::StdSimd,
+        Self::Native,
+    ];
+
+    /// The short lowercase name of the implementation
+    fn name(self) -> &'static str {
+        match self {
+            Implementation::Native => "native",
+            Implementation::StdSimd => "std-simd",
+            Implementation::SSE42 => "sse42",
+            Implementation::AVX2 => "avx2",
+            Implementation::AVX512 => "avx512",
+            Implementation::NEON => "neon",
+            Implementation::SIMD128 => "simd128",
+        }
+    }
+
+    /// Whether the implementation is compiled in and the CPU supports it
+    #[cfg(all(
+        feature = "runtime-detection",
+        any(target_arch = "x86_64", target_arch = "x86"),
+    ))]
+    fn is_supported(self) -> bool {
+        match self {
+            Implementation::AVX512 => {
+                std::is_x86_feature_detected!("avx512f")
+                    && std::is_x86_feature_detected!("avx512bw")
+                    && std::is_x86_feature_detected!("pclmulqdq")
+            }
+            Implementation::AVX2 => {
+                std::is_x86_feature_detected!("avx2") && std::is_x86_feature_detected!("pclmulqdq")
+            }
+            Implementation::SSE42 => std::is_x86_feature_detected!("sse4.2"),
+            Implementation::StdSimd => cfg!(feature = "portable"),
+            Implementation::Native => cfg!(not(feature = "portable")),
+            Implementation::NEON | Implementation::SIMD128 => false,
+        }
+    }
+
+    /// Whether the implementation is compiled in and the CPU supports it
+    #[cfg(not(all(
+        feature = "runtime-detection",
+        any(target_arch = "x86_64", target_arch = "x86"),
+    )))]
+    fn is_supported(self) -> bool {
+        self == Deserializer::algorithm()
+    }
+
+    /// The implementation used for parsing, on the first call it is taken
+    /// from [`IMPLEMENTATION_ENV`] or the fastest supported one
+    #[cfg(all(
+        feature = "runtime-detection",
+        any(target_arch = "x86_64", target_arch = "x86"),
+    ))]
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    pub(crate) fn selected() -> Self {
+        use std::sync::atomic::Ordering;
+        match SELECTED_IMPLEMENTATION.load(Ordering::Relaxed) {
+            0 => Self::select(),
+            i => Self::ALL[usize::from(i - 1)],
+        }
+    }
+
+    #[cfg(all(
+        feature = "runtime-detection",
+        any(target_arch = "x86_64", target_arch = "x86"),
+    ))]
+    #[cold]
+    fn select() -> Self {
+        let from_env = std::env::var(IMPLEMENTATION_ENV)
+            .ok()
+            .and_then(|name| name.parse::<Self>().ok())
+            .filter(|i| i.is_supported());
+        let selected = from_env
+            .or_else(|| Self::ALL.into_iter().find(|i| i.is_supported()))
+            .unwrap_or_default();
+        selected.store();
+        selected
+    }
+
+    #[cfg(all(
+        feature = "runtime-detection",
+        any(target_arch = "x86_64", target_arch = "x86"),
+    ))]
+    #[allow(clippy::cast_possible_truncation)]
+    fn store(self) {
+        let i = Self::ALL
+            .iter()
+            .position(|i| *i == self)
+            .unwrap_or_default();
+        SELECTED_IMPLEMENTATION.store(i as u8 + 1, std::sync::atomic::Ordering::Relaxed);
+    }
+}
+
+impl Default for Implementation {
+    /// The fallback that is always available
+    fn default() -> Self {
+        if cfg!(feature = "portable") {
+            Implementation::StdSimd
+        } else {
+            Implementation::Native
+        }
+    }
+}
+
+impl std::str::FromStr for Implementation {
+    type Err = Error;
+
+    /// Parses the name of an implementation, either the short one (`native`,
+    /// `std-simd`, `sse42`, `avx2`, `avx512`, `neon` or `simd128`) or the one
+    /// it is displayed with, ignoring case.
+    fn from_str(s: &str) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|i| s.eq_ignore_ascii_case(i.name()) || s.eq_ignore_ascii_case(&i.to_string()))
+            .ok_or_else(|| Error::generic(ErrorType::SimdUnsupported))
+    }
+}
+
 impl Deserializer<'_> {
+    /// Lists the implementations that can be used on this CPU, fastest
+    /// first. Only `x86` with the `runtime-detection` feature has more than
+    /// one.
+    #[must_use]
+    pub fn available_implementations() -> Vec<Implementation> {
+        Implementation::ALL
+            .into_iter()
+            .filter(|i| i.is_supported())
+            .collect()
+    }
+
+    /// Forces the implementation used for parsing and minifying from now on,
+    /// in every thread. It overrides the [`IMPLEMENTATION_ENV`] environment
+    /// variable and runtime detection, which reproduces the behaviour on
+    /// older CPUs or compares implementations in one binary.
+    ///
+    /// ```rust
+    /// use simd_json::{Deserializer, Implementation};
+    ///
+    /// let fallback = *Deserializer::available_implementations().last().unwrap();
+    /// Deserializer::set_implementation(fallback).unwrap();
+    /// assert_eq!(Deserializer::algorithm(), fallback);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`ErrorType::SimdUnsupported`] if the implementation isn't
+    /// one of the [`available_implementations`](Self::available_implementations).
+    pub fn set_implementation(implementation: Implementation) -> Result<()> {
+        if !implementation.is_supported() {
+            return Err(Error::generic(ErrorType::SimdUnsupported));
+        }
+        #[cfg(all(
+            feature = "runtime-detection",
+            any(target_arch = "x86_64", target_arch = "x86"),
+        ))]
+        implementation.store();
+        Ok(())
+    }
+
     /// returns the algorithm / architecture used by the deserializer
     #[cfg(all(
         feature = "runtime-detection",
@@ -407,17 +1167,7 @@ impl Deserializer<'_> {
     ))]
     #[must_use]
     pub fn algorithm() -> Implementation {
-        if std::is_x86_feature_detected!("avx2") {
-            Implementation::AVX2
-        } else if std::is_x86_feature_detected!("sse4.2") {
-            Implementation::SSE42
-        } else {
-            #[cfg(feature = "portable")]
-            let r = Implementation::StdSimd;
-            #[cfg(not(feature = "portable"))]
-            let r = Implementation::Native;
-            r
-        }
+        Implementation::selected()
     }
     #[cfg(not(any(
         all(
@@ -490,16 +1240,17 @@ impl<'de> Deserializer<'de> {
         any(target_arch = "x86_64", target_arch = "x86"),
     ))]
     pub(crate) fn parse_str_fn() -> ParseStrFn {
-        if std::is_x86_feature_detected!("avx2") {
-            impls::avx2::parse_str
-        } else if std::is_x86_feature_detected!("sse4.2") {
-            impls::sse42::parse_str
-        } else {
-            #[cfg(feature = "portable")]
-            let r = impls::portable::parse_str;
-            #[cfg(not(feature = "portable"))]
-            let r = impls::native::parse_str;
-            r
+        match Implementation::selected() {
+            Implementation::AVX512 => impls::avx512::parse_str,
+            Implementation::AVX2 => impls::avx2::parse_str,
+            Implementation::SSE42 => impls::sse42::parse_str,
+            _ => {
+                #[cfg(feature = "portable")]
+                let r = impls::portable::parse_str;
+                #[cfg(not(feature = "portable"))]
+                let r = impls::native::parse_str;
+                r
+            }
         }
     }
 
@@ -631,14 +1382,19 @@ impl Deserializer<'_> {
     ))]
     pub(crate) unsafe fn find_structural_bits_native(
         input: &[u8],
-        structural_indexes: &mut Vec<u32>,
+        structural_indexes: &mut Vec<StructuralIndex>,
+        comments: bool,
     ) -> std::result::Result<(), ErrorType> {
         match core::str::from_utf8(input) {
             Ok(_) => (),
             Err(_) => return Err(ErrorType::InvalidUtf8),
         }
         unsafe {
-            Self::_find_structural_bits::<impls::native::SimdInput>(input, structural_indexes)
+            Self::_find_structural_bits::<impls::native::SimdInput>(
+                input,
+                structural_indexes,
+                comments,
+            )
         }
     }
 
@@ -649,25 +1405,44 @@ impl Deserializer<'_> {
     ))]
     pub(crate) unsafe fn find_structural_bits(
         input: &[u8],
-        structural_indexes: &mut Vec<u32>,
+        structural_indexes: &mut Vec<StructuralIndex>,
+        comments: bool,
     ) -> std::result::Result<(), ErrorType> {
         unsafe {
-            use std::sync::atomic::{AtomicPtr, Ordering};
-
-            static FN: AtomicPtr<()> = AtomicPtr::new(get_fastest as FnRaw);
-
             // The wrappers below carry the ISA's `target_feature` so that LLVM can inline
             // the `#[target_feature]`-annotated SIMD primitives into the stage-1 loop;
             // without them every primitive stays an outlined call per 64-byte block.
+            #[target_feature(
+                enable = "avx512f",
+                enable = "avx512bw",
+                enable = "avx2",
+                enable = "pclmulqdq"
+            )]
+            unsafe fn find_structural_bits_avx512(
+                input: &[u8],
+                structural_indexes: &mut Vec<StructuralIndex>,
+                comments: bool,
+            ) -> core::result::Result<(), error::ErrorType> {
+                unsafe {
+                    Deserializer::_find_structural_bits::<impls::avx512::SimdInput>(
+                        input,
+                        structural_indexes,
+                        comments,
+                    )
+                }
+            }
+
             #[target_feature(enable = "avx2", enable = "pclmulqdq")]
             unsafe fn find_structural_bits_avx2(
                 input: &[u8],
-                structural_indexes: &mut Vec<u32>,
+                structural_indexes: &mut Vec<StructuralIndex>,
+                comments: bool,
             ) -> core::result::Result<(), error::ErrorType> {
                 unsafe {
                     Deserializer::_find_structural_bits::<impls::avx2::SimdInput>(
                         input,
                         structural_indexes,
+                        comments,
                     )
                 }
             }
@@ -675,47 +1450,44 @@ impl Deserializer<'_> {
             #[target_feature(enable = "sse4.2")]
             unsafe fn find_structural_bits_sse42(
                 input: &[u8],
-                structural_indexes: &mut Vec<u32>,
+                structural_indexes: &mut Vec<StructuralIndex>,
+                comments: bool,
             ) -> core::result::Result<(), error::ErrorType> {
                 unsafe {
                     Deserializer::_find_structural_bits::<impls::sse42::SimdInput>(
                         input,
                         structural_indexes,
+                        comments,
                     )
                 }
             }
 
-            #[cfg_attr(not(feature = "no-inline"), inline)]
-            fn get_fastest_available_implementation() -> FindStructuralBitsFn {
-                if std::is_x86_feature_detected!("avx2")
-                    && std::is_x86_feature_detected!("pclmulqdq")
-                {
-                    find_structural_bits_avx2
-                } else if std::is_x86_feature_detected!("sse4.2") {
-                    find_structural_bits_sse42
-                } else {
+            match Implementation::selected() {
+                Implementation::AVX512 => {
+                    find_structural_bits_avx512(input, structural_indexes, comments)
+                }
+                Implementation::AVX2 => {
+                    find_structural_bits_avx2(input, structural_indexes, comments)
+                }
+                Implementation::SSE42 => {
+                    find_structural_bits_sse42(input, structural_indexes, comments)
+                }
+                _ => {
                     #[cfg(feature = "portable")]
-                    let r = Deserializer::_find_structural_bits::<impls::portable::SimdInput>;
+                    let r = Deserializer::_find_structural_bits::<impls::portable::SimdInput>(
+                        input,
+                        structural_indexes,
+                        comments,
+                    );
                     #[cfg(not(feature = "portable"))]
-                    let r = Deserializer::find_structural_bits_native;
+                    let r = Deserializer::find_structural_bits_native(
+                        input,
+                        structural_indexes,
+                        comments,
+                    );
                     r
                 }
             }
-
-            #[cfg_attr(not(feature = "no-inline"), inline)]
-            unsafe fn get_fastest(
-                input: &[u8],
-                structural_indexes: &mut Vec<u32>,
-            ) -> core::result::Result<(), error::ErrorType> {
-                unsafe {
-                    let fun = get_fastest_available_implementation();
-                    FN.store(fun as FnRaw, Ordering::Relaxed);
-                    (fun)(input, structural_indexes)
-                }
-            }
-
-            let fun = FN.load(Ordering::Relaxed);
-            mem::transmute::<FnRaw, FindStructuralBitsFn>(fun)(input, structural_indexes)
         }
     }
 
@@ -733,7 +1505,8 @@ impl Deserializer<'_> {
     #[cfg_attr(not(feature = "no-inline"), inline)]
     pub(crate) unsafe fn find_structural_bits(
         input: &[u8],
-        structural_indexes: &mut Vec<u32>,
+        structural_indexes: &mut Vec<StructuralIndex>,
+        comments: bool,
     ) -> std::result::Result<(), ErrorType> {
         // This is a nasty hack, we don't have a chunked implementation for native rust
         // so we validate UTF8 ahead of time
@@ -743,7 +1516,11 @@ impl Deserializer<'_> {
         }
         #[cfg(not(feature = "portable"))]
         unsafe {
-            Self::_find_structural_bits::<impls::native::SimdInput>(input, structural_indexes)
+            Self::_find_structural_bits::<impls::native::SimdInput>(
+                input,
+                structural_indexes,
+                comments,
+            )
         }
     }
 
@@ -751,10 +1528,15 @@ impl Deserializer<'_> {
     #[cfg_attr(not(feature = "no-inline"), inline)]
     pub(crate) unsafe fn find_structural_bits(
         input: &[u8],
-        structural_indexes: &mut Vec<u32>,
+        structural_indexes: &mut Vec<StructuralIndex>,
+        comments: bool,
     ) -> std::result::Result<(), ErrorType> {
         unsafe {
-            Self::_find_structural_bits::<impls::portable::SimdInput>(input, structural_indexes)
+            Self::_find_structural_bits::<impls::portable::SimdInput>(
+                input,
+                structural_indexes,
+                comments,
+            )
         }
     }
 
@@ -766,9 +1548,16 @@ impl Deserializer<'_> {
     #[cfg_attr(not(feature = "no-inline"), inline)]
     pub(crate) unsafe fn find_structural_bits(
         input: &[u8],
-        structural_indexes: &mut Vec<u32>,
+        structural_indexes: &mut Vec<StructuralIndex>,
+        comments: bool,
     ) -> std::result::Result<(), ErrorType> {
-        unsafe { Self::_find_structural_bits::<impls::avx2::SimdInput>(input, structural_indexes) }
+        unsafe {
+            Self::_find_structural_bits::<impls::avx2::SimdInput>(
+                input,
+                structural_indexes,
+                comments,
+            )
+        }
     }
 
     #[cfg(all(
@@ -780,28 +1569,47 @@ impl Deserializer<'_> {
     #[cfg_attr(not(feature = "no-inline"), inline)]
     pub(crate) unsafe fn find_structural_bits(
         input: &[u8],
-        structural_indexes: &mut Vec<u32>,
+        structural_indexes: &mut Vec<StructuralIndex>,
+        comments: bool,
     ) -> std::result::Result<(), ErrorType> {
-        unsafe { Self::_find_structural_bits::<impls::sse42::SimdInput>(input, structural_indexes) }
+        unsafe {
+            Self::_find_structural_bits::<impls::sse42::SimdInput>(
+                input,
+                structural_indexes,
+                comments,
+            )
+        }
     }
 
     #[cfg(all(target_arch = "aarch64", not(feature = "portable")))]
     #[cfg_attr(not(feature = "no-inline"), inline)]
     pub(crate) unsafe fn find_structural_bits(
         input: &[u8],
-        structural_indexes: &mut Vec<u32>,
+        structural_indexes: &mut Vec<StructuralIndex>,
+        comments: bool,
     ) -> std::result::Result<(), ErrorType> {
-        unsafe { Self::_find_structural_bits::<impls::neon::SimdInput>(input, structural_indexes) }
+        unsafe {
+            Self::_find_structural_bits::<impls::neon::SimdInput>(
+                input,
+                structural_indexes,
+                comments,
+            )
+        }
     }
 
     #[cfg(all(target_feature = "simd128", not(feature = "portable")))]
     #[cfg_attr(not(feature = "no-inline"), inline)]
     pub(crate) unsafe fn find_structural_bits(
         input: &[u8],
-        structural_indexes: &mut Vec<u32>,
+        structural_indexes: &mut Vec<StructuralIndex>,
+        comments: bool,
     ) -> std::result::Result<(), ErrorType> {
         unsafe {
-            Self::_find_structural_bits::<impls::simd128::SimdInput>(input, structural_indexes)
+            Self::_find_structural_bits::<impls::simd128::SimdInput>(
+                input,
+                structural_indexes,
+                comments,
+            )
         }
     }
 }
@@ -858,61 +1666,111 @@ impl<'de> Deserializer<'de> {
     /// # Errors
     ///
     /// Will return `Err` if `input` is invalid JSON.
-    #[allow(clippy::uninit_vec)]
     #[cfg_attr(not(feature = "no-inline"), inline)]
     fn fill_tape(
         input: &'de mut [u8],
         buffer: &mut Buffers,
         tape: &mut Vec<Node<'de>>,
-    ) -> Result<()> {
-        const LOTS_OF_SPACES: [u8; SIMDINPUT_LENGTH] = [b' '; SIMDINPUT_LENGTH];
-        let len = input.len();
-        let simd_safe_len = len + SIMDINPUT_LENGTH;
-
-        if len > u32::MAX as usize {
+    ) -> Result<&'de [u8]> {
+        if input.len() > buffer.options.input_len {
             return Err(Self::error(ErrorType::InputTooLarge));
         }
 
-        buffer.string_buffer.clear();
-        buffer.string_buffer.reserve(len + SIMDJSON_PADDING);
+        unsafe { buffer.find_structural_bits(input, buffer.relaxed) }
+            .map_err(|e| stage1::locate_error(input, e, buffer.relaxed))?;
 
-        unsafe {
-            buffer.string_buffer.set_len(len + SIMDJSON_PADDING);
-        };
+        Self::fill_tape_from_structurals(input, buffer, tape)
+    }
 
-        let input_buffer = &mut buffer.input_buffer;
-        if input_buffer.capacity() < simd_safe_len {
-            *input_buffer = AlignedBuf::with_capacity(simd_safe_len);
+    /// Runs stage 2 only, using the structural indexes already stored in
+    /// `buffer`; the caller is responsible for having validated `input` as
+    /// UTF-8 and for the indexes to be the stage 1 result for `input`.
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    pub(crate) fn fill_tape_from_structurals(
+        input: &'de mut [u8],
+        buffer: &mut Buffers,
+        tape: &mut Vec<Node<'de>>,
+    ) -> Result<&'de [u8]> {
+        if input.len() > buffer.options.input_len {
+            return Err(Self::error(ErrorType::InputTooLarge));
+        }
+        if buffer.structural_indexes.is_empty() {
+            return Err(Self::error(ErrorType::Eof));
         }
 
-        unsafe {
-            input_buffer
-                .as_mut_ptr()
-                .copy_from_nonoverlapping(input.as_ptr(), len);
-
-            // initialize all remaining bytes
-            // this also ensures we have whitespace to terminate the buffer
-            input_buffer
-                .as_mut_ptr()
-                .add(len)
-                .copy_from_nonoverlapping(LOTS_OF_SPACES.as_ptr(), SIMDINPUT_LENGTH);
-
-            // safety: all bytes are initialized
-            input_buffer.set_len(simd_safe_len);
-
-            Self::find_structural_bits(input, &mut buffer.structural_indexes)
-                .map_err(Error::generic)?;
+        buffer.load_input(input);
+        let base = input.as_ptr() as usize;
+
+        macro_rules! build_tape {
+            ($relaxed:literal, $limited:literal, $non_finite:literal, $arbitrary_precision:literal) => {
+                Self::build_tape::<$relaxed, $limited, $non_finite, $arbitrary_precision>(
+                    input,
+                    &buffer.input_buffer,
+                    &mut buffer.string_buffer,
+                    &buffer.structural_indexes,
+                    &mut buffer.stage2_stack,
+                    &buffer.options,
+                    tape,
+                )
+            };
+        }
+        let res = match (
+            buffer.relaxed,
+            buffer.options.limits_stage2(),
+            buffer.non_finite,
+            buffer.arbitrary_precision,
+        ) {
+            (false, false, false, false) => build_tape!(false, false, false, false),
+            (false, false, false, true) => build_tape!(false, false, false, true),
+            (false, false, true, false) => build_tape!(false, false, true, false),
+            (false, false, true, true) => build_tape!(false, false, true, true),
+            (false, true, false, false) => build_tape!(false, true, false, false),
+            (false, true, false, true) => build_tape!(false, true, false, true),
+            (false, true, true, false) => build_tape!(false, true, true, false),
+            (false, true, true, true) => build_tape!(false, true, true, true),
+            (true, false, false, false) => build_tape!(true, false, false, false),
+            (true, false, false, true) => build_tape!(true, false, false, true),
+            (true, false, true, false) => build_tape!(true, false, true, false),
+            (true, false, true, true) => build_tape!(true, false, true, true),
+            (true, true, false, false) => build_tape!(true, true, false, false),
+            (true, true, false, true) => build_tape!(true, true, false, true),
+            (true, true, true, false) => build_tape!(true, true, true, false),
+            (true, true, true, true) => build_tape!(true, true, true, true),
         };
+        let res = res.and_then(|input| {
+            if buffer.duplicate_keys == Some(DuplicateKeyPolicy::Error)
+                && let Some(idx) = Self::find_duplicate_key(tape, base)
+            {
+                return Err(Error::new_c(idx, '"', ErrorType::DuplicateKey));
+            }
+            Ok(input)
+        });
+        // the copy of the input is still untouched by stage 2
+        res.map_err(|e| e.located_in(&buffer.input_buffer, &buffer.structural_indexes))
+    }
 
-        Self::build_tape(
-            input,
-            input_buffer,
-            &mut buffer.string_buffer,
-            &buffer.structural_indexes,
-            &mut buffer.stage2_stack,
-            buffer.max_depth,
-            tape,
-        )
+    /// Finds the first key that is repeated in an object of `tape` and
+    /// returns the byte index of its opening quote. Strings are unescaped in
+    /// place, so they start right behind their quote in the input at `base`.
+    fn find_duplicate_key(tape: &[Node<'de>], base: usize) -> Option<usize> {
+        let mut keys = std::collections::HashSet::new();
+        for (i, node) in tape.iter().enumerate() {
+            let Node::Object { len, .. } = node else {
+                continue;
+            };
+            keys.clear();
+            let mut j = i + 1;
+            for _ in 0..*len {
+                let Node::String(key) = tape[j] else {
+                    unreachable!("object keys are strings");
+                };
+                if !keys.insert(key) {
+                    return Some((key.as_ptr() as usize).saturating_sub(base + 1));
+                }
+                j += 1 + tape[j + 1].count();
+            }
+        }
+        None
     }
 
     /// Creates a serializer from a mutable slice of bytes using a temporary
@@ -924,36 +1782,36 @@ impl<'de> Deserializer<'de> {
     pub fn from_slice_with_buffers(input: &'de mut [u8], buffer: &mut Buffers) -> Result<Self> {
         let mut tape: Vec<Node<'de>> = Vec::with_capacity(buffer.structural_indexes.len());
 
-        Self::fill_tape(input, buffer, &mut tape)?;
-
-        Ok(Self { tape, idx: 0 })
-    }
+        // stage 2 hands the input back once it is done unescaping strings
+        #[cfg_attr(not(feature = "serde_impl"), allow(unused_variables))]
+        let input = Self::fill_tape(input, buffer, &mut tape)?;
+        Self::fill_spans(buffer, tape.len());
 
-    #[cfg(feature = "serde_impl")]
-    #[cfg_attr(not(feature = "no-inline"), inline)]
-    fn skip(&mut self) {
-        self.idx += 1;
-    }
-
-    /// Same as `next()` but we pull out the check so we don't need to
-    /// stry every time. Use this only if you know the next element exists!
-    ///
-    /// # Safety
-    ///
-    /// This function is not safe to use, it is meant for internal use
-    /// where it's know the tape isn't finished.
-    #[cfg_attr(not(feature = "no-inline"), inline)]
-    pub unsafe fn next_(&mut self) -> Node<'de> {
-        let r = *unsafe { self.tape.get_kinda_unchecked(self.idx) };
-        self.idx += 1;
-        r
+        Ok(Self {
+            tape,
+            idx: 0,
+            duplicate_keys: buffer.duplicate_keys,
+            #[cfg(feature = "serde_impl")]
+            raw_source: buffer.raw_values.then(|| {
+                serde::RawSource::new(
+                    mem::replace(&mut buffer.input_buffer, AlignedBuf::with_capacity(0)),
+                    input.len(),
+                    mem::take(buffer.structural_indexes_mut()),
+                    input,
+                )
+            }),
+            #[cfg(feature = "serde_impl")]
+            transient: 0..0,
+            _input: PhantomData,
+        })
     }
 
     #[cfg_attr(not(feature = "no-inline"), inline)]
     #[allow(clippy::cast_possible_truncation)]
     pub(crate) unsafe fn _find_structural_bits<S: Stage1Parse>(
         input: &[u8],
-        structural_indexes: &mut Vec<u32>,
+        structural_indexes: &mut Vec<StructuralIndex>,
+        comments: bool,
     ) -> std::result::Result<(), ErrorType> {
         let len = input.len();
         // 8 is a heuristic number to estimate it turns out a rate of 1/8 structural characters
@@ -967,18 +1825,7 @@ impl<'de> Deserializer<'de> {
         // spaces
 
         // persistent state across loop
-        // does the last iteration end with an odd-length sequence of backslashes?
-        // either 0 or 1, but a 64-bit value
-        let mut prev_iter_ends_odd_backslash: u64 = 0;
-        // does the previous iteration end inside a double-quote pair?
-        let mut prev_iter_inside_quote: u64 = 0;
-        // either all zeros or all ones
-        // does the previous iteration end on something that is a predecessor of a
-        // pseudo-structural character - i.e. whitespace or a structural character
-        // effectively the very first char is considered to follow "whitespace" for
-        // the
-        // purposes of pseudo-structural character detection so we initialize to 1
-        let mut prev_iter_ends_pseudo_pred: u64 = 1;
+        let mut state = Stage1State::new().with_comments(comments);
 
         // structurals are persistent state across loop as we flatten them on the
         // subsequent iteration into our array pointed to be base_ptr.
@@ -989,7 +1836,6 @@ impl<'de> Deserializer<'de> {
 
         let lenminus64: usize = len.saturating_sub(64);
         let mut idx: usize = 0;
-        let mut error_mask: u64 = 0; // for unescaped characters within strings (ASCII code points < 0x20)
 
         while idx < lenminus64 {
             /*
@@ -1000,36 +1846,11 @@ impl<'de> Deserializer<'de> {
             let chunk = unsafe { input.get_kinda_unchecked(idx..idx + 64) };
             unsafe { utf8_validator.update_from_chunks(chunk) };
 
-            let input = unsafe { S::new(chunk) };
-            // detect odd sequences of backslashes
-            let odd_ends: u64 =
-                input.find_odd_backslash_sequences(&mut prev_iter_ends_odd_backslash);
-
-            // detect insides of quote pairs ("quote_mask") and also our quote_bits
-            // themselves
-            let mut quote_bits: u64 = 0;
-            let quote_mask: u64 = input.find_quote_mask_and_bits(
-                odd_ends,
-                &mut prev_iter_inside_quote,
-                &mut quote_bits,
-                &mut error_mask,
-            );
-
             // take the previous iterations structural bits, not our current iteration,
             // and flatten
-            unsafe { S::flatten_bits(structural_indexes, idx as u32, structurals) };
-
-            let mut whitespace: u64 = 0;
-            unsafe { input.find_whitespace_and_structurals(&mut whitespace, &mut structurals) };
-
-            // fixup structurals to reflect quotes and add pseudo-structural characters
-            structurals = S::finalize_structurals(
-                structurals,
-                whitespace,
-                quote_mask,
-                quote_bits,
-                &mut prev_iter_ends_pseudo_pred,
-            );
+            let next = unsafe { state.classify::<S>(chunk) };
+            unsafe { S::flatten_bits(structural_indexes, idx as StructuralIndex, structurals) };
+            structurals = next;
             idx += SIMDINPUT_LENGTH;
         }
 
@@ -1045,45 +1866,19 @@ impl<'de> Deserializer<'de> {
             };
             unsafe { utf8_validator.update_from_chunks(&tmpbuf) };
 
-            let input = unsafe { S::new(&tmpbuf) };
-
-            // detect odd sequences of backslashes
-            let odd_ends: u64 =
-                input.find_odd_backslash_sequences(&mut prev_iter_ends_odd_backslash);
-
-            // detect insides of quote pairs ("quote_mask") and also our quote_bits
-            // themselves
-            let mut quote_bits: u64 = 0;
-            let quote_mask: u64 = input.find_quote_mask_and_bits(
-                odd_ends,
-                &mut prev_iter_inside_quote,
-                &mut quote_bits,
-                &mut error_mask,
-            );
-
             // take the previous iterations structural bits, not our current iteration,
             // and flatten
-            unsafe { S::flatten_bits(structural_indexes, idx as u32, structurals) };
-
-            let mut whitespace: u64 = 0;
-            unsafe { input.find_whitespace_and_structurals(&mut whitespace, &mut structurals) };
-
-            // fixup structurals to reflect quotes and add pseudo-structural characters
-            structurals = S::finalize_structurals(
-                structurals,
-                whitespace,
-                quote_mask,
-                quote_bits,
-                &mut prev_iter_ends_pseudo_pred,
-            );
+            let next = unsafe { state.classify::<S>(&tmpbuf) };
+            unsafe { S::flatten_bits(structural_indexes, idx as StructuralIndex, structurals) };
+            structurals = next;
             idx += SIMDINPUT_LENGTH;
         }
         // This test isn't in upstream, for some reason the error mask is et for then.
-        if prev_iter_inside_quote != 0 {
+        if state.inside_quote() || state.inside_comment() {
             return Err(ErrorType::Syntax);
         }
         // finally, flatten out the remaining structurals from the last iteration
-        unsafe { S::flatten_bits(structural_indexes, idx as u32, structurals) };
+        unsafe { S::flatten_bits(structural_indexes, idx as StructuralIndex, structurals) };
 
         // a valid JSON file cannot have zero structural indexes - we should have
         // found something (note that we compare to 1 as we always add the root!)
@@ -1091,7 +1886,7 @@ impl<'de> Deserializer<'de> {
             return Err(ErrorType::Eof);
         }
 
-        if error_mask != 0 {
+        if state.has_error() {
             return Err(ErrorType::Syntax);
         }
 
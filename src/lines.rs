@@ -0,0 +1,396 @@
+//! Newline delimited JSON ([JSON Lines](https://jsonlines.org/) / NDJSON)
+//!
+//! Every line is parsed as a document of its own, reusing the same
+//! [`Buffers`] for all of them. Errors are reported per line and do not end
+//! the iteration, so bad records can be skipped:
+//!
+//! ```rust
+//! use simd_json::{Buffers, JsonLines, prelude::*};
+//!
+//! let mut input = br#"{"level": "info"}
+//! {"level": "warn"
+//!
+//! {"level": "error"}"#
+//!     .to_vec();
+//! let mut buffers = Buffers::default();
+//! let mut levels = Vec::new();
+//! for record in JsonLines::new(&mut input, &mut buffers).borrowed_values() {
+//!     match record {
+//!         Ok(v) => levels.push(v["level"].as_str().unwrap_or_default().to_string()),
+//!         Err(e) => assert_eq!(e.line(), 2),
+//!     }
+//! }
+//! assert_eq!(levels, vec!["info", "error"]);
+//! ```
+
+use crate::{
+    BorrowedValue, Buffers, Error, OwnedValue, Result, Tape, borrowed, owned, to_tape_with_buffers,
+};
+use std::fmt;
+use std::io;
+
+/// An error on a specific line of a JSON lines input
+#[derive(Debug, PartialEq)]
+pub struct LineError {
+    line: usize,
+    error: Error,
+}
+
+impl LineError {
+    /// The line (starting at 1) the error was encountered on
+    #[must_use]
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// The parser error, its index is relative to the start of the line
+    #[must_use]
+    pub fn error(&self) -> &Error {
+        &self.error
+    }
+
+    /// Returns the parser error
+    #[must_use]
+    pub fn into_inner(self) -> Error {
+        self.error
+    }
+}
+
+impl std::error::Error for LineError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+impl fmt::Display for LineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.error)
+    }
+}
+
+#[cfg_attr(not(feature = "no-inline"), inline)]
+fn is_blank(line: &[u8]) -> bool {
+    line.iter()
+        .all(|b| matches!(b, b' ' | b'\t' | b'\r' | b'\n'))
+}
+
+/// Iterator over the lines of a mutable slice, yielding one parsed
+/// document per non blank line.
+///
+/// As with `to_tape` the slice is rewritten in the process and the results
+/// borrow from it. By default a [`Tape`] is produced per line, use
+/// [`JsonLines::borrowed_values`] or [`JsonLines::deserialize`] for other
+/// representations.
+pub struct JsonLines<'de, 'buf, T = Tape<'de>> {
+    rest: &'de mut [u8],
+    line: usize,
+    buffers: &'buf mut Buffers,
+    parse: fn(&'de mut [u8], &mut Buffers) -> Result<T>,
+}
+
+impl<'de, 'buf> JsonLines<'de, 'buf> {
+    /// Creates a new JSON lines iterator over `input`, yielding tapes
+    #[must_use]
+    pub fn new(input: &'de mut [u8], buffers: &'buf mut Buffers) -> Self {
+        Self {
+            rest: input,
+            line: 0,
+            buffers,
+            parse: to_tape_with_buffers,
+        }
+    }
+}
+
+impl<'de, 'buf, T> JsonLines<'de, 'buf, T> {
+    /// Yields a [`BorrowedValue`] per line instead
+    #[must_use]
+    pub fn borrowed_values(self) -> JsonLines<'de, 'buf, BorrowedValue<'de>> {
+        JsonLines {
+            rest: self.rest,
+            line: self.line,
+            buffers: self.buffers,
+            parse: borrowed::to_value_with_buffers,
+        }
+    }
+
+    /// Yields a deserialized `D` per line instead
+    #[cfg(feature = "serde_impl")]
+    #[must_use]
+    pub fn deserialize<D>(self) -> JsonLines<'de, 'buf, D>
+    where
+        D: serde_ext::Deserialize<'de>,
+    {
+        JsonLines {
+            rest: self.rest,
+            line: self.line,
+            buffers: self.buffers,
+            parse: crate::serde::from_slice_with_buffers,
+        }
+    }
+
+    /// The number of the line that was read last, starting at 1
+    #[must_use]
+    pub fn line(&self) -> usize {
+        self.line
+    }
+}
+
+impl<T> Iterator for JsonLines<'_, '_, T> {
+    type Item = std::result::Result<T, LineError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.rest.is_empty() {
+                return None;
+            }
+            let rest = std::mem::take(&mut self.rest);
+            let (line, rest) = match rest.iter().position(|b| *b == b'\n') {
+                Some(end) => {
+                    let (line, rest) = rest.split_at_mut(end);
+                    (line, &mut rest[1..])
+                }
+                None => (rest, &mut [][..]),
+            };
+            self.rest = rest;
+            self.line += 1;
+            if is_blank(line) {
+                continue;
+            }
+            return Some((self.parse)(line, self.buffers).map_err(|error| LineError {
+                line: self.line,
+                error,
+            }));
+        }
+    }
+}
+
+/// Reads JSON lines from a [`io::BufRead`], yielding one owned document per
+/// non blank line.
+///
+/// By default every line becomes an [`OwnedValue`], use
+/// [`JsonLinesReader::deserialize`] for serde types or
+/// [`JsonLinesReader::next_tape`] to get a tape that borrows the internal
+/// line buffer without any further allocation.
+///
+/// An IO error is reported with the line it happened on and ends the
+/// iteration.
+pub struct JsonLinesReader<'buf, R, T = OwnedValue> {
+    rdr: R,
+    data: Vec<u8>,
+    line: usize,
+    buffers: &'buf mut Buffers,
+    parse: for<'de> fn(&'de mut [u8], &mut Buffers) -> Result<T>,
+    failed: bool,
+}
+
+impl<'buf, R> JsonLinesReader<'buf, R>
+where
+    R: io::BufRead,
+{
+    /// Creates a new JSON lines reader, yielding owned values
+    #[must_use]
+    pub fn new(rdr: R, buffers: &'buf mut Buffers) -> Self {
+        Self {
+            rdr,
+            data: Vec::new(),
+            line: 0,
+            buffers,
+            parse: owned::to_value_with_buffers,
+            failed: false,
+        }
+    }
+}
+
+#[cfg(feature = "serde_impl")]
+impl<'buf, R, T> JsonLinesReader<'buf, R, T>
+where
+    R: io::BufRead,
+{
+    /// Yields a deserialized `D` per line instead
+    #[must_use]
+    pub fn deserialize<D>(self) -> JsonLinesReader<'buf, R, D>
+    where
+        D: serde_ext::de::DeserializeOwned,
+    {
+        JsonLinesReader {
+            rdr: self.rdr,
+            data: self.data,
+            line: self.line,
+            buffers: self.buffers,
+            parse: |s, buffers| crate::serde::from_slice_with_buffers(s, buffers),
+            failed: self.failed,
+        }
+    }
+}
+
+impl<R, T> JsonLinesReader<'_, R, T>
+where
+    R: io::BufRead,
+{
+    /// The number of the line that was read last, starting at 1
+    #[must_use]
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Reads the next non blank line, returns `false` at the end of the input
+    fn read_line(&mut self) -> std::result::Result<bool, LineError> {
+        loop {
+            self.data.clear();
+            match self.rdr.read_until(b'\n', &mut self.data) {
+                Ok(0) => return Ok(false),
+                Ok(_) => {
+                    self.line += 1;
+                    if !is_blank(&self.data) {
+                        return Ok(true);
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e) => {
+                    self.failed = true;
+                    return Err(LineError {
+                        line: self.line + 1,
+                        error: e.into(),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Parses the next non blank line into a tape borrowing the internal
+    /// line buffer, it is valid until the next call.
+    pub fn next_tape(&mut self) -> Option<std::result::Result<Tape<'_>, LineError>> {
+        if self.failed {
+            return None;
+        }
+        match self.read_line() {
+            Ok(true) => Some(
+                to_tape_with_buffers(&mut self.data, self.buffers).map_err(|error| LineError {
+                    line: self.line,
+                    error,
+                }),
+            ),
+            Ok(false) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+impl<R, T> Iterator for JsonLinesReader<'_, R, T>
+where
+    R: io::BufRead,
+{
+    type Item = std::result::Result<T, LineError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        match self.read_line() {
+            Ok(true) => {
+                Some(
+                    (self.parse)(&mut self.data, self.buffers).map_err(|error| LineError {
+                        line: self.line,
+                        error,
+                    }),
+                )
+            }
+            Ok(false) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{JsonLines, JsonLinesReader};
+    use crate::{Buffers, ErrorType, OwnedValue, prelude::*};
+
+    const INPUT: &[u8] = b"{\"a\": 1}\r\n\n  \n[1, 2]\n{\"a\": \n\"x\\ny\"\ntrue 1\n  null  ";
+
+    #[test]
+    fn tapes() {
+        let mut input = INPUT.to_vec();
+        let mut buffers = Buffers::default();
+        let mut lines = JsonLines::new(&mut input, &mut buffers);
+        let first = lines.next().expect("line 1").expect("valid");
+        assert_eq!(first.as_value().get("a").and_then(|v| v.as_u8()), Some(1));
+        assert_eq!(lines.line(), 1);
+        let second = lines.next().expect("line 4").expect("valid");
+        assert_eq!(second.as_value().as_array().map(|a| a.len()), Some(2));
+        assert_eq!(lines.line(), 4);
+        let e = lines.next().expect("line 5").expect_err("invalid");
+        assert_eq!(e.line(), 5);
+        let s = lines.next().expect("line 6").expect("valid");
+        assert_eq!(s.as_value().as_str(), Some("x\ny"));
+        let e = lines.next().expect("line 7").expect_err("invalid");
+        assert_eq!(e.line(), 7);
+        assert_eq!(e.error().error(), &ErrorType::TrailingData);
+        assert!(
+            lines
+                .next()
+                .expect("line 8")
+                .expect("valid")
+                .as_value()
+                .is_null()
+        );
+        assert!(lines.next().is_none());
+        // the first tape is still usable after iterating further
+        assert_eq!(first.as_value().get("a").and_then(|v| v.as_u8()), Some(1));
+    }
+
+    #[test]
+    fn borrowed_values() {
+        let mut input = INPUT.to_vec();
+        let mut buffers = Buffers::default();
+        let values: Vec<_> = JsonLines::new(&mut input, &mut buffers)
+            .borrowed_values()
+            .filter_map(Result::ok)
+            .collect();
+        assert_eq!(
+            values,
+            vec![json!({"a": 1}), json!([1, 2]), json!("x\ny"), json!(null)]
+        );
+    }
+
+    #[cfg(feature = "serde_impl")]
+    #[test]
+    fn deserialize() {
+        let mut input = b"[1, 2]\n[3]\n\"nope\"\n[]".to_vec();
+        let mut buffers = Buffers::default();
+        let values: Vec<_> = JsonLines::new(&mut input, &mut buffers)
+            .deserialize::<Vec<u8>>()
+            .map(|r| r.map_err(|e| e.line()))
+            .collect();
+        assert_eq!(
+            values,
+            vec![Ok(vec![1, 2]), Ok(vec![3]), Err(3), Ok(vec![])]
+        );
+    }
+
+    #[test]
+    fn reader() {
+        let mut buffers = Buffers::default();
+        let values: Vec<_> = JsonLinesReader::new(INPUT, &mut buffers).collect();
+        assert_eq!(values.len(), 6);
+        assert_eq!(values[0], Ok(json!({"a": 1})));
+        assert_eq!(values[2].as_ref().map_err(super::LineError::line), Err(5));
+        assert_eq!(values[5], Ok(OwnedValue::null()));
+
+        let mut rdr = JsonLinesReader::new(INPUT, &mut buffers);
+        let tape = rdr.next_tape().expect("line 1").expect("valid");
+        assert_eq!(tape.as_value().get("a").and_then(|v| v.as_u8()), Some(1));
+        assert_eq!(rdr.line(), 1);
+    }
+
+    #[cfg(feature = "serde_impl")]
+    #[test]
+    fn reader_deserialize() {
+        let mut buffers = Buffers::default();
+        let values: Vec<_> = JsonLinesReader::new(&b"[1]\n\n[2, 3]\n"[..], &mut buffers)
+            .deserialize::<Vec<u8>>()
+            .collect();
+        assert_eq!(values, vec![Ok(vec![1]), Ok(vec![2, 3])]);
+    }
+}
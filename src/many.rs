@@ -0,0 +1,378 @@
+//! Parsing many JSON documents out of a single buffer
+//!
+//! This is the equivalent of simdjson's `iterate_many`: stage 1 runs once
+//! over the whole input, the structural indexes are then split into
+//! documents that go through stage 2 one at a time. Documents can be
+//! separated by whitespace (concatenated JSON) or by the ASCII record
+//! separator `0x1E` as in [RFC 7464](https://www.rfc-editor.org/rfc/rfc7464)
+//! JSON text sequences.
+//!
+//! ```rust
+//! use simd_json::{Buffers, parse_many, prelude::*};
+//!
+//! let mut input = br#"{"id": 1} {"id": 2}
+//! [3]"#.to_vec();
+//! let mut buffers = Buffers::default();
+//! let docs = parse_many(&mut input, &mut buffers).unwrap();
+//! let ranges: Vec<_> = docs.map(|doc| doc.unwrap().0).collect();
+//! assert_eq!(ranges, vec![0..9, 10..19, 20..23]);
+//! ```
+
+use crate::error::Position;
+use crate::stage1::locate_error;
+use crate::to_offset;
+use crate::value::borrowed::BorrowDeserializer;
+use crate::{
+    BorrowedValue, Buffers, Deserializer, Error, ErrorType, Result, StructuralIndex, Tape,
+};
+use std::marker::PhantomData;
+use std::ops::Range;
+
+/// The ASCII record separator used by JSON text sequences
+const RS: u8 = 0x1E;
+
+#[cfg_attr(not(feature = "no-inline"), inline)]
+fn is_ws(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\r' | b'\n')
+}
+
+/// Runs stage 1 over `input` and returns an iterator over the documents it
+/// contains, along with their byte range in `input`.
+///
+/// As with `to_tape` the input is rewritten in the process and the results
+/// borrow from it. An invalid document produces an error (with an index
+/// relative to the start of `input`) but does not stop the iteration,
+/// errors found by stage 1 (invalid UTF-8, unterminated strings, ...) fail
+/// the whole input.
+///
+/// # Errors
+///
+/// Will return `Err` if stage 1 fails on `input`.
+pub fn parse_many<'de, 'buf>(
+    input: &'de mut [u8],
+    buffers: &'buf mut Buffers,
+) -> Result<Documents<'de, 'buf>> {
+    if input.len() > buffers.options.input_len {
+        return Err(Error::generic(ErrorType::InputTooLarge));
+    }
+    let mut indexes = Vec::new();
+    let mut separators = Vec::new();
+    match unsafe { Deserializer::find_structural_bits(input, &mut indexes, false) } {
+        Ok(()) => (),
+        // no structural characters means no documents
+        Err(ErrorType::Eof) => indexes.clear(),
+        Err(e) => return Err(locate_error(input, e, false)),
+    }
+
+    if input.contains(&RS) {
+        // A record separator is only a structural character if it follows
+        // whitespace, and the value following it is not if it's a scalar, so
+        // we replace separators with the start of the next value. Strings
+        // can't contain a separator, stage 1 rejects control characters in
+        // them.
+        indexes.retain(|i| input[to_offset(*i)] != RS);
+        for (i, b) in input.iter().enumerate() {
+            if *b != RS {
+                continue;
+            }
+            #[allow(clippy::cast_possible_truncation)]
+            separators.push(i as StructuralIndex);
+            if let Some(next) = input[i + 1..].iter().position(|b| !is_ws(*b)) {
+                let next = i + 1 + next;
+                if input[next] != RS {
+                    #[allow(clippy::cast_possible_truncation)]
+                    indexes.push(next as StructuralIndex);
+                }
+            }
+        }
+        indexes.sort_unstable();
+        indexes.dedup();
+    }
+
+    Ok(Documents {
+        rest: input,
+        rest_start: 0,
+        position: Position::START,
+        indexes,
+        separators,
+        pos: 0,
+        sep: 0,
+        buffers,
+        parse: |de| Ok(de.into_tape()),
+    })
+}
+
+/// Iterator over the documents of an input, see [`parse_many`].
+///
+/// By default a [`Tape`] is produced per document, use
+/// [`Documents::borrowed_values`] or [`Documents::deserialize`] for other
+/// representations.
+pub struct Documents<'de, 'buf, T = Tape<'de>> {
+    rest: &'de mut [u8],
+    rest_start: usize,
+    /// the line and column of `rest_start`, counted before stage 2
+    /// rewrites the documents
+    position: Position,
+    indexes: Vec<StructuralIndex>,
+    separators: Vec<StructuralIndex>,
+    pos: usize,
+    sep: usize,
+    buffers: &'buf mut Buffers,
+    parse: fn(Deserializer<'de>) -> Result<T>,
+}
+
+impl<'de, 'buf, T> Documents<'de, 'buf, T> {
+    /// Yields a [`BorrowedValue`] per document instead
+    #[must_use]
+    pub fn borrowed_values(self) -> Documents<'de, 'buf, BorrowedValue<'de>> {
+        self.with_parser(|de| Ok(BorrowDeserializer::from_deserializer(de).parse()))
+    }
+
+    /// Yields a deserialized `D` per document instead
+    #[cfg(feature = "serde_impl")]
+    #[must_use]
+    pub fn deserialize<D>(self) -> Documents<'de, 'buf, D>
+    where
+        D: serde_ext::Deserialize<'de>,
+    {
+        self.with_parser(|mut de| D::deserialize(&mut de))
+    }
+
+    fn with_parser<U>(self, parse: fn(Deserializer<'de>) -> Result<U>) -> Documents<'de, 'buf, U> {
+        Documents {
+            rest: self.rest,
+            rest_start: self.rest_start,
+            position: self.position,
+            indexes: self.indexes,
+            separators: self.separators,
+            pos: self.pos,
+            sep: self.sep,
+            buffers: self.buffers,
+            parse,
+        }
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn byte(&self, idx: usize) -> u8 {
+        self.rest[idx - self.rest_start]
+    }
+
+    /// The end of a document that ends right before `limit`, not counting
+    /// trailing whitespace
+    fn trim_end(&self, start: usize, limit: usize) -> usize {
+        let mut end = limit;
+        while end > start + 1 && is_ws(self.byte(end - 1)) {
+            end -= 1;
+        }
+        end
+    }
+
+    /// Finds the document starting at structural `self.pos`, returns the
+    /// structural index behind it and its byte range
+    fn find_document(&mut self) -> (usize, Range<usize>) {
+        let first = self.pos;
+        let start = to_offset(self.indexes[first]);
+        while self.sep < self.separators.len() && to_offset(self.separators[self.sep]) < start {
+            self.sep += 1;
+        }
+        let limit = self
+            .separators
+            .get(self.sep)
+            .map_or(self.rest_start + self.rest.len(), |s| to_offset(*s));
+        let mut k = first + 1;
+
+        if !matches!(self.byte(start), b'{' | b'[') {
+            // a scalar ends where the next value starts
+            let next = self
+                .indexes
+                .get(k)
+                .map_or(limit, |i| to_offset(*i).min(limit));
+            return (k, start..self.trim_end(start, next));
+        }
+
+        let mut depth: usize = 1;
+        while let Some(idx) = self.indexes.get(k).map(|i| to_offset(*i)) {
+            if idx >= limit {
+                break;
+            }
+            k += 1;
+            match self.byte(idx) {
+                b'{' | b'[' => depth += 1,
+                b'}' | b']' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return (k, start..idx + 1);
+                    }
+                }
+                _ => (),
+            }
+        }
+        // the document is truncated, stage 2 will tell what is missing
+        (k, start..self.trim_end(start, limit))
+    }
+}
+
+impl<T> Iterator for Documents<'_, '_, T> {
+    type Item = Result<(Range<usize>, T)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos >= self.indexes.len() {
+            return None;
+        }
+        let first = self.pos;
+        let (end, range) = self.find_document();
+        self.pos = end;
+
+        let rest = std::mem::take(&mut self.rest);
+        let (gap, rest) = rest.split_at_mut(range.start - self.rest_start);
+        let (doc, rest) = rest.split_at_mut(range.len());
+        let doc_start = self.position.advance(gap);
+        self.position = doc_start.advance(doc);
+        self.rest = rest;
+        self.rest_start = range.end;
+
+        let structural_indexes = self.buffers.structural_indexes_mut();
+        structural_indexes.clear();
+        #[allow(clippy::cast_possible_truncation)]
+        let start = range.start as StructuralIndex;
+        structural_indexes.extend(self.indexes[first..end].iter().map(|i| *i - start));
+
+        let mut tape = Vec::with_capacity(end - first);
+        let res =
+            Deserializer::fill_tape_from_structurals(doc, self.buffers, &mut tape).and_then(|_| {
+                (self.parse)(Deserializer {
+                    tape,
+                    idx: 0,
+                    duplicate_keys: self.buffers.duplicate_keys,
+                    #[cfg(feature = "serde_impl")]
+                    raw_source: None,
+                    #[cfg(feature = "serde_impl")]
+                    transient: 0..0,
+                    _input: PhantomData,
+                })
+            });
+        Some(match res {
+            Ok(v) => Ok((range, v)),
+            Err(e) => Err(e.offset_by(range.start, doc_start)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_many;
+    use crate::{Buffers, ErrorType, prelude::*};
+
+    fn ranges(input: &[u8]) -> Vec<Result<std::ops::Range<usize>, usize>> {
+        let mut input = input.to_vec();
+        let mut buffers = Buffers::default();
+        parse_many(&mut input, &mut buffers)
+            .expect("stage 1")
+            .map(|r| r.map(|(r, _)| r).map_err(|e| e.index()))
+            .collect()
+    }
+
+    #[test]
+    fn empty() {
+        assert!(ranges(b"").is_empty());
+        assert!(ranges(b" \n\t ").is_empty());
+        assert!(ranges(b"\x1e\n\x1e").is_empty());
+    }
+
+    #[test]
+    fn concatenated() {
+        let mut input = br#"{"a": [1, {"b": "]}"}]}[]"x y" 1 true
+null  -1.5e2{"c":{}}"#
+            .to_vec();
+        let mut buffers = Buffers::default();
+        let docs: Vec<_> = parse_many(&mut input, &mut buffers)
+            .expect("stage 1")
+            .borrowed_values()
+            .collect::<crate::Result<_>>()
+            .expect("valid documents");
+        let values: Vec<_> = docs.iter().map(|(_, v)| v.clone()).collect();
+        assert_eq!(
+            values,
+            vec![
+                json!({"a": [1, {"b": "]}"}]}),
+                json!([]),
+                json!("x y"),
+                json!(1),
+                json!(true),
+                json!(null),
+                json!(-150.0),
+                json!({"c": {}}),
+            ]
+        );
+        let ranges: Vec<_> = docs.into_iter().map(|(r, _)| r).collect();
+        assert_eq!(
+            ranges,
+            vec![
+                0..23,
+                23..25,
+                25..30,
+                31..32,
+                33..37,
+                38..42,
+                44..50,
+                50..58
+            ]
+        );
+    }
+
+    #[test]
+    fn text_sequences() {
+        assert_eq!(
+            ranges(b"\x1e{\"a\":1}\n\x1e1\n\x1e\"s\"\n\x1etrue\x1e[]"),
+            vec![Ok(1..8), Ok(10..11), Ok(13..16), Ok(18..22), Ok(23..25)]
+        );
+        // a truncated document does not affect the next one
+        let r = ranges(b"\x1e{\"a\":\n\x1e[1]\n\x1e12\n");
+        assert!(r[0].is_err());
+        assert_eq!(r[1..], [Ok(8..11), Ok(13..15)]);
+    }
+
+    #[test]
+    fn errors_continue() {
+        let mut input = br#"[1, 2] {"a" 1} [3] tru"#.to_vec();
+        let mut buffers = Buffers::default();
+        let docs: Vec<_> = parse_many(&mut input, &mut buffers)
+            .expect("stage 1")
+            .collect();
+        assert_eq!(docs.len(), 4);
+        assert!(docs[0].is_ok());
+        let e = docs[1].as_ref().expect_err("invalid");
+        assert_eq!(e.error(), &ErrorType::ExpectedObjectColon);
+        assert_eq!(e.index(), 12);
+        assert_eq!(
+            docs[2]
+                .as_ref()
+                .map(|(_, t)| t.as_value().get_idx(0).and_then(|v| v.as_u8())),
+            Ok(Some(3))
+        );
+        assert!(docs[3].is_err());
+    }
+
+    #[cfg(feature = "serde_impl")]
+    #[test]
+    fn deserialize() {
+        let mut input = b"[1] [2, 3] []".to_vec();
+        let mut buffers = Buffers::default();
+        let docs: Vec<Vec<u8>> = parse_many(&mut input, &mut buffers)
+            .expect("stage 1")
+            .deserialize()
+            .map(|r| r.map(|(_, v)| v))
+            .collect::<crate::Result<_>>()
+            .expect("valid documents");
+        assert_eq!(docs, vec![vec![1], vec![2, 3], vec![]]);
+    }
+
+    #[test]
+    fn stage1_errors() {
+        let mut input = br#"{} "unterminated"#.to_vec();
+        let mut buffers = Buffers::default();
+        assert!(parse_many(&mut input, &mut buffers).is_err());
+        let mut input = b"{} \"\xff\"".to_vec();
+        assert!(parse_many(&mut input, &mut buffers).is_err());
+    }
+}
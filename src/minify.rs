@@ -0,0 +1,303 @@
+use crate::stage1::Stage1State;
+use crate::{Deserializer, ErrorType, SIMDINPUT_LENGTH, Stage1Parse, impls};
+
+/// Copies `input` to `out` block by block, leaving out all whitespace that
+/// isn't inside of a string.
+#[cfg_attr(not(feature = "no-inline"), inline)]
+unsafe fn minify_with<S: Stage1Parse>(
+    input: &[u8],
+    out: &mut Vec<u8>,
+) -> std::result::Result<(), ErrorType> {
+    let start_len = out.len();
+    out.reserve(input.len());
+    let mut state = Stage1State::new();
+    let mut chunks = input.chunks_exact(SIMDINPUT_LENGTH);
+    for chunk in &mut chunks {
+        let keep = unsafe { state.significant::<S>(chunk) };
+        copy_kept(chunk, keep, out);
+    }
+    let rest = chunks.remainder();
+    if !rest.is_empty() {
+        // pad the last block with whitespace and ignore the padding
+        let mut tmpbuf = [b' '; SIMDINPUT_LENGTH];
+        tmpbuf[..rest.len()].copy_from_slice(rest);
+        let keep = unsafe { state.significant::<S>(&tmpbuf) } & (u64::MAX >> (64 - rest.len()));
+        copy_kept(rest, keep, out);
+    }
+    if state.inside_quote() {
+        out.truncate(start_len);
+        return Err(ErrorType::Syntax);
+    }
+    Ok(())
+}
+
+/// Copies the runs of bytes with a bit set in `keep`
+#[cfg_attr(not(feature = "no-inline"), inline)]
+fn copy_kept(chunk: &[u8], mut keep: u64, out: &mut Vec<u8>) {
+    if keep == u64::MAX {
+        out.extend_from_slice(chunk);
+        return;
+    }
+    while keep != 0 {
+        let start = keep.trailing_zeros() as usize;
+        let len = (!(keep >> start)).trailing_zeros() as usize;
+        out.extend_from_slice(&chunk[start..start + len]);
+        keep &= !((u64::MAX >> (64 - len)) << start);
+    }
+}
+
+/// architecture dependant minification
+impl Deserializer<'_> {
+    #[cfg(all(
+        feature = "runtime-detection",
+        any(target_arch = "x86_64", target_arch = "x86"),
+    ))]
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    pub(crate) fn minify(input: &[u8], out: &mut Vec<u8>) -> std::result::Result<(), ErrorType> {
+        use crate::Implementation;
+
+        // See `find_structural_bits` for why these wrappers exist.
+        #[target_feature(
+            enable = "avx512f",
+            enable = "avx512bw",
+            enable = "avx2",
+            enable = "pclmulqdq"
+        )]
+        unsafe fn minify_avx512(
+            input: &[u8],
+            out: &mut Vec<u8>,
+        ) -> std::result::Result<(), ErrorType> {
+            unsafe { minify_with::<impls::avx512::SimdInput>(input, out) }
+        }
+
+        #[target_feature(enable = "avx2", enable = "pclmulqdq")]
+        unsafe fn minify_avx2(
+            input: &[u8],
+            out: &mut Vec<u8>,
+        ) -> std::result::Result<(), ErrorType> {
+            unsafe { minify_with::<impls::avx2::SimdInput>(input, out) }
+        }
+
+        #[target_feature(enable = "sse4.2")]
+        unsafe fn minify_sse42(
+            input: &[u8],
+            out: &mut Vec<u8>,
+        ) -> std::result::Result<(), ErrorType> {
+            unsafe { minify_with::<impls::sse42::SimdInput>(input, out) }
+        }
+
+        unsafe {
+            match Implementation::selected() {
+                Implementation::AVX512 => minify_avx512(input, out),
+                Implementation::AVX2 => minify_avx2(input, out),
+                Implementation::SSE42 => minify_sse42(input, out),
+                _ => {
+                    #[cfg(feature = "portable")]
+                    let r = minify_with::<impls::portable::SimdInput>(input, out);
+                    #[cfg(not(feature = "portable"))]
+                    let r = minify_with::<impls::native::SimdInput>(input, out);
+                    r
+                }
+            }
+        }
+    }
+
+    #[cfg(not(any(
+        all(
+            feature = "runtime-detection",
+            any(target_arch = "x86_64", target_arch = "x86")
+        ),
+        feature = "portable",
+        target_feature = "avx2",
+        target_feature = "sse4.2",
+        target_feature = "simd128",
+        target_arch = "aarch64",
+    )))]
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    pub(crate) fn minify(input: &[u8], out: &mut Vec<u8>) -> std::result::Result<(), ErrorType> {
+        unsafe { minify_with::<impls::native::SimdInput>(input, out) }
+    }
+
+    #[cfg(all(feature = "portable", not(feature = "runtime-detection")))]
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    pub(crate) fn minify(input: &[u8], out: &mut Vec<u8>) -> std::result::Result<(), ErrorType> {
+        unsafe { minify_with::<impls::portable::SimdInput>(input, out) }
+    }
+
+    #[cfg(all(
+        target_feature = "avx2",
+        not(feature = "portable"),
+        not(feature = "runtime-detection"),
+    ))]
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    pub(crate) fn minify(input: &[u8], out: &mut Vec<u8>) -> std::result::Result<(), ErrorType> {
+        unsafe { minify_with::<impls::avx2::SimdInput>(input, out) }
+    }
+
+    #[cfg(all(
+        target_feature = "sse4.2",
+        not(target_feature = "avx2"),
+        not(feature = "runtime-detection"),
+        not(feature = "portable"),
+    ))]
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    pub(crate) fn minify(input: &[u8], out: &mut Vec<u8>) -> std::result::Result<(), ErrorType> {
+        unsafe { minify_with::<impls::sse42::SimdInput>(input, out) }
+    }
+
+    #[cfg(all(target_arch = "aarch64", not(feature = "portable")))]
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    pub(crate) fn minify(input: &[u8], out: &mut Vec<u8>) -> std::result::Result<(), ErrorType> {
+        unsafe { minify_with::<impls::neon::SimdInput>(input, out) }
+    }
+
+    #[cfg(all(target_feature = "simd128", not(feature = "portable")))]
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    pub(crate) fn minify(input: &[u8], out: &mut Vec<u8>) -> std::result::Result<(), ErrorType> {
+        unsafe { minify_with::<impls::simd128::SimdInput>(input, out) }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::minify_with;
+    use crate::{ErrorType, Stage1Parse, impls, minify};
+
+    /// Byte at a time reference minifier
+    fn reference(input: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut in_string = false;
+        let mut escaped = false;
+        for &b in input {
+            if in_string {
+                if escaped {
+                    escaped = false;
+                } else if b == b'\\' {
+                    escaped = true;
+                } else if b == b'"' {
+                    in_string = false;
+                }
+            } else if b == b'"' {
+                in_string = true;
+            } else if matches!(b, b' ' | b'\t' | b'\n' | b'\r') {
+                continue;
+            }
+            out.push(b);
+        }
+        out
+    }
+
+    fn inputs() -> Vec<Vec<u8>> {
+        let mut inputs: Vec<Vec<u8>> = vec![
+            b"".to_vec(),
+            b" \t\r\n".to_vec(),
+            br#" { "a b" : [ 1 , 2.5e3 , "\" x \\" , true ] ,"c\t": null } "#.to_vec(),
+            r#"  ["é 😀",  "\\\\ \" ",   tru e ]  "#.as_bytes().to_vec(),
+        ];
+        // strings and escapes crossing block boundaries
+        for pad in 0..70 {
+            let mut input = vec![b' '; pad];
+            input.extend_from_slice(br#"[ "x  \\\" y  "  ,  "\\"  ,  1 ]   "#);
+            inputs.push(input);
+        }
+        for entry in std::fs::read_dir("data").expect("data dir") {
+            let path = entry.expect("dir entry").path();
+            if path.extension().is_some_and(|e| e == "json") {
+                inputs.push(std::fs::read(&path).expect("readable"));
+            }
+        }
+        inputs
+    }
+
+    fn test_impl<S: Stage1Parse>() {
+        for input in inputs() {
+            let mut out = b"prefix".to_vec();
+            unsafe { minify_with::<S>(&input, &mut out) }.expect("terminated strings");
+            assert_eq!(&out[..6], b"prefix");
+            assert_eq!(
+                out[6..],
+                reference(&input),
+                "{}",
+                String::from_utf8_lossy(&input)
+            );
+        }
+    }
+
+    #[test]
+    fn minify_matches_reference() {
+        for input in inputs() {
+            let mut out = Vec::new();
+            minify(&input, &mut out).expect("terminated strings");
+            assert_eq!(out, reference(&input));
+        }
+    }
+
+    #[test]
+    fn minify_keeps_values() -> crate::Result<()> {
+        let mut input = std::fs::read("data/twitter.json").expect("readable");
+        let mut out = Vec::new();
+        minify(&input, &mut out)?;
+        assert!(out.len() < input.len());
+        assert_eq!(
+            crate::to_owned_value(&mut out)?,
+            crate::to_owned_value(&mut input)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn minify_unterminated_string() {
+        let mut out = b"[]".to_vec();
+        let err = minify(br#"[ "abc ]"#, &mut out).expect_err("unterminated");
+        assert_eq!(err.error(), &ErrorType::Syntax);
+        assert_eq!(out, b"[]");
+    }
+
+    #[cfg(all(not(target_arch = "aarch64"), not(target_feature = "simd128")))]
+    #[test]
+    fn minify_native() {
+        test_impl::<impls::native::SimdInput>();
+    }
+
+    #[cfg(feature = "portable")]
+    #[test]
+    fn minify_portable() {
+        test_impl::<impls::portable::SimdInput>();
+    }
+
+    #[cfg(target_feature = "avx2")]
+    #[test]
+    fn minify_avx() {
+        if std::is_x86_feature_detected!("avx2") {
+            test_impl::<impls::avx2::SimdInput>();
+        }
+    }
+
+    #[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+    #[test]
+    fn minify_avx512() {
+        if std::is_x86_feature_detected!("avx512f") && std::is_x86_feature_detected!("avx512bw") {
+            test_impl::<impls::avx512::SimdInput>();
+        }
+    }
+
+    #[cfg(target_feature = "sse4.2")]
+    #[test]
+    fn minify_sse() {
+        if std::is_x86_feature_detected!("sse4.2") {
+            test_impl::<impls::sse42::SimdInput>();
+        }
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    #[test]
+    fn minify_aarch64() {
+        test_impl::<impls::neon::SimdInput>();
+    }
+
+    #[cfg(target_feature = "simd128")]
+    #[test]
+    fn minify_simd128() {
+        test_impl::<impls::simd128::SimdInput>();
+    }
+}
@@ -0,0 +1,404 @@
+//! On-demand access to a JSON document
+//!
+//! Instead of building a tape, the [`Document`] keeps the structural indexes
+//! found by stage 1 and navigates them when asked to. Strings are unescaped
+//! and numbers are parsed only when they are accessed, values that are never
+//! looked at (for example the members of an object before the key that is
+//! requested) are skipped by counting brackets on the structural indexes.
+//!
+//! This makes picking a few fields out of a large document considerably
+//! cheaper than parsing all of it. The input is never modified, strings that
+//! contain escape sequences are unescaped into memory owned by the document.
+//!
+//! Only stage 1 validates the input ahead of time, the rest is validated
+//! when it's accessed: accessors return `None` for malformed values.
+//!
+//! ```rust
+//! use simd_json::{ondemand::Document, prelude::*};
+//!
+//! let input = br#"{"user": {"name": "ada", "tags": ["a", "b"]}, "id": 42}"#;
+//! let doc = Document::parse(input).unwrap();
+//! let root = doc.root();
+//! assert_eq!(root.get_u64("id"), Some(42));
+//! let user = root.get("user").unwrap();
+//! assert_eq!(user.get_str("name"), Some("ada"));
+//! assert_eq!(user.get("tags").and_then(|t| t.get_idx(1)).and_then(|t| t.into_string()), Some("b"));
+//! ```
+
+mod array;
+mod object;
+mod trait_impls;
+
+pub use array::{Array, Iter as ArrayIter};
+pub use object::{Iter as ObjectIter, Keys, Object, Values};
+
+use crate::stage1::locate_error;
+use crate::stage2::{is_valid_false_atom, is_valid_null_atom, is_valid_true_atom, non_finite_atom};
+use crate::stringparse::unescape;
+use crate::to_offset;
+use crate::{Buffers, Deserializer, Error, ErrorType, Result, StaticNode};
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// A JSON document that was run through stage 1 only.
+pub struct Document<'input> {
+    input: &'input [u8],
+    buffers: Buffers,
+    /// unescaped strings, keyed by the index of their opening quote
+    strings: RefCell<HashMap<usize, Box<str>>>,
+}
+
+impl std::fmt::Debug for Document<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Document")
+            .field("len", &self.input.len())
+            .field("structurals", &self.buffers.structural_indexes.len())
+            .finish()
+    }
+}
+
+impl<'input> Document<'input> {
+    /// Runs stage 1 over `input` and returns a document to navigate it.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if stage 1 finds `input` to be invalid, this covers
+    /// invalid UTF-8, unterminated strings and inputs without any value.
+    pub fn parse(input: &'input [u8]) -> Result<Self> {
+        Self::parse_with_buffers(input, Buffers::new(input.len()))
+    }
+
+    /// Same as [`Document::parse`] but uses the given buffers, they can be
+    /// taken back with [`Document::into_buffers`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if stage 1 finds `input` to be invalid.
+    pub fn parse_with_buffers(input: &'input [u8], mut buffers: Buffers) -> Result<Self> {
+        if input.len() > buffers.options.input_len {
+            return Err(Error::generic(ErrorType::InputTooLarge));
+        }
+        unsafe { buffers.find_structural_bits(input, false) }
+            .map_err(|e| locate_error(input, e, false))?;
+        buffers.load_input(input);
+        Ok(Self {
+            input,
+            buffers,
+            strings: RefCell::new(HashMap::new()),
+        })
+    }
+
+    /// Returns the buffers for reuse
+    #[must_use]
+    pub fn into_buffers(self) -> Buffers {
+        self.buffers
+    }
+
+    /// The root value of the document
+    #[must_use]
+    pub fn root(&self) -> Value<'_, 'input> {
+        Value { doc: self, pos: 0 }
+    }
+
+    /// The root value of the document, checking that nothing follows it
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if there is more than one value in the document.
+    pub fn root_checked(&self) -> Result<Value<'_, 'input>> {
+        let end = self.skip(0);
+        match end {
+            Some(end) if end == self.buffers.structural_indexes.len() => Ok(self.root()),
+            Some(end) => {
+                let idx = self.idx(end).unwrap_or_default();
+                Err(Error::new(
+                    idx,
+                    self.input.get(idx).map(|c| *c as char),
+                    ErrorType::TrailingData,
+                ))
+            }
+            None => Err(Error::new(self.input.len(), None, ErrorType::Eof)),
+        }
+    }
+
+    /// The byte offset of the structural character at `pos`
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn idx(&self, pos: usize) -> Option<usize> {
+        self.buffers
+            .structural_indexes
+            .get(pos)
+            .map(|i| to_offset(*i))
+    }
+
+    /// The structural character at `pos`
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn byte(&self, pos: usize) -> Option<u8> {
+        self.input.get(self.idx(pos)?).copied()
+    }
+
+    /// The position of the structural following the value at `pos`, this
+    /// skips over entire arrays and objects.
+    fn skip(&self, pos: usize) -> Option<usize> {
+        if !matches!(self.byte(pos)?, b'{' | b'[') {
+            return Some(pos + 1);
+        }
+        let mut depth: usize = 1;
+        let mut pos = pos + 1;
+        loop {
+            let c = self.byte(pos)?;
+            pos += 1;
+            match c {
+                b'{' | b'[' => depth += 1,
+                b'}' | b']' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(pos);
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// The string starting with the quote at `pos`
+    fn string(&self, pos: usize) -> Option<&str> {
+        let idx = self.idx(pos)?;
+        let raw = self.input.get(idx + 1..)?;
+        if self.input[idx] != b'"' {
+            return None;
+        }
+        let mut len = 0;
+        let mut escaped = false;
+        loop {
+            match raw.get(len)? {
+                b'"' => break,
+                b'\\' => {
+                    escaped = true;
+                    len += 2;
+                }
+                _ => len += 1,
+            }
+        }
+        if !escaped {
+            // Safety: stage 1 validated the input as UTF-8 and quotes are
+            // always on a character boundary.
+            return Some(unsafe { std::str::from_utf8_unchecked(&raw[..len]) });
+        }
+        if let Some(s) = self.strings.borrow().get(&idx) {
+            let s: *const str = &raw const **s;
+            // Safety: strings are never removed from the map and boxed so
+            // they don't move, the reference is bound to the lifetime of
+            // the document
+            return Some(unsafe { &*s });
+        }
+        // the padded copy of the input so reading a unicode escape never goes
+        // out of bounds
+        let s = unescape(self.buffers.input_buffer.get(idx + 1..)?, len)?.into_boxed_str();
+        let ptr: *const str = &raw const *s;
+        self.strings.borrow_mut().insert(idx, s);
+        // Safety: see above
+        Some(unsafe { &*ptr })
+    }
+
+    /// The scalar (other than a string) at `pos`
+    fn static_node(&self, pos: usize) -> Option<StaticNode> {
+        let idx = self.idx(pos)?;
+        let input = &self.buffers.input_buffer;
+        match *input.get(idx)? {
+            b't' if is_valid_true_atom(input.get(idx..)?) => Some(StaticNode::Bool(true)),
+            b'f' if is_valid_false_atom(input.get(idx..)?) => Some(StaticNode::Bool(false)),
+            b'n' if is_valid_null_atom(input.get(idx..)?) => Some(StaticNode::Null),
+            b'-' if self.buffers.non_finite => {
+                Deserializer::parse_negative::<true>(idx, input).ok()
+            }
+            b'-' => Deserializer::parse_negative::<false>(idx, input).ok(),
+            b'0'..=b'9' => Deserializer::parse_number(idx, input, false).ok(),
+            _ if self.buffers.non_finite => non_finite_atom(input.get(idx..)?),
+            _ => None,
+        }
+    }
+}
+
+/// A handle to a value in a [`Document`].
+///
+/// Handles are cheap to copy, nothing is parsed until one of the accessors
+/// is called.
+#[derive(Clone, Copy)]
+pub struct Value<'doc, 'input> {
+    doc: &'doc Document<'input>,
+    pos: usize,
+}
+
+impl std::fmt::Debug for Value<'_, '_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Value")
+            .field("offset", &self.offset())
+            .finish()
+    }
+}
+
+impl<'input> Value<'_, 'input> {
+    /// The byte offset of the value in the input
+    #[must_use]
+    pub fn offset(&self) -> Option<usize> {
+        self.doc.idx(self.pos)
+    }
+
+    /// The raw JSON text of the value, including nested values but
+    /// excluding surrounding whitespace
+    #[must_use]
+    pub fn raw_json(&self) -> Option<&'input str> {
+        let start = self.doc.idx(self.pos)?;
+        let next = self.doc.skip(self.pos)?;
+        let end = if matches!(self.doc.byte(self.pos)?, b'{' | b'[') {
+            self.doc.idx(next - 1)? + 1
+        } else {
+            let mut end = self.doc.idx(next).unwrap_or(self.doc.input.len());
+            while end > start + 1 && matches!(self.doc.input[end - 1], b' ' | b'\t' | b'\r' | b'\n')
+            {
+                end -= 1;
+            }
+            end
+        };
+        std::str::from_utf8(self.doc.input.get(start..end)?).ok()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Document, Value};
+    use crate::{OwnedValue, prelude::*};
+
+    /// Converts an on-demand value into an owned value, touching every part
+    fn to_owned(v: Value) -> OwnedValue {
+        if let Some(a) = v.as_array() {
+            OwnedValue::Array(Box::new(a.iter().map(to_owned).collect()))
+        } else if let Some(o) = v.as_object() {
+            let mut res = crate::owned::Object::default();
+            for (k, v) in &o {
+                res.insert(k.into(), to_owned(v));
+            }
+            OwnedValue::Object(Box::new(res))
+        } else if let Some(s) = v.into_string() {
+            OwnedValue::from(s)
+        } else {
+            OwnedValue::Static(v.as_static().expect("valid scalar"))
+        }
+    }
+
+    #[test]
+    fn matches_dom() {
+        for file in [
+            "twitter.json",
+            "twitterescaped.json",
+            "canada.json",
+            "citm_catalog.json",
+            "github_events.json",
+            "numbers.json",
+        ] {
+            let input = std::fs::read(format!("data/{file}")).expect("data file");
+            let expected = crate::to_owned_value(&mut input.clone()).expect("valid json");
+            let doc = Document::parse(&input).expect("valid json");
+            assert_eq!(
+                to_owned(doc.root_checked().expect("one value")),
+                expected,
+                "{file}"
+            );
+        }
+    }
+
+    #[test]
+    fn scalars() {
+        let input = r#"[1, -2, 3.5, true, false, null, "x\u00e9\n\ud83d\ude00é", "plain", 18446744073709551615]"#
+            .as_bytes();
+        let doc = Document::parse(input).expect("valid json");
+        let a = doc.root().as_array().expect("array");
+        assert_eq!(a.len(), 9);
+        assert_eq!(a.get(0).and_then(|v| v.as_u8()), Some(1));
+        assert_eq!(a.get(1).and_then(|v| v.as_i64()), Some(-2));
+        assert_eq!(a.get(2).and_then(|v| v.as_f64()), Some(3.5));
+        assert_eq!(a.get(3).and_then(|v| v.as_bool()), Some(true));
+        assert_eq!(a.get(4).and_then(|v| v.as_bool()), Some(false));
+        assert!(a.get(5).is_some_and(|v| v.is_null()));
+        assert_eq!(
+            a.get(6).and_then(ValueIntoString::into_string),
+            Some("xé\n😀é")
+        );
+        // unescaped strings are cached
+        let s1 = a
+            .get(6)
+            .and_then(ValueIntoString::into_string)
+            .expect("string");
+        let s2 = a
+            .get(6)
+            .and_then(ValueIntoString::into_string)
+            .expect("string");
+        assert!(std::ptr::eq(s1, s2));
+        assert_eq!(
+            a.get(7).and_then(ValueIntoString::into_string),
+            Some("plain")
+        );
+        assert_eq!(a.get(8).and_then(|v| v.as_u64()), Some(u64::MAX));
+        assert!(a.get(9).is_none());
+        assert_eq!(a.get(8).map(|v| v.value_type()), Some(ValueType::U64));
+    }
+
+    #[test]
+    fn objects() {
+        let input = br#"{"a": {"deep": [[[{"x": 1}]]]}, "b\"c": 2, "d": [1, 2, 3], "e": {}}"#;
+        let doc = Document::parse(input).expect("valid json");
+        let root = doc.root();
+        assert_eq!(root.get_u8("b\"c"), Some(2));
+        assert!(root.get("missing").is_none());
+        assert!(root.contains_key("e"));
+        assert_eq!(
+            root.get("e").and_then(|e| e.as_object()).map(|o| o.len()),
+            Some(0)
+        );
+        assert_eq!(
+            root.get("a").and_then(|a| a.raw_json()),
+            Some(r#"{"deep": [[[{"x": 1}]]]}"#)
+        );
+        assert_eq!(root.get("d").and_then(|d| d.raw_json()), Some("[1, 2, 3]"));
+        assert_eq!(
+            root.get("d")
+                .and_then(|d| d.get_idx(2))
+                .and_then(|v| v.raw_json()),
+            Some("3")
+        );
+        let keys: Vec<_> = root.as_object().expect("object").keys().collect();
+        assert_eq!(keys, vec!["a", "b\"c", "d", "e"]);
+        assert_eq!(root.value_type(), ValueType::Object);
+        assert_eq!(root.get_idx(0).map(|_| ()), None);
+    }
+
+    #[test]
+    fn malformed() {
+        // stage 1 accepts these, access reports `None`
+        let input = br#"{"a": tru, "b": [1 2], "c": 01, "d": "\x"}"#;
+        let doc = Document::parse(input).expect("stage 1");
+        let root = doc.root();
+        assert!(root.get("a").is_some_and(|a| a.as_bool().is_none()));
+        assert!(root.get("c").is_some_and(|c| c.as_u64().is_none()));
+        assert!(root.get("d").is_some_and(|d| d.as_str().is_none()));
+        let b = root.get("b").and_then(|b| b.as_array()).expect("array");
+        assert_eq!(b.len(), 1);
+
+        let doc = Document::parse(b"[1, [2, 3]").expect("stage 1");
+        assert!(doc.root().as_array().expect("array").get(1).is_some());
+        assert!(doc.root_checked().is_err());
+        assert!(doc.root().raw_json().is_none());
+        let doc = Document::parse(b"1 2").expect("stage 1");
+        assert!(doc.root_checked().is_err());
+        assert!(Document::parse(b"   ").is_err());
+        assert!(Document::parse(b"\"abc").is_err());
+    }
+
+    #[test]
+    fn buffer_reuse() {
+        let doc = Document::parse(br#"{"a": 1}"#).expect("valid json");
+        let buffers = doc.into_buffers();
+        let doc = Document::parse_with_buffers(b"[2]", buffers).expect("valid json");
+        assert_eq!(doc.root().get_idx(0).and_then(|v| v.as_u8()), Some(2));
+    }
+}
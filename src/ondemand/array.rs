@@ -0,0 +1,101 @@
+use super::Value;
+
+#[derive(Clone, Copy)]
+/// Wrapper around an on-demand value that allows interacting with it via a `Array`-like API.
+pub struct Array<'doc, 'input>(pub(super) Value<'doc, 'input>);
+
+/// Iterator over the elements of an on-demand array
+pub struct Iter<'doc, 'input> {
+    value: Value<'doc, 'input>,
+    // the position of the next element, `None` once the array ended
+    next: Option<usize>,
+}
+
+impl<'doc, 'input> Iterator for Iter<'doc, 'input> {
+    type Item = Value<'doc, 'input>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let pos = self.next?;
+        let doc = self.value.doc;
+        // anything but a comma after the element ends the array
+        self.next = doc
+            .skip(pos)
+            .filter(|next| doc.byte(*next) == Some(b','))
+            .map(|next| next + 1);
+        Some(Value { doc, pos })
+    }
+}
+
+// value_trait::Array for
+impl<'doc, 'input> Array<'doc, 'input> {
+    /// Gets a value based on n index, returns `None` if the array doesn't
+    /// contain the index it was asked for. The elements before it are
+    /// skipped without being parsed.
+    #[must_use]
+    pub fn get(&self, idx: usize) -> Option<Value<'doc, 'input>> {
+        self.iter().nth(idx)
+    }
+
+    /// Iterates over the values
+    #[must_use]
+    pub fn iter(&self) -> Iter<'doc, 'input> {
+        let Value { doc, pos } = self.0;
+        let next = (doc.byte(pos + 1) != Some(b']')).then_some(pos + 1);
+        Iter {
+            value: self.0,
+            next,
+        }
+    }
+
+    /// Number of elements, this has to walk the array
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    /// Returns if the array is empty
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+}
+
+impl<'doc, 'input> IntoIterator for &Array<'doc, 'input> {
+    type IntoIter = Iter<'doc, 'input>;
+    type Item = Value<'doc, 'input>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ondemand::Document;
+    use value_trait::base::ValueAsScalar;
+
+    #[test]
+    fn get_nested() -> crate::Result<()> {
+        let doc = Document::parse(b"[1,[2,[3]],4,{}]")?;
+        let a = doc.root().as_array().expect("is an array");
+        assert_eq!(a.len(), 4);
+        assert_eq!(a.get(0).and_then(|v| v.as_u64()), Some(1));
+        let a1 = a.get(1).and_then(|v| v.as_array()).expect("is an array");
+        assert_eq!(a1.get(0).and_then(|v| v.as_u64()), Some(2));
+        assert_eq!(a1.len(), 2);
+        assert_eq!(a.get(2).and_then(|v| v.as_u64()), Some(4));
+        assert!(a.get(3).is_some_and(|v| v.is_object()));
+        assert!(a.get(4).is_none());
+        let v = a.iter().map(|v| v.as_u8()).collect::<Vec<_>>();
+        assert_eq!(v, vec![Some(1), None, Some(4), None]);
+        Ok(())
+    }
+
+    #[test]
+    fn empty() -> crate::Result<()> {
+        let doc = Document::parse(b"[ ]")?;
+        let a = doc.root().as_array().expect("is an array");
+        assert!(a.is_empty());
+        assert_eq!(a.iter().count(), 0);
+        Ok(())
+    }
+}
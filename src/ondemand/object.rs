@@ -0,0 +1,147 @@
+use std::{borrow::Borrow, hash::Hash};
+
+use super::Value;
+
+#[derive(Clone, Copy)]
+/// Wrapper around an on-demand value that allows interacting with it via a `Object`-like API.
+pub struct Object<'doc, 'input>(pub(super) Value<'doc, 'input>);
+
+/// Iterator over the key value pairs of an on-demand object
+pub struct Iter<'doc, 'input> {
+    value: Value<'doc, 'input>,
+    // the position of the next key, `None` once the object ended
+    next: Option<usize>,
+}
+/// Iterator over the keys of an on-demand object
+pub struct Keys<'doc, 'input>(Iter<'doc, 'input>);
+/// Iterator over the values of an on-demand object
+pub struct Values<'doc, 'input>(Iter<'doc, 'input>);
+
+//value_trait::Object for
+impl<'doc, 'input> Object<'doc, 'input> {
+    /// Gets a value based on a key, returns `None` if the object doesn't
+    /// contain the key it was asked for. Values of other keys are skipped
+    /// without being parsed.
+    #[must_use]
+    pub fn get<Q>(&self, k: &Q) -> Option<Value<'doc, 'input>>
+    where
+        str: Borrow<Q> + Hash + Eq,
+        Q: ?Sized + Hash + Eq + Ord,
+    {
+        self.iter().find_map(|(s, v)| {
+            let s: &Q = s.borrow();
+            (s == k).then_some(v)
+        })
+    }
+
+    /// Iterates over the key value paris
+    #[must_use]
+    pub fn iter(&self) -> Iter<'doc, 'input> {
+        let Value { doc, pos } = self.0;
+        let next = (doc.byte(pos + 1) != Some(b'}')).then_some(pos + 1);
+        Iter {
+            value: self.0,
+            next,
+        }
+    }
+
+    /// Iterates over the keys
+    #[must_use]
+    pub fn keys(&self) -> Keys<'doc, 'input> {
+        Keys(self.iter())
+    }
+
+    /// Iterates over the values
+    #[must_use]
+    pub fn values(&self) -> Values<'doc, 'input> {
+        Values(self.iter())
+    }
+
+    /// Number of key/value pairs, this has to walk the object
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    /// Returns if the object is empty
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+}
+
+impl<'doc, 'input> IntoIterator for &Object<'doc, 'input> {
+    type IntoIter = Iter<'doc, 'input>;
+    type Item = (&'doc str, Value<'doc, 'input>);
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'doc, 'input> Iterator for Iter<'doc, 'input> {
+    type Item = (&'doc str, Value<'doc, 'input>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let pos = self.next.take()?;
+        let doc = self.value.doc;
+        if doc.byte(pos + 1)? != b':' {
+            return None;
+        }
+        let k = doc.string(pos)?;
+        let pos = pos + 2;
+        // anything but a comma after the value ends the object
+        self.next = doc
+            .skip(pos)
+            .filter(|next| doc.byte(*next) == Some(b','))
+            .map(|next| next + 1);
+        Some((k, Value { doc, pos }))
+    }
+}
+
+impl<'doc> Iterator for Keys<'doc, '_> {
+    type Item = &'doc str;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(k, _)| k)
+    }
+}
+
+impl<'doc, 'input> Iterator for Values<'doc, 'input> {
+    type Item = Value<'doc, 'input>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(_, v)| v)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ondemand::Document;
+    use value_trait::base::ValueAsScalar;
+
+    #[test]
+    fn get_ints() -> crate::Result<()> {
+        let input = br#"{"snot": 1, "badger":[2, {"x": 1}], "cake":3, "cookie":4}"#;
+        let doc = Document::parse(input)?;
+        let a = doc.root().as_object().expect("is an object");
+        assert_eq!(a.get("snot").and_then(|v| v.as_u64()), Some(1));
+        assert!(a.get("badger").is_some_and(|v| v.is_array()));
+        assert_eq!(a.get("cake").and_then(|v| v.as_u64()), Some(3));
+        assert_eq!(a.get("cookie").and_then(|v| v.as_u64()), Some(4));
+        assert!(a.get("monster").is_none());
+        assert!(a.get("x").is_none());
+        assert_eq!(a.len(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn iter() -> crate::Result<()> {
+        let input = r#"{"aé": 1, "b": {"c": 2}, "d": 3}"#.as_bytes();
+        let doc = Document::parse(input)?;
+        let o = doc.root().as_object().expect("is an object");
+        assert_eq!(o.keys().collect::<Vec<_>>(), vec!["aé", "b", "d"]);
+        assert_eq!(
+            o.values().map(|v| v.as_u8()).collect::<Vec<_>>(),
+            vec![Some(1), None, Some(3)]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,510 @@
+use std::{borrow::Borrow, hash::Hash};
+
+use value_trait::{
+    StaticNode, TryTypeError, ValueType,
+    base::{TypedValue, ValueAsScalar, ValueIntoArray, ValueIntoObject, ValueIntoString},
+    derived::{
+        ValueObjectAccessAsScalar, ValueObjectAccessTryAsScalar, ValueTryAsScalar,
+        ValueTryIntoString,
+    },
+};
+
+use super::{Array, Object, Value};
+
+// Custom functions
+impl Value<'_, '_> {
+    pub(super) fn as_static(&self) -> Option<StaticNode> {
+        self.doc.static_node(self.pos)
+    }
+}
+
+// TypedContainerValue
+impl Value<'_, '_> {
+    /// returns true if the current value can be represented as an array
+    #[must_use]
+    pub fn is_array(&self) -> bool {
+        self.as_array().is_some()
+    }
+
+    /// returns true if the current value can be represented as an object
+    #[must_use]
+    pub fn is_object(&self) -> bool {
+        self.as_object().is_some()
+    }
+}
+
+impl<'doc> ValueAsScalar for Value<'doc, '_> {
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn as_null(&self) -> Option<()> {
+        self.as_static()?.as_null()
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn as_bool(&self) -> Option<bool> {
+        self.as_static()?.as_bool()
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn as_i64(&self) -> Option<i64> {
+        self.as_static()?.as_i64()
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn as_u64(&self) -> Option<u64> {
+        self.as_static()?.as_u64()
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn as_f64(&self) -> Option<f64> {
+        self.as_static()?.as_f64()
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn as_str(&self) -> Option<&'doc str> {
+        self.into_string()
+    }
+}
+
+impl<'doc> ValueIntoString for Value<'doc, '_> {
+    type String = &'doc str;
+
+    fn into_string(self) -> Option<&'doc str> {
+        self.doc.string(self.pos)
+    }
+}
+
+impl<'doc, 'input> ValueIntoArray for Value<'doc, 'input> {
+    type Array = Array<'doc, 'input>;
+    fn into_array(self) -> Option<Self::Array> {
+        self.as_array()
+    }
+}
+impl<'doc, 'input> ValueIntoObject for Value<'doc, 'input> {
+    type Object = Object<'doc, 'input>;
+    fn into_object(self) -> Option<Self::Object> {
+        self.as_object()
+    }
+}
+
+impl TypedValue for Value<'_, '_> {
+    /// Scalars are parsed to tell their type, malformed values are reported
+    /// as `Null`.
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn value_type(&self) -> ValueType {
+        match self.doc.byte(self.pos) {
+            Some(b'{') => ValueType::Object,
+            Some(b'[') => ValueType::Array,
+            Some(b'"') => ValueType::String,
+            _ => match self.as_static() {
+                Some(StaticNode::Bool(_)) => ValueType::Bool,
+                Some(StaticNode::I64(_)) => ValueType::I64,
+                #[cfg(feature = "128bit")]
+                Some(StaticNode::I128(_)) => ValueType::I128,
+                Some(StaticNode::U64(_)) => ValueType::U64,
+                #[cfg(feature = "128bit")]
+                Some(StaticNode::U128(_)) => ValueType::U128,
+                Some(StaticNode::F64(_)) => ValueType::F64,
+                Some(StaticNode::Null) | None => ValueType::Null,
+            },
+        }
+    }
+}
+
+// TryValueObjectAccess
+impl<'doc, 'input> Value<'doc, 'input> {
+    /// Tries to get a value based on a key, returns a `TryTypeError` if the
+    /// current Value isn't an Object, returns `None` if the key isn't in the object
+    /// # Errors
+    /// if the value is not an object
+    pub fn try_get<Q>(&self, k: &Q) -> Result<Option<Value<'doc, 'input>>, TryTypeError>
+    where
+        str: Borrow<Q> + Hash + Eq,
+        Q: ?Sized + Hash + Eq + Ord,
+    {
+        Ok(self.try_as_object()?.get(k))
+    }
+}
+
+//TryValueArrayAccess
+impl<'doc, 'input> Value<'doc, 'input> {
+    /// Tries to get a value based on n index, returns a type error if the
+    /// current value isn't an Array, returns `None` if the index is out of bounds
+    /// # Errors
+    /// if the requested type doesn't match the actual type or the value is not an object
+    pub fn try_get_idx(&self, i: usize) -> Result<Option<Value<'doc, 'input>>, TryTypeError> {
+        Ok(self.try_as_array()?.get(i))
+    }
+}
+
+//ValueAsContainer
+impl<'doc, 'input> Value<'doc, 'input> {
+    /// Tries to represent the value as an array
+    #[must_use]
+    pub fn as_array(&self) -> Option<Array<'doc, 'input>> {
+        (self.doc.byte(self.pos)? == b'[').then_some(Array(*self))
+    }
+
+    /// Tries to represent the value as an object
+    #[must_use]
+    pub fn as_object(&self) -> Option<Object<'doc, 'input>> {
+        (self.doc.byte(self.pos)? == b'{').then_some(Object(*self))
+    }
+}
+
+// ContainerValueTryAs (needed as we don't have ValueAsContainer)
+impl<'doc, 'input> Value<'doc, 'input> {
+    /// Tries to represent the value as an array
+    /// # Errors
+    /// if the requested type doesn't match the actual type
+    pub fn try_as_array(&self) -> Result<Array<'doc, 'input>, TryTypeError> {
+        self.as_array().ok_or(TryTypeError {
+            expected: ValueType::Array,
+            got: self.value_type(),
+        })
+    }
+
+    /// Tries to represent the value as an object
+    /// # Errors
+    /// if the requested type doesn't match the actual type
+    pub fn try_as_object(&self) -> Result<Object<'doc, 'input>, TryTypeError> {
+        self.as_object().ok_or(TryTypeError {
+            expected: ValueType::Object,
+            got: self.value_type(),
+        })
+    }
+}
+
+// ValueObjectAccess (needed as we don't have ValueAsContainer ) and can't return references
+impl<'doc, 'input> Value<'doc, 'input> {
+    /// Gets a value based on a key, returns `None` if the
+    /// current Value isn't an Object or doesn't contain the key
+    /// it was asked for.
+    pub fn get<Q>(&self, k: &Q) -> Option<Value<'doc, 'input>>
+    where
+        str: Borrow<Q> + Hash + Eq,
+        Q: ?Sized + Hash + Eq + Ord,
+    {
+        self.as_object().and_then(|a| a.get(k))
+    }
+
+    /// Checks if a Value contains a given key. This will return
+    /// false if Value isn't an object
+    #[must_use]
+    pub fn contains_key(&self, k: &str) -> bool {
+        self.as_object().and_then(|a| a.get(k)).is_some()
+    }
+}
+
+// ValueArrayAccess (needed as we don't have ValueAsContainer)
+impl<'doc, 'input> Value<'doc, 'input> {
+    /// Gets a value based on n index, returns `None` if the
+    /// current Value isn't an Array or doesn't contain the index
+    /// it was asked for.
+    #[must_use]
+    pub fn get_idx(&self, i: usize) -> Option<Value<'doc, 'input>> {
+        self.as_array().and_then(|a| a.get(i))
+    }
+}
+
+// ValueObjectContainerAccess
+impl<'doc, 'input> Value<'doc, 'input> {
+    /// Tries to get an element of an object as a array
+    #[must_use]
+    pub fn get_array<Q>(&self, k: &Q) -> Option<Array<'doc, 'input>>
+    where
+        str: Borrow<Q> + Hash + Eq,
+        Q: ?Sized + Hash + Eq + Ord,
+    {
+        self.get(k)?.as_array()
+    }
+
+    /// Tries to get an element of an object as a object
+    #[must_use]
+    pub fn get_object<Q>(&self, k: &Q) -> Option<Object<'doc, 'input>>
+    where
+        str: Borrow<Q> + Hash + Eq,
+        Q: ?Sized + Hash + Eq + Ord,
+    {
+        self.get(k)?.as_object()
+    }
+}
+
+impl<'doc> ValueObjectAccessAsScalar for Value<'doc, '_> {
+    type Key = str;
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn get_bool<Q>(&self, k: &Q) -> Option<bool>
+    where
+        str: Borrow<Q> + Hash + Eq,
+        Q: ?Sized + Hash + Eq + Ord,
+    {
+        self.get(k)?.as_bool()
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn get_i128<Q>(&self, k: &Q) -> Option<i128>
+    where
+        str: Borrow<Q> + Hash + Eq,
+        Q: ?Sized + Hash + Eq + Ord,
+    {
+        self.get(k)?.as_i128()
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn get_i64<Q>(&self, k: &Q) -> Option<i64>
+    where
+        str: Borrow<Q> + Hash + Eq,
+        Q: ?Sized + Hash + Eq + Ord,
+    {
+        self.get(k)?.as_i64()
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn get_i32<Q>(&self, k: &Q) -> Option<i32>
+    where
+        str: Borrow<Q> + Hash + Eq,
+        Q: ?Sized + Hash + Eq + Ord,
+    {
+        self.get(k)?.as_i32()
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn get_i16<Q>(&self, k: &Q) -> Option<i16>
+    where
+        str: Borrow<Q> + Hash + Eq,
+        Q: ?Sized + Hash + Eq + Ord,
+    {
+        self.get(k)?.as_i16()
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn get_i8<Q>(&self, k: &Q) -> Option<i8>
+    where
+        str: Borrow<Q> + Hash + Eq,
+        Q: ?Sized + Hash + Eq + Ord,
+    {
+        self.get(k)?.as_i8()
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn get_u128<Q>(&self, k: &Q) -> Option<u128>
+    where
+        str: Borrow<Q> + Hash + Eq,
+        Q: ?Sized + Hash + Eq + Ord,
+    {
+        self.get(k)?.as_u128()
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn get_u64<Q>(&self, k: &Q) -> Option<u64>
+    where
+        str: Borrow<Q> + Hash + Eq,
+        Q: ?Sized + Hash + Eq + Ord,
+    {
+        self.get(k)?.as_u64()
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn get_usize<Q>(&self, k: &Q) -> Option<usize>
+    where
+        str: Borrow<Q> + Hash + Eq,
+        Q: ?Sized + Hash + Eq + Ord,
+    {
+        self.get(k)?.as_usize()
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn get_u32<Q>(&self, k: &Q) -> Option<u32>
+    where
+        str: Borrow<Q> + Hash + Eq,
+        Q: ?Sized + Hash + Eq + Ord,
+    {
+        self.get(k)?.as_u32()
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn get_u16<Q>(&self, k: &Q) -> Option<u16>
+    where
+        str: Borrow<Q> + Hash + Eq,
+        Q: ?Sized + Hash + Eq + Ord,
+    {
+        self.get(k)?.as_u16()
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn get_u8<Q>(&self, k: &Q) -> Option<u8>
+    where
+        str: Borrow<Q> + Hash + Eq,
+        Q: ?Sized + Hash + Eq + Ord,
+    {
+        self.get(k)?.as_u8()
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn get_f64<Q>(&self, k: &Q) -> Option<f64>
+    where
+        str: Borrow<Q> + Hash + Eq,
+        Q: ?Sized + Hash + Eq + Ord,
+    {
+        self.get(k)?.as_f64()
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn get_f32<Q>(&self, k: &Q) -> Option<f32>
+    where
+        str: Borrow<Q> + Hash + Eq,
+        Q: ?Sized + Hash + Eq + Ord,
+    {
+        self.get(k)?.as_f32()
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn get_str<Q>(&self, k: &Q) -> Option<&'doc str>
+    where
+        str: Borrow<Q> + Hash + Eq,
+        Q: ?Sized + Hash + Eq + Ord,
+    {
+        self.get(k)?.into_string()
+    }
+}
+
+impl ValueObjectAccessTryAsScalar for Value<'_, '_> {
+    type Key = str;
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn try_get_bool<Q>(&self, k: &Q) -> Result<Option<bool>, TryTypeError>
+    where
+        str: Borrow<Q> + Hash + Eq,
+        Q: ?Sized + Hash + Eq + Ord,
+    {
+        self.try_get(k)?.map(|v| v.try_as_bool()).transpose()
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn try_get_i128<Q>(&self, k: &Q) -> Result<Option<i128>, TryTypeError>
+    where
+        str: Borrow<Q> + Hash + Eq,
+        Q: ?Sized + Hash + Eq + Ord,
+    {
+        self.try_get(k)?.map(|v| v.try_as_i128()).transpose()
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn try_get_i64<Q>(&self, k: &Q) -> Result<Option<i64>, TryTypeError>
+    where
+        str: Borrow<Q> + Hash + Eq,
+        Q: ?Sized + Hash + Eq + Ord,
+    {
+        self.try_get(k)?.map(|v| v.try_as_i64()).transpose()
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn try_get_i32<Q>(&self, k: &Q) -> Result<Option<i32>, TryTypeError>
+    where
+        str: Borrow<Q> + Hash + Eq,
+        Q: ?Sized + Hash + Eq + Ord,
+    {
+        self.try_get(k)?.map(|v| v.try_as_i32()).transpose()
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn try_get_i16<Q>(&self, k: &Q) -> Result<Option<i16>, TryTypeError>
+    where
+        str: Borrow<Q> + Hash + Eq,
+        Q: ?Sized + Hash + Eq + Ord,
+    {
+        self.try_get(k)?.map(|v| v.try_as_i16()).transpose()
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn try_get_i8<Q>(&self, k: &Q) -> Result<Option<i8>, TryTypeError>
+    where
+        str: Borrow<Q> + Hash + Eq,
+        Q: ?Sized + Hash + Eq + Ord,
+    {
+        self.try_get(k)?.map(|v| v.try_as_i8()).transpose()
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn try_get_u128<Q>(&self, k: &Q) -> Result<Option<u128>, TryTypeError>
+    where
+        str: Borrow<Q> + Hash + Eq,
+        Q: ?Sized + Hash + Eq + Ord,
+    {
+        self.try_get(k)?.map(|v| v.try_as_u128()).transpose()
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn try_get_u64<Q>(&self, k: &Q) -> Result<Option<u64>, TryTypeError>
+    where
+        str: Borrow<Q> + Hash + Eq,
+        Q: ?Sized + Hash + Eq + Ord,
+    {
+        self.try_get(k)?.map(|v| v.try_as_u64()).transpose()
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn try_get_usize<Q>(&self, k: &Q) -> Result<Option<usize>, TryTypeError>
+    where
+        str: Borrow<Q> + Hash + Eq,
+        Q: ?Sized + Hash + Eq + Ord,
+    {
+        self.try_get(k)?.map(|v| v.try_as_usize()).transpose()
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn try_get_u32<Q>(&self, k: &Q) -> Result<Option<u32>, TryTypeError>
+    where
+        str: Borrow<Q> + Hash + Eq,
+        Q: ?Sized + Hash + Eq + Ord,
+    {
+        self.try_get(k)?.map(|v| v.try_as_u32()).transpose()
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn try_get_u16<Q>(&self, k: &Q) -> Result<Option<u16>, TryTypeError>
+    where
+        str: Borrow<Q> + Hash + Eq,
+        Q: ?Sized + Hash + Eq + Ord,
+    {
+        self.try_get(k)?.map(|v| v.try_as_u16()).transpose()
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn try_get_u8<Q>(&self, k: &Q) -> Result<Option<u8>, TryTypeError>
+    where
+        str: Borrow<Q> + Hash + Eq,
+        Q: ?Sized + Hash + Eq + Ord,
+    {
+        self.try_get(k)?.map(|v| v.try_as_u8()).transpose()
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn try_get_f64<Q>(&self, k: &Q) -> Result<Option<f64>, TryTypeError>
+    where
+        str: Borrow<Q> + Hash + Eq,
+        Q: ?Sized + Hash + Eq + Ord,
+    {
+        self.try_get(k)?.map(|v| v.try_as_f64()).transpose()
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn try_get_f32<Q>(&self, k: &Q) -> Result<Option<f32>, TryTypeError>
+    where
+        str: Borrow<Q> + Hash + Eq,
+        Q: ?Sized + Hash + Eq + Ord,
+    {
+        self.try_get(k)?.map(|v| v.try_as_f32()).transpose()
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn try_get_str<Q>(&self, k: &Q) -> Result<Option<&str>, TryTypeError>
+    where
+        str: Borrow<Q> + Hash + Eq,
+        Q: ?Sized + Hash + Eq + Ord,
+    {
+        self.try_get(k)?
+            .map(ValueTryIntoString::try_into_string)
+            .transpose()
+    }
+}
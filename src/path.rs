@@ -0,0 +1,449 @@
+//! `JSONPath` ([RFC 9535](https://www.rfc-editor.org/rfc/rfc9535)) queries.
+//!
+//! An expression is compiled once into a [`Path`] and can then be evaluated
+//! against a [`tape::Value`] or a [`&BorrowedValue`](crate::BorrowedValue).
+//! Every match comes with its normalized path. On the tape arrays and
+//! objects are skipped by their node count, so subtrees a query doesn't
+//! select are not visited.
+//!
+//! ```rust
+//! use simd_json::path::Path;
+//! use simd_json::prelude::*;
+//!
+//! let path = Path::compile("$.events[?@.level == 'error'].msg")?;
+//!
+//! let mut json = br#"{"events": [
+//!     {"level": "info", "msg": "started"},
+//!     {"level": "error", "msg": "disk full"}
+//! ]}"#.to_vec();
+//! let tape = simd_json::to_tape(&mut json)?;
+//! let matches = path.query(tape.as_value());
+//! assert_eq!(matches.len(), 1);
+//! assert_eq!(matches[0].path.to_string(), "$['events'][1]['msg']");
+//! assert_eq!(matches[0].value.as_str(), Some("disk full"));
+//! # Ok::<(), simd_json::Error>(())
+//! ```
+//!
+//! All selectors, filter expressions and the functions `length()`,
+//! `count()` and `value()` are supported. The regular expression functions
+//! `match()` and `search()` need the `path-regex` feature.
+
+mod eval;
+mod parser;
+
+use crate::Result;
+use crate::value::{borrowed, tape};
+use std::fmt::{self, Write as _};
+use std::str::FromStr;
+
+/// A compiled `JSONPath` expression
+#[derive(Debug, Clone)]
+pub struct Path {
+    query: parser::Query,
+}
+
+impl Path {
+    /// Compiles a `JSONPath` expression
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if `expr` is no valid `JSONPath` expression, this
+    /// includes filters that are not well-typed such as comparing a query
+    /// that selects more than one node.
+    pub fn compile(expr: &str) -> Result<Self> {
+        parser::parse(expr).map(|query| Self { query })
+    }
+
+    /// Evaluates the path against `value` and returns the selected nodes
+    /// with their normalized paths, in the order the query selects them
+    #[must_use]
+    pub fn query<'a, V>(&self, value: V) -> Vec<Match<'a, V>>
+    where
+        V: Queryable<'a>,
+    {
+        let mut locations = eval::Locations::tracked();
+        eval::select(&self.query, value, value, &mut locations)
+            .into_iter()
+            .map(|(value, location)| Match {
+                path: NormalizedPath(locations.path(location)),
+                value,
+            })
+            .collect()
+    }
+
+    /// Evaluates the path against `value` and returns the selected nodes
+    /// only, this saves keeping track of their paths
+    #[must_use]
+    pub fn select<'a, V>(&self, value: V) -> Vec<V>
+    where
+        V: Queryable<'a>,
+    {
+        let mut locations = eval::Locations::untracked();
+        eval::select(&self.query, value, value, &mut locations)
+            .into_iter()
+            .map(|(value, _)| value)
+            .collect()
+    }
+}
+
+impl FromStr for Path {
+    type Err = crate::Error;
+
+    fn from_str(expr: &str) -> Result<Self> {
+        Self::compile(expr)
+    }
+}
+
+/// A value a [`Path`] can be evaluated against, this is implemented for
+/// [`tape::Value`] and [`&BorrowedValue`](crate::BorrowedValue)
+pub trait Queryable<'a>: eval::Access<'a> {}
+
+impl<'a> Queryable<'a> for tape::Value<'a, '_> {}
+impl<'a> Queryable<'a> for &'a borrowed::Value<'_> {}
+
+/// A node selected by a [`Path`]
+#[derive(Debug, Clone)]
+pub struct Match<'a, V> {
+    /// The normalized path of the node
+    pub path: NormalizedPath<'a>,
+    /// The node
+    pub value: V,
+}
+
+/// A step of a [`NormalizedPath`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathElement<'a> {
+    /// The member of an object with this key
+    Member(&'a str),
+    /// The element of an array at this index
+    Index(usize),
+}
+
+/// The location of a node in a document as a list of members and indexes
+/// from the root. It displays in the normalized form of RFC 9535, for
+/// example `$['events'][1]['msg']`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct NormalizedPath<'a>(Vec<PathElement<'a>>);
+
+impl<'a> NormalizedPath<'a> {
+    /// The members and indexes leading to the node
+    #[must_use]
+    pub fn elements(&self) -> &[PathElement<'a>] {
+        &self.0
+    }
+
+    /// The location as a JSON pointer, see
+    /// [`BorrowedValue::pointer`](crate::BorrowedValue::pointer)
+    #[must_use]
+    pub fn to_pointer(&self) -> String {
+        let mut pointer = String::new();
+        for element in &self.0 {
+            pointer.push('/');
+            match element {
+                PathElement::Member(key) => {
+                    pointer.push_str(&key.replace('~', "~0").replace('/', "~1"));
+                }
+                PathElement::Index(idx) => {
+                    // writing to a string can't fail
+                    let _ = write!(pointer, "{idx}");
+                }
+            }
+        }
+        pointer
+    }
+}
+
+impl fmt::Display for NormalizedPath<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_char('$')?;
+        for element in &self.0 {
+            match element {
+                PathElement::Member(key) => {
+                    f.write_str("['")?;
+                    for c in key.chars() {
+                        match c {
+                            '\u{8}' => f.write_str(r"\b")?,
+                            '\u{c}' => f.write_str(r"\f")?,
+                            '\n' => f.write_str(r"\n")?,
+                            '\r' => f.write_str(r"\r")?,
+                            '\t' => f.write_str(r"\t")?,
+                            '\'' => f.write_str(r"\'")?,
+                            '\\' => f.write_str(r"\\")?,
+                            c if c < ' ' => write!(f, "\\u{:04x}", u32::from(c))?,
+                            c => f.write_char(c)?,
+                        }
+                    }
+                    f.write_str("']")?;
+                }
+                PathElement::Index(idx) => write!(f, "[{idx}]")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prelude::*;
+    use crate::{BorrowedValue, to_borrowed_value, to_tape};
+
+    const STORE: &str = r#"{"store": {
+        "book": [
+            {"category": "reference", "author": "Nigel Rees", "title": "Sayings of the Century", "price": 8.95},
+            {"category": "fiction", "author": "Evelyn Waugh", "title": "Sword of Honour", "price": 12.99},
+            {"category": "fiction", "author": "Herman Melville", "title": "Moby Dick", "isbn": "0-553-21311-3", "price": 8.99},
+            {"category": "fiction", "author": "J. R. R. Tolkien", "title": "The Lord of the Rings", "isbn": "0-395-19395-8", "price": 22.99}
+        ],
+        "bicycle": {"color": "red", "price": 399}
+    }}"#;
+
+    /// The normalized paths `expr` selects, checked to be the same on the
+    /// tape and on a borrowed value
+    fn paths(json: &str, expr: &str) -> Vec<String> {
+        let path = Path::compile(expr).expect("valid path");
+        let mut tape_input = json.as_bytes().to_vec();
+        let tape = to_tape(&mut tape_input).expect("valid JSON");
+        let mut value_input = json.as_bytes().to_vec();
+        let value = to_borrowed_value(&mut value_input).expect("valid JSON");
+
+        let on_tape: Vec<_> = path
+            .query(tape.as_value())
+            .iter()
+            .map(|m| m.path.to_string())
+            .collect();
+        let on_value: Vec<_> = path
+            .query(&value)
+            .iter()
+            .map(|m| m.path.to_string())
+            .collect();
+        assert_eq!(on_tape, on_value, "{expr}");
+        assert_eq!(path.select(&value).len(), on_value.len(), "{expr}");
+        on_value
+    }
+
+    fn invalid(expr: &str) -> bool {
+        Path::compile(expr).is_err()
+    }
+
+    #[test]
+    fn selectors() {
+        assert_eq!(paths(STORE, "$"), ["$"]);
+        assert_eq!(
+            paths(STORE, "$.store.book[*].author"),
+            [
+                "$['store']['book'][0]['author']",
+                "$['store']['book'][1]['author']",
+                "$['store']['book'][2]['author']",
+                "$['store']['book'][3]['author']",
+            ]
+        );
+        assert_eq!(
+            paths(STORE, "$['store'].bicycle[\"color\"]"),
+            ["$['store']['bicycle']['color']"]
+        );
+        assert_eq!(paths(STORE, "$.store.*").len(), 2);
+        assert_eq!(paths(STORE, "$.store.book[2]"), ["$['store']['book'][2]"]);
+        assert_eq!(paths(STORE, "$.store.book[-1]"), ["$['store']['book'][3]"]);
+        assert!(paths(STORE, "$.store.book[4]").is_empty());
+        assert_eq!(
+            paths(STORE, "$.store.book[0,1]"),
+            ["$['store']['book'][0]", "$['store']['book'][1]"]
+        );
+        assert_eq!(
+            paths(STORE, "$.store.book[:2]"),
+            ["$['store']['book'][0]", "$['store']['book'][1]"]
+        );
+        assert_eq!(
+            paths(STORE, "$.store.book[1::2]"),
+            ["$['store']['book'][1]", "$['store']['book'][3]"]
+        );
+        assert_eq!(
+            paths(STORE, "$.store.book[::-2]"),
+            ["$['store']['book'][3]", "$['store']['book'][1]"]
+        );
+        assert!(paths(STORE, "$.missing.book").is_empty());
+        assert!(paths(STORE, "$.store[0]").is_empty());
+    }
+
+    #[test]
+    fn descendants() {
+        assert_eq!(paths(STORE, "$..author").len(), 4);
+        assert_eq!(
+            paths(STORE, "$.store..price"),
+            [
+                "$['store']['book'][0]['price']",
+                "$['store']['book'][1]['price']",
+                "$['store']['book'][2]['price']",
+                "$['store']['book'][3]['price']",
+                "$['store']['bicycle']['price']",
+            ]
+        );
+        assert_eq!(paths(STORE, "$..book[2]"), ["$['store']['book'][2]"]);
+        // every node below the root
+        assert_eq!(paths(STORE, "$..*").len(), 27);
+        assert_eq!(
+            paths(
+                r#"{"o": {"j": 1, "k": 2}, "a": [5, 3, [{"j": 4}, {"k": 6}]]}"#,
+                "$..j"
+            ),
+            ["$['o']['j']", "$['a'][2][0]['j']"]
+        );
+    }
+
+    #[test]
+    fn filters() {
+        assert_eq!(
+            paths(STORE, "$..book[?@.isbn]"),
+            ["$['store']['book'][2]", "$['store']['book'][3]"]
+        );
+        assert_eq!(
+            paths(STORE, "$..book[?!@.isbn].title"),
+            [
+                "$['store']['book'][0]['title']",
+                "$['store']['book'][1]['title']",
+            ]
+        );
+        assert_eq!(
+            paths(STORE, "$..book[?@.price < 10].title"),
+            [
+                "$['store']['book'][0]['title']",
+                "$['store']['book'][2]['title']",
+            ]
+        );
+        assert_eq!(
+            paths(STORE, "$..book[?@.price > $.store.bicycle.price]").len(),
+            0
+        );
+        assert_eq!(
+            paths(
+                STORE,
+                "$..book[?@.category == 'fiction' && (@.price < 10 || @.price > 20)]"
+            ),
+            ["$['store']['book'][2]", "$['store']['book'][3]"]
+        );
+        assert_eq!(paths(STORE, "$.store[?@.color == \"red\"]").len(), 1);
+
+        let json = r#"{"a": [3, 5, 1, 2, 4, 6, {"b": "j"}, {"b": "k"}, {"b": {}}, {"b": "kilo"}]}"#;
+        assert_eq!(paths(json, "$.a[?@.b == 'kilo']"), ["$['a'][9]"]);
+        assert_eq!(
+            paths(json, "$.a[?@ > 3.5]"),
+            ["$['a'][1]", "$['a'][4]", "$['a'][5]"]
+        );
+        assert_eq!(paths(json, "$.a[?@.b]").len(), 4);
+        assert_eq!(paths(json, "$.a[?@ == 1.0]"), ["$['a'][2]"]);
+        assert_eq!(
+            paths(json, "$.a[?@<2 || @.b == \"k\"]"),
+            ["$['a'][2]", "$['a'][7]"]
+        );
+        // comparisons with nothing
+        assert_eq!(paths(json, "$.a[?@.c == @.d]").len(), 10);
+        assert!(paths(json, "$.a[?@.c < @.d]").is_empty());
+        // arrays and objects compare deeply
+        assert_eq!(
+            paths(r#"[[1, {"a": 2}], [1, {"a": 3}]]"#, "$[?@ == $[0]]"),
+            ["$[0]"]
+        );
+    }
+
+    #[test]
+    fn functions() {
+        let json = r#"[{"a": "abc"}, {"a": [1, 2]}, {"a": {"b": 1}}, {"a": 1}, {"b": [1, 2, 3]}]"#;
+        assert_eq!(paths(json, "$[?length(@.a) == 2]"), ["$[1]"]);
+        assert_eq!(paths(json, "$[?length(@.a) >= 1]").len(), 3);
+        assert_eq!(paths(json, "$[?count(@.*) == 1]").len(), 5);
+        assert_eq!(paths(json, "$[?count(@..*) > 2]"), ["$[1]", "$[4]"]);
+        assert_eq!(paths(json, "$[?value(@.a) == 1]"), ["$[3]"]);
+        // a value of several nodes is nothing
+        assert!(paths(json, "$[?value(@.b[*]) == 1]").is_empty());
+        assert_eq!(paths(json, "$[?length(@.b) == length($[4].b)]"), ["$[4]"]);
+    }
+
+    #[cfg(feature = "path-regex")]
+    #[test]
+    fn regex_functions() {
+        let json =
+            r#"[{"d": "1974-05-11"}, {"d": "1974-05-11T1"}, {"d": "x1974-05-11"}, {"d": 1}]"#;
+        assert_eq!(paths(json, r"$[?match(@.d, '1974-05-..')]"), ["$[0]"]);
+        assert_eq!(paths(json, r"$[?search(@.d, '1974-05-..')]").len(), 3);
+        assert_eq!(paths(json, r"$[?!search(@.d, '^x')]").len(), 4);
+        assert!(paths(json, "$[?match(@.d, '[')]").is_empty());
+        // the dot doesn't match line breaks in I-Regexp and `^` is no anchor
+        let json = r#"["a\nb", "a\rb", "a b", "^a b"]"#;
+        assert_eq!(paths(json, "$[?match(@, 'a.b')]"), ["$[2]"]);
+        assert_eq!(paths(json, "$[?search(@, '^a')]"), ["$[3]"]);
+        // patterns can come from the document
+        let json = r#"{"p": "b+", "v": ["abbb", "ab", "ac"]}"#;
+        assert_eq!(paths(json, "$.v[?match(@, 'ab+')]").len(), 2);
+        assert_eq!(paths(json, "$.v[?search(@, $.p)]").len(), 2);
+    }
+
+    #[test]
+    fn normalized_paths() {
+        let json = r#"{"a'b": {"\\": [{"c\td": {"\u0001/~": 1}}]}}"#;
+        let path = Path::compile("$..*").expect("valid path");
+        let mut input = json.as_bytes().to_vec();
+        let value = to_borrowed_value(&mut input).expect("valid JSON");
+        let matches = path.query(&value);
+        let last = matches.last().expect("a match");
+        assert_eq!(
+            last.path.to_string(),
+            r"$['a\'b']['\\'][0]['c\td']['\u0001/~']"
+        );
+        assert_eq!(last.path.to_pointer(), "/a'b/\\/0/c\td/\u{1}~1~0");
+        assert_eq!(value.pointer(&last.path.to_pointer()), Some(last.value));
+        assert_eq!(last.path.elements()[2..3], [PathElement::Index(0)]);
+        assert_eq!(NormalizedPath::default().to_string(), "$");
+        assert_eq!(NormalizedPath::default().to_pointer(), "");
+        assert_eq!(
+            Path::compile("$['\\u00e9\\'']")
+                .expect("valid path")
+                .query(&BorrowedValue::from(crate::json!({"é'": 1})))[0]
+                .path
+                .to_string(),
+            "$['é\\'']"
+        );
+        assert!("$.a".parse::<Path>().is_ok());
+    }
+
+    #[test]
+    fn invalid_paths() {
+        assert!(invalid(""));
+        assert!(invalid("a"));
+        assert!(invalid("$ "));
+        assert!(invalid(" $"));
+        assert!(invalid("$."));
+        assert!(invalid("$.1a"));
+        assert!(invalid("$[01]"));
+        assert!(invalid("$[-0]"));
+        assert!(invalid("$[9007199254740992]"));
+        assert!(invalid("$[1:2:3:4]"));
+        assert!(invalid("$['a'"));
+        assert!(invalid("$['\\x']"));
+        assert!(invalid("$[?@.a == 01]"));
+        assert!(invalid("$[?@.* == 1]"));
+        assert!(invalid("$[?@..a == 1]"));
+        assert!(invalid("$[?@.a == @.*]"));
+        assert!(invalid("$[?1 == 1 == 1]"));
+        assert!(invalid("$[?1]"));
+        assert!(invalid("$[?length(@.*) == 1]"));
+        assert!(invalid("$[?count(1) == 1]"));
+        assert!(invalid("$[?length(@.a)]"));
+        assert!(invalid("$[?count(@.*)]"));
+        assert!(invalid("$[?foo(@.a)]"));
+        assert!(invalid("$[?length (@.a) == 1]"));
+        assert!(!invalid("$[? @.a == 1 ]"));
+        assert!(!invalid("$[?@.a==1&&@.b!=2]"));
+        assert!(!invalid("$.store .book"));
+        assert!(invalid("$.store. book"));
+        let err = Path::compile("$.a[").expect_err("invalid path");
+        assert!(matches!(err.error(), crate::ErrorType::InvalidPath(_)));
+        #[cfg(not(feature = "path-regex"))]
+        assert!(invalid("$[?match(@.a, 'a')]"));
+        #[cfg(feature = "path-regex")]
+        {
+            assert!(!invalid("$[?match(@.a, 'a')]"));
+            assert!(invalid("$[?match(@.a, 'a') == true]"));
+        }
+    }
+}
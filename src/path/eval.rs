@@ -0,0 +1,790 @@
+//! Evaluation of compiled `JSONPath` queries against tapes and values
+
+use super::PathElement;
+use super::parser::{CmpOp, Comparable, Expr, Function, Literal, Query, Selector};
+use crate::value::{borrowed, number_to_static, tape};
+use crate::{Node, StaticNode};
+use std::cmp::Ordering;
+
+/// The kind of a node along with the scalar it holds
+#[derive(Clone, Copy)]
+pub enum Kind<'a> {
+    Null,
+    Bool(bool),
+    Number(Number),
+    String(&'a str),
+    Array,
+    Object,
+}
+
+impl Kind<'_> {
+    fn from_static(s: StaticNode) -> Self {
+        match s {
+            StaticNode::Null => Kind::Null,
+            StaticNode::Bool(b) => Kind::Bool(b),
+            s => Number::from_static(s).map_or(Kind::Null, Kind::Number),
+        }
+    }
+}
+
+/// A number, integers are compared exactly
+#[derive(Clone, Copy, Debug)]
+pub enum Number {
+    Int(i128),
+    Float(f64),
+}
+
+impl Number {
+    fn from_static(s: StaticNode) -> Option<Self> {
+        match s {
+            StaticNode::I64(i) => Some(Number::Int(i.into())),
+            StaticNode::U64(u) => Some(Number::Int(u.into())),
+            #[cfg(feature = "128bit")]
+            StaticNode::I128(i) => Some(Number::Int(i)),
+            #[cfg(feature = "128bit")]
+            #[allow(clippy::cast_precision_loss)]
+            StaticNode::U128(u) => {
+                Some(i128::try_from(u).map_or(Number::Float(u as f64), Number::Int))
+            }
+            #[allow(clippy::useless_conversion)] // .into() required by ordered-float
+            StaticNode::F64(f) => Some(Number::Float(f.into())),
+            StaticNode::Null | StaticNode::Bool(_) => None,
+        }
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn as_f64(self) -> f64 {
+        match self {
+            Number::Int(i) => i as f64,
+            Number::Float(f) => f,
+        }
+    }
+
+    fn compare(self, other: Self) -> Option<Ordering> {
+        match (self, other) {
+            (Number::Int(a), Number::Int(b)) => Some(a.cmp(&b)),
+            _ => self.as_f64().partial_cmp(&other.as_f64()),
+        }
+    }
+}
+
+/// Read access to the nodes of a document
+pub trait Access<'a>: Copy {
+    /// The kind of the node
+    fn kind(self) -> Kind<'a>;
+    /// The number of elements or members of an array or object
+    fn len(self) -> usize;
+    /// The member `name` of an object with its key in the document
+    fn member(self, name: &str) -> Option<(&'a str, Self)>;
+    /// The element `idx` of an array
+    fn element(self, idx: usize) -> Option<Self>;
+    /// The elements of an array
+    fn elements(self) -> impl Iterator<Item = Self>;
+    /// The members of an object
+    fn entries(self) -> impl Iterator<Item = (&'a str, Self)>;
+}
+
+impl<'a> Access<'a> for tape::Value<'a, '_> {
+    fn kind(self) -> Kind<'a> {
+        match self.0.first() {
+            Some(Node::Static(s)) => Kind::from_static(*s),
+            Some(Node::String(s)) => Kind::String(s),
+            Some(Node::Number(n, kind)) => Kind::from_static(number_to_static(n, *kind)),
+            Some(Node::Array { .. }) => Kind::Array,
+            Some(Node::Object { .. }) => Kind::Object,
+            None => Kind::Null,
+        }
+    }
+
+    fn len(self) -> usize {
+        match self.0.first() {
+            Some(Node::Array { len, .. } | Node::Object { len, .. }) => *len,
+            _ => 0,
+        }
+    }
+
+    fn member(self, name: &str) -> Option<(&'a str, Self)> {
+        // a linear scan over the keys, the values in between are skipped by
+        // their node count
+        self.entries().find(|(key, _)| *key == name)
+    }
+
+    fn element(self, idx: usize) -> Option<Self> {
+        self.get_idx(idx)
+    }
+
+    fn elements(self) -> impl Iterator<Item = Self> {
+        self.as_array().into_iter().flat_map(|a| a.iter())
+    }
+
+    // the map shortens the lifetime of the keys from the input to the tape
+    #[allow(clippy::map_identity)]
+    fn entries(self) -> impl Iterator<Item = (&'a str, Self)> {
+        self.as_object()
+            .into_iter()
+            .flat_map(|o| o.iter().map(|(key, value)| (key, value)))
+    }
+}
+
+impl<'a> Access<'a> for &'a borrowed::Value<'_> {
+    fn kind(self) -> Kind<'a> {
+        match self {
+            borrowed::Value::Static(s) => Kind::from_static(*s),
+            borrowed::Value::String(s) => Kind::String(s),
+            borrowed::Value::Number(n, kind) => Kind::from_static(number_to_static(n, *kind)),
+            borrowed::Value::Array(_) => Kind::Array,
+            borrowed::Value::Object(_) => Kind::Object,
+        }
+    }
+
+    fn len(self) -> usize {
+        match self {
+            borrowed::Value::Array(a) => a.len(),
+            borrowed::Value::Object(o) => o.len(),
+            _ => 0,
+        }
+    }
+
+    fn member(self, name: &str) -> Option<(&'a str, Self)> {
+        match self {
+            borrowed::Value::Object(o) => o.raw_entry().from_key(name).map(|(k, v)| (&**k, v)),
+            _ => None,
+        }
+    }
+
+    fn element(self, idx: usize) -> Option<Self> {
+        match self {
+            borrowed::Value::Array(a) => a.get(idx),
+            _ => None,
+        }
+    }
+
+    fn elements(self) -> impl Iterator<Item = Self> {
+        match self {
+            borrowed::Value::Array(a) => a.iter(),
+            _ => [].iter(),
+        }
+    }
+
+    fn entries(self) -> impl Iterator<Item = (&'a str, Self)> {
+        let object = match self {
+            borrowed::Value::Object(o) => Some(o),
+            _ => None,
+        };
+        object
+            .into_iter()
+            .flat_map(|o| o.iter().map(|(k, v)| (&**k, v)))
+    }
+}
+
+/// The location of the root node
+pub(super) const ROOT: usize = usize::MAX;
+
+/// Records where the selected nodes are, as a tree of path elements that
+/// point to their parent. Queries inside filters don't need locations.
+pub(super) struct Locations<'a>(Option<Vec<(usize, PathElement<'a>)>>);
+
+impl<'a> Locations<'a> {
+    pub(super) fn tracked() -> Self {
+        Self(Some(Vec::new()))
+    }
+
+    pub(super) fn untracked() -> Self {
+        Self(None)
+    }
+
+    fn child(&mut self, parent: usize, element: PathElement<'a>) -> usize {
+        match &mut self.0 {
+            Some(locations) => {
+                locations.push((parent, element));
+                locations.len() - 1
+            }
+            None => ROOT,
+        }
+    }
+
+    /// The path elements leading to `location`
+    pub(super) fn path(&self, mut location: usize) -> Vec<PathElement<'a>> {
+        let mut path = Vec::new();
+        while let Some((parent, element)) = self.0.as_ref().and_then(|l| l.get(location)) {
+            path.push(*element);
+            location = *parent;
+        }
+        path.reverse();
+        path
+    }
+}
+
+/// Evaluates `query` with `current` as `@` and `root` as `$`, returns the
+/// selected nodes with their locations
+pub(super) fn select<'a, V: Access<'a>>(
+    query: &Query,
+    current: V,
+    root: V,
+    locations: &mut Locations<'a>,
+) -> Vec<(V, usize)> {
+    let start = if query.relative { current } else { root };
+    let mut nodes = vec![(start, ROOT)];
+    let mut next = Vec::new();
+    for segment in &query.segments {
+        for (node, location) in nodes.drain(..) {
+            if segment.descendant {
+                let mut stack = vec![(node, location)];
+                let mut children = Vec::new();
+                while let Some((node, location)) = stack.pop() {
+                    for selector in &segment.selectors {
+                        apply(selector, node, location, root, locations, &mut next);
+                    }
+                    for_each_child(node, |child, element| {
+                        children.push((child, locations.child(location, element)));
+                    });
+                    stack.extend(children.drain(..).rev());
+                }
+            } else {
+                for selector in &segment.selectors {
+                    apply(selector, node, location, root, locations, &mut next);
+                }
+            }
+        }
+        std::mem::swap(&mut nodes, &mut next);
+    }
+    nodes
+}
+
+fn for_each_child<'a, V: Access<'a>>(node: V, mut f: impl FnMut(V, PathElement<'a>)) {
+    match node.kind() {
+        Kind::Array => {
+            for (idx, child) in node.elements().enumerate() {
+                f(child, PathElement::Index(idx));
+            }
+        }
+        Kind::Object => {
+            for (key, child) in node.entries() {
+                f(child, PathElement::Member(key));
+            }
+        }
+        _ => (),
+    }
+}
+
+/// The index `idx` selects in an array of `len` elements, negative ones
+/// count from the end
+#[allow(clippy::cast_possible_wrap, clippy::cast_sign_loss)]
+fn normalize(idx: i64, len: usize) -> Option<usize> {
+    let idx = if idx < 0 { len as i64 + idx } else { idx };
+    usize::try_from(idx).ok().filter(|idx| *idx < len)
+}
+
+/// The indexes a slice selects in an array of `len` elements, in order
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_possible_wrap,
+    clippy::cast_sign_loss
+)]
+fn slice(start: Option<i64>, end: Option<i64>, step: Option<i64>, len: usize) -> Vec<usize> {
+    let len = len as i64;
+    let step = step.unwrap_or(1);
+    let normalize = |i: i64| if i < 0 { len + i } else { i };
+    let mut indexes = Vec::new();
+    if step > 0 {
+        let lower = normalize(start.unwrap_or(0)).clamp(0, len);
+        let upper = normalize(end.unwrap_or(len)).clamp(0, len);
+        let mut i = lower;
+        while i < upper {
+            indexes.push(i as usize);
+            i += step;
+        }
+    } else if step < 0 {
+        let upper = normalize(start.unwrap_or(len - 1)).clamp(-1, len - 1);
+        let lower = normalize(end.unwrap_or(-len - 1)).clamp(-1, len - 1);
+        let mut i = upper;
+        while lower < i {
+            indexes.push(i as usize);
+            i += step;
+        }
+    }
+    indexes
+}
+
+fn apply<'a, V: Access<'a>>(
+    selector: &Selector,
+    node: V,
+    location: usize,
+    root: V,
+    locations: &mut Locations<'a>,
+    out: &mut Vec<(V, usize)>,
+) {
+    match selector {
+        Selector::Name(name) => {
+            if let Some((key, child)) = node.member(name) {
+                out.push((child, locations.child(location, PathElement::Member(key))));
+            }
+        }
+        Selector::Wildcard => for_each_child(node, |child, element| {
+            out.push((child, locations.child(location, element)));
+        }),
+        Selector::Index(idx) => {
+            if let Kind::Array = node.kind()
+                && let Some(idx) = normalize(*idx, node.len())
+                && let Some(child) = node.element(idx)
+            {
+                out.push((child, locations.child(location, PathElement::Index(idx))));
+            }
+        }
+        Selector::Slice { start, end, step } => {
+            if let Kind::Array = node.kind() {
+                let indexes = slice(*start, *end, *step, node.len());
+                let Some(&first) = indexes.first() else {
+                    return;
+                };
+                let mut push = |idx, child| {
+                    out.push((child, locations.child(location, PathElement::Index(idx))));
+                };
+                if let Ok(step) = usize::try_from(step.unwrap_or(1)) {
+                    // ascending, walk the elements up to the last index
+                    node.elements()
+                        .enumerate()
+                        .skip(first)
+                        .step_by(step)
+                        .take(indexes.len())
+                        .for_each(|(idx, child)| push(idx, child));
+                } else {
+                    // descending, the first index is the highest one
+                    let elements: Vec<V> = node.elements().take(first + 1).collect();
+                    for idx in indexes {
+                        push(idx, elements[idx]);
+                    }
+                }
+            }
+        }
+        Selector::Filter(expr) => for_each_child(node, |child, element| {
+            if test(expr, child, root) {
+                out.push((child, locations.child(location, element)));
+            }
+        }),
+    }
+}
+
+/// Evaluates a filter expression for the node `current`
+fn test<'a, V: Access<'a>>(expr: &Expr, current: V, root: V) -> bool {
+    match expr {
+        Expr::Or(exprs) => exprs.iter().any(|e| test(e, current, root)),
+        Expr::And(exprs) => exprs.iter().all(|e| test(e, current, root)),
+        Expr::Not(expr) => !test(expr, current, root),
+        Expr::Exists(query) => {
+            !select(query, current, root, &mut Locations::untracked()).is_empty()
+        }
+        Expr::Function(function) => logical(function, current, root),
+        Expr::Compare(left, op, right) => {
+            let left = operand(left, current, root);
+            let right = operand(right, current, root);
+            match op {
+                CmpOp::Eq => equal(&left, &right),
+                CmpOp::Ne => !equal(&left, &right),
+                CmpOp::Lt => less(&left, &right),
+                CmpOp::Le => less(&left, &right) || equal(&left, &right),
+                CmpOp::Gt => less(&right, &left),
+                CmpOp::Ge => less(&right, &left) || equal(&left, &right),
+            }
+        }
+    }
+}
+
+/// A value in a comparison, `Nothing` if a query selected no node
+enum Operand<'q, V> {
+    Nothing,
+    Node(V),
+    Literal(&'q Literal),
+    Number(Number),
+}
+
+/// A scalar value, as comparisons see it
+enum Scalar<'s> {
+    Null,
+    Bool(bool),
+    Number(Number),
+    String(&'s str),
+}
+
+impl<'a, V: Access<'a>> Operand<'_, V> {
+    /// The scalar value, `None` for nothing, arrays and objects
+    fn scalar<'r>(&'r self) -> Option<Scalar<'r>>
+    where
+        'a: 'r,
+    {
+        match self {
+            Operand::Nothing => None,
+            Operand::Node(node) => match node.kind() {
+                Kind::Null => Some(Scalar::Null),
+                Kind::Bool(b) => Some(Scalar::Bool(b)),
+                Kind::Number(n) => Some(Scalar::Number(n)),
+                Kind::String(s) => Some(Scalar::String(s)),
+                Kind::Array | Kind::Object => None,
+            },
+            Operand::Literal(literal) => Some(match literal {
+                Literal::Null => Scalar::Null,
+                Literal::Bool(b) => Scalar::Bool(*b),
+                Literal::Int(i) => Scalar::Number(Number::Int(*i)),
+                Literal::Float(f) => Scalar::Number(Number::Float(*f)),
+                Literal::String(s) => Scalar::String(s),
+            }),
+            Operand::Number(n) => Some(Scalar::Number(*n)),
+        }
+    }
+
+    fn as_str<'r>(&'r self) -> Option<&'r str>
+    where
+        'a: 'r,
+    {
+        match self.scalar() {
+            Some(Scalar::String(s)) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+fn equal<'a, V: Access<'a>>(left: &Operand<V>, right: &Operand<V>) -> bool {
+    match (left, right) {
+        (Operand::Nothing, Operand::Nothing) => true,
+        (Operand::Node(left), Operand::Node(right)) => deep_equal(*left, *right),
+        _ => match (left.scalar(), right.scalar()) {
+            (Some(left), Some(right)) => scalar_equal(&left, &right),
+            _ => false,
+        },
+    }
+}
+
+fn scalar_equal(left: &Scalar, right: &Scalar) -> bool {
+    match (left, right) {
+        (Scalar::Null, Scalar::Null) => true,
+        (Scalar::Bool(left), Scalar::Bool(right)) => left == right,
+        (Scalar::Number(left), Scalar::Number(right)) => {
+            left.compare(*right) == Some(Ordering::Equal)
+        }
+        (Scalar::String(left), Scalar::String(right)) => left == right,
+        _ => false,
+    }
+}
+
+fn deep_equal<'a, V: Access<'a>>(left: V, right: V) -> bool {
+    match (left.kind(), right.kind()) {
+        (Kind::Array, Kind::Array) => {
+            left.len() == right.len()
+                && left
+                    .elements()
+                    .zip(right.elements())
+                    .all(|(l, r)| deep_equal(l, r))
+        }
+        (Kind::Object, Kind::Object) => {
+            left.len() == right.len()
+                && left
+                    .entries()
+                    .all(|(key, l)| right.member(key).is_some_and(|(_, r)| deep_equal(l, r)))
+        }
+        (Kind::Array | Kind::Object, _) | (_, Kind::Array | Kind::Object) => false,
+        (left, right) => scalar_equal(&scalar_of(left), &scalar_of(right)),
+    }
+}
+
+fn scalar_of(kind: Kind<'_>) -> Scalar<'_> {
+    match kind {
+        Kind::Bool(b) => Scalar::Bool(b),
+        Kind::Number(n) => Scalar::Number(n),
+        Kind::String(s) => Scalar::String(s),
+        Kind::Null | Kind::Array | Kind::Object => Scalar::Null,
+    }
+}
+
+/// Only numbers and strings are ordered
+fn less<'a, V: Access<'a>>(left: &Operand<V>, right: &Operand<V>) -> bool {
+    match (left.scalar(), right.scalar()) {
+        (Some(Scalar::Number(left)), Some(Scalar::Number(right))) => {
+            left.compare(right) == Some(Ordering::Less)
+        }
+        (Some(Scalar::String(left)), Some(Scalar::String(right))) => left < right,
+        _ => false,
+    }
+}
+
+fn operand<'q, 'a, V: Access<'a>>(
+    comparable: &'q Comparable,
+    current: V,
+    root: V,
+) -> Operand<'q, V> {
+    match comparable {
+        Comparable::Literal(literal) => Operand::Literal(literal),
+        Comparable::Query(query) => {
+            singular(query, current, root).map_or(Operand::Nothing, Operand::Node)
+        }
+        Comparable::Function(function) => value(function, current, root),
+    }
+}
+
+/// The node a singular query selects, it is looked up directly
+fn singular<'a, V: Access<'a>>(query: &Query, current: V, root: V) -> Option<V> {
+    let mut node = if query.relative { current } else { root };
+    for segment in &query.segments {
+        node = match segment.selectors.first()? {
+            Selector::Name(name) => node.member(name)?.1,
+            Selector::Index(idx) => match node.kind() {
+                Kind::Array => node.element(normalize(*idx, node.len())?)?,
+                _ => return None,
+            },
+            _ => return None,
+        };
+    }
+    Some(node)
+}
+
+/// Evaluates a function returning a value
+fn value<'q, 'a, V: Access<'a>>(function: &'q Function, current: V, root: V) -> Operand<'q, V> {
+    #[allow(clippy::cast_possible_wrap)]
+    let count = |n: usize| Operand::Number(Number::Int(n as i128));
+    match function {
+        Function::Length(arg) => {
+            let arg = operand(arg, current, root);
+            if let Operand::Node(node) = arg {
+                match node.kind() {
+                    Kind::String(s) => count(s.chars().count()),
+                    Kind::Array | Kind::Object => count(node.len()),
+                    _ => Operand::Nothing,
+                }
+            } else {
+                arg.as_str()
+                    .map_or(Operand::Nothing, |s| count(s.chars().count()))
+            }
+        }
+        Function::Count(query) => {
+            count(select(query, current, root, &mut Locations::untracked()).len())
+        }
+        Function::Value(query) => {
+            match select(query, current, root, &mut Locations::untracked()).as_slice() {
+                [(node, _)] => Operand::Node(*node),
+                _ => Operand::Nothing,
+            }
+        }
+        Function::Regex { .. } => Operand::Nothing,
+    }
+}
+
+/// Evaluates a function returning a logical value
+#[cfg_attr(not(feature = "path-regex"), allow(unused_variables))]
+fn logical<'a, V: Access<'a>>(function: &Function, current: V, root: V) -> bool {
+    match function {
+        #[cfg(feature = "path-regex")]
+        Function::Regex {
+            full,
+            input,
+            pattern,
+            compiled,
+        } => {
+            let input = operand(input, current, root);
+            let Some(input) = input.as_str() else {
+                return false;
+            };
+            match compiled {
+                Some(regex) => regex.as_ref().is_some_and(|r| r.is_match(input)),
+                None => operand(pattern, current, root)
+                    .as_str()
+                    .and_then(|p| super::parser::compile_regex(p, *full))
+                    .is_some_and(|r| r.is_match(input)),
+            }
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::slice;
+    use crate::path::Path;
+    use crate::prelude::*;
+    use crate::{to_borrowed_value, to_tape};
+
+    /// The normalized paths `expr` selects in `json`, the tape and the
+    /// borrowed value have to select the same nodes
+    fn select(json: &str, expr: &str) -> Vec<String> {
+        let path = Path::compile(expr).expect("valid path");
+        let mut tape_input = json.as_bytes().to_vec();
+        let tape = to_tape(&mut tape_input).expect("valid JSON");
+        let mut value_input = json.as_bytes().to_vec();
+        let value = to_borrowed_value(&mut value_input).expect("valid JSON");
+
+        let on_tape: Vec<_> = path
+            .query(tape.as_value())
+            .into_iter()
+            .map(|m| (m.path.to_string(), m.value.encode()))
+            .collect();
+        let on_value: Vec<_> = path
+            .query(&value)
+            .into_iter()
+            .map(|m| (m.path.to_string(), m.value.encode()))
+            .collect();
+        assert_eq!(on_tape, on_value, "{expr}");
+        let selected: Vec<_> = path
+            .select(tape.as_value())
+            .iter()
+            .map(Writable::encode)
+            .collect();
+        let expected: Vec<_> = on_value.iter().map(|(_, v)| v.clone()).collect();
+        assert_eq!(selected, expected, "{expr}");
+        on_value.into_iter().map(|(path, _)| path).collect()
+    }
+
+    #[test]
+    fn rfc_examples() {
+        let names = r#"{"o": {"j j": {"k.k": 3}}, "'": {"@": 2}}"#;
+        let wildcards = r#"{"o": {"j": 1, "k": 2}, "a": [5, 3]}"#;
+        let letters = r#"["a", "b", "c", "d", "e", "f", "g"]"#;
+        let filters = r#"{"a": [3, 5, 1, 2, 4, 6, {"b": "j"}, {"b": "k"}, {"b": {}}, {"b": "kilo"}],
+            "o": {"p": 1, "q": 2, "r": 3, "s": 5, "t": {"u": 6}}, "e": "f"}"#;
+        let segments = r#"{"o": {"j": 1, "k": 2}, "a": [5, 3, [{"j": 4}, {"k": 6}]]}"#;
+        let nulls = r#"{"a": null, "b": [null], "c": [{}], "null": 1}"#;
+
+        let cases: &[(&str, &str, &[&str])] = &[
+            (names, "$.o['j j']", &["$['o']['j j']"]),
+            (names, "$.o['j j']['k.k']", &["$['o']['j j']['k.k']"]),
+            (names, r#"$.o["j j"]["k.k"]"#, &["$['o']['j j']['k.k']"]),
+            (names, r#"$["'"]["@"]"#, &[r"$['\'']['@']"]),
+            (wildcards, "$[*]", &["$['o']", "$['a']"]),
+            (wildcards, "$.o[*]", &["$['o']['j']", "$['o']['k']"]),
+            (
+                wildcards,
+                "$.o[*, *]",
+                &["$['o']['j']", "$['o']['k']", "$['o']['j']", "$['o']['k']"],
+            ),
+            (wildcards, "$.a[*]", &["$['a'][0]", "$['a'][1]"]),
+            (r#"["a", "b"]"#, "$[1]", &["$[1]"]),
+            (r#"["a", "b"]"#, "$[-2]", &["$[0]"]),
+            (letters, "$[1:3]", &["$[1]", "$[2]"]),
+            (letters, "$[5:]", &["$[5]", "$[6]"]),
+            (letters, "$[1:5:2]", &["$[1]", "$[3]"]),
+            (letters, "$[5:1:-2]", &["$[5]", "$[3]"]),
+            (
+                letters,
+                "$[::-1]",
+                &["$[6]", "$[5]", "$[4]", "$[3]", "$[2]", "$[1]", "$[0]"],
+            ),
+            (filters, "$.a[?@.b == 'kilo']", &["$['a'][9]"]),
+            (filters, "$.a[?(@.b == 'kilo')]", &["$['a'][9]"]),
+            (
+                filters,
+                "$.a[?@>3.5]",
+                &["$['a'][1]", "$['a'][4]", "$['a'][5]"],
+            ),
+            (
+                filters,
+                "$.a[?@.b]",
+                &["$['a'][6]", "$['a'][7]", "$['a'][8]", "$['a'][9]"],
+            ),
+            (filters, "$[?@.*]", &["$['a']", "$['o']"]),
+            (filters, "$[?@[?@.b]]", &["$['a']"]),
+            (
+                filters,
+                "$.o[?@<3, ?@<3]",
+                &["$['o']['p']", "$['o']['q']", "$['o']['p']", "$['o']['q']"],
+            ),
+            (
+                filters,
+                r#"$.a[?@<2 || @.b == "k"]"#,
+                &["$['a'][2]", "$['a'][7]"],
+            ),
+            (filters, "$.o[?@>1 && @<4]", &["$['o']['q']", "$['o']['r']"]),
+            (filters, "$.o[?@.u || @.x]", &["$['o']['t']"]),
+            (
+                filters,
+                "$.a[?@.b == $.x]",
+                &[
+                    "$['a'][0]",
+                    "$['a'][1]",
+                    "$['a'][2]",
+                    "$['a'][3]",
+                    "$['a'][4]",
+                    "$['a'][5]",
+                ],
+            ),
+            (segments, "$..j", &["$['o']['j']", "$['a'][2][0]['j']"]),
+            (segments, "$..[0]", &["$['a'][0]", "$['a'][2][0]"]),
+            (nulls, "$.a", &["$['a']"]),
+            (nulls, "$.a[0]", &[]),
+            (nulls, "$.a.d", &[]),
+            (nulls, "$.b[0]", &["$['b'][0]"]),
+            (nulls, "$.b[*]", &["$['b'][0]"]),
+            (nulls, "$.b[?@]", &["$['b'][0]"]),
+            (nulls, "$.b[?@==null]", &["$['b'][0]"]),
+            (nulls, "$.c[?@.d==null]", &[]),
+            (nulls, "$.null", &["$['null']"]),
+        ];
+        for &(json, expr, expected) in cases {
+            assert_eq!(select(json, expr), expected, "{expr}");
+        }
+        assert_eq!(select(filters, "$.a[?@ == @]").len(), 10);
+        assert_eq!(select(segments, "$..*").len(), 11);
+        assert_eq!(select(segments, "$..[*]"), select(segments, "$..*"));
+    }
+
+    #[test]
+    fn comparisons() {
+        // RFC 9535 2.3.5.3, a filter on constants selects all or nothing
+        let json = r#"{"obj": {"x": "y"}, "arr": [2, 3]}"#;
+        for (cmp, result) in [
+            ("$.absent1 == $.absent2", true),
+            ("$.absent1 <= $.absent2", true),
+            ("$.absent == 'g'", false),
+            ("$.absent1 != $.absent2", false),
+            ("$.absent != 'g'", true),
+            ("1 <= 2", true),
+            ("1 > 2", false),
+            ("13 == '13'", false),
+            ("'a' <= 'b'", true),
+            ("'a' > 'b'", false),
+            ("$.obj == $.arr", false),
+            ("$.obj != $.arr", true),
+            ("$.obj == $.obj", true),
+            ("$.obj != $.obj", false),
+            ("$.arr == $.arr", true),
+            ("$.arr != $.arr", false),
+            ("$.obj == 17", false),
+            ("$.obj != 17", true),
+            ("$.obj <= $.arr", false),
+            ("$.obj < $.arr", false),
+            ("$.obj <= $.obj", true),
+            ("$.arr <= $.arr", true),
+            ("1 <= $.arr", false),
+            ("1 >= $.arr", false),
+            ("1 > $.arr", false),
+            ("1 < $.arr", false),
+            ("true <= true", true),
+            ("true > true", false),
+            // numbers compare by value whatever their spelling
+            ("1 == 1.0", true),
+            ("100 == 1e2", true),
+            ("-0 == 0", true),
+            ("$.arr[0] == 2.0", true),
+            ("$.arr[1] > 2.5", true),
+            // strings compare by code point
+            ("'Z' < 'a'", true),
+            ("'ab' > 'a'", true),
+            (r"'\u00e9' > 'z'", true),
+            ("null == null", true),
+            ("null < null", false),
+            ("false < true", false),
+            ("$.obj.x == 'y'", true),
+        ] {
+            let selected = select(json, &format!("$[?{cmp}]"));
+            assert_eq!(selected.len(), if result { 2 } else { 0 }, "{cmp}");
+        }
+    }
+
+    #[test]
+    fn slices() {
+        assert_eq!(slice(None, None, None, 4), [0, 1, 2, 3]);
+        assert_eq!(slice(Some(1), Some(3), None, 4), [1, 2]);
+        assert_eq!(slice(None, None, Some(2), 5), [0, 2, 4]);
+        assert_eq!(slice(None, None, Some(-1), 3), [2, 1, 0]);
+        assert_eq!(slice(Some(-1), Some(-3), Some(-1), 4), [3, 2]);
+        assert_eq!(slice(Some(-10), Some(10), None, 2), [0, 1]);
+        assert_eq!(slice(None, None, Some(0), 3), Vec::<usize>::new());
+        assert_eq!(slice(Some(3), Some(1), None, 4), Vec::<usize>::new());
+    }
+}
@@ -0,0 +1,921 @@
+//! Parser for `JSONPath` expressions, it checks that they are well-typed as
+//! well so evaluation can't fail
+
+use crate::{Error, ErrorType, Result};
+
+/// The largest integer allowed in index and slice selectors, `2^53 - 1`
+const MAX_INT: i64 = 9_007_199_254_740_991;
+
+/// A query, either starting at the root (`$`) or, inside filters, at the
+/// current node (`@`)
+#[derive(Debug, Clone)]
+pub(super) struct Query {
+    pub(super) relative: bool,
+    pub(super) segments: Vec<Segment>,
+}
+
+impl Query {
+    /// A query that selects at most one node, only those can be compared
+    fn is_singular(&self) -> bool {
+        self.segments.iter().all(|s| {
+            !s.descendant
+                && matches!(
+                    s.selectors.as_slice(),
+                    [Selector::Name(_) | Selector::Index(_)]
+                )
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct Segment {
+    /// `..` segments apply the selectors to the node and all its
+    /// descendants
+    pub(super) descendant: bool,
+    pub(super) selectors: Vec<Selector>,
+}
+
+#[derive(Debug, Clone)]
+pub(super) enum Selector {
+    Name(String),
+    Wildcard,
+    Index(i64),
+    Slice {
+        start: Option<i64>,
+        end: Option<i64>,
+        step: Option<i64>,
+    },
+    Filter(Expr),
+}
+
+/// A logical expression of a filter selector
+#[derive(Debug, Clone)]
+pub(super) enum Expr {
+    Or(Vec<Expr>),
+    And(Vec<Expr>),
+    Not(Box<Expr>),
+    /// true if the query selects any node
+    Exists(Query),
+    /// a function returning a logical value
+    Function(Function),
+    Compare(Comparable, CmpOp, Comparable),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// A value in a comparison or a function argument of value type
+#[derive(Debug, Clone)]
+pub(super) enum Comparable {
+    Literal(Literal),
+    /// a singular query
+    Query(Query),
+    /// a function returning a value
+    Function(Function),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Literal {
+    Null,
+    Bool(bool),
+    Int(i128),
+    Float(f64),
+    String(String),
+}
+
+#[derive(Debug, Clone)]
+pub(super) enum Function {
+    Length(Box<Comparable>),
+    Count(Query),
+    Value(Query),
+    /// `match()` if `full`, `search()` otherwise
+    #[cfg_attr(not(feature = "path-regex"), allow(dead_code))]
+    Regex {
+        full: bool,
+        input: Box<Comparable>,
+        pattern: Box<Comparable>,
+        /// the compiled pattern if it is a literal, `None` if it isn't a
+        /// valid regular expression
+        #[cfg(feature = "path-regex")]
+        #[allow(clippy::option_option)]
+        compiled: Option<Option<regex::Regex>>,
+    },
+}
+
+/// The type a function returns
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FunctionType {
+    Value,
+    Logical,
+}
+
+impl Function {
+    fn result_type(&self) -> FunctionType {
+        match self {
+            Function::Length(_) | Function::Count(_) | Function::Value(_) => FunctionType::Value,
+            Function::Regex { .. } => FunctionType::Logical,
+        }
+    }
+}
+
+/// Translates an I-Regexp (RFC 9485) to the syntax of the `regex` crate,
+/// `.` doesn't match line breaks and `^` and `$` are no anchors
+#[cfg(feature = "path-regex")]
+pub(super) fn compile_regex(pattern: &str, full: bool) -> Option<regex::Regex> {
+    let mut translated = String::with_capacity(pattern.len() + 8);
+    translated.push_str(if full { r"\A(?:" } else { "(?:" });
+    let mut in_class = false;
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                translated.push(c);
+                translated.push(chars.next()?);
+            }
+            '[' if !in_class => {
+                in_class = true;
+                translated.push(c);
+            }
+            ']' if in_class => {
+                in_class = false;
+                translated.push(c);
+            }
+            '[' | '&' | '~' if in_class => {
+                translated.push('\\');
+                translated.push(c);
+            }
+            '.' if !in_class => translated.push_str(r"[^\n\r]"),
+            '^' | '$' if !in_class => {
+                translated.push('\\');
+                translated.push(c);
+            }
+            c => translated.push(c),
+        }
+    }
+    translated.push_str(if full { r")\z" } else { ")" });
+    regex::Regex::new(&translated).ok()
+}
+
+/// Parses `expr` into a query starting at the root
+pub(super) fn parse(expr: &str) -> Result<Query> {
+    let mut parser = Parser {
+        input: expr,
+        pos: 0,
+    };
+    if !parser.eat('$') {
+        return Err(parser.error("expected `$`"));
+    }
+    let segments = parser.segments()?;
+    if parser.pos < expr.len() {
+        return Err(parser.error("unexpected character"));
+    }
+    Ok(Query {
+        relative: false,
+        segments,
+    })
+}
+
+struct Parser<'p> {
+    input: &'p str,
+    pos: usize,
+}
+
+fn is_name_first(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_' || !c.is_ascii()
+}
+
+fn is_name_char(c: char) -> bool {
+    is_name_first(c) || c.is_ascii_digit()
+}
+
+impl<'p> Parser<'p> {
+    fn error(&self, msg: &str) -> Error {
+        Error::new(
+            self.pos,
+            self.peek(),
+            ErrorType::InvalidPath(msg.to_string()),
+        )
+    }
+
+    fn rest(&self) -> &'p str {
+        self.input.get(self.pos..).unwrap_or_default()
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_str(&mut self, s: &str) -> bool {
+        if self.rest().starts_with(s) {
+            self.pos += s.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char, msg: &str) -> Result<()> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.error(msg))
+        }
+    }
+
+    fn skip_blank(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start_matches([' ', '\t', '\n', '\r']).len();
+    }
+
+    fn segments(&mut self) -> Result<Vec<Segment>> {
+        let mut segments = Vec::new();
+        loop {
+            let start = self.pos;
+            self.skip_blank();
+            if self.eat_str("..") {
+                let selectors = if self.eat('*') {
+                    vec![Selector::Wildcard]
+                } else if self.peek() == Some('[') {
+                    self.bracketed()?
+                } else {
+                    vec![Selector::Name(self.member_name()?)]
+                };
+                segments.push(Segment {
+                    descendant: true,
+                    selectors,
+                });
+            } else if self.eat('.') {
+                let selector = if self.eat('*') {
+                    Selector::Wildcard
+                } else {
+                    Selector::Name(self.member_name()?)
+                };
+                segments.push(Segment {
+                    descendant: false,
+                    selectors: vec![selector],
+                });
+            } else if self.peek() == Some('[') {
+                segments.push(Segment {
+                    descendant: false,
+                    selectors: self.bracketed()?,
+                });
+            } else {
+                self.pos = start;
+                return Ok(segments);
+            }
+        }
+    }
+
+    fn member_name(&mut self) -> Result<String> {
+        let rest = self.rest();
+        if !rest.starts_with(is_name_first) {
+            return Err(self.error("expected a member name"));
+        }
+        let name = rest.split(|c| !is_name_char(c)).next().unwrap_or_default();
+        self.pos += name.len();
+        Ok(name.to_string())
+    }
+
+    fn bracketed(&mut self) -> Result<Vec<Selector>> {
+        self.expect('[', "expected `[`")?;
+        let mut selectors = Vec::new();
+        loop {
+            self.skip_blank();
+            selectors.push(self.selector()?);
+            self.skip_blank();
+            if self.eat(']') {
+                return Ok(selectors);
+            }
+            self.expect(',', "expected `,` or `]`")?;
+        }
+    }
+
+    fn selector(&mut self) -> Result<Selector> {
+        match self.peek() {
+            Some('\'' | '"') => Ok(Selector::Name(self.string()?)),
+            Some('*') => {
+                self.pos += 1;
+                Ok(Selector::Wildcard)
+            }
+            Some('?') => {
+                self.pos += 1;
+                self.skip_blank();
+                Ok(Selector::Filter(self.logical_or()?))
+            }
+            Some('-' | '0'..='9' | ':') => self.index_or_slice(),
+            _ => Err(self.error("expected a selector")),
+        }
+    }
+
+    fn index_or_slice(&mut self) -> Result<Selector> {
+        let start = self.maybe_int()?;
+        self.skip_blank();
+        if !self.eat(':') {
+            return start
+                .map(Selector::Index)
+                .ok_or_else(|| self.error("expected an index"));
+        }
+        self.skip_blank();
+        let end = self.maybe_int()?;
+        self.skip_blank();
+        let step = if self.eat(':') {
+            self.skip_blank();
+            self.maybe_int()?
+        } else {
+            None
+        };
+        Ok(Selector::Slice { start, end, step })
+    }
+
+    /// An integer without leading zeros in the range I-JSON allows
+    fn maybe_int(&mut self) -> Result<Option<i64>> {
+        let rest = self.rest();
+        let digits = rest.strip_prefix('-').unwrap_or(rest);
+        let len = digits.len()
+            - digits
+                .trim_start_matches(|c: char| c.is_ascii_digit())
+                .len();
+        if len == 0 {
+            return if digits.len() < rest.len() {
+                Err(self.error("expected an integer"))
+            } else {
+                Ok(None)
+            };
+        }
+        let text = &rest[..rest.len() - digits.len() + len];
+        if (digits.starts_with('0') && len > 1) || text == "-0" {
+            return Err(self.error("invalid integer"));
+        }
+        let int = text
+            .parse::<i64>()
+            .ok()
+            .filter(|i| (-MAX_INT..=MAX_INT).contains(i))
+            .ok_or_else(|| self.error("integer out of range"))?;
+        self.pos += text.len();
+        Ok(Some(int))
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let quote = self.peek().ok_or_else(|| self.error("expected a string"))?;
+        self.pos += 1;
+        let mut s = String::new();
+        loop {
+            let c = self
+                .peek()
+                .ok_or_else(|| self.error("unterminated string"))?;
+            self.pos += c.len_utf8();
+            match c {
+                '\\' => s.push(self.escape(quote)?),
+                c if c == quote => return Ok(s),
+                c if c < ' ' => {
+                    self.pos -= 1;
+                    return Err(self.error("control character in string"));
+                }
+                c => s.push(c),
+            }
+        }
+    }
+
+    fn escape(&mut self, quote: char) -> Result<char> {
+        let c = self
+            .peek()
+            .ok_or_else(|| self.error("unterminated string"))?;
+        self.pos += c.len_utf8();
+        Ok(match c {
+            'b' => '\u{8}',
+            'f' => '\u{c}',
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            '/' | '\\' => c,
+            c if c == quote => c,
+            'u' => {
+                let high = self.hex4()?;
+                if (0xDC00..0xE000).contains(&high) {
+                    return Err(self.error("unpaired surrogate"));
+                }
+                let code = if (0xD800..0xDC00).contains(&high) {
+                    if !self.eat_str("\\u") {
+                        return Err(self.error("unpaired surrogate"));
+                    }
+                    let low = self.hex4()?;
+                    if !(0xDC00..0xE000).contains(&low) {
+                        return Err(self.error("unpaired surrogate"));
+                    }
+                    0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+                } else {
+                    high
+                };
+                char::from_u32(code).ok_or_else(|| self.error("invalid escape"))?
+            }
+            _ => {
+                self.pos -= c.len_utf8();
+                return Err(self.error("invalid escape"));
+            }
+        })
+    }
+
+    fn hex4(&mut self) -> Result<u32> {
+        let hex = self
+            .rest()
+            .get(..4)
+            .filter(|h| h.chars().all(|c| c.is_ascii_hexdigit()))
+            .ok_or_else(|| self.error("expected four hex digits"))?;
+        self.pos += 4;
+        u32::from_str_radix(hex, 16).map_err(|_| self.error("expected four hex digits"))
+    }
+
+    fn logical_or(&mut self) -> Result<Expr> {
+        let mut exprs = vec![self.logical_and()?];
+        loop {
+            let start = self.pos;
+            self.skip_blank();
+            if !self.eat_str("||") {
+                self.pos = start;
+                break;
+            }
+            self.skip_blank();
+            exprs.push(self.logical_and()?);
+        }
+        Ok(if exprs.len() == 1 {
+            exprs.swap_remove(0)
+        } else {
+            Expr::Or(exprs)
+        })
+    }
+
+    fn logical_and(&mut self) -> Result<Expr> {
+        let mut exprs = vec![self.basic()?];
+        loop {
+            let start = self.pos;
+            self.skip_blank();
+            if !self.eat_str("&&") {
+                self.pos = start;
+                break;
+            }
+            self.skip_blank();
+            exprs.push(self.basic()?);
+        }
+        Ok(if exprs.len() == 1 {
+            exprs.swap_remove(0)
+        } else {
+            Expr::And(exprs)
+        })
+    }
+
+    fn basic(&mut self) -> Result<Expr> {
+        if self.eat('!') {
+            self.skip_blank();
+            let expr = if self.peek() == Some('(') {
+                self.paren()?
+            } else {
+                match self.comparable()? {
+                    Comparable::Query(query) => Expr::Exists(query),
+                    Comparable::Function(function) => self.test_function(function)?,
+                    Comparable::Literal(_) => return Err(self.error("literals can't be negated")),
+                }
+            };
+            return Ok(Expr::Not(Box::new(expr)));
+        }
+        if self.peek() == Some('(') {
+            return self.paren();
+        }
+        let left = self.comparable()?;
+        let start = self.pos;
+        self.skip_blank();
+        let Some(op) = self.comparison_op() else {
+            self.pos = start;
+            return match left {
+                Comparable::Query(query) => Ok(Expr::Exists(query)),
+                Comparable::Function(function) => self.test_function(function),
+                Comparable::Literal(_) => Err(self.error("expected a comparison")),
+            };
+        };
+        self.skip_blank();
+        let right = self.comparable()?;
+        Ok(Expr::Compare(
+            self.value_operand(left)?,
+            op,
+            self.value_operand(right)?,
+        ))
+    }
+
+    fn paren(&mut self) -> Result<Expr> {
+        self.expect('(', "expected `(`")?;
+        self.skip_blank();
+        let expr = self.logical_or()?;
+        self.skip_blank();
+        self.expect(')', "expected `)`")?;
+        Ok(expr)
+    }
+
+    fn comparison_op(&mut self) -> Option<CmpOp> {
+        for (s, op) in [
+            ("==", CmpOp::Eq),
+            ("!=", CmpOp::Ne),
+            ("<=", CmpOp::Le),
+            (">=", CmpOp::Ge),
+            ("<", CmpOp::Lt),
+            (">", CmpOp::Gt),
+        ] {
+            if self.eat_str(s) {
+                return Some(op);
+            }
+        }
+        None
+    }
+
+    /// A function used as a test has to return a logical value
+    fn test_function(&self, function: Function) -> Result<Expr> {
+        if function.result_type() == FunctionType::Logical {
+            Ok(Expr::Function(function))
+        } else {
+            Err(self.error("function result can't be used as a test"))
+        }
+    }
+
+    /// Checks that `comparable` yields a single value, only singular queries
+    /// and functions returning values do
+    fn value_operand(&self, comparable: Comparable) -> Result<Comparable> {
+        match &comparable {
+            Comparable::Query(query) if !query.is_singular() => {
+                Err(self.error("only singular queries can be compared"))
+            }
+            Comparable::Function(function) if function.result_type() != FunctionType::Value => {
+                Err(self.error("function result can't be compared"))
+            }
+            _ => Ok(comparable),
+        }
+    }
+
+    /// A literal, a query or a function call, the caller checks if it is
+    /// used correctly
+    fn comparable(&mut self) -> Result<Comparable> {
+        match self.peek() {
+            Some('@' | '$') => {
+                let relative = self.peek() == Some('@');
+                self.pos += 1;
+                Ok(Comparable::Query(Query {
+                    relative,
+                    segments: self.segments()?,
+                }))
+            }
+            Some('\'' | '"') => Ok(Comparable::Literal(Literal::String(self.string()?))),
+            Some('-' | '0'..='9') => Ok(Comparable::Literal(self.number()?)),
+            Some('a'..='z') => {
+                let rest = self.rest();
+                let name = rest
+                    .split(|c: char| !(c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_'))
+                    .next()
+                    .unwrap_or_default();
+                let start = self.pos;
+                self.pos += name.len();
+                if self.peek() == Some('(') {
+                    return self.function(name, start).map(Comparable::Function);
+                }
+                match name {
+                    "true" => Ok(Comparable::Literal(Literal::Bool(true))),
+                    "false" => Ok(Comparable::Literal(Literal::Bool(false))),
+                    "null" => Ok(Comparable::Literal(Literal::Null)),
+                    _ => {
+                        self.pos = start;
+                        Err(self.error("expected a literal, query or function"))
+                    }
+                }
+            }
+            _ => Err(self.error("expected a literal, query or function")),
+        }
+    }
+
+    fn number(&mut self) -> Result<Literal> {
+        let rest = self.rest();
+        let bytes = rest.as_bytes();
+        let digits = |from: usize| {
+            from + bytes
+                .get(from..)
+                .unwrap_or_default()
+                .iter()
+                .take_while(|b| b.is_ascii_digit())
+                .count()
+        };
+        let mut end = usize::from(bytes.first() == Some(&b'-'));
+        let int_start = end;
+        end = digits(end);
+        if end == int_start || (bytes[int_start] == b'0' && end > int_start + 1) {
+            return Err(self.error("invalid number"));
+        }
+        let mut float = false;
+        if bytes.get(end) == Some(&b'.') {
+            let frac = digits(end + 1);
+            if frac == end + 1 {
+                return Err(self.error("invalid number"));
+            }
+            end = frac;
+            float = true;
+        }
+        if matches!(bytes.get(end), Some(b'e' | b'E')) {
+            let mut exp = end + 1;
+            if matches!(bytes.get(exp), Some(b'+' | b'-')) {
+                exp += 1;
+            }
+            let exp_end = digits(exp);
+            if exp_end == exp {
+                return Err(self.error("invalid number"));
+            }
+            end = exp_end;
+            float = true;
+        }
+        let text = &rest[..end];
+        let literal = if float {
+            None
+        } else {
+            text.parse().ok().map(Literal::Int)
+        };
+        let literal = match literal {
+            Some(literal) => literal,
+            None => text
+                .parse()
+                .map(Literal::Float)
+                .map_err(|_| self.error("invalid number"))?,
+        };
+        self.pos += end;
+        Ok(literal)
+    }
+
+    fn function(&mut self, name: &str, start: usize) -> Result<Function> {
+        self.expect('(', "expected `(`")?;
+        self.skip_blank();
+        let function = match name {
+            "length" => Function::Length(Box::new(self.value_arg()?)),
+            "count" => Function::Count(self.nodes_arg()?),
+            "value" => Function::Value(self.nodes_arg()?),
+            "match" | "search" => {
+                let input = self.value_arg()?;
+                self.skip_blank();
+                self.expect(',', "expected `,`")?;
+                self.skip_blank();
+                let pattern = self.value_arg()?;
+                self.regex_function(name == "match", input, pattern, start)?
+            }
+            _ => {
+                self.pos = start;
+                return Err(self.error("unknown function"));
+            }
+        };
+        self.skip_blank();
+        self.expect(')', "expected `)`")?;
+        Ok(function)
+    }
+
+    #[cfg(feature = "path-regex")]
+    #[allow(clippy::unnecessary_wraps, clippy::unused_self)]
+    fn regex_function(
+        &self,
+        full: bool,
+        input: Comparable,
+        pattern: Comparable,
+        _start: usize,
+    ) -> Result<Function> {
+        let compiled = match &pattern {
+            Comparable::Literal(Literal::String(p)) => Some(compile_regex(p, full)),
+            Comparable::Literal(_) => Some(None),
+            _ => None,
+        };
+        Ok(Function::Regex {
+            full,
+            input: Box::new(input),
+            pattern: Box::new(pattern),
+            compiled,
+        })
+    }
+
+    #[cfg(not(feature = "path-regex"))]
+    fn regex_function(
+        &mut self,
+        _full: bool,
+        _input: Comparable,
+        _pattern: Comparable,
+        start: usize,
+    ) -> Result<Function> {
+        self.pos = start;
+        Err(self.error("`match` and `search` need the `path-regex` feature"))
+    }
+
+    /// An argument of value type
+    fn value_arg(&mut self) -> Result<Comparable> {
+        let arg = self.comparable()?;
+        self.value_operand(arg)
+    }
+
+    /// An argument of nodes type
+    fn nodes_arg(&mut self) -> Result<Query> {
+        match self.comparable()? {
+            Comparable::Query(query) => Ok(query),
+            _ => Err(self.error("expected a query")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse;
+    use crate::ErrorType;
+
+    /// Checks that every expression is accepted or rejected as expected,
+    /// rejections have to be `InvalidPath` errors
+    fn check(cases: &[(&str, bool)]) {
+        for &(expr, valid) in cases {
+            match parse(expr) {
+                Ok(_) => assert!(valid, "{expr} should be rejected"),
+                Err(e) => {
+                    assert!(!valid, "{expr} should be accepted: {e}");
+                    assert!(matches!(e.error(), ErrorType::InvalidPath(_)), "{expr}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rfc_examples() {
+        check(&[
+            // 1.5
+            ("$.store.book[*].author", true),
+            ("$..author", true),
+            ("$.store.*", true),
+            ("$.store..price", true),
+            ("$..book[2]", true),
+            ("$..book[2].author", true),
+            ("$..book[2].publisher", true),
+            ("$..book[-1]", true),
+            ("$..book[0,1]", true),
+            ("$..book[:2]", true),
+            ("$..book[?@.isbn]", true),
+            ("$..book[?@.price<10]", true),
+            ("$..*", true),
+            // 2.3.1 name selector
+            ("$.o['j j']", true),
+            ("$.o['j j']['k.k']", true),
+            (r#"$.o["j j"]["k.k"]"#, true),
+            (r#"$["'"]["@"]"#, true),
+            // 2.3.2 wildcard selector
+            ("$[*]", true),
+            ("$.o[*]", true),
+            ("$.o[*, *]", true),
+            ("$.a[*]", true),
+            // 2.3.3 index selector
+            ("$[1]", true),
+            ("$[-2]", true),
+            // 2.3.4 array slice selector
+            ("$[1:3]", true),
+            ("$[5:]", true),
+            ("$[1:5:2]", true),
+            ("$[5:1:-2]", true),
+            ("$[::-1]", true),
+            // 2.3.5 filter selector
+            ("$.a[?@.b == 'kilo']", true),
+            ("$.a[?(@.b == 'kilo')]", true),
+            ("$.a[?@>3.5]", true),
+            ("$.a[?@.b]", true),
+            ("$[?@.*]", true),
+            ("$[?@[?@.b]]", true),
+            ("$.o[?@<3, ?@<3]", true),
+            (r#"$.a[?@<2 || @.b == "k"]"#, true),
+            ("$.o[?@>1 && @<4]", true),
+            ("$.o[?@.u || @.x]", true),
+            ("$.a[?@.b == $.x]", true),
+            ("$.a[?@ == @]", true),
+            // 2.4 function extensions
+            ("$[?length(@) < 3]", true),
+            ("$[?length(@.*) < 3]", false),
+            ("$[?count(@.*) == 1]", true),
+            ("$[?count(1) == 1]", false),
+            (r#"$[?value(@..color) == "red"]"#, true),
+            ("$[?value(@..color)]", false),
+            // 2.5 segments
+            ("$[0, 3]", true),
+            ("$[0:2, 5]", true),
+            ("$[0, 0]", true),
+            ("$..j", true),
+            ("$..[0]", true),
+            ("$..[*]", true),
+            // 2.6 semantics of null
+            ("$.a", true),
+            ("$.a[0]", true),
+            ("$.a.d", true),
+            ("$.b[0]", true),
+            ("$.b[*]", true),
+            ("$.b[?@]", true),
+            ("$.b[?@==null]", true),
+            ("$.c[?@.d==null]", true),
+            ("$.null", true),
+        ]);
+    }
+
+    #[test]
+    fn integers() {
+        check(&[
+            ("$[9007199254740991]", true),
+            ("$[-9007199254740991]", true),
+            ("$[9007199254740992]", false),
+            ("$[-9007199254740992]", false),
+            ("$[:9007199254740991]", true),
+            ("$[-9007199254740992:]", false),
+            ("$[::9007199254740992]", false),
+            ("$[0]", true),
+            ("$[01]", false),
+            ("$[-01]", false),
+            ("$[00]", false),
+            ("$[0:01]", false),
+            ("$[::01]", false),
+            ("$[-0]", false),
+            ("$[-0:]", false),
+            ("$[::-0]", false),
+            ("$[1e2]", false),
+            ("$[1.0]", false),
+            // number literals in filters allow `-0`, fractions and exponents
+            ("$[?@ == 0]", true),
+            ("$[?@ == -0]", true),
+            ("$[?@ == -0.5e-3]", true),
+            ("$[?@ == 1E+2]", true),
+            ("$[?@ == 01]", false),
+            ("$[?@ == -01]", false),
+            ("$[?@ == 1.]", false),
+            ("$[?@ == .5]", false),
+            ("$[?@ == 1e]", false),
+            ("$[?@ == +1]", false),
+        ]);
+    }
+
+    #[test]
+    fn functions() {
+        check(&[
+            // arity
+            ("$[?length() == 1]", false),
+            ("$[?length(@.a, @.b) == 1]", false),
+            ("$[?count() == 1]", false),
+            ("$[?count(@.a, @.b) == 1]", false),
+            ("$[?value() == 1]", false),
+            ("$[?value(@.a, @.b) == 1]", false),
+            // argument types
+            ("$[?length(1) == 1]", true),
+            ("$[?length('abc') == 3]", true),
+            ("$[?length(@.a) == 1]", true),
+            ("$[?length(count(@.*)) == 1]", true),
+            ("$[?length(value(@..a)) == 1]", true),
+            ("$[?length(@..a) == 1]", false),
+            ("$[?count(1) == 1]", false),
+            ("$[?count('a') == 1]", false),
+            ("$[?count(length(@)) == 1]", false),
+            ("$[?count(@..a) == 1]", true),
+            ("$[?value(1) == 1]", false),
+            ("$[?value(@[*]) == 1]", true),
+            // result types
+            ("$[?length(@.a)]", false),
+            ("$[?count(@.*)]", false),
+            ("$[?!value(@.a)]", false),
+            ("$[?count(@.*) == count(@.*)]", true),
+            // names
+            ("$[?foo(@.a) == 1]", false),
+            ("$[?Length(@.a) == 1]", false),
+            ("$[?length (@.a) == 1]", false),
+        ]);
+    }
+
+    #[test]
+    fn regex_functions() {
+        let cases = [
+            ("$[?match(@.a, 'a')]", true),
+            ("$[?search(@.a, 'a')]", true),
+            ("$[?!match(@.a, $.p)]", true),
+            ("$[?match(@.a)]", false),
+            ("$[?search(@.a, 'a', 'b')]", false),
+            ("$[?match(@.*, 'a')]", false),
+            ("$[?match(@.a, 'a') == true]", false),
+        ];
+        #[cfg(feature = "path-regex")]
+        check(&cases);
+        #[cfg(not(feature = "path-regex"))]
+        {
+            let without: Vec<_> = cases.iter().map(|&(expr, _)| (expr, false)).collect();
+            check(&without);
+            let e = parse("$[?search(@.a, 'a')]").expect_err("needs the feature");
+            assert!(e.to_string().contains("path-regex"), "{e}");
+        }
+    }
+}
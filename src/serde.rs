@@ -7,9 +7,17 @@
 /// directly to structs this is the place to go.
 ///
 mod de;
+mod number;
+mod raw;
 mod se;
+mod stream;
 mod value;
+pub use self::number::Number;
+pub(crate) use self::number::{TOKEN as NUMBER_TOKEN, serialize as serialize_number};
+pub(crate) use self::raw::{RawSource, TOKEN as RAW_VALUE_TOKEN};
+pub use self::raw::{RawValue, to_raw_value};
 pub use self::se::*;
+pub use self::stream::{DEFAULT_CHUNK_SIZE, StreamDeserializer};
 pub use self::value::*;
 use crate::{BorrowedValue, OwnedValue};
 use crate::{Buffers, Deserializer, Error, ErrorType, Node, Result, macros::stry};
@@ -78,11 +86,60 @@ where
     T::deserialize(&mut deserializer)
 }
 
+/// Parses a byte slice using a serde deserializer without modifying it.
+///
+/// The input is copied before parsing, strings that contain no escape
+/// sequences are borrowed from `s` while strings that had to be unescaped
+/// are passed to the deserializer as owned. For a `&str` use
+/// `from_bytes(s.as_bytes())`.
+///
+/// # Errors
+///
+/// Will return `Err` if `s` is invalid JSON.
+#[cfg_attr(not(feature = "no-inline"), inline)]
+pub fn from_bytes<'a, T>(s: &'a [u8]) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    let mut buffers = Buffers::new(s.len());
+    from_bytes_with_buffers(s, &mut buffers)
+}
+
+/// Parses a byte slice using a serde deserializer without modifying it.
+///
+/// The input is copied before parsing, strings that contain no escape
+/// sequences are borrowed from `s` while strings that had to be unescaped
+/// are passed to the deserializer as owned.
+///
+/// Passes in reusable buffers to reduce allocations
+///
+/// # Errors
+///
+/// Will return `Err` if `s` is invalid JSON.
+#[cfg_attr(not(feature = "no-inline"), inline)]
+pub fn from_bytes_with_buffers<'a, T>(s: &'a [u8], buffers: &mut Buffers) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    let mut copy = std::mem::take(&mut buffers.scratch);
+    copy.clear();
+    copy.extend_from_slice(s);
+    let base = copy.as_ptr() as usize;
+    let res = Deserializer::from_slice_with_buffers(&mut copy, buffers).and_then(|de| {
+        // Safety: the deserializer is dropped before the copy
+        let mut de = unsafe { de.reborrow(s, base) };
+        T::deserialize(&mut de)
+    });
+    buffers.scratch = copy;
+    res
+}
+
 /// Parses a str using a serde deserializer.
 /// note that the slice will be rewritten in the process and
 /// might not remain a valid utf8 string in its entirety.
 ///
-/// It is recommended to use `from_slice` instead.
+/// It is recommended to use `from_slice`, or `from_bytes` if the input
+/// must not be modified, instead.
 ///
 /// # Errors
 ///
@@ -107,7 +164,8 @@ where
 /// note that the slice will be rewritten in the process and
 /// might not remain a valid utf8 string in its entirety.
 ///
-/// It is recommended to use `from_slice_with_buffers` instead.
+/// It is recommended to use `from_slice_with_buffers`, or
+/// `from_bytes_with_buffers` if the input must not be modified, instead.
 ///
 /// Passes in reusable buffers to reduce allocations.
 ///
@@ -137,10 +195,11 @@ where
 ///
 /// # Warning
 ///
-/// Since simd-json does not support streaming and requires mutability of the data, this function
-/// will read the entire reader into memory before parsing it.
+/// Since simd-json requires mutability of the data, this function will read the entire
+/// reader into memory before parsing it.
 ///
-/// You might do better using `from_slice` and managing the read buffer yourself.
+/// To parse a large array with bounded memory use [`StreamDeserializer::array_items`],
+/// which reads the input in chunks and yields one item at a time.
 ///
 /// # Errors
 ///
@@ -182,6 +241,120 @@ where
     T::deserialize(&mut deserializer)
 }
 
+/// Hands the text a number or raw value hands over as its only field to
+/// `write`, which writes it as is
+pub(crate) fn write_verbatim<T, W>(token: &str, value: &T, write: W) -> Result<()>
+where
+    T: ?Sized + serde_ext::Serialize,
+    W: FnOnce(&str) -> Result<()>,
+{
+    if token == NUMBER_TOKEN {
+        number::write_text(value, write)
+    } else {
+        raw::write_text(value, write)
+    }
+}
+
+/// A serializer that only takes strings and hands them to a closure, so
+/// the text of numbers and raw values can be written without building a
+/// value from it first. Everything else fails with `error`.
+pub(crate) struct TextSerializer<W> {
+    write: W,
+    error: ErrorType,
+}
+
+impl<W> TextSerializer<W> {
+    pub(crate) fn new(write: W, error: ErrorType) -> Self {
+        Self { write, error }
+    }
+}
+
+/// Serializer methods that fail for anything but a string
+macro_rules! not_text {
+    ($($method:ident($($arg:ty),*) -> $ok:ty;)*) => {
+        $(
+            fn $method(self, $(_: $arg),*) -> Result<$ok> {
+                Err(Error::generic(self.error))
+            }
+        )*
+    };
+}
+
+impl<W> serde_ext::Serializer for TextSerializer<W>
+where
+    W: FnOnce(&str) -> Result<()>,
+{
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = serde_ext::ser::Impossible<(), Error>;
+    type SerializeTuple = serde_ext::ser::Impossible<(), Error>;
+    type SerializeTupleStruct = serde_ext::ser::Impossible<(), Error>;
+    type SerializeTupleVariant = serde_ext::ser::Impossible<(), Error>;
+    type SerializeMap = serde_ext::ser::Impossible<(), Error>;
+    type SerializeStruct = serde_ext::ser::Impossible<(), Error>;
+    type SerializeStructVariant = serde_ext::ser::Impossible<(), Error>;
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn serialize_str(self, value: &str) -> Result<()> {
+        (self.write)(value)
+    }
+
+    not_text! {
+        serialize_bool(bool) -> ();
+        serialize_i8(i8) -> ();
+        serialize_i16(i16) -> ();
+        serialize_i32(i32) -> ();
+        serialize_i64(i64) -> ();
+        serialize_u8(u8) -> ();
+        serialize_u16(u16) -> ();
+        serialize_u32(u32) -> ();
+        serialize_u64(u64) -> ();
+        serialize_f32(f32) -> ();
+        serialize_f64(f64) -> ();
+        serialize_char(char) -> ();
+        serialize_bytes(&[u8]) -> ();
+        serialize_none() -> ();
+        serialize_unit() -> ();
+        serialize_unit_struct(&'static str) -> ();
+        serialize_unit_variant(&'static str, u32, &'static str) -> ();
+        serialize_seq(Option<usize>) -> Self::SerializeSeq;
+        serialize_tuple(usize) -> Self::SerializeTuple;
+        serialize_tuple_struct(&'static str, usize) -> Self::SerializeTupleStruct;
+        serialize_tuple_variant(&'static str, u32, &'static str, usize) -> Self::SerializeTupleVariant;
+        serialize_map(Option<usize>) -> Self::SerializeMap;
+        serialize_struct(&'static str, usize) -> Self::SerializeStruct;
+        serialize_struct_variant(&'static str, u32, &'static str, usize) -> Self::SerializeStructVariant;
+    }
+
+    fn serialize_some<T>(self, _value: &T) -> Result<()>
+    where
+        T: ?Sized + serde_ext::Serialize,
+    {
+        Err(Error::generic(self.error))
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, _value: &T) -> Result<()>
+    where
+        T: ?Sized + serde_ext::Serialize,
+    {
+        Err(Error::generic(self.error))
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<()>
+    where
+        T: ?Sized + serde_ext::Serialize,
+    {
+        Err(Error::generic(self.error))
+    }
+}
+
 impl serde::de::Error for Error {
     fn custom<T: fmt::Display>(msg: T) -> Self {
         Self::generic(ErrorType::Serde(msg.to_string()))
@@ -195,14 +368,70 @@ impl serde_ext::ser::Error for Error {
 }
 
 // Functions purely used by serde
-impl<'de> Deserializer<'de> {
+impl<'de, T> Deserializer<'de, T>
+where
+    T: AsRef<[Node<'de>]>,
+{
+    /// Hands the string `s` of the tape to `visitor`, borrowed unless it
+    /// only lives as long as the deserializer
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn visit_str<V>(&self, visitor: V, s: &'de str) -> Result<V::Value>
+    where
+        V: serde_ext::de::Visitor<'de>,
+    {
+        if self.transient.contains(&(s.as_ptr() as usize)) {
+            visitor.visit_str(s)
+        } else {
+            visitor.visit_borrowed_str(s)
+        }
+    }
+
+    /// Moves a deserializer over a copy of `input` starting at `base` to
+    /// `input`. Strings and numbers are borrowed from `input` unless they
+    /// were changed by unescaping, those are only handed out as `transient`.
+    ///
+    /// # Safety
+    ///
+    /// The deserializer must not outlive the copy.
+    unsafe fn reborrow<'input>(self, input: &'input [u8], base: usize) -> Deserializer<'input> {
+        let transient = base..base + input.len();
+        let reborrow = |s: &'de str| {
+            crate::value::borrowed::reborrow(input, transient.start, s).unwrap_or_else(|| {
+                // Safety: `s` is in the copy, which outlives the deserializer,
+                // and as it is `transient` it is never borrowed
+                unsafe { std::mem::transmute::<&'de str, &'input str>(s) }
+            })
+        };
+        let tape = self
+            .tape
+            .as_ref()
+            .iter()
+            .map(|node| match *node {
+                Node::String(s) => Node::String(reborrow(s)),
+                Node::Number(n, kind) => Node::Number(reborrow(n), kind),
+                Node::Object { len, count } => Node::Object { len, count },
+                Node::Array { len, count } => Node::Array { len, count },
+                Node::Static(s) => Node::Static(s),
+            })
+            .collect::<Vec<_>>();
+        Deserializer {
+            tape,
+            idx: 0,
+            duplicate_keys: self.duplicate_keys,
+            raw_source: self.raw_source.map(|source| source.with_borrowed(input)),
+            transient,
+            _input: std::marker::PhantomData,
+        }
+    }
+
     #[cfg_attr(not(feature = "no-inline"), inline)]
     fn next(&mut self) -> Result<Node<'de>> {
         let r = self
             .tape
+            .as_ref()
             .get(self.idx)
             .copied()
-            .ok_or_else(|| Self::error(ErrorType::Syntax));
+            .ok_or_else(|| Deserializer::error(ErrorType::Syntax));
         self.idx += 1;
         r
     }
@@ -210,131 +439,166 @@ impl<'de> Deserializer<'de> {
     #[cfg_attr(not(feature = "no-inline"), inline)]
     fn peek(&self) -> Result<Node<'de>> {
         self.tape
+            .as_ref()
             .get(self.idx)
             .copied()
-            .ok_or_else(|| Self::error(ErrorType::Eof))
+            .ok_or_else(|| Deserializer::error(ErrorType::Eof))
+    }
+
+    /// The source text of the next value, the value is skipped. Without the
+    /// input the value is encoded from its nodes.
+    fn next_raw(&mut self) -> Result<std::borrow::Cow<'de, str>> {
+        let node = stry!(self.peek());
+        let Some(source) = self.raw_source.as_mut() else {
+            let end = self.idx + node.count();
+            let nodes = stry!(
+                self.tape
+                    .as_ref()
+                    .get(self.idx..end)
+                    .ok_or_else(|| Deserializer::error(ErrorType::Eof))
+            );
+            self.idx = end;
+            return Ok(std::borrow::Cow::Owned(crate::tape::Value(nodes).encode()));
+        };
+        let text = stry!(
+            source
+                .text(self.idx)
+                .ok_or_else(|| Deserializer::error(ErrorType::Syntax))
+        );
+        self.idx += node.count();
+        Ok(text)
+    }
+
+    /// The next node if it is a static value, arbitrary precision numbers
+    /// are converted to the closest one
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn next_static(&mut self) -> Option<StaticNode> {
+        match unsafe { self.next_() } {
+            Node::Static(s) => Some(s),
+            Node::Number(n, kind) => Some(crate::value::number_to_static(n, kind)),
+            _ => None,
+        }
     }
 
     #[cfg_attr(not(feature = "no-inline"), inline)]
     #[allow(clippy::cast_sign_loss)]
     fn parse_u8(&mut self) -> Result<u8> {
-        match unsafe { self.next_() } {
-            Node::Static(s) => s
+        match self.next_static() {
+            Some(s) => s
                 .as_u8()
-                .ok_or_else(|| Self::error(ErrorType::ExpectedUnsigned)),
-            _ => Err(Self::error(ErrorType::ExpectedUnsigned)),
+                .ok_or_else(|| Deserializer::error(ErrorType::ExpectedUnsigned)),
+            None => Err(Deserializer::error(ErrorType::ExpectedUnsigned)),
         }
     }
 
     #[cfg_attr(not(feature = "no-inline"), inline)]
     #[allow(clippy::cast_sign_loss)]
     fn parse_u16(&mut self) -> Result<u16> {
-        let next = unsafe { self.next_() };
-        match next {
-            Node::Static(s) => s
+        match self.next_static() {
+            Some(s) => s
                 .as_u16()
-                .ok_or_else(|| Self::error(ErrorType::ExpectedUnsigned)),
-            _ => Err(Self::error(ErrorType::ExpectedUnsigned)),
+                .ok_or_else(|| Deserializer::error(ErrorType::ExpectedUnsigned)),
+            None => Err(Deserializer::error(ErrorType::ExpectedUnsigned)),
         }
     }
 
     #[cfg_attr(not(feature = "no-inline"), inline)]
     #[allow(clippy::cast_sign_loss)]
     fn parse_u32(&mut self) -> Result<u32> {
-        match unsafe { self.next_() } {
-            Node::Static(s) => s
+        match self.next_static() {
+            Some(s) => s
                 .as_u32()
-                .ok_or_else(|| Self::error(ErrorType::ExpectedUnsigned)),
-            _ => Err(Self::error(ErrorType::ExpectedUnsigned)),
+                .ok_or_else(|| Deserializer::error(ErrorType::ExpectedUnsigned)),
+            None => Err(Deserializer::error(ErrorType::ExpectedUnsigned)),
         }
     }
 
     #[cfg_attr(not(feature = "no-inline"), inline)]
     #[allow(clippy::cast_sign_loss)]
     fn parse_u64(&mut self) -> Result<u64> {
-        match unsafe { self.next_() } {
-            Node::Static(s) => s
+        match self.next_static() {
+            Some(s) => s
                 .as_u64()
-                .ok_or_else(|| Self::error(ErrorType::ExpectedUnsigned)),
-            _ => Err(Self::error(ErrorType::ExpectedUnsigned)),
+                .ok_or_else(|| Deserializer::error(ErrorType::ExpectedUnsigned)),
+            None => Err(Deserializer::error(ErrorType::ExpectedUnsigned)),
         }
     }
 
     #[cfg_attr(not(feature = "no-inline"), inline)]
     #[allow(clippy::cast_sign_loss)]
     fn parse_u128(&mut self) -> Result<u128> {
-        match unsafe { self.next_() } {
-            Node::Static(s) => s
+        match self.next_static() {
+            Some(s) => s
                 .as_u128()
-                .ok_or_else(|| Self::error(ErrorType::ExpectedUnsigned)),
-            _ => Err(Self::error(ErrorType::ExpectedUnsigned)),
+                .ok_or_else(|| Deserializer::error(ErrorType::ExpectedUnsigned)),
+            None => Err(Deserializer::error(ErrorType::ExpectedUnsigned)),
         }
     }
 
     #[cfg_attr(not(feature = "no-inline"), inline)]
     #[allow(clippy::cast_sign_loss)]
     fn parse_i8(&mut self) -> Result<i8> {
-        match unsafe { self.next_() } {
-            Node::Static(s) => s
+        match self.next_static() {
+            Some(s) => s
                 .as_i8()
-                .ok_or_else(|| Self::error(ErrorType::ExpectedSigned)),
-            _ => Err(Self::error(ErrorType::ExpectedSigned)),
+                .ok_or_else(|| Deserializer::error(ErrorType::ExpectedSigned)),
+            None => Err(Deserializer::error(ErrorType::ExpectedSigned)),
         }
     }
 
     #[cfg_attr(not(feature = "no-inline"), inline)]
     #[allow(clippy::cast_sign_loss)]
     fn parse_i16(&mut self) -> Result<i16> {
-        match unsafe { self.next_() } {
-            Node::Static(s) => s
+        match self.next_static() {
+            Some(s) => s
                 .as_i16()
-                .ok_or_else(|| Self::error(ErrorType::ExpectedSigned)),
-            _ => Err(Self::error(ErrorType::ExpectedSigned)),
+                .ok_or_else(|| Deserializer::error(ErrorType::ExpectedSigned)),
+            None => Err(Deserializer::error(ErrorType::ExpectedSigned)),
         }
     }
 
     #[cfg_attr(not(feature = "no-inline"), inline)]
     #[allow(clippy::cast_sign_loss)]
     fn parse_i32(&mut self) -> Result<i32> {
-        match unsafe { self.next_() } {
-            Node::Static(s) => s
+        match self.next_static() {
+            Some(s) => s
                 .as_i32()
-                .ok_or_else(|| Self::error(ErrorType::ExpectedSigned)),
-            _ => Err(Self::error(ErrorType::ExpectedSigned)),
+                .ok_or_else(|| Deserializer::error(ErrorType::ExpectedSigned)),
+            None => Err(Deserializer::error(ErrorType::ExpectedSigned)),
         }
     }
 
     #[cfg_attr(not(feature = "no-inline"), inline)]
     #[allow(clippy::cast_sign_loss)]
     fn parse_i64(&mut self) -> Result<i64> {
-        match unsafe { self.next_() } {
-            Node::Static(s) => s
+        match self.next_static() {
+            Some(s) => s
                 .as_i64()
-                .ok_or_else(|| Self::error(ErrorType::ExpectedSigned)),
-            _ => Err(Self::error(ErrorType::ExpectedSigned)),
+                .ok_or_else(|| Deserializer::error(ErrorType::ExpectedSigned)),
+            None => Err(Deserializer::error(ErrorType::ExpectedSigned)),
         }
     }
 
     #[cfg_attr(not(feature = "no-inline"), inline)]
     #[allow(clippy::cast_sign_loss)]
     fn parse_i128(&mut self) -> Result<i128> {
-        match unsafe { self.next_() } {
-            Node::Static(s) => s
+        match self.next_static() {
+            Some(s) => s
                 .as_i128()
-                .ok_or_else(|| Self::error(ErrorType::ExpectedSigned)),
-            _ => Err(Self::error(ErrorType::ExpectedSigned)),
+                .ok_or_else(|| Deserializer::error(ErrorType::ExpectedSigned)),
+            None => Err(Deserializer::error(ErrorType::ExpectedSigned)),
         }
     }
 
     #[cfg_attr(not(feature = "no-inline"), inline)]
     #[allow(clippy::cast_possible_wrap, clippy::cast_precision_loss)]
     fn parse_double(&mut self) -> Result<f64> {
-        match unsafe { self.next_() } {
+        match self.next_static() {
             #[allow(clippy::useless_conversion)] // .into() required by ordered-float
-            Node::Static(StaticNode::F64(n)) => Ok(n.into()),
-            Node::Static(StaticNode::I64(n)) => Ok(n as f64),
-            Node::Static(StaticNode::U64(n)) => Ok(n as f64),
-            _ => Err(Self::error(ErrorType::ExpectedFloat)),
+            Some(StaticNode::F64(n)) => Ok(n.into()),
+            Some(StaticNode::I64(n)) => Ok(n as f64),
+            Some(StaticNode::U64(n)) => Ok(n as f64),
+            _ => Err(Deserializer::error(ErrorType::ExpectedFloat)),
         }
     }
 }
@@ -400,6 +664,10 @@ impl TryInto<serde_json::Value> for OwnedValue {
                 }
             }
             Self::String(b) => Value::String(b),
+            Self::Number(n, _) => Value::Number(
+                n.parse()
+                    .map_err(|_| SerdeConversionError::NumberOutOfBounds)?,
+            ),
             Self::Array(a) => Value::Array(
                 a.into_iter()
                     .map(TryInto::try_into)
@@ -467,6 +735,10 @@ impl TryInto<serde_json::Value> for BorrowedValue<'_> {
                 }
             }
             BorrowedValue::String(b) => Value::String(b.to_string()),
+            BorrowedValue::Number(n, _) => Value::Number(
+                n.parse()
+                    .map_err(|_| SerdeConversionError::NumberOutOfBounds)?,
+            ),
             BorrowedValue::Array(a) => Value::Array(
                 a.into_iter()
                     .map(TryInto::try_into)
@@ -1010,4 +1282,36 @@ mod test {
         ser_deser_map!(NewTypeStruct(1) => 3_i8, HashMap<NewTypeStruct, i8>);
         ser_deser_map!(E::UnitVariant => 3_i8, HashMap<E, i8>);
     }
+
+    #[test]
+    fn from_bytes_borrows() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Doc<'a> {
+            plain: &'a str,
+            escaped: std::borrow::Cow<'a, str>,
+            n: u8,
+        }
+        let input = br#"{"plain": "abc", "escaped": "a\"b", "n": 1}"#;
+        let copy = *input;
+        let d: Doc = crate::from_bytes(input).unwrap();
+        assert_eq!(d.plain, "abc");
+        assert_eq!(d.escaped, "a\"b");
+        assert_eq!(d.n, 1);
+        assert!(input.as_ptr_range().contains(&d.plain.as_ptr()));
+        assert_eq!(input, &copy);
+
+        let mut buffers = crate::Buffers::default();
+        let err = super::from_bytes_with_buffers::<Doc>(br#"{"plain": "abc""#, &mut buffers);
+        assert!(err.is_err());
+        // a borrowed `&str` can't be produced for an escaped string
+        let err = super::from_bytes_with_buffers::<Doc>(
+            br#"{"plain": "a\nb", "escaped": "", "n": 1}"#,
+            &mut buffers,
+        );
+        assert!(err.is_err());
+        let keys: HashMap<String, &str> =
+            super::from_bytes_with_buffers(br#"{"a\tb": "c", "d": "e"}"#, &mut buffers).unwrap();
+        assert_eq!(keys.get("a\tb"), Some(&"c"));
+        assert_eq!(keys.get("d"), Some(&"e"));
+    }
 }
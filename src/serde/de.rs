@@ -1,11 +1,17 @@
+use crate::serde::{NUMBER_TOKEN, RAW_VALUE_TOKEN, value::shared::TokenAccess};
 use crate::serde_ext::de::IntoDeserializer;
-use crate::{Deserializer, Error, ErrorType, Node, Result, StaticNode, macros::stry};
+use crate::{
+    Deserializer, DuplicateKeyPolicy, Error, ErrorType, Node, Result, StaticNode, cow::Cow,
+    macros::stry,
+};
 use serde_ext::de::{self, DeserializeSeed, MapAccess, SeqAccess, Visitor};
 use serde_ext::forward_to_deserialize_any;
+use std::collections::HashMap;
 use std::str;
 
-impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de>
+impl<'a, 'de, T> de::Deserializer<'de> for &'a mut Deserializer<'de, T>
 where
+    T: AsRef<[Node<'de>]>,
     'de: 'a,
 {
     type Error = Error;
@@ -19,7 +25,8 @@ where
         V: Visitor<'de>,
     {
         match stry!(self.next()) {
-            Node::String(s) => visitor.visit_borrowed_str(s),
+            Node::String(s) => self.visit_str(visitor, s),
+            Node::Number(n, _) => visitor.visit_map(TokenAccess::new(NUMBER_TOKEN, Cow::from(n))),
             Node::Static(StaticNode::Null) => visitor.visit_unit(),
             Node::Static(StaticNode::Bool(b)) => visitor.visit_bool(b),
             #[allow(clippy::useless_conversion)] // .into() required by ordered-float
@@ -68,7 +75,7 @@ where
         V: Visitor<'de>,
     {
         if let Ok(Node::String(s)) = self.next() {
-            visitor.visit_borrowed_str(s)
+            self.visit_str(visitor, s)
         } else {
             Err(Deserializer::error(ErrorType::ExpectedString))
         }
@@ -284,11 +291,16 @@ where
 
     // As is done here, serializers are encouraged to treat newtype structs as
     // insignificant wrappers around the data they contain. That means not
-    // parsing anything other than the contained value.
-    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    // parsing anything other than the contained value. Raw values are the
+    // exception, they get the source text of the value instead.
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
+        if name == RAW_VALUE_TOKEN {
+            let raw = stry!(self.next_raw());
+            return visitor.visit_map(TokenAccess::new(RAW_VALUE_TOKEN, raw));
+        }
         visitor.visit_newtype_struct(self)
     }
 
@@ -300,7 +312,7 @@ where
         // Parse the opening bracket of the sequence.
         if let Ok(Node::Object { len, count: _ }) = self.next() {
             // Give the visitor access to each element of the sequence.
-            visitor.visit_map(CommaSeparated::new(self, len))
+            visitor.visit_map(CommaSeparated::new_map(self, len))
         } else {
             Err(Deserializer::error(ErrorType::ExpectedMap))
         }
@@ -318,7 +330,9 @@ where
     {
         match self.next() {
             // Give the visitor access to each element of the sequence.
-            Ok(Node::Object { len, count: _ }) => visitor.visit_map(CommaSeparated::new(self, len)),
+            Ok(Node::Object { len, count: _ }) => {
+                visitor.visit_map(CommaSeparated::new_map(self, len))
+            }
             Ok(Node::Array { len, count: _ }) => visitor.visit_seq(CommaSeparated::new(self, len)),
             _ => Err(Deserializer::error(ErrorType::ExpectedMap)),
         }
@@ -354,17 +368,20 @@ where
 }
 
 // From  https://github.com/serde-rs/json/blob/2d81cbd11302bd246db248dfb335110d1827e893/src/de.rs
-struct VariantAccess<'a, 'de> {
-    de: &'a mut Deserializer<'de>,
+struct VariantAccess<'a, 'de, T> {
+    de: &'a mut Deserializer<'de, T>,
 }
 
-impl<'a, 'de> VariantAccess<'a, 'de> {
-    fn new(de: &'a mut Deserializer<'de>) -> Self {
+impl<'a, 'de, T> VariantAccess<'a, 'de, T> {
+    fn new(de: &'a mut Deserializer<'de, T>) -> Self {
         VariantAccess { de }
     }
 }
 
-impl<'de> de::EnumAccess<'de> for VariantAccess<'_, 'de> {
+impl<'de, T> de::EnumAccess<'de> for VariantAccess<'_, 'de, T>
+where
+    T: AsRef<[Node<'de>]>,
+{
     type Error = Error;
     type Variant = Self;
 
@@ -377,16 +394,19 @@ impl<'de> de::EnumAccess<'de> for VariantAccess<'_, 'de> {
     }
 }
 
-impl<'de> de::VariantAccess<'de> for VariantAccess<'_, 'de> {
+impl<'de, T> de::VariantAccess<'de> for VariantAccess<'_, 'de, T>
+where
+    T: AsRef<[Node<'de>]>,
+{
     type Error = Error;
 
     fn unit_variant(self) -> Result<()> {
         de::Deserialize::deserialize(self.de)
     }
 
-    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    fn newtype_variant_seed<S>(self, seed: S) -> Result<S::Value>
     where
-        T: de::DeserializeSeed<'de>,
+        S: de::DeserializeSeed<'de>,
     {
         seed.deserialize(self.de)
     }
@@ -409,26 +429,101 @@ impl<'de> de::VariantAccess<'de> for VariantAccess<'_, 'de> {
 // In order to handle commas correctly when deserializing a JSON array or map,
 // we need to track whether we are on the first element or past the first
 // element.
-struct CommaSeparated<'a, 'de: 'a> {
-    de: &'a mut Deserializer<'de>,
+struct CommaSeparated<'a, 'de: 'a, T> {
+    de: &'a mut Deserializer<'de, T>,
     len: usize,
+    /// the map entries hidden by the duplicate key policy, empty if none are
+    skip: Vec<bool>,
 }
-impl<'a, 'de> CommaSeparated<'a, 'de> {
+impl<'a, 'de, T> CommaSeparated<'a, 'de, T>
+where
+    T: AsRef<[Node<'de>]>,
+{
     #[cfg_attr(not(feature = "no-inline"), inline)]
-    fn new(de: &'a mut Deserializer<'de>, len: usize) -> Self {
-        CommaSeparated { de, len }
+    fn new(de: &'a mut Deserializer<'de, T>, len: usize) -> Self {
+        CommaSeparated {
+            de,
+            len,
+            skip: Vec::new(),
+        }
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn new_map(de: &'a mut Deserializer<'de, T>, len: usize) -> Self {
+        let skip = match de.duplicate_keys {
+            Some(DuplicateKeyPolicy::FirstWins | DuplicateKeyPolicy::LastWins) => {
+                de.duplicate_entries(len)
+            }
+            Some(DuplicateKeyPolicy::KeepAll | DuplicateKeyPolicy::Error) | None => Vec::new(),
+        };
+        CommaSeparated { de, len, skip }
+    }
+}
+
+/// Objects with up to this many keys are checked for duplicates by comparing
+/// each key with the ones before it, larger ones use a `HashMap`.
+const LINEAR_KEY_SCAN: usize = 16;
+
+impl<'de, T> Deserializer<'de, T>
+where
+    T: AsRef<[Node<'de>]>,
+{
+    /// The first `len` keys of the object starting at `idx`
+    fn object_keys(&self, len: usize) -> impl Iterator<Item = &'de str> + '_ {
+        let tape = self.tape.as_ref();
+        let mut idx = self.idx;
+        (0..len).map_while(move |_| {
+            let Some(Node::String(key)) = tape.get(idx) else {
+                return None;
+            };
+            idx += 1 + tape.get(idx + 1).map_or(1, Node::count);
+            Some(*key)
+        })
+    }
+
+    /// Marks the entries of the object with `len` keys starting at `idx`
+    /// that the duplicate key policy hides, returns an empty `Vec` if there
+    /// are no duplicate keys.
+    fn duplicate_entries(&self, len: usize) -> Vec<bool> {
+        let mut skip = Vec::new();
+        let mut seen: HashMap<&str, usize> =
+            HashMap::with_capacity(if len > LINEAR_KEY_SCAN { len } else { 0 });
+        for (entry, key) in self.object_keys(len).enumerate() {
+            // the last earlier entry with the same key
+            let prev = if len <= LINEAR_KEY_SCAN {
+                self.object_keys(entry)
+                    .enumerate()
+                    .filter(|&(_, k)| k == key)
+                    .last()
+                    .map(|(prev, _)| prev)
+            } else {
+                seen.insert(key, entry)
+            };
+            if let Some(prev) = prev {
+                skip.resize(len, false);
+                if self.duplicate_keys == Some(DuplicateKeyPolicy::FirstWins) {
+                    skip[entry] = true;
+                } else {
+                    skip[prev] = true;
+                }
+            }
+        }
+        skip
     }
 }
 
 // `SeqAccess` is provided to the `Visitor` to give it the ability to iterate
 // through elements of the sequence.
-impl<'de> SeqAccess<'de> for CommaSeparated<'_, 'de> {
+impl<'de, T> SeqAccess<'de> for CommaSeparated<'_, 'de, T>
+where
+    T: AsRef<[Node<'de>]>,
+{
     type Error = Error;
 
     #[cfg_attr(not(feature = "no-inline"), inline)]
-    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    fn next_element_seed<S>(&mut self, seed: S) -> Result<Option<S::Value>>
     where
-        T: DeserializeSeed<'de>,
+        S: DeserializeSeed<'de>,
     {
         if self.len == 0 {
             Ok(None)
@@ -445,7 +540,10 @@ impl<'de> SeqAccess<'de> for CommaSeparated<'_, 'de> {
 
 // `MapAccess` is provided to the `Visitor` to give it the ability to iterate
 // through entries of the map.
-impl<'de> MapAccess<'de> for CommaSeparated<'_, 'de> {
+impl<'de, T> MapAccess<'de> for CommaSeparated<'_, 'de, T>
+where
+    T: AsRef<[Node<'de>]>,
+{
     type Error = Error;
 
     #[cfg_attr(not(feature = "no-inline"), inline)]
@@ -453,6 +551,18 @@ impl<'de> MapAccess<'de> for CommaSeparated<'_, 'de> {
     where
         K: DeserializeSeed<'de>,
     {
+        if !self.skip.is_empty() {
+            // hidden entries are skipped, key and value
+            while self.len > 0 && self.skip[self.skip.len() - self.len] {
+                self.len -= 1;
+                self.de.idx += 1 + self
+                    .de
+                    .tape
+                    .as_ref()
+                    .get(self.de.idx + 1)
+                    .map_or(1, Node::count);
+            }
+        }
         if self.len == 0 {
             Ok(None)
         } else {
@@ -466,7 +576,7 @@ impl<'de> MapAccess<'de> for CommaSeparated<'_, 'de> {
     where
         V: DeserializeSeed<'de>,
     {
-        if self.de.idx >= self.de.tape.len() {
+        if self.de.idx >= self.de.tape.as_ref().len() {
             return Err(Deserializer::error(ErrorType::Eof));
         }
         // read the value
@@ -481,8 +591,8 @@ impl<'de> MapAccess<'de> for CommaSeparated<'_, 'de> {
 
 // `MapKey` is provided to the `Visitor` to give it the ability to parse integers
 // from string as JSON keys are always string
-struct MapKey<'de: 'a, 'a> {
-    de: &'a mut Deserializer<'de>,
+struct MapKey<'de: 'a, 'a, T> {
+    de: &'a mut Deserializer<'de, T>,
 }
 
 macro_rules! deserialize_integer_key {
@@ -501,7 +611,10 @@ macro_rules! deserialize_integer_key {
     };
 }
 
-impl<'de> de::Deserializer<'de> for MapKey<'de, '_> {
+impl<'de, T> de::Deserializer<'de> for MapKey<'de, '_, T>
+where
+    T: AsRef<[Node<'de>]>,
+{
     type Error = Error;
 
     #[cfg_attr(not(feature = "no-inline"), inline)]
@@ -510,7 +623,7 @@ impl<'de> de::Deserializer<'de> for MapKey<'de, '_> {
         V: de::Visitor<'de>,
     {
         match stry!(self.de.next()) {
-            Node::String(s) => visitor.visit_borrowed_str(s),
+            Node::String(s) => self.de.visit_str(visitor, s),
             _ => Err(Deserializer::error(ErrorType::ExpectedString)),
         }
     }
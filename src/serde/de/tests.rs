@@ -107,3 +107,87 @@ fn test_deser_u16() -> Result<(), Box<dyn std::error::Error>> {
     };
     Ok(())
 }
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+#[test]
+fn duplicate_key_policies() {
+    use crate::{Buffers, DuplicateKeyPolicy, ErrorType};
+    use std::collections::BTreeMap;
+
+    let input = br#"{"x": 1, "y": {"z": [2]}, "x": 3, "y": 4}"#;
+    let parse = |policy| {
+        let mut buffers = Buffers::default().with_duplicate_keys(policy);
+        let map = crate::serde::from_slice_with_buffers::<BTreeMap<String, crate::OwnedValue>>(
+            &mut input.to_vec(),
+            &mut buffers,
+        );
+        let point =
+            crate::serde::from_slice_with_buffers::<Point>(&mut input.to_vec(), &mut buffers);
+        (map, point)
+    };
+
+    let entries = |x, y| BTreeMap::from([("x".to_string(), x), ("y".to_string(), y)]);
+
+    let (map, point) = parse(DuplicateKeyPolicy::FirstWins);
+    let expected = entries(crate::json!(1), crate::json!({"z": [2]}));
+    assert_eq!(map.ok(), Some(expected));
+    // `y` isn't an `i32` but the first one is kept
+    assert!(point.is_err());
+
+    let (map, point) = parse(DuplicateKeyPolicy::LastWins);
+    assert_eq!(map.ok(), Some(entries(crate::json!(3), crate::json!(4))));
+    assert_eq!(point.ok(), Some(Point { x: 3, y: 4 }));
+
+    // serde sees every entry, the derived struct rejects the duplicate field
+    let (map, point) = parse(DuplicateKeyPolicy::KeepAll);
+    assert_eq!(map.ok(), Some(entries(crate::json!(3), crate::json!(4))));
+    assert!(point.is_err());
+
+    let (map, point) = parse(DuplicateKeyPolicy::Error);
+    assert_eq!(
+        map.as_ref().map_err(crate::Error::error).err(),
+        Some(&ErrorType::DuplicateKey)
+    );
+    assert_eq!(point.map_err(|e| e.index()).err(), Some(26));
+}
+
+#[test]
+fn duplicate_keys_without_policy() {
+    // serde keeps every entry unless a policy is set, whatever the value default
+    let input = br#"{"x": 1, "y": 2, "x": 3}"#;
+    let point = crate::serde::from_slice::<Point>(&mut input.to_vec());
+    assert!(point.is_err());
+}
+
+#[test]
+fn duplicate_keys_in_large_objects() {
+    use crate::{Buffers, DuplicateKeyPolicy};
+    use std::collections::BTreeMap;
+
+    // past the linear scan the keys are tracked in a map
+    for n in [4_usize, 40] {
+        let entries: Vec<String> = (0..n)
+            .map(|i| format!(r#""k{}": {i}"#, i % (n / 2)))
+            .collect();
+        let input = format!(r#"{{{}, "k0": -1}}"#, entries.join(", "));
+        for (policy, k0, k_last) in [
+            (DuplicateKeyPolicy::FirstWins, 0, n / 2 - 1),
+            (DuplicateKeyPolicy::LastWins, -1, n - 1),
+        ] {
+            let mut buffers = Buffers::default().with_duplicate_keys(policy);
+            let map: BTreeMap<String, crate::OwnedValue> = crate::serde::from_slice_with_buffers(
+                &mut input.clone().into_bytes(),
+                &mut buffers,
+            )
+            .expect("valid");
+            assert_eq!(map.len(), n / 2);
+            assert_eq!(map["k0"], k0);
+            assert_eq!(map[&format!("k{}", n / 2 - 1)], k_last);
+        }
+    }
+}
@@ -0,0 +1,227 @@
+use crate::serde::TextSerializer;
+use crate::stage2::is_number;
+use crate::value::number_to_static;
+use crate::{Error, ErrorType, NumberKind, prelude::*};
+use serde_ext::de::{self, Deserialize, MapAccess, Visitor};
+use serde_ext::ser::{self, Serialize, SerializeStruct};
+use std::fmt;
+use std::str::FromStr;
+
+/// The name `serde_json` hands arbitrary precision numbers over with, as a
+/// struct or map with a single field of this name holding the number's text.
+pub(crate) const TOKEN: &str = "$serde_json::private::Number";
+
+/// A JSON number of any size and precision, kept as its text.
+///
+/// Parsing with [`Buffers::with_arbitrary_precision`](crate::Buffers::with_arbitrary_precision)
+/// hands numbers to serde in the same form as `serde_json`'s
+/// `arbitrary_precision` `Number`, so they can be read into this or into
+/// `serde_json::Number` and are written back byte for byte.
+///
+/// ```rust
+/// use simd_json::{Buffers, serde::Number};
+/// let mut json = br#"[123456789012345678901234567890, 1.50]"#.to_vec();
+/// let mut buffers = Buffers::default().with_arbitrary_precision(true);
+/// let numbers: Vec<Number> = simd_json::serde::from_slice_with_buffers(&mut json, &mut buffers)?;
+/// assert_eq!(numbers[0].as_str(), "123456789012345678901234567890");
+/// assert_eq!(numbers[1].as_f64(), Some(1.5));
+/// assert_eq!(simd_json::to_string(&numbers)?, "[123456789012345678901234567890,1.50]");
+/// # Ok::<(), simd_json::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Number(String, NumberKind);
+
+impl Number {
+    /// `text` has to follow the JSON number grammar
+    fn new(text: String) -> Self {
+        let kind = NumberKind::of(&text);
+        Self(text, kind)
+    }
+
+    /// What the number converts to
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    #[must_use]
+    pub fn kind(&self) -> NumberKind {
+        self.1
+    }
+
+    /// The text of the number
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The number as an `i64` if it is an integer that fits into one
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    #[must_use]
+    pub fn as_i64(&self) -> Option<i64> {
+        number_to_static(&self.0, self.1).as_i64()
+    }
+
+    /// The number as an `u64` if it is an integer that fits into one
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    #[must_use]
+    pub fn as_u64(&self) -> Option<u64> {
+        number_to_static(&self.0, self.1).as_u64()
+    }
+
+    /// The closest `f64` to the number
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    #[must_use]
+    pub fn as_f64(&self) -> Option<f64> {
+        number_to_static(&self.0, self.1).cast_f64()
+    }
+}
+
+impl FromStr for Number {
+    type Err = Error;
+
+    /// Checks `s` against the JSON number grammar
+    fn from_str(s: &str) -> Result<Self, Error> {
+        if is_number(s) {
+            Ok(Self::new(s.to_owned()))
+        } else {
+            Err(Error::generic(ErrorType::InvalidNumber))
+        }
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<Number> for String {
+    fn from(n: Number) -> Self {
+        n.0
+    }
+}
+
+impl Serialize for Number {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        serialize(&self.0, serializer)
+    }
+}
+
+/// Hands the text of a number handed over as the [`TOKEN`] field to
+/// `write`, checked against the JSON number grammar so it can be written as is
+pub(crate) fn write_text<T, W>(value: &T, write: W) -> Result<(), Error>
+where
+    T: ?Sized + Serialize,
+    W: FnOnce(&str) -> Result<(), Error>,
+{
+    let write = |text: &str| {
+        if is_number(text) {
+            write(text)
+        } else {
+            Err(Error::generic(ErrorType::InvalidNumber))
+        }
+    };
+    value.serialize(TextSerializer::new(write, ErrorType::InvalidNumber))
+}
+
+/// Hands the text of a number over to `serializer` as a struct with a
+/// single [`TOKEN`] field
+pub(crate) fn serialize<S>(number: &str, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: ser::Serializer,
+{
+    let mut s = serializer.serialize_struct(TOKEN, 1)?;
+    s.serialize_field(TOKEN, number)?;
+    s.end()
+}
+
+impl<'de> Deserialize<'de> for Number {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(NumberVisitor)
+    }
+}
+
+struct NumberVisitor;
+
+impl<'de> Visitor<'de> for NumberVisitor {
+    type Value = Number;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a JSON number")
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Number, E> {
+        Ok(Number::new(v.to_string()))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Number, E> {
+        Ok(Number::new(v.to_string()))
+    }
+
+    fn visit_i128<E>(self, v: i128) -> Result<Number, E> {
+        Ok(Number::new(v.to_string()))
+    }
+
+    fn visit_u128<E>(self, v: u128) -> Result<Number, E> {
+        Ok(Number::new(v.to_string()))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Number, E>
+    where
+        E: de::Error,
+    {
+        if v.is_finite() {
+            Ok(Number::new(v.to_string()))
+        } else {
+            Err(de::Error::invalid_value(de::Unexpected::Float(v), &self))
+        }
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Number, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        if map.next_key::<NumberKey>()?.is_none() {
+            return Err(de::Error::invalid_type(de::Unexpected::Map, &self));
+        }
+        let text: String = map.next_value()?;
+        text.parse().map_err(de::Error::custom)
+    }
+}
+
+/// The only key of a number handed over as a map
+struct NumberKey;
+
+impl<'de> Deserialize<'de> for NumberKey {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct KeyVisitor;
+
+        impl Visitor<'_> for KeyVisitor {
+            type Value = NumberKey;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a JSON number")
+            }
+
+            fn visit_str<E>(self, s: &str) -> Result<NumberKey, E>
+            where
+                E: de::Error,
+            {
+                if s == TOKEN {
+                    Ok(NumberKey)
+                } else {
+                    Err(de::Error::invalid_value(de::Unexpected::Str(s), &self))
+                }
+            }
+        }
+
+        deserializer.deserialize_identifier(KeyVisitor)
+    }
+}
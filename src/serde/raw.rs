@@ -0,0 +1,341 @@
+use crate::serde::TextSerializer;
+use crate::stage2::record_spans;
+use crate::{AlignedBuf, ErrorType, OwnedValue, Result, StructuralIndex, macros::stry, prelude::*};
+use serde_ext::de::{self, Deserialize, MapAccess, Visitor};
+use serde_ext::ser::{self, Serialize, SerializeStruct};
+use std::borrow::Cow;
+use std::fmt;
+use std::ops::Range;
+
+/// The name `serde_json` hands raw values over with, as a struct or map with
+/// a single field of this name holding the value's text.
+pub(crate) const TOKEN: &str = "$serde_json::private::RawValue";
+
+/// A JSON value kept as the text it was parsed from.
+///
+/// A `Box<RawValue>` field captures a part of the document as is, the
+/// original bytes of the value are kept even though strings are unescaped in
+/// place while parsing. A `&RawValue` field borrows them from the input
+/// instead, with [`from_slice`](crate::serde::from_slice) this only works
+/// for values without escaped strings as those are rewritten,
+/// [`from_bytes`](crate::serde::from_bytes) leaves the input untouched.
+/// This allows to deserialize only the parts of a document that are needed
+/// and to forward the rest untouched. Serializing a `RawValue` writes its
+/// text as is.
+///
+/// Capturing needs the deserializer to keep a copy of the input and its
+/// structural indexes, so it is opt-in with
+/// [`Buffers::with_raw_values`](crate::Buffers::with_raw_values) and the
+/// `_with_buffers` functions. Without it, and with the value and tape
+/// deserializers, the value is encoded from what was parsed instead and
+/// can't be borrowed.
+///
+/// ```rust
+/// use serde::{Deserialize, Serialize};
+/// use simd_json::{Buffers, serde::RawValue};
+///
+/// #[derive(Deserialize, Serialize)]
+/// struct Envelope {
+///     kind: String,
+///     payload: Box<RawValue>,
+/// }
+///
+/// let mut json = br#"{"kind": "trade", "payload": {"price": 1.50, "note": "caf\u00e9"}}"#.to_vec();
+/// let mut buffers = Buffers::default().with_raw_values(true);
+/// let envelope: Envelope = simd_json::serde::from_slice_with_buffers(&mut json, &mut buffers)?;
+/// assert_eq!(envelope.payload.get(), r#"{"price": 1.50, "note": "caf\u00e9"}"#);
+/// assert_eq!(
+///     simd_json::to_string(&envelope)?,
+///     r#"{"kind":"trade","payload":{"price": 1.50, "note": "caf\u00e9"}}"#
+/// );
+///
+/// #[derive(Deserialize)]
+/// struct Borrowed<'input> {
+///     #[serde(borrow)]
+///     payload: &'input RawValue,
+/// }
+///
+/// let json = r#"{"payload": [1, "caf\u00e9"]}"#;
+/// let borrowed: Borrowed =
+///     simd_json::serde::from_bytes_with_buffers(json.as_bytes(), &mut buffers)?;
+/// assert_eq!(borrowed.payload.get(), r#"[1, "caf\u00e9"]"#);
+/// # Ok::<(), simd_json::Error>(())
+/// ```
+#[repr(transparent)]
+pub struct RawValue {
+    json: str,
+}
+
+impl RawValue {
+    fn from_owned(json: Box<str>) -> Box<Self> {
+        // Safety: `RawValue` is a transparent wrapper around `str`
+        unsafe { Box::from_raw(Box::into_raw(json) as *mut Self) }
+    }
+
+    fn from_borrowed(json: &str) -> &Self {
+        // Safety: `RawValue` is a transparent wrapper around `str`
+        unsafe { &*(std::ptr::from_ref::<str>(json) as *const Self) }
+    }
+
+    /// Creates a raw value from JSON text, the text is kept as is.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if `json` is invalid JSON.
+    pub fn from_string(json: String) -> Result<Box<Self>> {
+        stry!(crate::validate(json.as_bytes()));
+        Ok(Self::from_owned(json.into_boxed_str()))
+    }
+
+    /// The JSON text of the value
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    #[must_use]
+    pub fn get(&self) -> &str {
+        &self.json
+    }
+}
+
+/// Serializes `value` into a raw value.
+///
+/// # Errors
+///
+/// Will return `Err` if `value` can not be serialized.
+pub fn to_raw_value<T>(value: &T) -> Result<Box<RawValue>>
+where
+    T: ?Sized + Serialize,
+{
+    crate::serde::to_string(value).map(|json| RawValue::from_owned(json.into_boxed_str()))
+}
+
+impl ToOwned for RawValue {
+    type Owned = Box<RawValue>;
+
+    fn to_owned(&self) -> Box<RawValue> {
+        RawValue::from_owned(self.json.to_owned().into_boxed_str())
+    }
+}
+
+impl Clone for Box<RawValue> {
+    fn clone(&self) -> Self {
+        (**self).to_owned()
+    }
+}
+
+impl From<Box<RawValue>> for Box<str> {
+    fn from(raw: Box<RawValue>) -> Self {
+        // Safety: `RawValue` is a transparent wrapper around `str`
+        unsafe { Box::from_raw(Box::into_raw(raw) as *mut str) }
+    }
+}
+
+impl fmt::Debug for RawValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("RawValue")
+            .field(&format_args!("{}", &self.json))
+            .finish()
+    }
+}
+
+impl fmt::Display for RawValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.json)
+    }
+}
+
+impl Serialize for RawValue {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        let mut s = serializer.serialize_struct(TOKEN, 1)?;
+        s.serialize_field(TOKEN, &self.json)?;
+        s.end()
+    }
+}
+
+/// Hands the text of a raw value handed over as the [`TOKEN`] field to
+/// `write`
+pub(crate) fn write_text<T, W>(value: &T, write: W) -> Result<()>
+where
+    T: ?Sized + Serialize,
+    W: FnOnce(&str) -> Result<()>,
+{
+    value.serialize(TextSerializer::new(write, ErrorType::ExpectedString))
+}
+
+impl<'de> Deserialize<'de> for Box<RawValue> {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(TOKEN, RawValueVisitor)
+    }
+}
+
+struct RawValueVisitor;
+
+impl<'de> Visitor<'de> for RawValueVisitor {
+    type Value = Box<RawValue>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("any valid JSON value")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> std::result::Result<Box<RawValue>, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        if map.next_key::<RawKey>()?.is_none() {
+            return Err(de::Error::invalid_type(de::Unexpected::Map, &self));
+        }
+        let text: String = map.next_value()?;
+        Ok(RawValue::from_owned(text.into_boxed_str()))
+    }
+
+    /// Deserializers that do not know about raw values hand over the value
+    /// itself, it is encoded instead
+    fn visit_newtype_struct<D>(
+        self,
+        deserializer: D,
+    ) -> std::result::Result<Box<RawValue>, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let value = OwnedValue::deserialize(deserializer)?;
+        Ok(RawValue::from_owned(value.encode().into_boxed_str()))
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for &'a RawValue {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(TOKEN, BorrowedRawValueVisitor)
+    }
+}
+
+struct BorrowedRawValueVisitor;
+
+impl<'de> Visitor<'de> for BorrowedRawValueVisitor {
+    type Value = &'de RawValue;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("any valid JSON value borrowed from the input")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> std::result::Result<&'de RawValue, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        if map.next_key::<RawKey>()?.is_none() {
+            return Err(de::Error::invalid_type(de::Unexpected::Map, &self));
+        }
+        let text: &'de str = map.next_value()?;
+        Ok(RawValue::from_borrowed(text))
+    }
+}
+
+/// The only key of a raw value handed over as a map
+struct RawKey;
+
+impl<'de> Deserialize<'de> for RawKey {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct KeyVisitor;
+
+        impl Visitor<'_> for KeyVisitor {
+            type Value = RawKey;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a raw value")
+            }
+
+            fn visit_str<E>(self, s: &str) -> std::result::Result<RawKey, E>
+            where
+                E: de::Error,
+            {
+                if s == TOKEN {
+                    Ok(RawKey)
+                } else {
+                    Err(de::Error::invalid_value(de::Unexpected::Str(s), &self))
+                }
+            }
+        }
+
+        deserializer.deserialize_identifier(KeyVisitor)
+    }
+}
+
+/// The original input of a document kept by the deserializer, so the text
+/// of a value can still be sliced out after its strings were unescaped in
+/// place
+pub(crate) struct RawSource<'de> {
+    /// the padded copy of the input stage 1 and 2 read from
+    input: AlignedBuf,
+    len: usize,
+    /// the input the deserializer borrows from, the text of values whose
+    /// bytes were not rewritten is borrowed from here
+    borrowed: &'de [u8],
+    structural_indexes: Vec<StructuralIndex>,
+    /// the span of every node, recorded with the first lookup
+    spans: Vec<Range<usize>>,
+}
+
+impl fmt::Debug for RawSource<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RawSource")
+            .field("len", &self.len)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'de> RawSource<'de> {
+    pub(crate) fn new(
+        input: AlignedBuf,
+        len: usize,
+        structural_indexes: Vec<StructuralIndex>,
+        borrowed: &'de [u8],
+    ) -> Self {
+        Self {
+            input,
+            len,
+            borrowed,
+            structural_indexes,
+            spans: Vec::new(),
+        }
+    }
+
+    /// Moves the source over to another input with the same content the
+    /// deserializer borrows from
+    pub(crate) fn with_borrowed(self, borrowed: &[u8]) -> RawSource<'_> {
+        RawSource {
+            input: self.input,
+            len: self.len,
+            borrowed,
+            structural_indexes: self.structural_indexes,
+            spans: self.spans,
+        }
+    }
+
+    /// The text of the value at tape index `node`, borrowed if it is
+    /// unchanged in the input the deserializer borrows from
+    pub(crate) fn text(&mut self, node: usize) -> Option<Cow<'de, str>> {
+        let input = self.input.get(..self.len)?;
+        if self.spans.is_empty() {
+            record_spans(input, &self.structural_indexes, &mut self.spans);
+        }
+        let span = self.spans.get(node)?.clone();
+        let original = input.get(span.clone())?;
+        match self.borrowed.get(span) {
+            Some(borrowed) if borrowed == original => {
+                std::str::from_utf8(borrowed).ok().map(Cow::Borrowed)
+            }
+            _ => std::str::from_utf8(original)
+                .ok()
+                .map(|text| Cow::Owned(text.to_owned())),
+        }
+    }
+}
@@ -1,5 +1,6 @@
 mod pp;
-use crate::{Error, ErrorType};
+use crate::serde::{NUMBER_TOKEN, RAW_VALUE_TOKEN};
+use crate::{Error, ErrorType, NonFinitePolicy};
 pub use pp::*;
 use serde_ext::ser;
 use std::io::Write;
@@ -21,7 +22,7 @@ where
     T: ser::Serialize + ?Sized,
 {
     let v = Vec::with_capacity(512);
-    let mut s = Serializer(v);
+    let mut s = Serializer(v, NonFinitePolicy::default());
     to.serialize(&mut s).map(|()| s.0)
 }
 
@@ -46,10 +47,45 @@ where
     T: ser::Serialize + ?Sized,
     W: Write,
 {
-    let mut s = Serializer(writer);
+    let mut s = Serializer(writer, NonFinitePolicy::default());
     to.serialize(&mut s)
 }
-struct Serializer<W: Write>(W);
+
+/// Write a value to a writer, with `non_finite` deciding how `NaN` and
+/// infinite floats are written
+/// # Errors
+/// when the data can not be written or a non-finite float is rejected
+#[cfg_attr(not(feature = "no-inline"), inline)]
+pub fn to_writer_with_non_finite<T, W>(
+    writer: W,
+    to: &T,
+    non_finite: NonFinitePolicy,
+) -> crate::Result<()>
+where
+    T: ser::Serialize + ?Sized,
+    W: Write,
+{
+    let mut s = Serializer(writer, non_finite);
+    to.serialize(&mut s)
+}
+
+/// Writes `num` if it is finite, otherwise according to `non_finite`
+#[cfg_attr(not(feature = "no-inline"), inline)]
+fn write_f64<G: BaseGenerator>(
+    g: &mut G,
+    num: f64,
+    non_finite: NonFinitePolicy,
+) -> crate::Result<()> {
+    if num.is_finite() {
+        iomap!(g.write_float(num))
+    } else if non_finite == NonFinitePolicy::Error {
+        Err(Error::generic(ErrorType::NonFiniteFloat))
+    } else {
+        iomap!(non_finite.write(g.get_writer(), num))
+    }
+}
+
+struct Serializer<W: Write>(W, NonFinitePolicy);
 
 impl<W> BaseGenerator for Serializer<W>
 where
@@ -202,6 +238,9 @@ struct SerializeMap<'serializer, W: Write + 'serializer> {
     s: &'serializer mut Serializer<W>,
     first: bool,
     wrote_closing: bool,
+    /// the token of an arbitrary precision number or raw value, its text is
+    /// written as is
+    verbatim: Option<&'static str>,
 }
 
 impl<W> ser::SerializeMap for SerializeMap<'_, W>
@@ -497,6 +536,12 @@ where
     where
         T: ?Sized + serde_ext::Serialize,
     {
+        if let Some(token) = self.verbatim {
+            let s = &mut *self.s;
+            return crate::serde::write_verbatim(token, value, |text| {
+                iomap!(s.write(text.as_bytes()))
+            });
+        }
         let SerializeMap {
             ref mut s,
             ref mut first,
@@ -517,7 +562,7 @@ where
     }
     #[cfg_attr(not(feature = "no-inline"), inline)]
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        if self.first {
+        if self.first || self.verbatim.is_some() {
             Ok(())
         } else {
             iomap!(self.s.write(b"}"))
@@ -635,11 +680,13 @@ where
 
     #[cfg_attr(not(feature = "no-inline"), inline)]
     fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
-        iomap!(self.write_float(f64::from(v)))
+        let non_finite = self.1;
+        write_f64(self, f64::from(v), non_finite)
     }
     #[cfg_attr(not(feature = "no-inline"), inline)]
     fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
-        iomap!(self.write_float(v))
+        let non_finite = self.1;
+        write_f64(self, v, non_finite)
     }
     #[cfg_attr(not(feature = "no-inline"), inline)]
     fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
@@ -783,15 +830,24 @@ where
             s: self,
             first: true,
             wrote_closing,
+            verbatim: None,
         })
     }
 
     #[cfg_attr(not(feature = "no-inline"), inline)]
     fn serialize_struct(
         self,
-        _name: &'static str,
+        name: &'static str,
         len: usize,
     ) -> Result<Self::SerializeStruct, Self::Error> {
+        if name == NUMBER_TOKEN || name == RAW_VALUE_TOKEN {
+            return Ok(SerializeMap {
+                s: self,
+                first: true,
+                wrote_closing: true,
+                verbatim: Some(name),
+            });
+        }
         self.serialize_map(Some(len))
     }
 
@@ -830,6 +886,27 @@ mod test {
     #[cfg(not(target_arch = "wasm32"))]
     use proptest::prelude::*;
 
+    #[test]
+    fn non_finite() {
+        use crate::{ErrorType, NonFinitePolicy, to_writer_with_non_finite};
+        let v = (f64::NAN, f64::INFINITY, f64::NEG_INFINITY, 1.5_f32);
+        let write = |policy| {
+            let mut out = Vec::new();
+            to_writer_with_non_finite(&mut out, &v, policy).map(|()| out)
+        };
+        assert_eq!(
+            write(NonFinitePolicy::Null).ok(),
+            Some(b"[null,null,null,1.5]".to_vec())
+        );
+        assert_eq!(
+            write(NonFinitePolicy::Literal).ok(),
+            Some(b"[NaN,Infinity,-Infinity,1.5]".to_vec())
+        );
+        let err = write(NonFinitePolicy::Error).expect_err("non-finite");
+        assert_eq!(err.error(), &ErrorType::NonFiniteFloat);
+        assert_eq!(crate::to_string(&f32::NAN).ok(), Some("null".to_string()));
+    }
+
     #[test]
     fn enum_tuple() {
         #[derive(serde::Serialize, Clone)]
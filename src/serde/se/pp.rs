@@ -1,10 +1,11 @@
-use crate::{Error, ErrorType, macros::stry};
+use crate::serde::{NUMBER_TOKEN, RAW_VALUE_TOKEN};
+use crate::{Error, ErrorType, NonFinitePolicy, macros::stry};
 use serde_ext::ser;
 use std::io::Write;
 use std::str;
 use value_trait::generator::BaseGenerator;
 
-use super::key_must_be_a_string;
+use super::{key_must_be_a_string, write_f64};
 
 macro_rules! iomap {
     ($e:expr_2021) => {
@@ -49,13 +50,37 @@ where
     let mut s = PrettySerializer::new(writer);
     to.serialize(&mut s)
 }
+
+/// Write a value to a writer, with `non_finite` deciding how `NaN` and
+/// infinite floats are written
+/// # Errors
+/// when the data can not be written or a non-finite float is rejected
+#[cfg_attr(not(feature = "no-inline"), inline)]
+pub fn to_writer_pretty_with_non_finite<T, W>(
+    writer: W,
+    to: &T,
+    non_finite: NonFinitePolicy,
+) -> crate::Result<()>
+where
+    T: ser::Serialize + ?Sized,
+    W: Write,
+{
+    let mut s = PrettySerializer::new(writer);
+    s.non_finite = non_finite;
+    to.serialize(&mut s)
+}
 struct PrettySerializer<W: Write> {
     writer: W,
     dent: u32,
+    non_finite: NonFinitePolicy,
 }
 impl<W: Write> PrettySerializer<W> {
     fn new(writer: W) -> Self {
-        Self { writer, dent: 0 }
+        Self {
+            writer,
+            dent: 0,
+            non_finite: NonFinitePolicy::default(),
+        }
     }
 }
 
@@ -479,6 +504,9 @@ where
 struct SerializeMap<'serializer, W: Write + 'serializer> {
     s: &'serializer mut PrettySerializer<W>,
     first: bool,
+    /// the token of an arbitrary precision number or raw value, its text is
+    /// written as is
+    verbatim: Option<&'static str>,
 }
 
 impl<W> ser::SerializeMap for SerializeMap<'_, W>
@@ -539,6 +567,12 @@ where
     where
         T: ?Sized + serde_ext::Serialize,
     {
+        if let Some(token) = self.verbatim {
+            let s = &mut *self.s;
+            return crate::serde::write_verbatim(token, value, |text| {
+                iomap!(s.write(text.as_bytes()))
+            });
+        }
         let SerializeMap {
             ref mut s,
             ref mut first,
@@ -563,7 +597,7 @@ where
     }
     #[cfg_attr(not(feature = "no-inline"), inline)]
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        if self.first {
+        if self.first || self.verbatim.is_some() {
             Ok(())
         } else {
             self.s.dedent();
@@ -690,11 +724,13 @@ where
 
     #[cfg_attr(not(feature = "no-inline"), inline)]
     fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
-        iomap!(self.write_float(f64::from(v)))
+        let non_finite = self.non_finite;
+        write_f64(self, f64::from(v), non_finite)
     }
     #[cfg_attr(not(feature = "no-inline"), inline)]
     fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
-        iomap!(self.write_float(v))
+        let non_finite = self.non_finite;
+        write_f64(self, v, non_finite)
     }
     #[cfg_attr(not(feature = "no-inline"), inline)]
     fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
@@ -843,15 +879,23 @@ where
         .map(move |()| SerializeMap {
             s: self,
             first: true,
+            verbatim: None,
         })
     }
 
     #[cfg_attr(not(feature = "no-inline"), inline)]
     fn serialize_struct(
         self,
-        _name: &'static str,
+        name: &'static str,
         len: usize,
     ) -> Result<Self::SerializeStruct, Self::Error> {
+        if name == NUMBER_TOKEN || name == RAW_VALUE_TOKEN {
+            return Ok(SerializeMap {
+                s: self,
+                first: true,
+                verbatim: Some(name),
+            });
+        }
         self.serialize_map(Some(len))
     }
 
@@ -1038,4 +1082,26 @@ mod test {
         let v2: Value = from_slice(&mut res).expect("generated bad json");
         assert_eq!(v, v2);
     }
+
+    #[test]
+    fn non_finite() {
+        use crate::{NonFinitePolicy, to_writer_pretty_with_non_finite};
+        let v = crate::json!({"a": [1.5]});
+        let mut out = Vec::new();
+        to_writer_pretty_with_non_finite(&mut out, &(v, f64::NAN), NonFinitePolicy::Literal)
+            .expect("literal");
+        assert_eq!(
+            String::from_utf8_lossy(&out),
+            "[\n  {\n    \"a\": [\n      1.5\n    ]\n  },\n  NaN\n]"
+        );
+        let mut out = Vec::new();
+        assert!(
+            to_writer_pretty_with_non_finite(&mut out, &[f64::INFINITY], NonFinitePolicy::Error)
+                .is_err()
+        );
+        assert_eq!(
+            crate::to_string_pretty(&[f64::NEG_INFINITY]).ok(),
+            Some("[\n  null\n]".to_string())
+        );
+    }
 }
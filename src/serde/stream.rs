@@ -0,0 +1,527 @@
+use crate::error::Position;
+use crate::stage1::Stage1State;
+use crate::to_offset;
+use crate::{Buffers, Deserializer, Error, ErrorType, Result, SIMDINPUT_LENGTH, StructuralIndex};
+use serde::de::DeserializeOwned;
+use std::io;
+use std::marker::PhantomData;
+
+/// Default number of bytes requested from the reader at a time.
+pub const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+/// What the stream expects to see at the next structural character.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Expect {
+    /// A (top level) value
+    Value,
+    /// The `[` opening the array we stream the items of
+    ArrayStart,
+    /// The first item or the `]` of an empty array
+    FirstItem,
+    /// A `,` followed by the next item or the closing `]`
+    NextItem,
+    /// An item after a `,`
+    Item,
+    /// Nothing but whitespace
+    End,
+}
+
+/// A deserializer that reads JSON from an [`io::Read`] in fixed-size chunks
+/// and yields one value at a time.
+///
+/// Chunks are run through a resumable stage 1 that carries the quote and
+/// backslash state from one chunk to the next, so the input is only ever
+/// scanned once. As soon as a value is complete it goes through stage 2
+/// straight from the chunk buffer and is handed out, the memory used is
+/// bounded by the chunk size plus the size of the largest value handed out.
+/// That size is limited together with the other
+/// [`ParserOptions`](crate::ParserOptions) of the [`Buffers`] used.
+///
+/// There are two modes:
+///
+/// * [`StreamDeserializer::new`] yields a sequence of whitespace separated
+///   top level values (as found in concatenated JSON or JSON lines). Every
+///   top level value is held in memory in full, so a single large document
+///   takes as much memory as with [`from_reader`](crate::serde::from_reader).
+/// * [`StreamDeserializer::array_items`] yields the items of a single top
+///   level array, only one item is held in memory at a time. This is the
+///   only mode that bounds the memory for a large array body.
+///
+/// Once an error has been returned the iterator is exhausted.
+///
+/// ```rust
+/// use simd_json::{OwnedValue, json, serde::StreamDeserializer};
+///
+/// let data = br#"[{"id": 1}, {"id": 2}, 3]"#;
+/// let items: Vec<OwnedValue> = StreamDeserializer::array_items(&data[..])
+///     .collect::<simd_json::Result<_>>()
+///     .unwrap();
+/// assert_eq!(items, vec![json!({"id": 1}), json!({"id": 2}), json!(3)]);
+/// ```
+pub struct StreamDeserializer<R, T> {
+    rdr: R,
+    buffers: Buffers,
+    chunk_size: usize,
+    /// the data read but not yet discarded, `buf[0]` is at `offset` in the stream
+    buf: Vec<u8>,
+    offset: usize,
+    /// the line and column of `buf[0]`
+    position: Position,
+    /// the part of `buf` that went through stage 1
+    scanned: usize,
+    /// the part of `buf` that was handed out already
+    consumed: usize,
+    stage1: Stage1State,
+    /// structural indexes into `buf`, starting at `head` for the current value
+    structurals: Vec<StructuralIndex>,
+    head: usize,
+    /// the structurals of the current value that were looked at so far
+    checked: usize,
+    depth: usize,
+    expect: Expect,
+    items: bool,
+    eof: bool,
+    failed: bool,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<R, T> StreamDeserializer<R, T>
+where
+    R: io::Read,
+    T: DeserializeOwned,
+{
+    /// Creates a stream deserializer yielding every top level value of `rdr`.
+    #[must_use]
+    pub fn new(rdr: R) -> Self {
+        Self::with_expect(rdr, Expect::Value, false)
+    }
+
+    /// Creates a stream deserializer that expects `rdr` to contain a single
+    /// array and yields its items one by one.
+    #[must_use]
+    pub fn array_items(rdr: R) -> Self {
+        Self::with_expect(rdr, Expect::ArrayStart, true)
+    }
+
+    fn with_expect(rdr: R, expect: Expect, items: bool) -> Self {
+        Self {
+            rdr,
+            buffers: Buffers::new(DEFAULT_CHUNK_SIZE),
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            buf: Vec::new(),
+            offset: 0,
+            position: Position::START,
+            scanned: 0,
+            consumed: 0,
+            stage1: Stage1State::new(),
+            structurals: Vec::new(),
+            head: 0,
+            checked: 0,
+            depth: 0,
+            expect,
+            items,
+            eof: false,
+            failed: false,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Sets the number of bytes requested from the reader at a time, it is
+    /// rounded up to a multiple of 64.
+    #[must_use]
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1).div_ceil(SIMDINPUT_LENGTH) * SIMDINPUT_LENGTH;
+        self
+    }
+
+    /// Uses the given buffers for stage 2, this also sets the maximum
+    /// nesting depth.
+    #[must_use]
+    pub fn with_buffers(mut self, buffers: Buffers) -> Self {
+        self.buffers = buffers;
+        self
+    }
+
+    /// The number of bytes of the stream that were consumed by the values
+    /// returned so far.
+    #[must_use]
+    pub fn byte_offset(&self) -> usize {
+        self.offset + self.consumed
+    }
+
+    /// Returns the underlying reader.
+    #[must_use]
+    pub fn into_inner(self) -> R {
+        self.rdr
+    }
+
+    fn error(&self, idx: usize, err: ErrorType) -> Error {
+        let c = self.buf.get(idx).map(|b| *b as char);
+        Error::new(self.offset + idx, c, err).at(self.position_of(idx))
+    }
+
+    /// The line and column of `buf[idx]`
+    fn position_of(&self, idx: usize) -> Position {
+        self.position.advance(&self.buf[..idx.min(self.buf.len())])
+    }
+
+    /// Reads the next chunk and runs stage 1 over all complete blocks,
+    /// dropping the part of the buffer that was handed out already.
+    #[allow(clippy::cast_possible_truncation)]
+    fn fill(&mut self) -> Result<()> {
+        if self.consumed > 0 {
+            let consumed = self.consumed;
+            self.position = self.position.advance(&self.buf[..consumed]);
+            self.buf.drain(..consumed);
+            self.offset += consumed;
+            self.scanned -= consumed;
+            self.consumed = 0;
+            self.structurals.drain(..self.head);
+            self.checked -= self.head;
+            self.head = 0;
+            let consumed = consumed as StructuralIndex;
+            for idx in &mut self.structurals {
+                *idx -= consumed;
+            }
+        }
+
+        let len = self.buf.len();
+        self.buf.resize(len + self.chunk_size, 0);
+        let read = loop {
+            match self.rdr.read(&mut self.buf[len..]) {
+                Ok(read) => break read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e) => {
+                    self.buf.truncate(len);
+                    return Err(Error::generic(ErrorType::Io(e)));
+                }
+            }
+        };
+        self.buf.truncate(len + read);
+
+        if read == 0 {
+            self.eof = true;
+            // pad the last block with whitespace so it can be scanned
+            let tail = self.buf.len() - self.scanned;
+            self.buf.resize(
+                self.scanned + tail.div_ceil(SIMDINPUT_LENGTH) * SIMDINPUT_LENGTH,
+                b' ',
+            );
+        }
+        if self.buf.len() > self.buffers.options.input_len {
+            return Err(Error::generic(ErrorType::InputTooLarge));
+        }
+
+        let end =
+            self.scanned + (self.buf.len() - self.scanned) / SIMDINPUT_LENGTH * SIMDINPUT_LENGTH;
+        unsafe {
+            Deserializer::feed_stage1(
+                &mut self.stage1,
+                &self.buf[self.scanned..end],
+                self.scanned as StructuralIndex,
+                Some(&mut self.structurals),
+            );
+        }
+        self.scanned = end;
+
+        if self.stage1.has_error() || (self.eof && self.stage1.inside_quote()) {
+            return Err(self.error(
+                self.structurals
+                    .get(self.head)
+                    .map_or(self.consumed, |i| *i as usize),
+                ErrorType::Syntax,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Looks for the end of the value starting at `head`, returns the
+    /// position behind its last structural and its last byte.
+    fn find_value_end(&mut self) -> Result<Option<(usize, usize)>> {
+        while self.checked < self.structurals.len() {
+            let k = self.checked;
+            let idx = to_offset(self.structurals[k]);
+            let c = self.buf[idx];
+            if k == self.head {
+                self.checked += 1;
+                self.depth = usize::from(matches!(c, b'{' | b'['));
+                continue;
+            }
+            if self.depth == 0 {
+                // a scalar ends where the next structural starts
+                return Ok(Some((k, idx)));
+            }
+            self.checked += 1;
+            match c {
+                b'{' | b'[' => {
+                    self.depth += 1;
+                    if self.depth > self.buffers.options.depth {
+                        return Err(self.error(idx, ErrorType::DepthLimitExceeded));
+                    }
+                }
+                b'}' | b']' => {
+                    self.depth -= 1;
+                    if self.depth == 0 {
+                        return Ok(Some((k + 1, idx + 1)));
+                    }
+                }
+                _ => (),
+            }
+        }
+        if self.eof && self.checked > self.head {
+            if self.depth == 0 {
+                return Ok(Some((self.checked, self.buf.len())));
+            }
+            return Err(self.error(self.buf.len(), ErrorType::Eof));
+        }
+        Ok(None)
+    }
+
+    /// Handles the array punctuation in `array_items` mode, returns `true`
+    /// once the next structural is the start of a value.
+    fn advance(&mut self) -> Result<bool> {
+        while let Some(idx) = self.structurals.get(self.head).map(|i| to_offset(*i)) {
+            let c = self.buf[idx];
+            match (self.expect, c) {
+                (Expect::Value | Expect::Item, _) => return Ok(true),
+                (Expect::ArrayStart, b'[') => self.expect = Expect::FirstItem,
+                (Expect::ArrayStart, _) => return Err(self.error(idx, ErrorType::ExpectedArray)),
+                (Expect::FirstItem | Expect::NextItem, b']') => self.expect = Expect::End,
+                (Expect::FirstItem, _) => {
+                    self.expect = Expect::Item;
+                    return Ok(true);
+                }
+                (Expect::NextItem, b',') => self.expect = Expect::Item,
+                (Expect::NextItem, _) => {
+                    return Err(self.error(idx, ErrorType::ExpectedArrayComma));
+                }
+                (Expect::End, _) => return Err(self.error(idx, ErrorType::TrailingData)),
+            }
+            self.head += 1;
+            self.checked = self.head;
+            self.consumed = idx + 1;
+        }
+        Ok(false)
+    }
+
+    fn parse_next(&mut self) -> Result<Option<T>> {
+        let (end, byte_end) = loop {
+            if self.advance()?
+                && let Some(r) = self.find_value_end()?
+            {
+                break r;
+            }
+            if self.eof {
+                return match self.expect {
+                    Expect::Value | Expect::End => Ok(None),
+                    _ => Err(self.error(self.buf.len(), ErrorType::Eof)),
+                };
+            }
+            self.fill()?;
+        };
+
+        let start = to_offset(self.structurals[self.head]);
+        if simdutf8::basic::from_utf8(&self.buf[start..byte_end]).is_err() {
+            return Err(self.error(start, ErrorType::InvalidUtf8));
+        }
+        let structural_indexes = self.buffers.structural_indexes_mut();
+        structural_indexes.clear();
+        #[allow(clippy::cast_possible_truncation)]
+        structural_indexes.extend(
+            self.structurals[self.head..end]
+                .iter()
+                .map(|i| *i - start as StructuralIndex),
+        );
+
+        self.head = end;
+        self.checked = end;
+        self.consumed = byte_end;
+        if self.items {
+            self.expect = Expect::NextItem;
+        }
+
+        // stage 2 unescapes strings in place, which only touches the part of
+        // the buffer that was just consumed
+        let (before, value) = self.buf.split_at_mut(start);
+        let mut tape = Vec::with_capacity(self.buffers.structural_indexes.len());
+        Deserializer::fill_tape_from_structurals(
+            &mut value[..byte_end - start],
+            &mut self.buffers,
+            &mut tape,
+        )
+        .map_err(|e| e.offset_by(self.offset + start, self.position.advance(before)))?;
+        let mut de = Deserializer {
+            tape,
+            idx: 0,
+            duplicate_keys: self.buffers.duplicate_keys,
+            #[cfg(feature = "serde_impl")]
+            raw_source: None,
+            #[cfg(feature = "serde_impl")]
+            transient: 0..0,
+            _input: PhantomData,
+        };
+        T::deserialize(&mut de).map(Some)
+    }
+}
+
+impl<R, T> Iterator for StreamDeserializer<R, T>
+where
+    R: io::Read,
+    T: DeserializeOwned,
+{
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        match self.parse_next() {
+            Ok(Some(v)) => Some(Ok(v)),
+            Ok(None) => None,
+            Err(e) => {
+                self.failed = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::StreamDeserializer;
+    use crate::{ErrorType, OwnedValue, json};
+    use serde::Deserialize;
+
+    fn values(input: &[u8], chunk_size: usize) -> Vec<crate::Result<OwnedValue>> {
+        StreamDeserializer::new(input)
+            .with_chunk_size(chunk_size)
+            .collect()
+    }
+
+    #[test]
+    fn concatenated_values() {
+        let input = br#"{"a": [1, 2, {"b": "}]"}]} 1 "x\"y" [] true{}null -2.5e3"#;
+        for chunk_size in [1, 64, 128, 4096] {
+            let vs: Vec<OwnedValue> = values(input, chunk_size)
+                .into_iter()
+                .collect::<crate::Result<_>>()
+                .expect("valid stream");
+            assert_eq!(
+                vs,
+                vec![
+                    json!({"a": [1, 2, {"b": "}]"}]}),
+                    json!(1),
+                    json!("x\"y"),
+                    json!([]),
+                    json!(true),
+                    json!({}),
+                    json!(null),
+                    json!(-2500.0),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn empty_stream() {
+        assert!(values(b"", 64).is_empty());
+        assert!(values(b"  \n\t ", 64).is_empty());
+    }
+
+    #[test]
+    fn carries_state_across_chunks() {
+        // a string full of escapes and structural characters that crosses
+        // many chunk boundaries
+        let s = "\\\\\\\"{[,:]}".repeat(100);
+        let input = format!(r#"["{s}", "{s}"]"#);
+        let vs = values(input.as_bytes(), 64);
+        let expected = "\\\"{[,:]}".repeat(100);
+        assert_eq!(
+            vs.into_iter().collect::<crate::Result<Vec<_>>>(),
+            Ok(vec![json!([expected, expected])])
+        );
+    }
+
+    #[test]
+    fn array_items() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Item {
+            id: u64,
+        }
+        let input: String = format!(
+            "[{}]",
+            (0..1000)
+                .map(|i| format!(r#"{{"id": {i}}}"#))
+                .collect::<Vec<_>>()
+                .join(",\n")
+        );
+        let items: Vec<Item> = StreamDeserializer::array_items(input.as_bytes())
+            .with_chunk_size(64)
+            .collect::<crate::Result<_>>()
+            .expect("valid array");
+        assert_eq!(items.len(), 1000);
+        assert_eq!(items[999], Item { id: 999 });
+
+        let empty: Vec<crate::Result<OwnedValue>> =
+            StreamDeserializer::array_items(&b" [ ] "[..]).collect();
+        assert!(empty.is_empty());
+        let scalars: Vec<OwnedValue> = StreamDeserializer::array_items(&b"[1,\"2\" , 3]"[..])
+            .collect::<crate::Result<_>>()
+            .expect("valid array");
+        assert_eq!(scalars, vec![json!(1), json!("2"), json!(3)]);
+    }
+
+    #[test]
+    fn array_items_errors() {
+        let first = |input: &'static [u8], expected: ErrorType| {
+            let e = StreamDeserializer::<_, OwnedValue>::array_items(input)
+                .find_map(Result::err)
+                .expect("invalid array");
+            assert_eq!(e.error(), &expected);
+        };
+        first(b"{}", ErrorType::ExpectedArray);
+        first(b"[1 2]", ErrorType::ExpectedArrayComma);
+        first(b"[1] 2", ErrorType::TrailingData);
+        first(b"[1, 2", ErrorType::Eof);
+    }
+
+    #[test]
+    fn errors_stop_the_stream() {
+        let vs = values(br#"1 {"a": } 2"#, 64);
+        assert_eq!(vs.len(), 2);
+        assert_eq!(vs[0], Ok(json!(1)));
+        let e = vs[1].as_ref().expect_err("invalid value");
+        assert_eq!(e.index(), 8);
+
+        let vs = values(br#"1 "unterminated"#, 64);
+        assert!(vs.last().is_some_and(Result::is_err));
+        let vs = values(b"1 [[1]", 64);
+        assert_eq!(vs[1].as_ref().map_err(crate::Error::is_eof), Err(true));
+        let vs = values(b"\"\xff\"", 64);
+        assert_eq!(
+            vs[0].as_ref().map_err(crate::Error::error),
+            Err(&ErrorType::InvalidUtf8)
+        );
+    }
+
+    #[test]
+    fn errors_have_lines() {
+        for chunk_size in [1, 4096] {
+            let vs = values(b"1\n2\n{\"a\":\n [tru]}", chunk_size);
+            let e = vs[2].as_ref().expect_err("invalid value");
+            assert_eq!(e.index(), 12);
+            assert_eq!((e.line(), e.column()), (Some(4), Some(3)));
+            assert_eq!(e.path(), Some("/a/0"));
+        }
+    }
+
+    #[test]
+    fn depth_limit() {
+        let input = "[".repeat(2000);
+        let vs = values(input.as_bytes(), 64);
+        assert_eq!(
+            vs[0].as_ref().map_err(crate::Error::error),
+            Err(&ErrorType::DepthLimitExceeded)
+        );
+    }
+}
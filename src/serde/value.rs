@@ -1,6 +1,8 @@
 mod borrowed;
+mod lazy;
 mod owned;
-mod shared;
+pub(super) mod shared;
+mod tape;
 
 pub use self::borrowed::from_refvalue as from_refborrowed_value;
 pub use self::borrowed::from_value as from_borrowed_value;
@@ -1,10 +1,11 @@
 // A lot of this logic is a re-implementation or copy of serde_json::Value
 use crate::Error;
-use crate::ObjectHasher;
 use crate::{ErrorType, cow::Cow};
+use crate::{NumberKind, ObjectHasher};
 use crate::{
     prelude::*,
-    serde::value::shared::MapKeyDeserializer,
+    serde::value::shared::{MapKeyDeserializer, deserialize_numbers},
+    serde::{NUMBER_TOKEN, value::shared::TokenAccess},
     value::borrowed::{Object, Value},
 };
 use serde_ext::{
@@ -51,6 +52,7 @@ impl<'de> de::Deserializer<'de> for Value<'de> {
                 Cow::Owned(s) => visitor.visit_string(s),
             },
 
+            Value::Number(n, _) => visitor.visit_map(TokenAccess::new(NUMBER_TOKEN, n)),
             Value::Array(a) => visitor.visit_seq(Array(a.into_iter())),
             Value::Object(o) => visitor.visit_map(ObjectAccess::new(o.into_iter())),
         }
@@ -135,9 +137,14 @@ impl<'de> de::Deserializer<'de> for Value<'de> {
         }
     }
 
+    deserialize_numbers! {
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64 deserialize_i128
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_u128
+        deserialize_f32 deserialize_f64
+    }
+
     forward_to_deserialize_any! {
-        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
-            bytes byte_buf unit unit_struct seq tuple
+        bool char str string bytes byte_buf unit unit_struct seq tuple
             tuple_struct map identifier ignored_any
     }
 }
@@ -481,6 +488,11 @@ impl<'de> Visitor<'de> for ValueVisitor {
 
         let mut m = Object::with_capacity_and_hasher(size, ObjectHasher::default());
         while let Some(k) = map.next_key::<&str>()? {
+            // an arbitrary precision number is handed over as a map
+            if m.is_empty() && k == NUMBER_TOKEN {
+                let number = map.next_value::<&str>()?;
+                return Ok(Value::Number(Cow::from(number), NumberKind::of(number)));
+            }
             let v = map.next_value()?;
             m.insert(k.into(), v);
         }
@@ -623,6 +635,9 @@ impl<'de> de::Deserializer<'de> for &'de Value<'de> {
             #[allow(clippy::useless_conversion)] // .into() required by ordered-float
             Value::Static(StaticNode::F64(n)) => visitor.visit_f64((*n).into()),
             Value::String(s) => visitor.visit_borrowed_str(s),
+            Value::Number(n, _) => {
+                visitor.visit_map(TokenAccess::new(NUMBER_TOKEN, Cow::from(&**n)))
+            }
             Value::Array(a) => visitor.visit_seq(ArrayRef(a.as_slice().iter())),
             Value::Object(o) => visitor.visit_map(ObjectRefAccess::new(o.iter())),
         }
@@ -706,9 +721,14 @@ impl<'de> de::Deserializer<'de> for &'de Value<'de> {
         visitor.visit_enum(EnumRefDeserializer { variant, value })
     }
 
+    deserialize_numbers! {
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64 deserialize_i128
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_u128
+        deserialize_f32 deserialize_f64
+    }
+
     forward_to_deserialize_any! {
-        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
-            bytes byte_buf unit unit_struct seq tuple
+        bool char str string bytes byte_buf unit unit_struct seq tuple
             tuple_struct map identifier ignored_any
     }
 }
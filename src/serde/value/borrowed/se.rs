@@ -1,4 +1,5 @@
 use super::to_value;
+use crate::serde::{NUMBER_TOKEN, Number, RAW_VALUE_TOKEN};
 use crate::{
     Error, ErrorType, Result,
     cow::Cow,
@@ -30,6 +31,7 @@ impl Serialize for Value<'_> {
             #[cfg(feature = "128bit")]
             Value::Static(StaticNode::I128(i)) => serializer.serialize_i128(*i),
             Value::String(s) => serializer.serialize_str(s),
+            Value::Number(n, _) => crate::serde::serialize_number(n, serializer),
             Value::Array(v) => {
                 let mut seq = serializer.serialize_seq(Some(v.len()))?;
                 for e in v.as_ref() {
@@ -576,6 +578,20 @@ impl<'se> serde::ser::SerializeStruct for SerializeMap<'se> {
     }
 
     fn end(self) -> Result<Value<'se>> {
+        // an arbitrary precision number
+        if self.map.len() == 1
+            && let Some(Value::String(number)) = self.map.get(NUMBER_TOKEN)
+        {
+            let kind = stry!(number.parse::<Number>()).kind();
+            return Ok(Value::Number(number.clone(), kind));
+        }
+        // a raw value
+        if self.map.len() == 1
+            && let Some(Value::String(json)) = self.map.get(RAW_VALUE_TOKEN)
+        {
+            let mut json = json.as_bytes().to_vec();
+            return crate::to_borrowed_value(&mut json).map(Value::into_static);
+        }
         serde::ser::SerializeMap::end(self)
     }
 }
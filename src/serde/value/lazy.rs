@@ -0,0 +1,83 @@
+use crate::Error;
+use crate::value::lazy::Value;
+use serde_ext::de::{self, IntoDeserializer, Visitor};
+use serde_ext::ser::{self, Serialize};
+use std::borrow::Cow;
+
+impl Serialize for Value<'_, '_, '_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        match self {
+            Value::Tape(v) => v.serialize(serializer),
+            Value::Value(v) => v.serialize(serializer),
+        }
+    }
+}
+
+/// Forwards the deserializer methods to the tape or borrowed value
+macro_rules! forward_to_value_deserializer {
+    ($($method:ident($($arg:ident: $ty:ty),*);)*) => {
+        $(
+            fn $method<V>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value, Error>
+            where
+                V: Visitor<'de>,
+            {
+                match self {
+                    Value::Tape(v) => de::Deserializer::$method(v, $($arg,)* visitor),
+                    Value::Value(Cow::Borrowed(v)) => de::Deserializer::$method(v, $($arg,)* visitor),
+                    Value::Value(Cow::Owned(v)) => de::Deserializer::$method(v, $($arg,)* visitor),
+                }
+            }
+        )*
+    };
+}
+
+/// A lazy value is deserialized from the tape as long as it was not
+/// upgraded, the borrowed value is deserialized otherwise
+impl<'de> de::Deserializer<'de> for Value<'de, 'de, '_> {
+    type Error = Error;
+
+    forward_to_value_deserializer! {
+        deserialize_any();
+        deserialize_bool();
+        deserialize_i8();
+        deserialize_i16();
+        deserialize_i32();
+        deserialize_i64();
+        deserialize_i128();
+        deserialize_u8();
+        deserialize_u16();
+        deserialize_u32();
+        deserialize_u64();
+        deserialize_u128();
+        deserialize_f32();
+        deserialize_f64();
+        deserialize_char();
+        deserialize_str();
+        deserialize_string();
+        deserialize_bytes();
+        deserialize_byte_buf();
+        deserialize_option();
+        deserialize_unit();
+        deserialize_unit_struct(name: &'static str);
+        deserialize_newtype_struct(name: &'static str);
+        deserialize_seq();
+        deserialize_tuple(len: usize);
+        deserialize_tuple_struct(name: &'static str, len: usize);
+        deserialize_map();
+        deserialize_struct(name: &'static str, fields: &'static [&'static str]);
+        deserialize_enum(name: &'static str, variants: &'static [&'static str]);
+        deserialize_identifier();
+        deserialize_ignored_any();
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Value<'de, 'de, '_> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
@@ -1,9 +1,10 @@
 // A lot of this logic is a re-implementation or copy of serde_json::Value
-use crate::ErrorType;
-use crate::{Error, ObjectHasher};
+use crate::{Error, NumberKind, ObjectHasher};
+use crate::{ErrorType, cow::Cow};
 use crate::{
     prelude::*,
-    serde::value::shared::MapKeyDeserializer,
+    serde::value::shared::{MapKeyDeserializer, deserialize_numbers},
+    serde::{NUMBER_TOKEN, value::shared::TokenAccess},
     value::owned::{Object, Value},
 };
 use serde_ext::{
@@ -37,6 +38,7 @@ impl<'de> de::Deserializer<'de> for Value {
             #[allow(clippy::useless_conversion)] // .into() required by ordered-float
             Value::Static(StaticNode::F64(n)) => visitor.visit_f64(n.into()),
             Value::String(s) => visitor.visit_string(s),
+            Value::Number(n, _) => visitor.visit_map(TokenAccess::new(NUMBER_TOKEN, Cow::from(n))),
             Value::Array(a) => visitor.visit_seq(Array(a.into_iter())),
             Value::Object(o) => visitor.visit_map(ObjectAccess {
                 i: o.into_iter(),
@@ -123,9 +125,14 @@ impl<'de> de::Deserializer<'de> for Value {
         }
     }
 
+    deserialize_numbers! {
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64 deserialize_i128
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_u128
+        deserialize_f32 deserialize_f64
+    }
+
     forward_to_deserialize_any! {
-        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
-            bytes byte_buf unit unit_struct seq tuple
+        bool char str string bytes byte_buf unit unit_struct seq tuple
             tuple_struct map identifier ignored_any
     }
 }
@@ -467,7 +474,13 @@ impl<'de> Visitor<'de> for ValueVisitor {
         let size = map.size_hint().unwrap_or_default();
 
         let mut m = Object::with_capacity_and_hasher(size, ObjectHasher::default());
-        while let Some(k) = map.next_key()? {
+        while let Some(k) = map.next_key::<String>()? {
+            // an arbitrary precision number is handed over as a map
+            if m.is_empty() && k == NUMBER_TOKEN {
+                let number: String = map.next_value()?;
+                let kind = NumberKind::of(&number);
+                return Ok(Value::Number(number, kind));
+            }
             let v = map.next_value()?;
             m.insert(k, v);
         }
@@ -610,6 +623,9 @@ impl<'de> de::Deserializer<'de> for &'de Value {
             #[allow(clippy::useless_conversion)] // .into() required by ordered-float
             Value::Static(StaticNode::F64(n)) => visitor.visit_f64((*n).into()),
             Value::String(s) => visitor.visit_borrowed_str(s),
+            Value::Number(n, _) => {
+                visitor.visit_map(TokenAccess::new(NUMBER_TOKEN, Cow::from(n.as_str())))
+            }
             Value::Array(a) => visitor.visit_seq(ArrayRef(a.as_slice().iter())),
             Value::Object(o) => visitor.visit_map(ObjectRefAccess::new(o.iter())),
         }
@@ -694,9 +710,14 @@ impl<'de> de::Deserializer<'de> for &'de Value {
         visitor.visit_enum(EnumRefDeserializer { variant, value })
     }
 
+    deserialize_numbers! {
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64 deserialize_i128
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_u128
+        deserialize_f32 deserialize_f64
+    }
+
     forward_to_deserialize_any! {
-        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
-            bytes byte_buf unit unit_struct seq tuple
+        bool char str string bytes byte_buf unit unit_struct seq tuple
             tuple_struct map identifier ignored_any
     }
 }
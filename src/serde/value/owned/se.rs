@@ -1,4 +1,5 @@
 use super::to_value;
+use crate::serde::{NUMBER_TOKEN, Number, RAW_VALUE_TOKEN};
 use crate::{
     Error, ErrorType, ObjectHasher, Result, StaticNode,
     macros::stry,
@@ -27,6 +28,7 @@ impl Serialize for Value {
             #[cfg(feature = "128bit")]
             Value::Static(StaticNode::I128(i)) => serializer.serialize_i128(*i),
             Value::String(s) => serializer.serialize_str(s),
+            Value::Number(n, _) => crate::serde::serialize_number(n, serializer),
             Value::Array(v) => {
                 let mut seq = serializer.serialize_seq(Some(v.len()))?;
                 for e in v.as_ref() {
@@ -556,6 +558,20 @@ impl serde::ser::SerializeStruct for SerializeMap {
     }
 
     fn end(self) -> Result<Value> {
+        // an arbitrary precision number
+        if self.map.len() == 1
+            && let Some(Value::String(number)) = self.map.get(NUMBER_TOKEN)
+        {
+            let kind = stry!(number.parse::<Number>()).kind();
+            return Ok(Value::Number(number.clone(), kind));
+        }
+        // a raw value
+        if self.map.len() == 1
+            && let Some(Value::String(json)) = self.map.get(RAW_VALUE_TOKEN)
+        {
+            let mut json = json.as_bytes().to_vec();
+            return crate::to_owned_value(&mut json);
+        }
         serde::ser::SerializeMap::end(self)
     }
 }
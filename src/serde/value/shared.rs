@@ -2,7 +2,7 @@ use crate::Error;
 use crate::cow::Cow;
 use serde::de::{Unexpected, Visitor};
 use serde::forward_to_deserialize_any;
-use serde_ext::de::{self, IntoDeserializer};
+use serde_ext::de::{self, DeserializeSeed, IntoDeserializer, MapAccess};
 
 macro_rules! deserialize_integer_key {
     ($method:ident => $visit:ident) => {
@@ -106,12 +106,12 @@ impl<'de> serde::Deserializer<'de> for MapKeyDeserializer<'de> {
     }
 }
 
-struct BorrowedCowStrDeserializer<'de> {
+pub(crate) struct BorrowedCowStrDeserializer<'de> {
     value: Cow<'de, str>,
 }
 
 impl<'de> BorrowedCowStrDeserializer<'de> {
-    fn new(value: Cow<'de, str>) -> Self {
+    pub(crate) fn new(value: Cow<'de, str>) -> Self {
         BorrowedCowStrDeserializer { value }
     }
 }
@@ -159,7 +159,7 @@ impl<'de> de::Deserializer<'de> for BorrowedCowStrDeserializer<'de> {
         tuple_struct map struct identifier ignored_any
     }
 }
-struct UnitOnly;
+pub(crate) struct UnitOnly;
 
 impl<'de> de::VariantAccess<'de> for UnitOnly {
     type Error = Error;
@@ -215,3 +215,74 @@ impl<'de> de::EnumAccess<'de> for BorrowedCowStrDeserializer<'de> {
         Ok((value, UnitOnly))
     }
 }
+
+/// Deserializes numbers kept as their text like the closest static value
+/// when a number type is asked for, everything else goes to
+/// `deserialize_any`.
+macro_rules! deserialize_numbers {
+    ($($method:ident)*) => {
+        $(
+            #[cfg_attr(not(feature = "no-inline"), inline)]
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Error>
+            where
+                V: Visitor<'de>,
+            {
+                match &self {
+                    Value::Number(n, kind) => {
+                        Value::Static(crate::value::number_to_static(n, *kind))
+                            .deserialize_any(visitor)
+                    }
+                    _ => self.deserialize_any(visitor),
+                }
+            }
+        )*
+    };
+}
+pub(crate) use deserialize_numbers;
+
+/// Hands a value over to a visitor as a map with a single `token` entry
+/// holding its text, the way `serde_json` passes arbitrary precision numbers
+/// and raw values through serde
+pub(crate) struct TokenAccess<'de> {
+    token: &'static str,
+    value: Option<Cow<'de, str>>,
+}
+
+impl<'de> TokenAccess<'de> {
+    pub(crate) fn new(token: &'static str, value: Cow<'de, str>) -> Self {
+        Self {
+            token,
+            value: Some(value),
+        }
+    }
+}
+
+impl<'de> MapAccess<'de> for TokenAccess<'de> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        if self.value.is_some() {
+            seed.deserialize(de::value::BorrowedStrDeserializer::new(self.token))
+                .map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        match self.value.take() {
+            Some(value) => seed.deserialize(BorrowedCowStrDeserializer::new(value)),
+            None => Err(de::Error::custom("token value requested twice")),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(usize::from(self.value.is_some()))
+    }
+}
@@ -0,0 +1,142 @@
+use crate::value::tape::{Array, Node, Object, Value};
+use crate::{Deserializer, DuplicateKeyPolicy, Error, StaticNode};
+use serde_ext::de::{self, IntoDeserializer, Visitor};
+use serde_ext::ser::{self, Serialize, SerializeMap as _, SerializeSeq as _};
+use std::marker::PhantomData;
+
+impl Serialize for Value<'_, '_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        match self.0.first().expect("invalid tape value") {
+            Node::Static(StaticNode::Null) => serializer.serialize_unit(),
+            Node::Static(StaticNode::Bool(b)) => serializer.serialize_bool(*b),
+            #[allow(clippy::useless_conversion)] // .into() required by ordered-float
+            Node::Static(StaticNode::F64(f)) => serializer.serialize_f64((*f).into()),
+            Node::Static(StaticNode::U64(i)) => serializer.serialize_u64(*i),
+            #[cfg(feature = "128bit")]
+            Node::Static(StaticNode::U128(i)) => serializer.serialize_u128(*i),
+            Node::Static(StaticNode::I64(i)) => serializer.serialize_i64(*i),
+            #[cfg(feature = "128bit")]
+            Node::Static(StaticNode::I128(i)) => serializer.serialize_i128(*i),
+            Node::String(s) => serializer.serialize_str(s),
+            Node::Number(n, _) => crate::serde::serialize_number(n, serializer),
+            Node::Array { .. } => self.as_array().expect("array node").serialize(serializer),
+            Node::Object { .. } => self.as_object().expect("object node").serialize(serializer),
+        }
+    }
+}
+
+impl Serialize for Array<'_, '_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(self.len()))?;
+        for e in self {
+            seq.serialize_element(&e)?;
+        }
+        seq.end()
+    }
+}
+
+impl Serialize for Object<'_, '_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(self.len()))?;
+        for (k, v) in self {
+            map.serialize_entry(k, &v)?;
+        }
+        map.end()
+    }
+}
+
+impl<'tape> Value<'tape, '_> {
+    /// A deserializer that walks the nodes of the value, they are borrowed
+    /// from the tape. Objects with duplicate keys are handled according to
+    /// `policy`, deserializing the value directly keeps every entry.
+    #[must_use]
+    pub fn deserializer_with(
+        self,
+        policy: DuplicateKeyPolicy,
+    ) -> Deserializer<'tape, &'tape [Node<'tape>]> {
+        Deserializer {
+            tape: self.0,
+            idx: 0,
+            duplicate_keys: Some(policy),
+            raw_source: None,
+            transient: 0..0,
+            _input: PhantomData,
+        }
+    }
+}
+
+/// Forwards the deserializer methods to the tape deserializer
+macro_rules! forward_to_tape_deserializer {
+    ($($method:ident($($arg:ident: $ty:ty),*);)*) => {
+        $(
+            fn $method<V>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value, Error>
+            where
+                V: Visitor<'tape>,
+            {
+                de::Deserializer::$method(
+                    &mut self.deserializer_with(DuplicateKeyPolicy::KeepAll),
+                    $($arg,)*
+                    visitor,
+                )
+            }
+        )*
+    };
+}
+
+/// The tape doesn't keep the input, so a [`RawValue`](crate::serde::RawValue)
+/// gets the value encoded from its nodes rather than its source text, and
+/// borrowing one as `&RawValue` fails.
+impl<'tape> de::Deserializer<'tape> for Value<'tape, '_> {
+    type Error = Error;
+
+    forward_to_tape_deserializer! {
+        deserialize_any();
+        deserialize_bool();
+        deserialize_i8();
+        deserialize_i16();
+        deserialize_i32();
+        deserialize_i64();
+        deserialize_i128();
+        deserialize_u8();
+        deserialize_u16();
+        deserialize_u32();
+        deserialize_u64();
+        deserialize_u128();
+        deserialize_f32();
+        deserialize_f64();
+        deserialize_char();
+        deserialize_str();
+        deserialize_string();
+        deserialize_bytes();
+        deserialize_byte_buf();
+        deserialize_option();
+        deserialize_unit();
+        deserialize_unit_struct(name: &'static str);
+        deserialize_newtype_struct(name: &'static str);
+        deserialize_seq();
+        deserialize_tuple(len: usize);
+        deserialize_tuple_struct(name: &'static str, len: usize);
+        deserialize_map();
+        deserialize_struct(name: &'static str, fields: &'static [&'static str]);
+        deserialize_enum(name: &'static str, variants: &'static [&'static str]);
+        deserialize_identifier();
+        deserialize_ignored_any();
+    }
+}
+
+impl<'tape> IntoDeserializer<'tape, Error> for Value<'tape, '_> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
@@ -0,0 +1,905 @@
+use crate::{
+    Buffers, Deserializer, Error, ErrorType, SIMDINPUT_LENGTH, Stage1Parse, StructuralIndex, impls,
+};
+use std::thread;
+
+/// Inputs with less than this per thread are not worth splitting up
+const MIN_PARALLEL_PART: usize = 1 << 20;
+
+/// Turns an error found by stage 1 into one pointing at its cause. Stage 1
+/// only knows that something is wrong somewhere, so the input is scanned
+/// again to find out where, skipping comments if they are enabled.
+#[cold]
+#[inline(never)]
+pub(crate) fn locate_error(input: &[u8], error: ErrorType, comments: bool) -> Error {
+    let e = match error {
+        ErrorType::Eof => Error::new(input.len(), None, error),
+        ErrorType::InvalidUtf8 => {
+            let idx = std::str::from_utf8(input)
+                .err()
+                .map_or(0, |e| e.valid_up_to());
+            Error::new(idx, None, error)
+        }
+        ErrorType::Syntax => {
+            // an unterminated string, a control character in a string or an
+            // unterminated comment or stray `/` with comments
+            let mut string_start = None;
+            let mut i = 0;
+            while i < input.len() {
+                let b = input[i];
+                match (string_start, b) {
+                    (None, b'"') => string_start = Some(i),
+                    (None, b'/') if comments => {
+                        let rest = &input[i + 1..];
+                        let len = match rest.first() {
+                            Some(b'/') => rest.iter().position(|&c| c == b'\n'),
+                            // past the closing `/`, which must not open another comment
+                            Some(b'*') => {
+                                rest[1..].windows(2).position(|w| w == b"*/").map(|e| e + 3)
+                            }
+                            _ => None,
+                        };
+                        let Some(len) = len.or((rest.first() == Some(&b'/')).then_some(rest.len()))
+                        else {
+                            return Error::new_c(i, '/', error).located(input);
+                        };
+                        i += len;
+                    }
+                    (Some(_), b'"') => string_start = None,
+                    (Some(_), b'\\') => i += 1,
+                    (Some(_), 0..0x20) => return Error::new_c(i, b as char, error).located(input),
+                    _ => (),
+                }
+                i += 1;
+            }
+            match string_start {
+                Some(start) => Error::new_c(start, '"', error),
+                None => Error::new(input.len(), None, error),
+            }
+        }
+        error => Error::generic(error),
+    };
+    e.located(input)
+}
+
+/// The state stage 1 carries from one 64 byte block to the next.
+///
+/// Keeping it outside of the scanning loop allows the input to be fed in
+/// pieces (as long as every piece but the last is a multiple of 64 bytes)
+/// and produce the exact same structural indexes as a single pass would.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Stage1State {
+    // does the last iteration end with an odd-length sequence of backslashes?
+    // either 0 or 1, but a 64-bit value
+    prev_iter_ends_odd_backslash: u64,
+    // does the previous iteration end inside a double-quote pair?
+    prev_iter_inside_quote: u64,
+    // either all zeros or all ones
+    // does the previous iteration end on something that is a predecessor of a
+    // pseudo-structural character - i.e. whitespace or a structural character
+    // effectively the very first char is considered to follow "whitespace" for
+    // the
+    // purposes of pseudo-structural character detection so we initialize to 1
+    prev_iter_ends_pseudo_pred: u64,
+    // for unescaped characters within strings (ASCII code points < 0x20)
+    error_mask: u64,
+    // are `//` and `/* */` comments whitespace (relaxed mode)?
+    comments: bool,
+    // does the previous iteration end inside a comment or on the `/` that
+    // might start one?
+    comment: Comment,
+    // did any block contain a `/`? only tracked with comments
+    seen_slash: bool,
+}
+
+/// Where a block ends relative to comments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comment {
+    None,
+    /// after a `/` outside of strings, it has to start a comment
+    Slash,
+    /// inside a `//` comment
+    Line,
+    /// inside a `/* */` comment
+    Block,
+    /// inside a `/* */` comment right after a `*`
+    BlockStar,
+}
+
+impl Default for Stage1State {
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Stage1State {
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    pub(crate) const fn new() -> Self {
+        Self {
+            prev_iter_ends_odd_backslash: 0,
+            prev_iter_inside_quote: 0,
+            prev_iter_ends_pseudo_pred: 1,
+            error_mask: 0,
+            comments: false,
+            comment: Comment::None,
+            seen_slash: false,
+        }
+    }
+
+    /// Treats `//` and `/* */` comments outside of strings as whitespace.
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    pub(crate) const fn with_comments(mut self, comments: bool) -> Self {
+        self.comments = comments;
+        self
+    }
+
+    /// Are we inside of a string at the end of the last block?
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    pub(crate) fn inside_quote(&self) -> bool {
+        self.prev_iter_inside_quote != 0
+    }
+
+    /// Are we inside of a block comment (or right after a `/`) at the end of
+    /// the last block? Line comments end with the input.
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    pub(crate) fn inside_comment(&self) -> bool {
+        !matches!(self.comment, Comment::None | Comment::Line)
+    }
+
+    /// Did we see an unescaped control character inside a string?
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    pub(crate) fn has_error(&self) -> bool {
+        self.error_mask != 0
+    }
+
+    /// Classifies a single 64 byte block and returns its structural bits.
+    ///
+    /// The bits are final once returned, the caller decides when to flatten
+    /// them (the single pass loop delays this to hide the latency of the
+    /// carryless multiply).
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    pub(crate) unsafe fn classify<S: Stage1Parse>(&mut self, chunk: &[u8]) -> u64 {
+        let start = *self;
+        let input = unsafe { S::new(chunk) };
+        // detect odd sequences of backslashes
+        let odd_ends: u64 =
+            input.find_odd_backslash_sequences(&mut self.prev_iter_ends_odd_backslash);
+
+        // detect insides of quote pairs ("quote_mask") and also our quote_bits
+        // themselves
+        let mut quote_bits: u64 = 0;
+        let mut quote_mask: u64 = input.find_quote_mask_and_bits(
+            odd_ends,
+            &mut self.prev_iter_inside_quote,
+            &mut quote_bits,
+            &mut self.error_mask,
+        );
+
+        let mut whitespace: u64 = 0;
+        let mut structurals: u64 = 0;
+        unsafe { input.find_whitespace_and_structurals(&mut whitespace, &mut structurals) };
+
+        // the quote mask is right up to the first comment, so a block without
+        // a `/` outside of strings that doesn't start in a comment needs no
+        // further work
+        if self.comments {
+            let slashes = unsafe { input.cmp_mask_against_input(b'/') };
+            self.seen_slash |= slashes != 0;
+            if start.comment != Comment::None || slashes & !quote_mask != 0 {
+                let comment_mask;
+                (quote_mask, quote_bits, comment_mask) = self.rescan_comments(&start, chunk);
+                whitespace |= comment_mask;
+                structurals &= !comment_mask;
+            }
+        }
+
+        // fixup structurals to reflect quotes and add pseudo-structural characters
+        S::finalize_structurals(
+            structurals,
+            whitespace,
+            quote_mask,
+            quote_bits,
+            &mut self.prev_iter_ends_pseudo_pred,
+        )
+    }
+
+    /// Scalar version of the string tracking for a block that touches a
+    /// comment, starting from the state before the block. Returns the quote
+    /// mask, the quote bits and the bits of all comment bytes, and replaces
+    /// the carried string, escape and error state the SIMD pass computed.
+    ///
+    /// A `/` outside of strings that doesn't start a comment is an error.
+    #[inline(never)]
+    fn rescan_comments(&mut self, start: &Self, chunk: &[u8]) -> (u64, u64, u64) {
+        let mut in_string = start.prev_iter_inside_quote != 0;
+        let mut escaped = start.prev_iter_ends_odd_backslash != 0;
+        let mut comment = start.comment;
+        let mut quote_mask: u64 = 0;
+        let mut quote_bits: u64 = 0;
+        let mut comment_mask: u64 = 0;
+        let mut error_mask: u64 = 0;
+        for (i, &c) in chunk.iter().enumerate() {
+            let bit = 1 << i;
+            let was_escaped = escaped;
+            escaped = c == b'\\' && !escaped;
+            match (comment, c) {
+                (Comment::Slash, b'/') => comment = Comment::Line,
+                (Comment::Slash, b'*') | (Comment::BlockStar, b'/') => {
+                    comment = if comment == Comment::Slash {
+                        Comment::Block
+                    } else {
+                        Comment::None
+                    };
+                }
+                (Comment::Line, b'\n') => comment = Comment::None,
+                (Comment::Block | Comment::BlockStar, b'*') => comment = Comment::BlockStar,
+                (Comment::BlockStar, _) => comment = Comment::Block,
+                (Comment::Line | Comment::Block, _) => (),
+                (Comment::Slash | Comment::None, _) => {
+                    if comment == Comment::Slash {
+                        error_mask |= bit;
+                        comment = Comment::None;
+                    }
+                    if in_string {
+                        if c == b'"' && !was_escaped {
+                            quote_bits |= bit;
+                            in_string = false;
+                        } else {
+                            quote_mask |= bit;
+                            if c < 0x20 {
+                                error_mask |= bit;
+                            }
+                        }
+                    } else if c == b'"' && !was_escaped {
+                        quote_bits |= bit;
+                        quote_mask |= bit;
+                        in_string = true;
+                    } else if c == b'/' {
+                        comment = Comment::Slash;
+                    }
+                    if comment == Comment::None {
+                        continue;
+                    }
+                }
+            }
+            comment_mask |= bit;
+        }
+        self.prev_iter_inside_quote = if in_string { u64::MAX } else { 0 };
+        self.prev_iter_ends_odd_backslash = u64::from(escaped);
+        self.error_mask = start.error_mask | error_mask;
+        self.comment = comment;
+        (quote_mask, quote_bits, comment_mask)
+    }
+
+    /// Classifies a single 64 byte block and returns the bits of all bytes
+    /// that are not whitespace outside of a string.
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    pub(crate) unsafe fn significant<S: Stage1Parse>(&mut self, chunk: &[u8]) -> u64 {
+        let input = unsafe { S::new(chunk) };
+        let odd_ends: u64 =
+            input.find_odd_backslash_sequences(&mut self.prev_iter_ends_odd_backslash);
+        let mut quote_bits: u64 = 0;
+        let quote_mask: u64 = input.find_quote_mask_and_bits(
+            odd_ends,
+            &mut self.prev_iter_inside_quote,
+            &mut quote_bits,
+            &mut self.error_mask,
+        );
+        let mut whitespace: u64 = 0;
+        let mut structurals: u64 = 0;
+        unsafe { input.find_whitespace_and_structurals(&mut whitespace, &mut structurals) };
+        !(whitespace & !quote_mask)
+    }
+
+    /// Only tracks whether a block ends inside a string or an escape, this
+    /// is all that is needed to know the state at the start of the next one.
+    /// The error mask is not meaningful afterwards.
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    unsafe fn advance<S: Stage1Parse>(&mut self, chunk: &[u8]) {
+        let input = unsafe { S::new(chunk) };
+        let odd_ends: u64 =
+            input.find_odd_backslash_sequences(&mut self.prev_iter_ends_odd_backslash);
+        let mut quote_bits: u64 = 0;
+        input.find_quote_mask_and_bits(
+            odd_ends,
+            &mut self.prev_iter_inside_quote,
+            &mut quote_bits,
+            &mut self.error_mask,
+        );
+    }
+
+    /// Moves on to the end of a part that ends in `assumed` when started
+    /// outside of strings and comments. Starting inside a string flips the
+    /// string carry, unless comments might be involved. Returns `false` if
+    /// the part has to be rescanned from the actual state instead.
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn skip(&mut self, assumed: &Self) -> bool {
+        if self.prev_iter_ends_odd_backslash != 0
+            || self.comment != Comment::None
+            || (self.inside_quote() && assumed.seen_slash)
+        {
+            return false;
+        }
+        self.prev_iter_ends_odd_backslash = assumed.prev_iter_ends_odd_backslash;
+        self.prev_iter_inside_quote ^= assumed.prev_iter_inside_quote;
+        self.comment = assumed.comment;
+        true
+    }
+
+    /// Runs stage 1 over `input`, whose length has to be a multiple of 64,
+    /// appending the structural indexes offset by `base` to `structural_indexes`.
+    ///
+    /// Without `structural_indexes` only the state carried to the next block
+    /// is computed, which is considerably cheaper unless comments are enabled.
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    #[allow(clippy::cast_possible_truncation)]
+    pub(crate) unsafe fn feed<S: Stage1Parse>(
+        &mut self,
+        input: &[u8],
+        base: StructuralIndex,
+        structural_indexes: Option<&mut Vec<StructuralIndex>>,
+    ) {
+        debug_assert_eq!(input.len() % SIMDINPUT_LENGTH, 0);
+        let Some(structural_indexes) = structural_indexes else {
+            for chunk in input.chunks_exact(SIMDINPUT_LENGTH) {
+                if self.comments {
+                    unsafe { self.classify::<S>(chunk) };
+                } else {
+                    unsafe { self.advance::<S>(chunk) };
+                }
+            }
+            return;
+        };
+        let mut idx: usize = 0;
+        while idx < input.len() {
+            let structurals = unsafe { self.classify::<S>(&input[idx..idx + SIMDINPUT_LENGTH]) };
+            idx += SIMDINPUT_LENGTH;
+            // `flatten_bits` expects the index of the block following the bits
+            let next = base + idx as StructuralIndex;
+            unsafe { S::flatten_bits(structural_indexes, next, structurals) };
+        }
+    }
+}
+
+/// architecture dependant resumable stage 1, it does not validate UTF-8
+impl Deserializer<'_> {
+    #[cfg(all(
+        feature = "runtime-detection",
+        any(target_arch = "x86_64", target_arch = "x86"),
+    ))]
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    pub(crate) unsafe fn feed_stage1(
+        state: &mut Stage1State,
+        input: &[u8],
+        base: StructuralIndex,
+        structural_indexes: Option<&mut Vec<StructuralIndex>>,
+    ) {
+        use crate::Implementation;
+
+        // See `find_structural_bits` for why these wrappers exist.
+        #[target_feature(
+            enable = "avx512f",
+            enable = "avx512bw",
+            enable = "avx2",
+            enable = "pclmulqdq"
+        )]
+        unsafe fn feed_avx512(
+            state: &mut Stage1State,
+            input: &[u8],
+            base: StructuralIndex,
+            structural_indexes: Option<&mut Vec<StructuralIndex>>,
+        ) {
+            unsafe { state.feed::<impls::avx512::SimdInput>(input, base, structural_indexes) }
+        }
+
+        #[target_feature(enable = "avx2", enable = "pclmulqdq")]
+        unsafe fn feed_avx2(
+            state: &mut Stage1State,
+            input: &[u8],
+            base: StructuralIndex,
+            structural_indexes: Option<&mut Vec<StructuralIndex>>,
+        ) {
+            unsafe { state.feed::<impls::avx2::SimdInput>(input, base, structural_indexes) }
+        }
+
+        #[target_feature(enable = "sse4.2")]
+        unsafe fn feed_sse42(
+            state: &mut Stage1State,
+            input: &[u8],
+            base: StructuralIndex,
+            structural_indexes: Option<&mut Vec<StructuralIndex>>,
+        ) {
+            unsafe { state.feed::<impls::sse42::SimdInput>(input, base, structural_indexes) }
+        }
+
+        unsafe {
+            match Implementation::selected() {
+                Implementation::AVX512 => feed_avx512(state, input, base, structural_indexes),
+                Implementation::AVX2 => feed_avx2(state, input, base, structural_indexes),
+                Implementation::SSE42 => feed_sse42(state, input, base, structural_indexes),
+                _ => {
+                    #[cfg(feature = "portable")]
+                    state.feed::<impls::portable::SimdInput>(input, base, structural_indexes);
+                    #[cfg(not(feature = "portable"))]
+                    state.feed::<impls::native::SimdInput>(input, base, structural_indexes);
+                }
+            }
+        }
+    }
+
+    #[cfg(not(any(
+        all(
+            feature = "runtime-detection",
+            any(target_arch = "x86_64", target_arch = "x86")
+        ),
+        feature = "portable",
+        target_feature = "avx2",
+        target_feature = "sse4.2",
+        target_feature = "simd128",
+        target_arch = "aarch64",
+    )))]
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    pub(crate) unsafe fn feed_stage1(
+        state: &mut Stage1State,
+        input: &[u8],
+        base: StructuralIndex,
+        structural_indexes: Option<&mut Vec<StructuralIndex>>,
+    ) {
+        unsafe { state.feed::<impls::native::SimdInput>(input, base, structural_indexes) }
+    }
+
+    #[cfg(all(feature = "portable", not(feature = "runtime-detection")))]
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    pub(crate) unsafe fn feed_stage1(
+        state: &mut Stage1State,
+        input: &[u8],
+        base: StructuralIndex,
+        structural_indexes: Option<&mut Vec<StructuralIndex>>,
+    ) {
+        unsafe { state.feed::<impls::portable::SimdInput>(input, base, structural_indexes) }
+    }
+
+    #[cfg(all(
+        target_feature = "avx2",
+        not(feature = "portable"),
+        not(feature = "runtime-detection"),
+    ))]
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    pub(crate) unsafe fn feed_stage1(
+        state: &mut Stage1State,
+        input: &[u8],
+        base: StructuralIndex,
+        structural_indexes: Option<&mut Vec<StructuralIndex>>,
+    ) {
+        unsafe { state.feed::<impls::avx2::SimdInput>(input, base, structural_indexes) }
+    }
+
+    #[cfg(all(
+        target_feature = "sse4.2",
+        not(target_feature = "avx2"),
+        not(feature = "runtime-detection"),
+        not(feature = "portable"),
+    ))]
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    pub(crate) unsafe fn feed_stage1(
+        state: &mut Stage1State,
+        input: &[u8],
+        base: StructuralIndex,
+        structural_indexes: Option<&mut Vec<StructuralIndex>>,
+    ) {
+        unsafe { state.feed::<impls::sse42::SimdInput>(input, base, structural_indexes) }
+    }
+
+    #[cfg(all(target_arch = "aarch64", not(feature = "portable")))]
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    pub(crate) unsafe fn feed_stage1(
+        state: &mut Stage1State,
+        input: &[u8],
+        base: StructuralIndex,
+        structural_indexes: Option<&mut Vec<StructuralIndex>>,
+    ) {
+        unsafe { state.feed::<impls::neon::SimdInput>(input, base, structural_indexes) }
+    }
+
+    #[cfg(all(target_feature = "simd128", not(feature = "portable")))]
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    pub(crate) unsafe fn feed_stage1(
+        state: &mut Stage1State,
+        input: &[u8],
+        base: StructuralIndex,
+        structural_indexes: Option<&mut Vec<StructuralIndex>>,
+    ) {
+        unsafe { state.feed::<impls::simd128::SimdInput>(input, base, structural_indexes) }
+    }
+}
+
+impl Buffers {
+    /// Runs stage 1 over `input` into the structural indexes, using as many
+    /// threads as configured and worth it for the size of `input`. With
+    /// `comments` they are treated as whitespace.
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    pub(crate) unsafe fn find_structural_bits(
+        &mut self,
+        input: &[u8],
+        comments: bool,
+    ) -> std::result::Result<(), ErrorType> {
+        let threads = self.stage1_threads.min(input.len() / MIN_PARALLEL_PART);
+        let structural_indexes = self.structural_indexes_mut();
+        if threads > 1 {
+            unsafe {
+                Deserializer::find_structural_bits_parallel(
+                    input,
+                    structural_indexes,
+                    threads,
+                    comments,
+                )
+            }
+        } else {
+            unsafe { Deserializer::find_structural_bits(input, structural_indexes, comments) }
+        }
+    }
+}
+
+fn join<T>(handle: thread::ScopedJoinHandle<'_, T>) -> T {
+    handle
+        .join()
+        .unwrap_or_else(|e| std::panic::resume_unwind(e))
+}
+
+impl Deserializer<'_> {
+    /// Parallel stage 1
+    ///
+    /// The input is split into one part per thread at 64 byte boundaries. What a
+    /// block produces depends on three bits of state carried over from the block
+    /// before it: whether it ended on an odd run of backslashes, inside a string
+    /// and on a predecessor of a pseudo-structural character. These are
+    /// reconciled in three steps:
+    ///
+    /// 1. every part is scanned for the string and escape carries only, assuming
+    ///    it starts outside of both (in parallel)
+    /// 2. the actual carries are passed from part to part, starting inside a
+    ///    string flips the string carry of a part, and the last block of every
+    ///    part is run in full to get the pseudo-structural carry (serial, one
+    ///    block per part)
+    /// 3. every part runs the full stage 1 from its actual starting state (in
+    ///    parallel) and the structural indexes are concatenated
+    ///
+    /// A part starting in the middle of an odd run of backslashes is rescanned
+    /// in the second step, which is rare enough not to matter. With comments
+    /// the same goes for parts starting inside a comment, and for parts
+    /// starting inside a string that contain a `/`, as flipping the string
+    /// carry doesn't work once comments are involved.
+    #[allow(clippy::cast_possible_truncation)]
+    pub(crate) unsafe fn find_structural_bits_parallel(
+        input: &[u8],
+        structural_indexes: &mut Vec<StructuralIndex>,
+        threads: usize,
+        comments: bool,
+    ) -> std::result::Result<(), ErrorType> {
+        let len = input.len();
+        let blocks = len.div_ceil(SIMDINPUT_LENGTH);
+        let part_blocks = blocks.div_ceil(threads.max(1));
+        if part_blocks == 0 || part_blocks == blocks {
+            return unsafe { Self::find_structural_bits(input, structural_indexes, comments) };
+        }
+
+        // the last block is padded with whitespace, like in the serial version
+        let full = len - len % SIMDINPUT_LENGTH;
+        let mut tail = [b' '; SIMDINPUT_LENGTH];
+        tail[..len - full].copy_from_slice(&input[full..]);
+        let tail = &tail;
+
+        // parts as ranges of blocks, the last block of each is handled on its own
+        let parts: Vec<(usize, usize)> = (0..blocks)
+            .step_by(part_blocks)
+            .map(|start| (start, (start + part_blocks).min(blocks)))
+            .collect();
+        let body = |(start, end): (usize, usize)| {
+            &input[start * SIMDINPUT_LENGTH..(end - 1) * SIMDINPUT_LENGTH]
+        };
+        let last = |(_, end): (usize, usize)| {
+            let start = (end - 1) * SIMDINPUT_LENGTH;
+            if start < full {
+                &input[start..start + SIMDINPUT_LENGTH]
+            } else {
+                &tail[..]
+            }
+        };
+
+        thread::scope(|s| {
+            let utf8 = s.spawn(|| simdutf8::basic::from_utf8(input).is_ok());
+
+            // 1. string and escape carries, assuming a clean start
+            let carries: Vec<Stage1State> = parts
+                .iter()
+                .map(|p| {
+                    let p = *p;
+                    s.spawn(move || {
+                        let mut state = Stage1State::new().with_comments(comments);
+                        unsafe { Self::feed_stage1(&mut state, body(p), 0, None) };
+                        state
+                    })
+                })
+                .collect::<Vec<_>>()
+                .into_iter()
+                .map(join)
+                .collect();
+
+            // 2. the actual state at the start of every part
+            let mut starts = Vec::with_capacity(parts.len());
+            let mut state = Stage1State::new().with_comments(comments);
+            let mut scratch = Vec::new();
+            for (p, assumed) in parts.iter().zip(carries) {
+                starts.push(state);
+                if !state.skip(&assumed) {
+                    unsafe { Self::feed_stage1(&mut state, body(*p), 0, None) };
+                }
+                scratch.clear();
+                unsafe { Self::feed_stage1(&mut state, last(*p), 0, Some(&mut scratch)) };
+            }
+            if state.inside_quote() || state.inside_comment() {
+                return Err(ErrorType::Syntax);
+            }
+
+            // 3. the structural indexes of every part
+            let results: Vec<(Vec<StructuralIndex>, bool)> = parts
+                .iter()
+                .zip(starts)
+                .map(|(p, mut state)| {
+                    let p = *p;
+                    s.spawn(move || {
+                        state.error_mask = 0;
+                        let mut res = Vec::with_capacity((p.1 - p.0) * SIMDINPUT_LENGTH / 8);
+                        let last_base = (p.1 - 1) * SIMDINPUT_LENGTH;
+                        unsafe {
+                            let base = (p.0 * SIMDINPUT_LENGTH) as StructuralIndex;
+                            Self::feed_stage1(&mut state, body(p), base, Some(&mut res));
+                            let base = last_base as StructuralIndex;
+                            Self::feed_stage1(&mut state, last(p), base, Some(&mut res));
+                        }
+                        (res, state.has_error())
+                    })
+                })
+                .collect::<Vec<_>>()
+                .into_iter()
+                .map(join)
+                .collect();
+
+            structural_indexes.clear();
+            structural_indexes.reserve(results.iter().map(|(r, _)| r.len()).sum());
+            let mut has_error = false;
+            for (r, e) in results {
+                structural_indexes.extend_from_slice(&r);
+                has_error |= e;
+            }
+
+            if structural_indexes.is_empty() {
+                return Err(ErrorType::Eof);
+            }
+            if has_error {
+                return Err(ErrorType::Syntax);
+            }
+            if join(utf8) {
+                Ok(())
+            } else {
+                Err(ErrorType::InvalidUtf8)
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Stage1State;
+    use crate::{Deserializer, StructuralIndex};
+
+    fn padded(input: &[u8]) -> Vec<u8> {
+        let mut v = input.to_vec();
+        v.resize(input.len().div_ceil(64).max(1) * 64, b' ');
+        v
+    }
+
+    #[test]
+    fn feed_matches_single_pass() {
+        let input = br#"{"a\\\"b": [1, 2.5, "\\", true], "c\"": {"d": null}, "e": "xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx\\\\"}"#;
+        let mut expected = Vec::new();
+        unsafe { Deserializer::find_structural_bits(input, &mut expected, false) }
+            .expect("valid json");
+
+        let data = padded(input);
+        for split in (0..=data.len()).step_by(64) {
+            let mut state = Stage1State::new();
+            let mut indexes = Vec::new();
+            unsafe {
+                Deserializer::feed_stage1(&mut state, &data[..split], 0, Some(&mut indexes));
+                #[allow(clippy::cast_possible_truncation)]
+                Deserializer::feed_stage1(
+                    &mut state,
+                    &data[split..],
+                    split as StructuralIndex,
+                    Some(&mut indexes),
+                );
+            }
+            assert!(!state.inside_quote());
+            assert!(!state.has_error());
+            assert_eq!(indexes, expected);
+        }
+    }
+
+    fn assert_parallel_matches(input: &[u8], comments: bool, what: &dyn std::fmt::Display) {
+        let mut expected = Vec::new();
+        let expected_res =
+            unsafe { Deserializer::find_structural_bits(input, &mut expected, comments) };
+        let blocks = input.len().div_ceil(64);
+        for threads in [2, 3, 7, blocks.min(64)] {
+            let mut indexes = Vec::new();
+            let res = unsafe {
+                Deserializer::find_structural_bits_parallel(input, &mut indexes, threads, comments)
+            };
+            assert_eq!(res, expected_res, "{what}, {threads} threads");
+            if res.is_ok() {
+                assert_eq!(indexes, expected, "{what}, {threads} threads");
+            }
+        }
+    }
+
+    #[test]
+    fn parallel_matches_serial_on_data() {
+        for entry in std::fs::read_dir("data").expect("data dir") {
+            let path = entry.expect("dir entry").path();
+            if path.extension().is_some_and(|e| e == "json") {
+                let input = std::fs::read(&path).expect("readable");
+                assert_parallel_matches(&input, false, &path.display());
+            }
+        }
+    }
+
+    #[test]
+    fn parallel_carries_across_parts() {
+        // strings and runs of backslashes crossing every block boundary
+        for pad in 0..70 {
+            for slashes in 1..5 {
+                let mut input = vec![b'['];
+                input.extend(std::iter::repeat_n(b' ', pad));
+                input.push(b'"');
+                input.extend(std::iter::repeat_n(b'x', 60));
+                input.extend(std::iter::repeat_n(b'\\', slashes * 2));
+                input.extend_from_slice(br#"\"y", "#);
+                input.extend(std::iter::repeat_n(b'\\', slashes * 2 + 1));
+                input.extend_from_slice(br#"""#);
+                input.extend(std::iter::repeat_n(b'z', 70));
+                input.extend_from_slice(br#"", 1, {"a" :true}]"#);
+                assert_parallel_matches(
+                    &input,
+                    false,
+                    &format_args!("pad {pad}, {slashes} slashes"),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn parallel_errors() {
+        let long = "x".repeat(200);
+        assert_parallel_matches(
+            format!(r#"["{long}", "{long}]"#).as_bytes(),
+            false,
+            &"unterminated string",
+        );
+        assert_parallel_matches(
+            format!("[\"{long}\u{1}\", \"{long}\"]").as_bytes(),
+            false,
+            &"control character",
+        );
+        let mut invalid = format!(r#"["{long}", "{long}"]"#).into_bytes();
+        invalid[300] = 0xff;
+        assert_parallel_matches(&invalid, false, &"invalid UTF-8");
+        assert_parallel_matches(&[b' '; 300], false, &"whitespace only");
+    }
+
+    #[test]
+    fn buffers_use_threads_for_large_inputs() -> crate::Result<()> {
+        let item = br#"{"a": "b\\", "c": [1, 2.5, null]},"#;
+        let mut input = b"[".to_vec();
+        while input.len() < 3 * super::MIN_PARALLEL_PART {
+            input.extend_from_slice(item);
+        }
+        input.extend_from_slice(b"{}]");
+        let expected = crate::to_owned_value(&mut input.clone())?;
+        let mut buffers = crate::Buffers::default().with_stage1_threads(4);
+        let value = crate::owned::to_value_with_buffers(&mut input, &mut buffers)?;
+        assert_eq!(value, expected);
+        Ok(())
+    }
+
+    /// The input with comments and its strict equivalent, with every
+    /// comment replaced by spaces, padded so the pieces cross block
+    /// boundaries
+    fn commented(pad: usize) -> (Vec<u8>, Vec<u8>) {
+        let pieces: [(&str, bool); 12] = [
+            ("[", false),
+            (" ", false),
+            (r#""a//b\"/*""#, false),
+            ("// c \"\\\n", true),
+            (",1", false),
+            (r#"/* "x" * / ** */"#, true),
+            (",", false),
+            ("/*/ still a comment\n*/", true),
+            ("true", false),
+            ("/**/", true),
+            (r#", "/*", null"#, false),
+            ("//", true),
+        ];
+        let mut input = Vec::new();
+        let mut blanked = Vec::new();
+        for (i, (piece, comment)) in pieces.iter().enumerate() {
+            let piece = if i == 1 {
+                " ".repeat(pad)
+            } else {
+                (*piece).to_string()
+            };
+            input.extend_from_slice(piece.as_bytes());
+            blanked.extend(
+                piece
+                    .bytes()
+                    .map(|b| if *comment && b != b'\n' { b' ' } else { b }),
+            );
+        }
+        input.extend_from_slice(b"\n]");
+        blanked.extend_from_slice(b"\n]");
+        (input, blanked)
+    }
+
+    #[test]
+    fn comments_are_whitespace() {
+        for pad in 0..130 {
+            let (input, blanked) = commented(pad);
+            let mut expected = Vec::new();
+            let mut indexes = Vec::new();
+            unsafe {
+                Deserializer::find_structural_bits(&blanked, &mut expected, false).expect("strict");
+                Deserializer::find_structural_bits(&input, &mut indexes, true).expect("comments");
+            }
+            assert_eq!(indexes, expected, "pad {pad}");
+        }
+    }
+
+    #[test]
+    fn parallel_comments() {
+        for pad in (0..70).step_by(7) {
+            let mut input = b"[".to_vec();
+            for _ in 0..12 {
+                input.extend_from_slice(&commented(pad).0);
+                input.push(b',');
+            }
+            input.extend_from_slice(b"1]");
+            assert_parallel_matches(&input, true, &format_args!("pad {pad}"));
+        }
+    }
+
+    #[test]
+    fn comment_errors() {
+        use crate::{ErrorType, stage1::locate_error};
+        for (input, at) in [
+            (&br#"[1, "\"/*", /* 2 ]"#[..], 12),
+            (b"[1 / 2]", 3),
+            (b"[1, /", 4),
+        ] {
+            let mut indexes = Vec::new();
+            let err = unsafe { Deserializer::find_structural_bits(input, &mut indexes, true) }
+                .expect_err("invalid comment");
+            assert_eq!(err, ErrorType::Syntax);
+            assert_eq!(locate_error(input, err, true).index(), at);
+        }
+        // a `/` ending a block still has to start a comment
+        let mut input = vec![b' '; 63];
+        input.push(b'/');
+        input.extend_from_slice(b"/ c\n[1]");
+        let mut indexes = Vec::new();
+        unsafe { Deserializer::find_structural_bits(&input, &mut indexes, true) }.expect("comment");
+        input[64] = b'1';
+        unsafe { Deserializer::find_structural_bits(&input, &mut indexes, true) }
+            .expect_err("stray slash");
+    }
+}
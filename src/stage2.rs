@@ -1,10 +1,16 @@
 #![allow(dead_code)]
-use crate::charutils::is_not_structural_or_whitespace;
+use crate::charutils::{is_not_structural_or_whitespace, is_structural_or_whitespace};
 #[allow(unused_imports)]
 use crate::macros::unlikely;
 use crate::safer_unchecked::GetSaferUnchecked;
+use crate::to_offset;
+use crate::value::NumberKind;
 use crate::value::tape::Node;
-use crate::{Deserializer, Error, ErrorType, InternalError, Result};
+use crate::{
+    Buffers, Deserializer, Error, ErrorType, InternalError, ParserOptions, Result,
+    SIMDJSON_PADDING, StructuralIndex,
+};
+use std::ops::Range;
 use value_trait::StaticNode;
 
 #[cfg_attr(not(feature = "no-inline"), inline)]
@@ -83,6 +89,155 @@ pub fn is_valid_null_atom(loc: &[u8]) -> bool {
     error == 0
 }
 
+/// Reads `NaN`, `Infinity` or `-Infinity` from the start of `loc`, these
+/// are only accepted with non-finite literals enabled.
+#[cold]
+pub(crate) fn non_finite_atom(loc: &[u8]) -> Option<StaticNode> {
+    let (num, len) = if loc.starts_with(b"NaN") {
+        (f64::NAN, 3)
+    } else if loc.starts_with(b"Infinity") {
+        (f64::INFINITY, 8)
+    } else if loc.starts_with(b"-Infinity") {
+        (f64::NEG_INFINITY, 9)
+    } else {
+        return None;
+    };
+    let end = *loc.get(len)?;
+    (is_structural_or_whitespace(end) != 0).then(|| StaticNode::from(num))
+}
+
+/// Checks the number at the start of `loc` against the JSON grammar without
+/// converting it and returns its length, used for arbitrary precision
+/// numbers so neither its size nor its precision are limited. The number has
+/// to be followed by a structural character, whitespace or the end of `loc`.
+pub(crate) fn raw_number_len(loc: &[u8]) -> Option<usize> {
+    let digits = |from: usize| {
+        loc.get(from..)
+            .map_or(0, |l| l.iter().take_while(|c| c.is_ascii_digit()).count())
+    };
+    let mut len = usize::from(loc.first() == Some(&b'-'));
+    match loc.get(len) {
+        Some(b'0') => len += 1,
+        Some(b'1'..=b'9') => len += digits(len),
+        _ => return None,
+    }
+    if loc.get(len) == Some(&b'.') {
+        let fraction = digits(len + 1);
+        if fraction == 0 {
+            return None;
+        }
+        len += 1 + fraction;
+    }
+    if matches!(loc.get(len), Some(b'e' | b'E')) {
+        len += 1;
+        if matches!(loc.get(len), Some(b'+' | b'-')) {
+            len += 1;
+        }
+        let exponent = digits(len);
+        if exponent == 0 {
+            return None;
+        }
+        len += exponent;
+    }
+    loc.get(len)
+        .is_none_or(|c| is_structural_or_whitespace(*c) != 0)
+        .then_some(len)
+}
+
+/// Is all of `text` a number following the JSON grammar?
+pub(crate) fn is_number(text: &str) -> bool {
+    raw_number_len(text.as_bytes()) == Some(text.len())
+}
+
+/// Reads the scalar at the start of `loc` with `read`. With `RELAXED` a
+/// scalar can be directly followed by a comment, which the scalar readers
+/// don't take for its end, so they get another go at the scalar without it.
+#[cfg_attr(not(feature = "no-inline"), inline)]
+pub(crate) fn read_scalar<const RELAXED: bool, T>(
+    loc: &[u8],
+    read: impl Fn(&[u8]) -> Option<T>,
+) -> Option<T> {
+    match read(loc) {
+        None if RELAXED => before_comment(loc, read),
+        r => r,
+    }
+}
+
+/// Checks an atom like `read_scalar` does.
+#[cfg_attr(not(feature = "no-inline"), inline)]
+pub(crate) fn valid_atom<const RELAXED: bool>(loc: &[u8], valid: fn(&[u8]) -> bool) -> bool {
+    read_scalar::<RELAXED, _>(loc, |loc| valid(loc).then_some(())).is_some()
+}
+
+/// The length of the scalar at the start of `text`, which reaches up to the
+/// next structural character, without the whitespace and comments after it.
+pub(crate) fn scalar_len(text: &[u8]) -> usize {
+    if text.first() == Some(&b'"') {
+        let mut escaped = false;
+        let closing = text.iter().skip(1).position(|&c| {
+            let end = c == b'"' && !escaped;
+            escaped = c == b'\\' && !escaped;
+            end
+        });
+        closing.map_or(text.len(), |p| p + 2)
+    } else {
+        text.iter()
+            .position(|&c| c == b'/' || c.is_ascii_whitespace())
+            .unwrap_or(text.len())
+    }
+}
+
+/// Records the byte range of every node of the document in `input` in tape
+/// order. Every node starts at a structural character, the remaining ones
+/// close containers or separate their entries. Scalars end before the
+/// whitespace and comments in front of the next structural character.
+pub(crate) fn record_spans(
+    input: &[u8],
+    structural_indexes: &[StructuralIndex],
+    spans: &mut Vec<Range<usize>>,
+) {
+    spans.clear();
+    let mut open: Vec<usize> = Vec::new();
+    for (i, idx) in structural_indexes.iter().enumerate() {
+        let idx = to_offset(*idx);
+        match input.get(idx) {
+            Some(b',' | b':') => (),
+            Some(b']' | b'}') => {
+                if let Some(span) = open.pop().and_then(|n| spans.get_mut(n)) {
+                    span.end = idx + 1;
+                }
+            }
+            Some(b'[' | b'{') => {
+                open.push(spans.len());
+                spans.push(idx..idx);
+            }
+            Some(_) => {
+                let next = structural_indexes
+                    .get(i + 1)
+                    .map_or(input.len(), |next| to_offset(*next));
+                spans.push(idx..idx + input.get(idx..next).map_or(0, scalar_len));
+            }
+            None => break,
+        }
+    }
+}
+
+/// Calls `read` on a padded copy of the scalar at the start of `loc` if it
+/// ends right at a comment.
+#[cold]
+fn before_comment<T>(loc: &[u8], read: impl Fn(&[u8]) -> Option<T>) -> Option<T> {
+    let len = loc
+        .iter()
+        .position(|&c| c == b'/' || is_structural_or_whitespace(c) != 0)?;
+    let rest = &loc[len..];
+    if !(rest.starts_with(b"//") || rest.starts_with(b"/*")) {
+        return None;
+    }
+    let mut scalar = loc[..len].to_vec();
+    scalar.resize(len + SIMDJSON_PADDING, b' ');
+    read(&scalar)
+}
+
 #[derive(Debug)]
 enum State {
     ObjectKey,
@@ -97,22 +252,86 @@ pub(crate) enum StackState {
 }
 
 impl<'de> Deserializer<'de> {
+    /// Records the spans of the `nodes` nodes of the tape parsed last if
+    /// `buffer` has spans enabled.
+    pub(crate) fn fill_spans(buffer: &mut Buffers, nodes: usize) {
+        if let Some(spans) = &mut buffer.spans {
+            record_spans(
+                &buffer.input_buffer,
+                &buffer.structural_indexes,
+                &mut spans.0,
+            );
+            spans.0.truncate(nodes);
+        }
+    }
+
+    /// Parses a number starting with a `-`, falling back to `-Infinity` if
+    /// non-finite literals are allowed.
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    pub(crate) fn parse_negative<const NON_FINITE: bool>(
+        idx: usize,
+        buf: &[u8],
+    ) -> Result<StaticNode> {
+        match Self::parse_number(idx, buf, true) {
+            Err(e) if NON_FINITE => buf.get(idx..).and_then(non_finite_atom).ok_or(e),
+            r => r,
+        }
+    }
+
+    /// Parses a number with `parse_negative` or `parse_number`, with
+    /// `RELAXED` it can be directly followed by a comment.
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    pub(crate) fn parse_scalar_number<const RELAXED: bool, const NON_FINITE: bool>(
+        idx: usize,
+        buf: &[u8],
+        negative: bool,
+    ) -> Result<StaticNode> {
+        let parse = |idx, buf: &[u8]| {
+            if negative {
+                Self::parse_negative::<NON_FINITE>(idx, buf)
+            } else {
+                Self::parse_number(idx, buf, false)
+            }
+        };
+        match parse(idx, buf) {
+            Err(e) if RELAXED => buf
+                .get(idx..)
+                .and_then(|loc| before_comment(loc, |loc| parse(0, loc).ok()))
+                .ok_or(e),
+            r => r,
+        }
+    }
+
+    /// Builds the tape from the structural indexes, with `RELAXED` trailing
+    /// commas in arrays and objects and comments right after scalars are
+    /// accepted. The string, member and node
+    /// limits of `options` are only checked when `LIMITED` is set. With
+    /// `NON_FINITE` `NaN` and the infinities are accepted and with
+    /// `ARBITRARY_PRECISION` numbers are kept as their text. Returns `input`
+    /// with its strings unescaped, which the tape borrows from.
     #[cfg_attr(not(feature = "no-inline"), inline)]
     #[allow(
         clippy::cognitive_complexity,
+        clippy::too_many_arguments,
         clippy::too_many_lines,
         unused_unsafe,
         clippy::needless_continue
     )]
-    pub(crate) fn build_tape(
+    pub(crate) fn build_tape<
+        const RELAXED: bool,
+        const LIMITED: bool,
+        const NON_FINITE: bool,
+        const ARBITRARY_PRECISION: bool,
+    >(
         input: &'de mut [u8],
         input2: &[u8],
         buffer: &mut [u8],
-        structural_indexes: &[u32],
+        structural_indexes: &[StructuralIndex],
         stack: &mut Vec<StackState>,
-        max_depth: usize,
+        options: &ParserOptions,
         res: &mut Vec<Node<'de>>,
-    ) -> Result<()> {
+    ) -> Result<&'de [u8]> {
+        let max_depth = options.depth;
         res.clear();
         res.reserve(structural_indexes.len());
         // While a valid json can have at max len/2 (`[[[]]]`)elements that are relevant
@@ -169,6 +388,9 @@ impl<'de> Deserializer<'de> {
 
         macro_rules! insert_res {
             ($t:expr_2021) => {
+                if LIMITED && unlikely!(r_i >= options.nodes) {
+                    fail!(ErrorType::TooManyNodes);
+                }
                 unsafe {
                     res_ptr.add(r_i).write($t);
                     r_i += 1;
@@ -180,13 +402,13 @@ impl<'de> Deserializer<'de> {
                 unsafe {
                     res.set_len(r_i);
                 }
-                return Ok(());
+                return Ok(input);
             };
         }
         macro_rules! update_char {
             () => {
                 if i < structural_indexes.len() {
-                    idx = *get!(structural_indexes, i) as usize;
+                    idx = to_offset(*get!(structural_indexes, i));
                     i += 1;
                     c = *get!(input2, idx);
                 } else {
@@ -217,10 +439,45 @@ impl<'de> Deserializer<'de> {
                     any(target_arch = "x86_64", target_arch = "x86"),
                 )))]
                 let s = s2try!(unsafe { Self::parse_str_(input_ptr, &input2, buffer, idx) });
+                if LIMITED && unlikely!(s.len() > options.string_len) {
+                    fail!(ErrorType::StringTooLong);
+                }
                 insert_res!(Node::String(s));
             }};
         }
 
+        macro_rules! insert_number {
+            ($negative:literal) => {
+                if ARBITRARY_PRECISION {
+                    if let Some(len) =
+                        read_scalar::<RELAXED, _>(get!(input2, idx..), raw_number_len)
+                    {
+                        // numbers are ASCII and never touched by unescaping
+                        let number = unsafe {
+                            std::str::from_utf8_unchecked(std::slice::from_raw_parts(
+                                input_ptr.add(idx),
+                                len,
+                            ))
+                        };
+                        insert_res!(Node::Number(number, NumberKind::of(number)));
+                    } else if $negative
+                        && NON_FINITE
+                        && let Some(n) =
+                            read_scalar::<RELAXED, _>(get!(input2, idx..), non_finite_atom)
+                    {
+                        insert_res!(Node::Static(n));
+                    } else {
+                        fail!(ErrorType::InvalidNumber);
+                    }
+                } else {
+                    insert_res!(Node::Static(s2try!(Self::parse_scalar_number::<
+                        RELAXED,
+                        NON_FINITE,
+                    >(idx, input2, $negative))));
+                }
+            };
+        }
+
         // The continue cases are the most frequently called onces it's
         // worth pulling them out into a macro (aka inlining them)
         // Since we don't have a 'gogo' in rust.
@@ -231,6 +488,12 @@ impl<'de> Deserializer<'de> {
                     b',' => {
                         cnt += 1;
                         update_char!();
+                        // a trailing comma, only allowed in relaxed mode
+                        if RELAXED && c == b']' {
+                            cnt -= 1;
+                            goto!(ScopeEnd);
+                        }
+                        check_members!();
                         goto!(MainArraySwitch);
                     }
                     b']' => {
@@ -251,9 +514,14 @@ impl<'de> Deserializer<'de> {
                         cnt += 1;
                         update_char!();
                         if c == b'"' {
+                            check_members!();
                             insert_str!();
                             goto!(ObjectKey);
                         }
+                        if RELAXED && c == b'}' {
+                            cnt -= 1;
+                            goto!(ScopeEnd);
+                        }
                         fail!(ErrorType::ExpectedObjectKey);
                     }
                     b'}' => {
@@ -273,6 +541,7 @@ impl<'de> Deserializer<'de> {
                     cnt = 0;
                     goto!(ScopeEnd);
                 }
+                check_members!();
                 goto!(MainArraySwitch);
             };
         }
@@ -282,6 +551,7 @@ impl<'de> Deserializer<'de> {
                 update_char!();
                 match c {
                     b'"' => {
+                        check_members!();
                         insert_str!();
                         goto!(ObjectKey)
                     }
@@ -327,6 +597,22 @@ impl<'de> Deserializer<'de> {
                 }
             };
         }
+        // the member `cnt` is about to be added, `cnt` is 1-based
+        macro_rules! check_members {
+            () => {
+                if LIMITED && unlikely!(cnt > options.members) {
+                    fail!(ErrorType::TooManyMembers);
+                }
+            };
+        }
+        // a scalar root followed by more, points at what follows it
+        macro_rules! trailing_data {
+            () => {
+                idx = to_offset(*get!(structural_indexes, i));
+                c = *get!(input2, idx);
+                fail!(ErrorType::TrailingData);
+            };
+        }
         // State start, we pull this outside of the
         // loop to reduce the number of required checks
         update_char!();
@@ -346,6 +632,7 @@ impl<'de> Deserializer<'de> {
                 update_char!();
                 match c {
                     b'"' => {
+                        check_members!();
                         insert_str!();
                         state = State::ObjectKey;
                     }
@@ -375,12 +662,13 @@ impl<'de> Deserializer<'de> {
                     cnt = 0;
                     state = State::ScopeEnd;
                 } else {
+                    check_members!();
                     state = State::MainArraySwitch;
                 }
             }
             b't' => {
                 unsafe {
-                    if !is_valid_true_atom(get!(input2, idx..)) {
+                    if !valid_atom::<RELAXED>(get!(input2, idx..), is_valid_true_atom) {
                         fail!(ErrorType::ExpectedTrue);
                     }
                 };
@@ -388,11 +676,11 @@ impl<'de> Deserializer<'de> {
                 if i == structural_indexes.len() {
                     success!();
                 }
-                fail!(ErrorType::TrailingData);
+                trailing_data!();
             }
             b'f' => {
                 unsafe {
-                    if !is_valid_false_atom(get!(input2, idx..)) {
+                    if !valid_atom::<RELAXED>(get!(input2, idx..), is_valid_false_atom) {
                         fail!(ErrorType::ExpectedFalse);
                     }
                 };
@@ -400,11 +688,11 @@ impl<'de> Deserializer<'de> {
                 if i == structural_indexes.len() {
                     success!();
                 }
-                fail!(ErrorType::TrailingData);
+                trailing_data!();
             }
             b'n' => {
                 unsafe {
-                    if !is_valid_null_atom(get!(input2, idx..)) {
+                    if !valid_atom::<RELAXED>(get!(input2, idx..), is_valid_null_atom) {
                         fail!(ErrorType::ExpectedNull);
                     }
                 };
@@ -412,32 +700,41 @@ impl<'de> Deserializer<'de> {
                 if i == structural_indexes.len() {
                     success!();
                 }
-                fail!(ErrorType::TrailingData);
+                trailing_data!();
             }
             b'"' => {
                 insert_str!();
                 if i == structural_indexes.len() {
                     success!();
                 }
-                fail!(ErrorType::TrailingData);
+                trailing_data!();
             }
             b'-' => {
-                insert_res!(Node::Static(s2try!(Self::parse_number(idx, input2, true))));
+                insert_number!(true);
 
                 if i == structural_indexes.len() {
                     success!();
                 }
-                fail!(ErrorType::TrailingData);
+                trailing_data!();
             }
             b'0'..=b'9' => {
-                insert_res!(Node::Static(s2try!(Self::parse_number(idx, input2, false))));
+                insert_number!(false);
 
                 if i == structural_indexes.len() {
                     success!();
                 }
-                fail!(ErrorType::TrailingData);
+                trailing_data!();
             }
             _ => {
+                if NON_FINITE
+                    && let Some(n) = read_scalar::<RELAXED, _>(get!(input2, idx..), non_finite_atom)
+                {
+                    insert_res!(Node::Static(n));
+                    if i == structural_indexes.len() {
+                        success!();
+                    }
+                    trailing_data!();
+                }
                 fail!();
             }
         }
@@ -459,36 +756,32 @@ impl<'de> Deserializer<'de> {
                         }
                         b't' => {
                             insert_res!(Node::Static(StaticNode::Bool(true)));
-                            if !is_valid_true_atom(get!(input2, idx..)) {
+                            if !valid_atom::<RELAXED>(get!(input2, idx..), is_valid_true_atom) {
                                 fail!(ErrorType::ExpectedTrue);
                             }
                             object_continue!();
                         }
                         b'f' => {
                             insert_res!(Node::Static(StaticNode::Bool(false)));
-                            if !is_valid_false_atom(get!(input2, idx..)) {
+                            if !valid_atom::<RELAXED>(get!(input2, idx..), is_valid_false_atom) {
                                 fail!(ErrorType::ExpectedFalse);
                             }
                             object_continue!();
                         }
                         b'n' => {
                             insert_res!(Node::Static(StaticNode::Null));
-                            if !is_valid_null_atom(get!(input2, idx..)) {
+                            if !valid_atom::<RELAXED>(get!(input2, idx..), is_valid_null_atom) {
                                 fail!(ErrorType::ExpectedNull);
                             }
                             object_continue!();
                         }
                         b'-' => {
-                            insert_res!(Node::Static(s2try!(Self::parse_number(
-                                idx, input2, true
-                            ))));
+                            insert_number!(true);
 
                             object_continue!();
                         }
                         b'0'..=b'9' => {
-                            insert_res!(Node::Static(s2try!(Self::parse_number(
-                                idx, input2, false
-                            ))));
+                            insert_number!(false);
 
                             object_continue!();
                         }
@@ -519,6 +812,13 @@ impl<'de> Deserializer<'de> {
                             array_begin!();
                         }
                         _c => {
+                            if NON_FINITE
+                                && let Some(n) =
+                                    read_scalar::<RELAXED, _>(get!(input2, idx..), non_finite_atom)
+                            {
+                                insert_res!(Node::Static(n));
+                                object_continue!();
+                            }
                             fail!();
                         }
                     }
@@ -590,36 +890,32 @@ impl<'de> Deserializer<'de> {
                         }
                         b't' => {
                             insert_res!(Node::Static(StaticNode::Bool(true)));
-                            if !is_valid_true_atom(get!(input2, idx..)) {
+                            if !valid_atom::<RELAXED>(get!(input2, idx..), is_valid_true_atom) {
                                 fail!(ErrorType::ExpectedTrue);
                             }
                             array_continue!();
                         }
                         b'f' => {
                             insert_res!(Node::Static(StaticNode::Bool(false)));
-                            if !is_valid_false_atom(get!(input2, idx..)) {
+                            if !valid_atom::<RELAXED>(get!(input2, idx..), is_valid_false_atom) {
                                 fail!(ErrorType::ExpectedFalse);
                             }
                             array_continue!();
                         }
                         b'n' => {
                             insert_res!(Node::Static(StaticNode::Null));
-                            if !is_valid_null_atom(get!(input2, idx..)) {
+                            if !valid_atom::<RELAXED>(get!(input2, idx..), is_valid_null_atom) {
                                 fail!(ErrorType::ExpectedNull);
                             }
                             array_continue!();
                         }
                         b'-' => {
-                            insert_res!(Node::Static(s2try!(Self::parse_number(
-                                idx, input2, true
-                            ))));
+                            insert_number!(true);
 
                             array_continue!();
                         }
                         b'0'..=b'9' => {
-                            insert_res!(Node::Static(s2try!(Self::parse_number(
-                                idx, input2, false
-                            ))));
+                            insert_number!(false);
 
                             array_continue!();
                         }
@@ -650,6 +946,13 @@ impl<'de> Deserializer<'de> {
                             array_begin!();
                         }
                         _c => {
+                            if NON_FINITE
+                                && let Some(n) =
+                                    read_scalar::<RELAXED, _>(get!(input2, idx..), non_finite_atom)
+                            {
+                                insert_res!(Node::Static(n));
+                                array_continue!();
+                            }
                             fail!();
                         }
                     }
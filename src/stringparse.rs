@@ -85,3 +85,36 @@ pub(crate) fn get_unicode_codepoint(mut src_ptr: &[u8]) -> Result<(u32, usize),
     }
     Ok((code_point, src_offset))
 }
+
+/// Unescapes the first `len` bytes of `src`, the raw content of a string.
+/// `src` has to be padded so reading a unicode escape never goes out of
+/// bounds, returns `None` for invalid escapes or UTF-8.
+pub(crate) fn unescape(src: &[u8], len: usize) -> Option<String> {
+    let mut dst = String::with_capacity(len);
+    let mut i = 0;
+    while i < len {
+        let b = src[i];
+        if b == b'\\' {
+            let escape_char = src[i + 1];
+            if escape_char == b'u' {
+                let (cp, offset) = get_unicode_codepoint(&src[i..]).ok()?;
+                dst.push(char::from_u32(cp)?);
+                i += offset;
+            } else {
+                let escape_result = ESCAPE_MAP[escape_char as usize];
+                if escape_result == 0 {
+                    return None;
+                }
+                dst.push(escape_result as char);
+                i += 2;
+            }
+        } else {
+            let start = i;
+            while i < len && src[i] != b'\\' {
+                i += 1;
+            }
+            dst.push_str(std::str::from_utf8(&src[start..i]).ok()?);
+        }
+    }
+    Some(dst)
+}
@@ -100,7 +100,7 @@ fn test_tape_object_simple() {
     let d = unsafe { d.as_bytes_mut() };
     let simd = Deserializer::from_slice(d).expect("");
     assert_eq!(
-        simd.tape,
+        *simd.tape,
         [
             Node::Object { len: 2, count: 4 },
             Node::String("hello"), // <-- This is already escaped
@@ -117,7 +117,7 @@ fn test_tape_object_escaped() {
     let d = unsafe { d.as_bytes_mut() };
     let simd = Deserializer::from_slice(d).expect("");
     assert_eq!(
-        simd.tape,
+        *simd.tape,
         [
             Node::Object { len: 2, count: 7 },
             Node::String(r#"hell"o"#), // <-- This is already escaped
@@ -196,6 +196,393 @@ fn silly_float2() {
     let parsed = to_owned_value(&mut bytes).expect("failed to parse generated float");
     assert_eq!(v, parsed);
 }
+
+#[test]
+fn non_finite_literals() {
+    let mut buffers = crate::Buffers::default().with_non_finite_literals(true);
+    let mut lenient = |input: &str| {
+        crate::owned::to_value_with_buffers(&mut input.as_bytes().to_vec(), &mut buffers)
+    };
+    let v = lenient(r#"[NaN, Infinity,-Infinity, {"a": -Infinity}, -1]"#).expect("lenient");
+    assert!(
+        v.get_idx(0)
+            .and_then(ValueAsScalar::as_f64)
+            .is_some_and(f64::is_nan)
+    );
+    assert_eq!(
+        v.get_idx(1).and_then(ValueAsScalar::as_f64),
+        Some(f64::INFINITY)
+    );
+    assert_eq!(
+        v.get_idx(2).and_then(ValueAsScalar::as_f64),
+        Some(f64::NEG_INFINITY)
+    );
+    assert_eq!(
+        v.get_idx(3)
+            .and_then(|o| o.get("a"))
+            .and_then(ValueAsScalar::as_f64),
+        Some(f64::NEG_INFINITY)
+    );
+    assert_eq!(lenient("Infinity").ok(), Some(Value::from(f64::INFINITY)));
+    for invalid in [
+        "Nan",
+        "nan",
+        "Infinit",
+        "[-Inf]",
+        "[NaNa]",
+        "+Infinity",
+        "[Infinity1]",
+        "NaN NaN",
+    ] {
+        assert!(lenient(invalid).is_err(), "{invalid}");
+    }
+    for strict in ["NaN", "[Infinity]", r#"{"a": -Infinity}"#] {
+        assert!(
+            to_owned_value(&mut strict.as_bytes().to_vec()).is_err(),
+            "{strict}"
+        );
+        assert!(crate::validate(strict.as_bytes()).is_err(), "{strict}");
+    }
+    let mut buffers = crate::Buffers::default().with_non_finite_literals(true);
+    assert!(crate::validate_with_buffers(b"[NaN, -Infinity]", &mut buffers).is_ok());
+}
+
+#[test]
+fn relaxed_comments_and_trailing_commas() {
+    let mut buffers = crate::Buffers::default().with_relaxed(true);
+    let mut relaxed = |input: &str| {
+        crate::owned::to_value_with_buffers(&mut input.as_bytes().to_vec(), &mut buffers)
+    };
+    let expected = crate::json!({"a": [1, 2], "b": {"c": "/* d */"}, "e": []});
+    let input = r#"// config
+    {
+        "a": [1, 2,], // two
+        /* the "b" key */ "b": {"c": "/* d */",},
+        "e": [/**/],
+    }"#;
+    assert_eq!(relaxed(input).ok(), Some(expected));
+    assert_eq!(relaxed("[1, 2,]").ok(), Some(crate::json!([1, 2])));
+    // comments can directly follow scalars and are left in the input
+    let input = "[1/**/, -2.5// x\n, true/**/, null//\n, \"a\"/* \" */]";
+    let mut data = input.as_bytes().to_vec();
+    let mut other = crate::Buffers::default().with_relaxed(true);
+    let value = crate::owned::to_value_with_buffers(&mut data, &mut other);
+    assert_eq!(value.ok(), Some(crate::json!([1, -2.5, true, null, "a"])));
+    assert_eq!(data, input.as_bytes());
+    assert!(crate::validate_with_buffers(input.as_bytes(), &mut other).is_ok());
+    let mut other = other.with_arbitrary_precision(true);
+    assert!(crate::validate_with_buffers(b"[1/**/, 20// x\n]", &mut other).is_ok());
+    for invalid in [
+        "[,]",
+        "[1,,]",
+        "{,}",
+        r#"{"a": 1,,}"#,
+        "[1 /* */ 2]",
+        "[1 / 2]",
+        "[1] /*",
+        "[1,]]",
+    ] {
+        assert!(relaxed(invalid).is_err(), "{invalid}");
+    }
+    for strict in ["[1, 2,]", r#"{"a": 1,}"#, "[1] // one"] {
+        assert!(
+            to_owned_value(&mut strict.as_bytes().to_vec()).is_err(),
+            "{strict}"
+        );
+    }
+}
+
+#[test]
+fn error_locations() {
+    let location = |e: &crate::Error| (e.index(), e.line(), e.column(), e.path().map(String::from));
+    let locate = |input: &[u8]| {
+        let e = to_owned_value(&mut input.to_vec()).expect_err("invalid input");
+        // validating finds the same location
+        let v = crate::validate(input).expect_err("invalid input");
+        assert_eq!(location(&e), location(&v));
+        location(&e)
+    };
+
+    let input = "{\n  \"items\": [1, 2, 3, {\"price\": tru}]\n}";
+    assert_eq!(
+        locate(input.as_bytes()),
+        (33, Some(2), Some(32), Some("/items/3/price".into()))
+    );
+    assert_eq!(
+        locate(br#"{"a/b~c": {"x\u0041": [nul]}}"#),
+        (23, Some(1), Some(24), Some("/a~1b~0c/xA/0".into()))
+    );
+    assert_eq!(locate(b"1 2"), (2, Some(1), Some(3), Some(String::new())));
+    // the offset of the backslash, not of the string
+    assert_eq!(locate(br#"["ab\x"]"#).0, 4);
+    // stage 1 errors don't know the path
+    assert_eq!(locate(b"[1,\n \"abc"), (5, Some(2), Some(2), None));
+    assert_eq!(locate(b"[\"a\xff\"]"), (3, Some(1), Some(4), None));
+
+    // documents are located in the whole input
+    let mut input = b"{\"a\": 1}\n{\"b\": [tru]}".to_vec();
+    let mut buffers = crate::Buffers::default();
+    let docs: Vec<_> = crate::parse_many(&mut input, &mut buffers)
+        .expect("stage 1")
+        .collect();
+    let e = docs[1].as_ref().expect_err("invalid document");
+    assert_eq!(location(e), (16, Some(2), Some(8), Some("/b/0".into())));
+
+    // errors not found in the input have no location
+    assert_eq!(crate::Error::generic(crate::ErrorType::Eof).line(), None);
+}
+
+#[test]
+fn error_messages() {
+    let input = "{\n  \"server\": {\n  \t\"port\" 8080\n}}";
+    let e = to_owned_value(&mut input.as_bytes().to_vec()).expect_err("missing colon");
+    assert_eq!(
+        e.to_string(),
+        "expected `:` after object key at line 3 column 11 (at /server/port)"
+    );
+    assert_eq!(
+        e.render(input),
+        "error: expected `:` after object key
+ --> line 3, column 11 (at /server/port)
+  |
+3 |   \t\"port\" 8080
+  |   \t       ^"
+    );
+
+    let input = "[\r\n  \"\u{e9}\", nul\r\n]";
+    let e = to_owned_value(&mut input.as_bytes().to_vec()).expect_err("invalid null");
+    assert_eq!(e.to_string(), "expected `null` at line 2 column 9 (at /1)");
+    assert_eq!(
+        e.render(input.as_bytes()),
+        "error: expected `null`\n --> line 2, column 9 (at /1)\n  |\n2 |   \"\u{e9}\", nul\n  |        ^"
+    );
+
+    let e = crate::Error::generic(crate::ErrorType::Unexpected(
+        Some(ValueType::Array),
+        Some(ValueType::String),
+    ));
+    assert_eq!(e.to_string(), "expected array but found string");
+    assert_eq!(e.render(""), "error: expected array but found string");
+}
+
+#[test]
+fn structural_indexes_of_reused_buffers() {
+    use crate::Buffers;
+    let mut buffers = Buffers::default();
+    crate::to_tape_with_buffers(&mut br#"{"a": [1, 2]}"#.to_vec(), &mut buffers).expect("valid");
+    let first: &[u32] = buffers.structural_indexes();
+    assert_eq!(first, [0, 1, 4, 6, 7, 8, 10, 11, 12]);
+    #[cfg(feature = "wide-index")]
+    assert_eq!(
+        buffers.wide_structural_indexes(),
+        [0, 1, 4, 6, 7, 8, 10, 11, 12]
+    );
+
+    crate::to_tape_with_buffers(&mut b"[true]".to_vec(), &mut buffers).expect("valid");
+    assert_eq!(buffers.structural_indexes(), [0, 1, 5]);
+}
+
+#[test]
+fn duplicate_key_policies() {
+    use crate::{Buffers, DuplicateKeyPolicy, ErrorType, borrowed, owned};
+    let input = r#"{"a": 1, "b": {"c": 2}, "a": 3, "a": {"d": 4}}"#;
+    let parse = |policy| {
+        let mut buffers = Buffers::default().with_duplicate_keys(policy);
+        let owned = owned::to_value_with_buffers(&mut input.as_bytes().to_vec(), &mut buffers);
+        let from_bytes = borrowed::to_value_from_bytes_with_buffers(input.as_bytes(), &mut buffers)
+            .map(borrowed::Value::into_static);
+        let borrowed =
+            borrowed::to_value_with_buffers(&mut input.as_bytes().to_vec(), &mut buffers)
+                .map(borrowed::Value::into_static);
+        assert_eq!(from_bytes, borrowed);
+        if policy != DuplicateKeyPolicy::KeepAll {
+            // converting drops duplicates
+            assert_eq!(
+                owned.as_ref().ok(),
+                borrowed.ok().map(owned::Value::from).as_ref()
+            );
+        }
+        owned
+    };
+
+    assert_eq!(
+        parse(DuplicateKeyPolicy::FirstWins).ok(),
+        Some(crate::json!({"a": 1, "b": {"c": 2}}))
+    );
+    assert_eq!(
+        parse(DuplicateKeyPolicy::LastWins).ok(),
+        Some(crate::json!({"a": {"d": 4}, "b": {"c": 2}}))
+    );
+    let all = parse(DuplicateKeyPolicy::KeepAll).expect("valid");
+    assert_eq!(all.as_object().map(halfbrown::HashMap::len), Some(4));
+
+    let e = parse(DuplicateKeyPolicy::Error).expect_err("duplicate key");
+    assert_eq!(e.error(), &ErrorType::DuplicateKey);
+    assert_eq!(e.index(), 24);
+    assert_eq!(e.path(), Some(""));
+    let mut nested = br#"[{"x": {"y": 1, "z": 2, "y": 3}}]"#.to_vec();
+    let mut buffers = Buffers::default().with_duplicate_keys(DuplicateKeyPolicy::Error);
+    let e = owned::to_value_with_buffers(&mut nested, &mut buffers).expect_err("duplicate key");
+    assert_eq!((e.index(), e.path()), (24, Some("/0/x")));
+}
+
+#[test]
+fn parser_limits() {
+    use crate::{Buffers, ErrorType, ParserOptions, borrowed};
+    let parse = |input: &str, options: ParserOptions| {
+        let mut buffers = Buffers::from(options);
+        let tape =
+            crate::to_tape_with_buffers(&mut input.as_bytes().to_vec(), &mut buffers).map(|_| ());
+        let value = borrowed::to_value_with_buffers(&mut input.as_bytes().to_vec(), &mut buffers)
+            .map(|_| ());
+        assert_eq!(tape, value);
+        let valid = crate::validate_with_buffers(input.as_bytes(), &mut buffers);
+        assert_eq!(tape, valid, "{input}");
+        tape
+    };
+    let fails = |input: &str, options: ParserOptions, index: usize, err: ErrorType| {
+        let e = parse(input, options).expect_err(input);
+        assert_eq!((e.index(), e.error()), (index, &err));
+    };
+    let options = ParserOptions::default();
+
+    let input_len = options.with_max_input_len(4);
+    assert!(parse("[1]", input_len).is_ok());
+    fails("[1,2]", input_len, 0, ErrorType::InputTooLarge);
+
+    let depth = options.with_max_depth(2);
+    assert!(parse("[[1]]", depth).is_ok());
+    fails("[[[1]]]", depth, 2, ErrorType::DepthLimitExceeded);
+
+    let string_len = options.with_max_string_len(3);
+    assert!(
+        parse(
+            r#"["abc", "a\nb", "\u00e9\ud83d\ude00"]"#,
+            options.with_max_string_len(6)
+        )
+        .is_ok()
+    );
+    assert!(parse(r#"["abc", "a\nb"]"#, string_len).is_ok());
+    fails(
+        r#"["\u00e9\u00e9"]"#,
+        string_len,
+        1,
+        ErrorType::StringTooLong,
+    );
+    fails(
+        r#"["abc", "abcd"]"#,
+        string_len,
+        8,
+        ErrorType::StringTooLong,
+    );
+    fails(r#"{"abcd": 1}"#, string_len, 1, ErrorType::StringTooLong);
+
+    let members = options.with_max_members(2);
+    assert!(parse(r#"[1, {"a": 2, "b": 3}, []]"#, options.with_max_members(3)).is_ok());
+    fails("[1, 2, 3]", members, 7, ErrorType::TooManyMembers);
+    fails(
+        r#"[{"a": 1, "b": 2, "c": 3}]"#,
+        members,
+        18,
+        ErrorType::TooManyMembers,
+    );
+    let mut buffers = Buffers::from(members).with_relaxed(true);
+    assert!(crate::to_tape_with_buffers(&mut b"[1, 2,]".to_vec(), &mut buffers).is_ok());
+    assert!(crate::validate_with_buffers(b"[1, 2,]", &mut buffers).is_ok());
+    let empty = options.with_max_members(0);
+    assert!(parse("[]", empty).is_ok());
+    fails("[1]", empty, 1, ErrorType::TooManyMembers);
+    fails(r#"{"a": 1}"#, empty, 1, ErrorType::TooManyMembers);
+
+    let nodes = options.with_max_nodes(3);
+    assert!(parse("[1, 2]", nodes).is_ok());
+    assert!(parse(r#"{"a": 1}"#, nodes).is_ok());
+    fails("[1, 2, 3]", nodes, 7, ErrorType::TooManyNodes);
+    fails(
+        r#"[[], "a"]"#,
+        options.with_max_nodes(2),
+        5,
+        ErrorType::TooManyNodes,
+    );
+    fails(r#"{"a": [1]}"#, nodes, 7, ErrorType::TooManyNodes);
+    fails(
+        "true",
+        options.with_max_nodes(0),
+        0,
+        ErrorType::TooManyNodes,
+    );
+
+    #[cfg(feature = "serde_impl")]
+    {
+        let mut buffers = Buffers::from(members);
+        let e = crate::serde::from_slice_with_buffers::<Vec<u8>>(
+            &mut b"[1, 2, 3]".to_vec(),
+            &mut buffers,
+        )
+        .expect_err("too many members");
+        assert_eq!(e.error(), &ErrorType::TooManyMembers);
+    }
+}
+
+#[test]
+fn arbitrary_precision_numbers() {
+    use crate::{Buffers, ErrorType, borrowed};
+    let input =
+        r#"[1.50, 123456789012345678901234567890, -0.000000000000000000001, 1e400, {"a": -0}, 7]"#;
+    let mut buffers = Buffers::default().with_arbitrary_precision(true);
+
+    let mut bytes = input.as_bytes().to_vec();
+    let tape = crate::to_tape_with_buffers(&mut bytes, &mut buffers).expect("valid");
+    assert_eq!(tape.0[1], Node::Number("1.50", crate::NumberKind::F64));
+    assert_eq!(
+        tape.as_value().encode(),
+        input.replace(", ", ",").replace(": ", ":")
+    );
+
+    let mut bytes = input.as_bytes().to_vec();
+    let v = borrowed::to_value_with_buffers(&mut bytes, &mut buffers).expect("valid");
+    assert_eq!(v.encode(), input.replace(", ", ",").replace(": ", ":"));
+    assert_eq!(v[0].as_f64(), Some(1.5));
+    #[cfg(feature = "128bit")]
+    assert_eq!(
+        v[1].as_i128(),
+        Some(123_456_789_012_345_678_901_234_567_890)
+    );
+    #[cfg(not(feature = "128bit"))]
+    assert!(v[1].is_f64());
+    assert_eq!(v[4]["a"].as_i64(), Some(0));
+    assert_eq!(v[5], 7);
+    assert_eq!(v[5].value_type(), ValueType::I64);
+    let owned = Value::from(v.clone());
+    assert_eq!(owned, v);
+    assert_eq!(owned.encode(), v.encode());
+    let bytes_value =
+        borrowed::to_value_from_bytes_with_buffers(input.as_bytes(), &mut buffers).expect("valid");
+    assert_eq!(bytes_value, v);
+
+    for invalid in ["01", "[1.]", "-", "[1e]", "[1e+]", "[- 1]", "[1.5x]"] {
+        let e = crate::to_tape_with_buffers(&mut invalid.as_bytes().to_vec(), &mut buffers)
+            .expect_err(invalid);
+        assert_eq!(e.error(), &ErrorType::InvalidNumber, "{invalid}");
+        assert!(crate::validate_with_buffers(invalid.as_bytes(), &mut buffers).is_err());
+    }
+    assert!(
+        crate::validate_with_buffers(b"[1e400, 123456789012345678901234567890]", &mut buffers)
+            .is_ok()
+    );
+    assert!(to_owned_value(&mut input.as_bytes().to_vec()).is_err());
+
+    // -Infinity isn't a number but is still read with non-finite literals
+    let mut buffers = buffers.with_non_finite_literals(true);
+    let mut bytes = b"[-Infinity, -1]".to_vec();
+    let v = borrowed::to_value_with_buffers(&mut bytes, &mut buffers).expect("valid");
+    assert_eq!(v[0].as_f64(), Some(f64::NEG_INFINITY));
+    assert_eq!(
+        v[1],
+        borrowed::Value::Number("-1".into(), crate::NumberKind::I64)
+    );
+}
+
 #[cfg(not(feature = "128bit"))]
 #[cfg(not(target_arch = "wasm32"))]
 fn arb_json_value() -> BoxedStrategy<Value> {
@@ -1,12 +1,12 @@
-use crate::{Deserializer, SIMDJSON_PADDING, Stage1Parse, impls};
+use crate::{Deserializer, SIMDJSON_PADDING, Stage1Parse, StructuralIndex, impls};
 
-fn test_find_structural_bits<S: Stage1Parse>(input_str: &str, expected: &[u32]) {
+fn test_find_structural_bits<S: Stage1Parse>(input_str: &str, expected: &[StructuralIndex]) {
     let mut input = input_str.as_bytes().to_vec();
     input.append(&mut vec![0; SIMDJSON_PADDING]);
     let mut res = Vec::new();
 
     unsafe {
-        Deserializer::_find_structural_bits::<S>(input.as_slice(), &mut res)
+        Deserializer::_find_structural_bits::<S>(input.as_slice(), &mut res, false)
             .expect("failed to find structural bits");
     };
     println!("{input_str}");
@@ -58,6 +58,160 @@ fn find_structural_bits_avx() {
     }
 }
 
+#[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+#[test]
+fn find_structural_bits_avx512() {
+    if std::is_x86_feature_detected!("avx512f") && std::is_x86_feature_detected!("avx512bw") {
+        find_structural_bits_test_cases::<impls::avx512::SimdInput>();
+    }
+}
+
+/// Documents whose strings and escapes cross the 32 and 64 byte blocks at
+/// every offset
+#[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+fn block_crossing_inputs() -> Vec<String> {
+    let mut inputs = Vec::new();
+    for pad in 0..70 {
+        let fill = "x".repeat(pad);
+        inputs.push(format!(
+            r#"{{"{fill}": ["{fill}\"\\", "{fill}é😀\n", {pad}, true, null]}}"#
+        ));
+        inputs.push(format!(
+            r#"[" {fill} ",{{"a\tb":[{pad}.5e3, "{fill}é"]}}, "\/{fill}😀"]"#
+        ));
+        // invalid escapes have to fail at the same index
+        inputs.push(format!(r#"["{fill}\q", "{fill}\u12", "\ud800{fill}"]"#));
+    }
+    inputs
+}
+
+#[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+#[test]
+fn avx512_matches_avx2() {
+    if !(std::is_x86_feature_detected!("avx512f")
+        && std::is_x86_feature_detected!("avx512bw")
+        && std::is_x86_feature_detected!("avx2")
+        && std::is_x86_feature_detected!("pclmulqdq"))
+    {
+        return;
+    }
+    for input in block_crossing_inputs() {
+        let mut padded = input.clone().into_bytes();
+        padded.append(&mut vec![0; SIMDJSON_PADDING]);
+        let mut avx2 = Vec::new();
+        let mut avx512 = Vec::new();
+        unsafe {
+            Deserializer::_find_structural_bits::<impls::avx2::SimdInput>(
+                &padded, &mut avx2, false,
+            )
+            .expect("avx2");
+            Deserializer::_find_structural_bits::<impls::avx512::SimdInput>(
+                &padded,
+                &mut avx512,
+                false,
+            )
+            .expect("avx512");
+        }
+        assert_eq!(avx2, avx512, "{input}");
+
+        // every string in the document, parsed by both
+        let mut data = input.clone().into_bytes();
+        data.append(&mut vec![0; SIMDJSON_PADDING * 2]);
+        let mut buffer = vec![0; input.len() + SIMDJSON_PADDING];
+        for &idx in &avx2 {
+            let idx = crate::to_offset(idx);
+            if data[idx] != b'"' {
+                continue;
+            }
+            let mut a = input.clone().into_bytes();
+            let mut b = input.clone().into_bytes();
+            let a = unsafe {
+                impls::avx2::parse_str(a.as_mut_ptr().into(), &data, &mut buffer, idx)
+                    .map(str::to_string)
+            };
+            let b = unsafe {
+                impls::avx512::parse_str(b.as_mut_ptr().into(), &data, &mut buffer, idx)
+                    .map(str::to_string)
+            };
+            assert_eq!(a, b, "{input} at {idx}");
+        }
+    }
+}
+
+/// Inputs whose backslash runs and (invalid) UTF-8 sequences cross the 64
+/// byte blocks at every offset
+#[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+fn block_crossing_bytes() -> Vec<Vec<u8>> {
+    let mut inputs: Vec<Vec<u8>> = block_crossing_inputs()
+        .into_iter()
+        .map(String::into_bytes)
+        .collect();
+    for pad in 0..70 {
+        for backslashes in 1..4 {
+            let mut input = format!(r#"["{}"#, "x".repeat(pad)).into_bytes();
+            input.extend(std::iter::repeat_n(b'\\', backslashes));
+            input.extend_from_slice(br#"", {"a": "]"}]"#);
+            inputs.push(input);
+        }
+        for invalid in [
+            &b"\x80"[..],
+            b"\xe2\x82",
+            b"\xc0\xaf",
+            b"\xed\xa0\x80",
+            b"\xf4\x90\x80\x80",
+            "€😀".as_bytes(),
+        ] {
+            let mut input = format!(r#"["{}"#, "x".repeat(pad)).into_bytes();
+            input.extend_from_slice(invalid);
+            input.extend_from_slice(br#"", 1]"#);
+            inputs.push(input);
+        }
+    }
+    inputs
+}
+
+#[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+#[test]
+fn avx512_matches_native() {
+    if !(std::is_x86_feature_detected!("avx512f") && std::is_x86_feature_detected!("avx512bw")) {
+        return;
+    }
+    for input in block_crossing_bytes() {
+        let mut padded = input.clone();
+        padded.append(&mut vec![0; SIMDJSON_PADDING]);
+        for comments in [false, true] {
+            let mut native = Vec::new();
+            let mut avx512 = Vec::new();
+            let (native_res, avx512_res) = unsafe {
+                (
+                    Deserializer::_find_structural_bits::<impls::native::SimdInput>(
+                        &padded,
+                        &mut native,
+                        comments,
+                    ),
+                    Deserializer::_find_structural_bits::<impls::avx512::SimdInput>(
+                        &padded,
+                        &mut avx512,
+                        comments,
+                    ),
+                )
+            };
+            // the native backend relies on the input being validated up front
+            let utf8 = std::str::from_utf8(&input).is_ok();
+            let input = String::from_utf8_lossy(&input);
+            if native_res.is_ok() {
+                assert_eq!(native, avx512, "{input}");
+            }
+            let native_res = native_res.and(if utf8 {
+                Ok(())
+            } else {
+                Err(crate::ErrorType::InvalidUtf8)
+            });
+            assert_eq!(native_res, avx512_res, "{input}");
+        }
+    }
+}
+
 #[cfg(target_feature = "sse4.2")]
 #[test]
 fn find_structural_bits_sse() {
@@ -77,3 +231,44 @@ fn find_structural_bits_aarch64() {
 fn find_structural_bits_simd128() {
     find_structural_bits_test_cases::<impls::simd128::SimdInput>();
 }
+
+#[cfg(all(
+    feature = "wide-index",
+    not(target_arch = "aarch64"),
+    not(target_feature = "simd128")
+))]
+#[test]
+fn flatten_bits_wide() {
+    // offsets past 4 GiB must survive flattening
+    let base: StructuralIndex = 5 << 30;
+    let mut res = Vec::new();
+    unsafe {
+        <impls::native::SimdInput as Stage1Parse>::flatten_bits(
+            &mut res,
+            base + 64,
+            0b1001 | 1 << 63,
+        );
+    };
+    assert_eq!(res, vec![base, base + 3, base + 63]);
+}
+
+#[test]
+fn implementation_names() {
+    use crate::Implementation;
+    for name in [
+        "avx512",
+        "AVX-512",
+        "Avx2",
+        "sse42",
+        "native",
+        "Rust Native",
+        "std-simd",
+    ] {
+        let implementation: Implementation = name.parse().expect(name);
+        assert_eq!(
+            implementation.to_string().parse::<Implementation>().ok(),
+            Some(implementation)
+        );
+    }
+    assert!("avx3".parse::<Implementation>().is_err());
+}
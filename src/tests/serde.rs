@@ -1149,3 +1149,373 @@ fn overdriven_next_value_seed_errors_instead_of_oob() {
     let mut input = b"{}".to_vec();
     assert!(from_slice::<Evil>(&mut input).is_err());
 }
+
+#[test]
+fn arbitrary_precision_round_trip() {
+    use crate::{BorrowedValue, Buffers, serde::Number};
+
+    #[derive(Deserialize, Serialize, PartialEq, Debug)]
+    struct Trade {
+        id: Number,
+        price: Number,
+        qty: u64,
+        rate: f64,
+        raw: OwnedValue,
+    }
+
+    let input = r#"{"id":123456789012345678901234567890,"price":1.50,"qty":7,"rate":-0.25,"raw":[1e400,-0.000000000000000000001]}"#;
+    let mut buffers = Buffers::default().with_arbitrary_precision(true);
+    let trade: Trade =
+        crate::serde::from_slice_with_buffers(&mut input.as_bytes().to_vec(), &mut buffers)
+            .expect("valid");
+    assert_eq!(trade.id.as_str(), "123456789012345678901234567890");
+    assert_eq!(trade.price.as_f64(), Some(1.5));
+    assert_eq!(trade.qty, 7);
+    assert_eq!(
+        trade.raw[0],
+        OwnedValue::Number("1e400".into(), crate::NumberKind::F64)
+    );
+    assert_eq!(crate::to_string(&trade).expect("serializable"), input);
+    assert_eq!(
+        crate::to_string_pretty(&trade.raw).expect("serializable"),
+        "[\n  1e400,\n  -0.000000000000000000001\n]"
+    );
+
+    // through the values
+    let mut bytes = input.as_bytes().to_vec();
+    let value = crate::to_borrowed_value_with_buffers(&mut bytes, &mut buffers).expect("valid");
+    assert_eq!(crate::to_string(&value).expect("serializable"), input);
+    let from_value: Trade = crate::serde::from_refborrowed_value(&value).expect("valid");
+    assert_eq!(from_value, trade);
+    let from_value: Trade =
+        crate::serde::from_owned_value(OwnedValue::from(value.clone())).expect("valid");
+    assert_eq!(from_value, trade);
+    // typed fields turn into plain numbers, `Number`s keep their text
+    let owned = crate::serde::to_owned_value(&trade).expect("valid");
+    assert_eq!(
+        owned["price"],
+        OwnedValue::Number("1.50".into(), crate::NumberKind::F64)
+    );
+    assert_eq!(owned.encode(), input);
+    let borrowed: BorrowedValue = crate::serde::to_borrowed_value(&trade).expect("valid");
+    assert_eq!(borrowed, owned);
+
+    assert!("1.5e".parse::<Number>().is_err());
+    assert_eq!(
+        "-1.5e+3".parse::<Number>().map(|n| n.to_string()).ok(),
+        Some("-1.5e+3".into())
+    );
+}
+
+#[test]
+fn raw_value_sources() {
+    use crate::{Buffers, serde::RawValue};
+
+    #[derive(Deserialize)]
+    struct Borrowed<'input> {
+        #[serde(borrow)]
+        value: &'input RawValue,
+    }
+
+    // escaped strings are rewritten in place so they can't be borrowed
+    let mut buffers = Buffers::default().with_raw_values(true);
+    for input in [r#"{"value": "a\nb"}"#, r#"{"value": ["\u0041"]}"#] {
+        let mut bytes = input.as_bytes().to_vec();
+        assert!(
+            crate::serde::from_slice::<Borrowed>(&mut bytes).is_err(),
+            "{input}"
+        );
+        let mut bytes = input.as_bytes().to_vec();
+        let res = crate::serde::from_slice_with_buffers::<Borrowed>(&mut bytes, &mut buffers);
+        assert!(res.is_err(), "{input}");
+    }
+    let mut input = br#"{"value": ["ab", 1]}"#.to_vec();
+    let borrowed: Borrowed =
+        crate::serde::from_slice_with_buffers(&mut input, &mut buffers).expect("valid");
+    assert_eq!(borrowed.value.get(), r#"["ab", 1]"#);
+
+    // relaxed values keep their comments and trailing commas
+    let input = r#"{"a": [1, /* one */ 2,], // b
+        "b": {"c": 3,},}"#;
+    let mut buffers = Buffers::default().with_relaxed(true).with_raw_values(true);
+    let raws: std::collections::BTreeMap<String, Box<RawValue>> =
+        crate::serde::from_slice_with_buffers(&mut input.as_bytes().to_vec(), &mut buffers)
+            .expect("valid");
+    assert_eq!(raws["a"].get(), "[1, /* one */ 2,]");
+    assert_eq!(raws["b"].get(), r#"{"c": 3,}"#);
+
+    // without opting in the value is encoded from the tape
+    let raw: Box<RawValue> = crate::serde::from_slice(&mut b"[1, 2 ]".to_vec()).expect("valid");
+    assert_eq!(raw.get(), "[1,2]");
+    let raw: Box<RawValue> = crate::serde::from_bytes(b"[1, 2 ]").expect("valid");
+    assert_eq!(raw.get(), "[1,2]");
+
+    // turning raw values off again encodes them from the tape
+    let input = r#"{"value": [1, "a\nb"] }"#;
+    let mut buffers = Buffers::default().with_raw_values(true);
+    let parse = |buffers: &mut Buffers| {
+        let raw: std::collections::BTreeMap<String, Box<RawValue>> =
+            crate::serde::from_slice_with_buffers(&mut input.as_bytes().to_vec(), buffers)
+                .expect("valid");
+        raw["value"].get().to_string()
+    };
+    assert_eq!(parse(&mut buffers), r#"[1, "a\nb"]"#);
+    let mut buffers = buffers.with_raw_values(false);
+    assert_eq!(parse(&mut buffers), r#"[1,"a\nb"]"#);
+    assert_eq!(parse(&mut buffers), r#"[1,"a\nb"]"#);
+    let mut buffers = buffers.with_raw_values(true);
+    assert_eq!(parse(&mut buffers), r#"[1, "a\nb"]"#);
+}
+
+#[test]
+fn raw_value_capture() {
+    use crate::{Buffers, serde::RawValue};
+
+    // raw values need the buffers to keep the input
+    fn from_slice<'a, T: Deserialize<'a>>(s: &'a mut [u8]) -> crate::Result<T> {
+        let mut buffers = Buffers::default().with_raw_values(true);
+        crate::serde::from_slice_with_buffers(s, &mut buffers)
+    }
+    fn from_bytes<'a, T: Deserialize<'a>>(s: &'a [u8]) -> crate::Result<T> {
+        let mut buffers = Buffers::default().with_raw_values(true);
+        crate::serde::from_bytes_with_buffers(s, &mut buffers)
+    }
+
+    #[derive(Deserialize, Serialize, Debug)]
+    struct Envelope {
+        kind: String,
+        payload: Box<RawValue>,
+        note: Option<Box<RawValue>>,
+        id: u64,
+    }
+
+    #[derive(Deserialize)]
+    struct Borrowed<'input> {
+        #[serde(borrow)]
+        payload: &'input RawValue,
+        #[serde(borrow)]
+        note: Option<&'input RawValue>,
+    }
+
+    let payload = r#"{ "text": "café \"q\"", "list": [1, [], {}] , "n": -1.50e3 }"#;
+    let input = format!(r#"{{"kind":"a\tb", "payload" : {payload} , "note":"x\ny"  , "id": 7}}"#);
+    let envelope: Envelope = from_slice(&mut input.as_bytes().to_vec()).expect("valid");
+    assert_eq!(envelope.kind, "a\tb");
+    assert_eq!(envelope.payload.get(), payload);
+    assert_eq!(envelope.note.as_ref().map(|n| n.get()), Some(r#""x\ny""#));
+    assert_eq!(envelope.id, 7);
+    let expected = format!(r#"{{"kind":"a\tb","payload":{payload},"note":"x\ny","id":7}}"#);
+    assert_eq!(crate::to_string(&envelope).expect("serializable"), expected);
+    assert_eq!(
+        crate::to_string_pretty(&envelope.note).expect("serializable"),
+        r#""x\ny""#
+    );
+
+    let raws: Vec<Box<RawValue>> =
+        from_slice(&mut br#" [1 , "a\nb",{"x":[]}, true,null,[ ] ] "#.to_vec()).expect("valid");
+    let raws: Vec<&str> = raws.iter().map(|r| r.get()).collect();
+    assert_eq!(
+        raws,
+        [r"1", r#""a\nb""#, r#"{"x":[]}"#, "true", "null", "[ ]"]
+    );
+    let raw: Box<RawValue> = from_slice(&mut b" \"a\\u0041\" ".to_vec()).expect("valid");
+    assert_eq!(raw.get(), r#""a\u0041""#);
+
+    // reused buffers only keep the input when asked to, otherwise the value
+    // is encoded from the tape
+    let mut buffers = Buffers::default();
+    let mut reencoded = input.as_bytes().to_vec();
+    let envelope: Envelope =
+        crate::serde::from_slice_with_buffers(&mut reencoded, &mut buffers).expect("valid");
+    assert_eq!(
+        envelope.payload.get(),
+        r#"{"text":"café \"q\"","list":[1,[],{}],"n":-1500.0}"#
+    );
+    let mut buffers = Buffers::default().with_raw_values(true);
+    for _ in 0..2 {
+        let envelope: Envelope =
+            crate::serde::from_slice_with_buffers(&mut input.as_bytes().to_vec(), &mut buffers)
+                .expect("valid");
+        assert_eq!(envelope.payload.get(), payload);
+    }
+
+    // the value (de)serializers encode and parse raw values
+    let owned = crate::serde::to_owned_value(&envelope).expect("valid");
+    assert_eq!(owned["payload"]["n"], -1500.0);
+    let borrowed: crate::BorrowedValue = crate::serde::to_borrowed_value(&envelope).expect("valid");
+    assert_eq!(borrowed, owned);
+    let from_value: Envelope = crate::serde::from_owned_value(owned).expect("valid");
+    assert_eq!(
+        from_value.payload.get(),
+        r#"{"text":"café \"q\"","list":[1,[],{}],"n":-1500.0}"#
+    );
+
+    // borrowed raw values need the text to be unchanged in the input
+    let mut json = br#"{"payload": [1, {"a": "b"}] , "note": null}"#.to_vec();
+    let borrowed: Borrowed = from_slice(&mut json).expect("valid");
+    assert_eq!(borrowed.payload.get(), r#"[1, {"a": "b"}]"#);
+    assert!(borrowed.note.is_none());
+    assert!(from_slice::<Borrowed>(&mut input.as_bytes().to_vec()).is_err());
+    let borrowed: Borrowed = from_bytes(input.as_bytes()).expect("valid");
+    assert_eq!(borrowed.payload.get(), payload);
+    assert_eq!(borrowed.note.map(RawValue::get), Some(r#""x\ny""#));
+    assert_eq!(
+        crate::to_string(borrowed.payload).expect("serializable"),
+        payload
+    );
+    // the value deserializers have no text to borrow
+    let value = crate::serde::to_borrowed_value(&envelope).expect("valid");
+    assert!(crate::serde::from_refborrowed_value::<Borrowed>(&value).is_err());
+
+    assert!(RawValue::from_string("[1,".to_string()).is_err());
+    let raw = RawValue::from_string(" [1, 2]".to_string()).expect("valid");
+    assert_eq!(raw.clone().get(), " [1, 2]");
+    assert_eq!(
+        crate::serde::to_raw_value(&[1, 2]).expect("valid").get(),
+        "[1,2]"
+    );
+}
+
+#[test]
+fn serialize_tape_and_lazy_values() {
+    use crate::value::{lazy, tape};
+
+    #[derive(Serialize)]
+    struct Response<'tape, 'input> {
+        data: tape::Value<'tape, 'input>,
+        tags: Option<tape::Array<'tape, 'input>>,
+        meta: Option<tape::Object<'tape, 'input>>,
+        lazy: lazy::Value<'tape, 'tape, 'input>,
+    }
+
+    let input = r#"{"type":"user","data":{"name":"a\"b","age":42,"score":-1.5,"ok":true,"none":null,"tags":["x",[],{}]},"meta":{}}"#;
+    let mut bytes = input.as_bytes().to_vec();
+    let tape = crate::to_tape(&mut bytes).expect("valid");
+    let value = tape.as_value();
+    let data = value.get("data").expect("data");
+    let response = Response {
+        data,
+        tags: data.get("tags").and_then(|t| t.as_array()),
+        meta: value.get("meta").and_then(|m| m.as_object()),
+        lazy: lazy::Value::Tape(data),
+    };
+    let data_json =
+        r#"{"name":"a\"b","age":42,"score":-1.5,"ok":true,"none":null,"tags":["x",[],{}]}"#;
+    let expected =
+        format!(r#"{{"data":{data_json},"tags":["x",[],{{}}],"meta":{{}},"lazy":{data_json}}}"#);
+    assert_eq!(crate::to_string(&response).expect("serializable"), expected);
+    assert_eq!(
+        serde_json::to_string(&response).expect("serializable"),
+        expected
+    );
+    let owned = crate::serde::to_owned_value(&response).expect("valid");
+    assert_eq!(
+        owned["data"],
+        crate::serde::to_owned_value(data).expect("valid")
+    );
+
+    // once upgraded the lazy value serializes the borrowed value
+    let mut upgraded = lazy::Value::Tape(data);
+    upgraded.insert("new", 1).expect("object");
+    assert!(matches!(upgraded, lazy::Value::Value(_)));
+    assert_eq!(
+        crate::to_string(&upgraded).expect("serializable"),
+        upgraded.encode()
+    );
+}
+
+#[test]
+fn deserialize_tape_and_lazy_values() {
+    use crate::value::lazy;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct User<'a> {
+        name: &'a str,
+        age: u8,
+        tags: Vec<&'a str>,
+        nick: Option<String>,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    enum Kind {
+        User,
+        Group(u32),
+    }
+
+    let input = r#"{"type":"User","data":{"name":"snot","age":42,"tags":["a","b"],"nick":null},"group":{"Group":7},"list":[1,2,3]}"#;
+    let mut bytes = input.as_bytes().to_vec();
+    let tape = crate::to_tape(&mut bytes).expect("valid");
+    let value = tape.as_value();
+    let expected = User {
+        name: "snot",
+        age: 42,
+        tags: vec!["a", "b"],
+        nick: None,
+    };
+
+    let kind: Kind = value
+        .get("type")
+        .expect("type")
+        .deserialize()
+        .expect("kind");
+    assert_eq!(kind, Kind::User);
+    let data = value.get("data").expect("data");
+    let user: User = data.deserialize().expect("user");
+    assert_eq!(user, expected);
+    let group: Kind = Kind::deserialize(value.get("group").expect("group")).expect("group");
+    assert_eq!(group, Kind::Group(7));
+    // the subtree ends with the value, the nodes after it are not visited
+    let list: Vec<u8> = value
+        .get("list")
+        .expect("list")
+        .deserialize()
+        .expect("list");
+    assert_eq!(list, vec![1, 2, 3]);
+    assert!(data.deserialize::<Vec<u8>>().is_err());
+
+    let lazy_value = lazy::Value::Tape(data);
+    assert_eq!(User::deserialize(lazy_value).expect("user"), expected);
+    let mut upgraded = lazy::Value::Tape(data);
+    upgraded.insert("age", 43).expect("object");
+    assert!(upgraded.is_value());
+    assert_eq!(
+        User::deserialize(upgraded.clone()).expect("user"),
+        User {
+            age: 43,
+            ..expected
+        }
+    );
+}
+
+#[test]
+fn deserialize_tape_values_with_policy_and_raw_values() {
+    use crate::{DuplicateKeyPolicy, serde::RawValue};
+    use std::collections::BTreeMap;
+
+    #[derive(Deserialize)]
+    struct Envelope {
+        kind: String,
+        payload: Box<RawValue>,
+    }
+
+    let mut bytes = br#"{"a": 1, "a": 2, "b": [3, {"c": "\n"}]}"#.to_vec();
+    let tape = crate::to_tape(&mut bytes).expect("valid");
+    let value = tape.as_value();
+
+    // directly every entry is visited, so the last one ends up in the map
+    let all: BTreeMap<String, crate::OwnedValue> = value.deserialize().expect("map");
+    assert_eq!(all["a"], 2);
+    let mut de = value.deserializer_with(DuplicateKeyPolicy::FirstWins);
+    let first = BTreeMap::<String, crate::OwnedValue>::deserialize(&mut de).expect("map");
+    assert_eq!(first["a"], 1);
+
+    // raw values are encoded from the tape
+    let raw: Box<RawValue> = value.get("b").expect("b").deserialize().expect("raw");
+    assert_eq!(raw.get(), r#"[3,{"c":"\n"}]"#);
+    let mut bytes = br#"{"kind": "k", "payload": {"x": [1, 2]}}"#.to_vec();
+    let tape = crate::to_tape(&mut bytes).expect("valid");
+    let envelope: Envelope = tape.as_value().deserialize().expect("envelope");
+    assert_eq!(envelope.kind, "k");
+    assert_eq!(envelope.payload.get(), r#"{"x":[1,2]}"#);
+    assert!(tape.as_value().deserialize::<&RawValue>().is_err());
+}
@@ -0,0 +1,408 @@
+use crate::safer_unchecked::GetSaferUnchecked;
+use crate::stage1::locate_error;
+use crate::stage2::{
+    is_valid_false_atom, is_valid_null_atom, is_valid_true_atom, non_finite_atom, raw_number_len,
+    read_scalar, valid_atom,
+};
+use crate::stringparse::{ESCAPE_MAP, get_unicode_codepoint};
+use crate::to_offset;
+use crate::{
+    Buffers, Deserializer, Error, ErrorType, InternalError, ParserOptions, Result, StructuralIndex,
+};
+
+impl Deserializer<'_> {
+    /// Validates `input` using `buffer`, the input is only read and copied
+    /// into the buffers. Strings are checked but not unescaped, so the string
+    /// buffer is not used.
+    pub(crate) fn validate(input: &[u8], buffer: &mut Buffers) -> Result<()> {
+        if input.len() > buffer.options.input_len {
+            return Err(Self::error(ErrorType::InputTooLarge));
+        }
+
+        unsafe { buffer.find_structural_bits(input, buffer.relaxed) }
+            .map_err(|e| locate_error(input, e, buffer.relaxed))?;
+        buffer.copy_input(input);
+
+        if buffer.structural_indexes.is_empty() {
+            return Err(Self::error(ErrorType::Eof));
+        }
+
+        macro_rules! validate_structure {
+            ($relaxed:literal, $limited:literal, $non_finite:literal, $arbitrary_precision:literal) => {
+                Self::validate_structure::<$relaxed, $limited, $non_finite, $arbitrary_precision>(
+                    &buffer.input_buffer,
+                    &buffer.structural_indexes,
+                    &mut buffer.validate_stack,
+                    &buffer.options,
+                )
+            };
+        }
+        let res = match (
+            buffer.relaxed,
+            buffer.options.limits_stage2(),
+            buffer.non_finite,
+            buffer.arbitrary_precision,
+        ) {
+            (false, false, false, false) => validate_structure!(false, false, false, false),
+            (false, false, false, true) => validate_structure!(false, false, false, true),
+            (false, false, true, false) => validate_structure!(false, false, true, false),
+            (false, false, true, true) => validate_structure!(false, false, true, true),
+            (false, true, false, false) => validate_structure!(false, true, false, false),
+            (false, true, false, true) => validate_structure!(false, true, false, true),
+            (false, true, true, false) => validate_structure!(false, true, true, false),
+            (false, true, true, true) => validate_structure!(false, true, true, true),
+            (true, false, false, false) => validate_structure!(true, false, false, false),
+            (true, false, false, true) => validate_structure!(true, false, false, true),
+            (true, false, true, false) => validate_structure!(true, false, true, false),
+            (true, false, true, true) => validate_structure!(true, false, true, true),
+            (true, true, false, false) => validate_structure!(true, true, false, false),
+            (true, true, false, true) => validate_structure!(true, true, false, true),
+            (true, true, true, false) => validate_structure!(true, true, true, false),
+            (true, true, true, true) => validate_structure!(true, true, true, true),
+        };
+        res.map_err(|e| e.located_in(&buffer.input_buffer, &buffer.structural_indexes))
+    }
+
+    /// Stage 2 without a tape: walks the structural indexes with the same
+    /// state machine as `build_tape` and reports the same errors, but neither
+    /// writes nodes nor unescapes strings. `stack` holds the closing character
+    /// and the number of members of every open container. The limits of
+    /// `options` other than the depth are only checked when `LIMITED` is set,
+    /// `NON_FINITE` and `ARBITRARY_PRECISION` are the same as for `build_tape`.
+    #[allow(clippy::too_many_lines)]
+    fn validate_structure<
+        const RELAXED: bool,
+        const LIMITED: bool,
+        const NON_FINITE: bool,
+        const ARBITRARY_PRECISION: bool,
+    >(
+        input: &[u8],
+        structural_indexes: &[StructuralIndex],
+        stack: &mut Vec<(u8, usize)>,
+        options: &ParserOptions,
+    ) -> Result<()> {
+        stack.clear();
+
+        let mut i = 0;
+        let mut idx: usize = 0;
+        let mut c: u8 = 0;
+        // the nodes the tape would have
+        let mut nodes: usize = 0;
+
+        macro_rules! fail {
+            () => {
+                return Err(Error::new_c(
+                    idx,
+                    c as char,
+                    ErrorType::InternalError(InternalError::TapeError),
+                ))
+            };
+            ($t:expr_2021) => {
+                return Err(Error::new_c(idx, c as char, $t))
+            };
+        }
+        macro_rules! update_char {
+            () => {
+                if let Some(next) = structural_indexes.get(i) {
+                    idx = to_offset(*next);
+                    i += 1;
+                    c = unsafe { *input.get_kinda_unchecked(idx) };
+                } else {
+                    fail!(ErrorType::Syntax);
+                }
+            };
+        }
+        macro_rules! count_node {
+            () => {
+                nodes += 1;
+                if LIMITED && nodes > options.nodes {
+                    fail!(ErrorType::TooManyNodes);
+                }
+            };
+        }
+        macro_rules! check_str {
+            () => {
+                let len = Self::validate_str(input, idx)?;
+                if LIMITED && len > options.string_len {
+                    fail!(ErrorType::StringTooLong);
+                }
+                count_node!();
+            };
+        }
+        // a member of the innermost container starts at `idx`
+        macro_rules! add_member {
+            () => {
+                if let Some((_, members)) = stack.last_mut() {
+                    *members += 1;
+                    if LIMITED && *members > options.members {
+                        fail!(ErrorType::TooManyMembers);
+                    }
+                }
+            };
+        }
+        // a key and its colon, leaves `c` on the first character of the value
+        macro_rules! object_key {
+            () => {
+                add_member!();
+                check_str!();
+                update_char!();
+                if c != b':' {
+                    fail!(ErrorType::ExpectedObjectColon);
+                }
+                update_char!();
+            };
+        }
+
+        update_char!();
+        // the root is a scalar and is followed by nothing
+        let scalar_root = !matches!(c, b'{' | b'[');
+
+        'value: loop {
+            match c {
+                b'{' | b'[' => {
+                    if stack.len() >= options.depth {
+                        fail!(ErrorType::DepthLimitExceeded);
+                    }
+                    count_node!();
+                    let close = if c == b'{' { b'}' } else { b']' };
+                    stack.push((close, 0));
+                    update_char!();
+                    if c == close {
+                        stack.pop();
+                    } else if close == b']' {
+                        add_member!();
+                        continue 'value;
+                    } else if c == b'"' {
+                        object_key!();
+                        continue 'value;
+                    } else {
+                        fail!(ErrorType::ExpectedObjectContent);
+                    }
+                }
+                b'"' => {
+                    check_str!();
+                }
+                b't' => {
+                    if !valid_atom::<RELAXED>(
+                        unsafe { input.get_kinda_unchecked(idx..) },
+                        is_valid_true_atom,
+                    ) {
+                        fail!(ErrorType::ExpectedTrue);
+                    }
+                    count_node!();
+                }
+                b'f' => {
+                    if !valid_atom::<RELAXED>(
+                        unsafe { input.get_kinda_unchecked(idx..) },
+                        is_valid_false_atom,
+                    ) {
+                        fail!(ErrorType::ExpectedFalse);
+                    }
+                    count_node!();
+                }
+                b'n' => {
+                    if !valid_atom::<RELAXED>(
+                        unsafe { input.get_kinda_unchecked(idx..) },
+                        is_valid_null_atom,
+                    ) {
+                        fail!(ErrorType::ExpectedNull);
+                    }
+                    count_node!();
+                }
+                // numbers of any size and precision are kept as their text
+                b'-' | b'0'..=b'9'
+                    if ARBITRARY_PRECISION
+                        && read_scalar::<RELAXED, _>(&input[idx..], raw_number_len).is_some() =>
+                {
+                    count_node!();
+                }
+                b'-' => {
+                    Self::parse_scalar_number::<RELAXED, NON_FINITE>(idx, input, true)?;
+                    count_node!();
+                }
+                b'0'..=b'9' => {
+                    Self::parse_scalar_number::<RELAXED, NON_FINITE>(idx, input, false)?;
+                    count_node!();
+                }
+                _ => {
+                    if !(NON_FINITE
+                        && read_scalar::<RELAXED, _>(&input[idx..], non_finite_atom).is_some())
+                    {
+                        fail!();
+                    }
+                    count_node!();
+                }
+            }
+
+            // a value is complete, continue with the container around it
+            loop {
+                let Some(&(close, _)) = stack.last() else {
+                    if i == structural_indexes.len() {
+                        return Ok(());
+                    } else if scalar_root {
+                        // point at the value behind the root
+                        idx = to_offset(structural_indexes[i]);
+                        c = input[idx];
+                        fail!(ErrorType::TrailingData);
+                    }
+                    fail!();
+                };
+                update_char!();
+                match c {
+                    b',' => {
+                        update_char!();
+                        if RELAXED && c == close {
+                            // a trailing comma
+                            stack.pop();
+                        } else if close == b']' {
+                            add_member!();
+                            continue 'value;
+                        } else if c == b'"' {
+                            object_key!();
+                            continue 'value;
+                        } else {
+                            fail!(ErrorType::ExpectedObjectKey);
+                        }
+                    }
+                    _ if c == close => {
+                        stack.pop();
+                    }
+                    _ if close == b'}' => {
+                        fail!(ErrorType::ExpectedObjectContent);
+                    }
+                    _ => {
+                        fail!(ErrorType::ExpectedArrayContent);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Checks the escapes of the string starting at `idx` and returns its
+    /// unescaped length, stage 1 already made sure it is terminated, valid
+    /// UTF-8 and free of control characters.
+    fn validate_str(input: &[u8], idx: usize) -> Result<usize> {
+        use ErrorType::{InvalidEscape, InvalidUnicodeCodepoint};
+        // skip leading `"`
+        let src = unsafe { input.get_kinda_unchecked(idx + 1..) };
+        let mut i = 0;
+        // escapes are shorter than what they stand for
+        let mut shrunk = 0;
+        loop {
+            match unsafe { *src.get_kinda_unchecked(i) } {
+                b'"' => return Ok(i - shrunk),
+                b'\\' => {
+                    let escape_char = unsafe { *src.get_kinda_unchecked(i + 1) };
+                    if escape_char == b'u' {
+                        match get_unicode_codepoint(unsafe { src.get_kinda_unchecked(i..) }) {
+                            Ok((cp, offset)) if cp <= 0x0010_FFFF => {
+                                i += offset;
+                                shrunk += offset - char::from_u32(cp).map_or(3, char::len_utf8);
+                            }
+                            _ => {
+                                return Err(Self::error_c(
+                                    idx + 1 + i,
+                                    'u',
+                                    InvalidUnicodeCodepoint,
+                                ));
+                            }
+                        }
+                    } else if ESCAPE_MAP[escape_char as usize] == 0 {
+                        return Err(Self::error_c(
+                            idx + 1 + i,
+                            escape_char as char,
+                            InvalidEscape,
+                        ));
+                    } else {
+                        i += 2;
+                        shrunk += 1;
+                    }
+                }
+                _ => i += 1,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Buffers, Error, ErrorType, validate, validate_with_buffers};
+
+    fn assert_same_as_tape(input: &[u8]) {
+        // the string parsers differ in the offsets of escape errors
+        let res = validate(input);
+        let tape = crate::to_tape(&mut input.to_vec()).map(drop);
+        assert_eq!(
+            res.as_ref().map_err(Error::error),
+            tape.as_ref().map_err(Error::error),
+            "{}",
+            String::from_utf8_lossy(input)
+        );
+    }
+
+    #[test]
+    fn matches_tape() {
+        for input in [
+            r#"{"a": [1, -2.5, "\\\"é😀", true, false, null], "b": {}}"#.as_bytes(),
+            b"[]",
+            b" 1 ",
+            br#""snot""#,
+            b"[1, 2] 3",
+            b"1 2",
+            b"[1,]",
+            b"[1 2]",
+            b"{1: 2}",
+            br#"{"a" 1}"#,
+            br#"{"a": 1,}"#,
+            br#"{"a": 1]"#,
+            b"[1}",
+            b"[tru]",
+            b"[fals]",
+            b"[nul]",
+            b"[-]",
+            br#"["\x"]"#,
+            br#"["\u12"]"#,
+            br#"["\udc00"]"#,
+            b"[[[",
+            b"]",
+            b"",
+            b"  ",
+        ] {
+            assert_same_as_tape(input);
+        }
+    }
+
+    #[test]
+    fn matches_tape_on_data() {
+        for dir in ["data", "data/pass", "data/fail"] {
+            for entry in std::fs::read_dir(dir).expect("data dir") {
+                let path = entry.expect("dir entry").path();
+                if path.extension().is_some_and(|e| e == "json") {
+                    assert_same_as_tape(&std::fs::read(&path).expect("readable"));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn depth_limit() {
+        let mut buffers = Buffers::with_max_depth(0, 2);
+        assert!(validate_with_buffers(b"[[1]]", &mut buffers).is_ok());
+        let err = validate_with_buffers(b"[[[1]]]", &mut buffers).expect_err("too deep");
+        assert_eq!(err.error(), &ErrorType::DepthLimitExceeded);
+        // the buffers can be reused after an error
+        assert!(validate_with_buffers(br#"{"a": [2]}"#, &mut buffers).is_ok());
+    }
+
+    #[test]
+    fn relaxed() {
+        let mut buffers = Buffers::default().with_relaxed(true);
+        let input = br#"{"a": [1, 2,], /* "b": */ "c": {"d": [],}, // e
+        }"#;
+        assert!(validate_with_buffers(input, &mut buffers).is_ok());
+        assert!(validate(input).is_err());
+        for invalid in [&b"[,]"[..], b"[1,,]", b"{,}", b"[1] /*", b"[1 // ]"] {
+            assert!(validate_with_buffers(invalid, &mut buffers).is_err());
+        }
+    }
+}
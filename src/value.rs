@@ -58,9 +58,12 @@ pub mod owned;
 pub mod tape;
 
 pub mod lazy;
+mod pointer;
 
 pub use self::borrowed::{
     Value as BorrowedValue, to_value as to_borrowed_value,
+    to_value_from_bytes as to_borrowed_value_from_bytes,
+    to_value_from_bytes_with_buffers as to_borrowed_value_from_bytes_with_buffers,
     to_value_with_buffers as to_borrowed_value_with_buffers,
 };
 pub use self::owned::{
@@ -126,6 +129,77 @@ where
     }
 }
 
+/// What the text of an arbitrary precision number converts to, worked out
+/// once when the number is read so neither the type of the number nor its
+/// value need to guess.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NumberKind {
+    /// an integer that fits into an `i64`
+    I64,
+    /// a positive integer that only fits into an `u64`
+    U64,
+    /// an integer that only fits into an `i128`
+    #[cfg(feature = "128bit")]
+    I128,
+    /// a positive integer that only fits into an `u128`
+    #[cfg(feature = "128bit")]
+    U128,
+    /// anything else, it is read as the closest `f64`
+    F64,
+}
+
+impl NumberKind {
+    /// Works out the kind of `number`, which has to follow the JSON number
+    /// grammar: an integer if it fits one, otherwise a float.
+    #[must_use]
+    pub fn of(number: &str) -> Self {
+        if number.parse::<i64>().is_ok() {
+            Self::I64
+        } else if number.parse::<u64>().is_ok() {
+            Self::U64
+        } else {
+            #[cfg(feature = "128bit")]
+            if number.parse::<i128>().is_ok() {
+                return Self::I128;
+            } else if number.parse::<u128>().is_ok() {
+                return Self::U128;
+            }
+            Self::F64
+        }
+    }
+
+    /// The type a number of this kind has
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    #[must_use]
+    pub const fn value_type(self) -> ValueType {
+        match self {
+            Self::I64 => ValueType::I64,
+            Self::U64 => ValueType::U64,
+            #[cfg(feature = "128bit")]
+            Self::I128 => ValueType::I128,
+            #[cfg(feature = "128bit")]
+            Self::U128 => ValueType::U128,
+            Self::F64 => ValueType::F64,
+        }
+    }
+}
+
+/// Converts the text of an arbitrary precision number of the given `kind`
+/// into a static value.
+pub(crate) fn number_to_static(number: &str, kind: NumberKind) -> StaticNode {
+    let n = match kind {
+        NumberKind::I64 => number.parse().ok().map(StaticNode::I64),
+        NumberKind::U64 => number.parse().ok().map(StaticNode::U64),
+        #[cfg(feature = "128bit")]
+        NumberKind::I128 => number.parse().ok().map(StaticNode::I128),
+        #[cfg(feature = "128bit")]
+        NumberKind::U128 => number.parse().ok().map(StaticNode::U128),
+        NumberKind::F64 => None,
+    };
+    // the grammar is checked while parsing so the float can't fail
+    n.unwrap_or_else(|| StaticNode::from(number.parse::<f64>().unwrap_or(f64::NAN)))
+}
+
 struct ValueDeserializer<'de, Value, Key>
 where
     Value: ValueBuilder<'de> + From<Vec<Value>> + From<HashMap<Key, Value, ObjectHasher>> + 'de,
@@ -156,6 +230,7 @@ where
         match unsafe { self.de.next_() } {
             Node::Static(s) => Value::from(s),
             Node::String(s) => Value::from(s),
+            Node::Number(n, kind) => Value::from(number_to_static(n, kind)),
             Node::Array { len, count: _ } => self.parse_array(len),
             Node::Object { len, count: _ } => self.parse_map(len),
         }
@@ -186,12 +261,11 @@ where
         // element so we eat this
         for _ in 0..len {
             if let Node::String(key) = unsafe { self.de.next_() } {
-                #[cfg(not(feature = "value-no-dup-keys"))]
-                unsafe {
-                    res.insert_nocheck(key.into(), self.parse());
-                };
-                #[cfg(feature = "value-no-dup-keys")]
-                res.insert(key.into(), self.parse());
+                let value = self.parse();
+                self.de
+                    .duplicate_keys
+                    .unwrap_or_default()
+                    .insert(&mut res, key.into(), value);
             } else {
                 unreachable!("parse_map: key needs to be a string");
             }
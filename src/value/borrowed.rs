@@ -24,9 +24,9 @@ mod cmp;
 mod from;
 mod serialize;
 
-use super::ObjectHasher;
+use super::{NumberKind, ObjectHasher};
 use crate::{Buffers, prelude::*};
-use crate::{Deserializer, Node, Result};
+use crate::{Deserializer, DuplicateKeyPolicy, Node, Result};
 use crate::{cow::Cow, safer_unchecked::GetSaferUnchecked as _};
 use halfbrown::HashMap;
 use std::fmt;
@@ -74,6 +74,48 @@ pub fn to_value_with_buffers<'value>(
     }
 }
 
+/// Parses a slice of bytes into a Value dom without modifying it.
+///
+/// The input is copied before parsing, strings that contain no escape
+/// sequences borrow from `s` while strings that had to be unescaped are
+/// owned.
+///
+/// # Errors
+///
+/// Will return `Err` if `s` is invalid JSON.
+pub fn to_value_from_bytes(s: &[u8]) -> Result<Value<'_>> {
+    let mut buffers = Buffers::new(s.len());
+    to_value_from_bytes_with_buffers(s, &mut buffers)
+}
+
+/// Parses a slice of bytes into a Value dom without modifying it.
+///
+/// The input is copied before parsing, strings that contain no escape
+/// sequences borrow from `s` while strings that had to be unescaped are
+/// owned.
+///
+/// Passes in reusable buffers to reduce allocations, this includes the
+/// copy of the input.
+///
+/// # Errors
+///
+/// Will return `Err` if `s` is invalid JSON.
+pub fn to_value_from_bytes_with_buffers<'value>(
+    s: &'value [u8],
+    buffers: &mut Buffers,
+) -> Result<Value<'value>> {
+    let mut scratch = std::mem::take(&mut buffers.scratch);
+    scratch.clear();
+    scratch.extend_from_slice(s);
+    let base = scratch.as_ptr() as usize;
+    let res = Deserializer::from_slice_with_buffers(&mut scratch, buffers).map(|de| {
+        BorrowBytesDeserializer::new(s, base, &de.tape, de.duplicate_keys.unwrap_or_default())
+            .parse()
+    });
+    buffers.scratch = scratch;
+    res
+}
+
 /// Borrowed JSON-DOM Value, consider using the `ValueTrait`
 /// to access its content
 #[derive(Debug, Clone)]
@@ -83,6 +125,9 @@ pub enum Value<'value> {
     Static(StaticNode),
     /// string type
     String(Cow<'value, str>),
+    /// a number kept as its source text and what it converts to, see
+    /// [`Buffers::with_arbitrary_precision`]
+    Number(Cow<'value, str>, NumberKind),
     /// array type
     Array(Box<Vec<Value<'value>>>),
     /// object type
@@ -93,6 +138,7 @@ impl<'value> Value<'value> {
     fn as_static(&self) -> Option<StaticNode> {
         match self {
             Self::Static(s) => Some(*s),
+            Self::Number(n, kind) => Some(super::number_to_static(n, *kind)),
             _ => None,
         }
     }
@@ -115,6 +161,12 @@ impl<'value> Value<'value> {
                     s.into_owned(),
                 )))
             },
+            Self::Number(n, kind) => unsafe {
+                std::mem::transmute::<Value<'value>, Value<'static>>(Self::Number(
+                    Cow::from(n.into_owned()),
+                    kind,
+                ))
+            },
             // For an array we turn every value into a static
             Self::Array(arr) => arr.into_iter().map(Value::into_static).collect(),
             // For an object, we turn all keys into owned Cows and all values into 'static Values
@@ -145,6 +197,12 @@ impl<'value> Value<'value> {
                     s.to_string(),
                 )))
             },
+            Self::Number(n, kind) => unsafe {
+                std::mem::transmute::<Value<'value>, Value<'static>>(Self::Number(
+                    Cow::from(n.to_string()),
+                    *kind,
+                ))
+            },
             // For an array we turn every value into a static
             Self::Array(arr) => arr.iter().cloned().map(Value::into_static).collect(),
             // For an object, we turn all keys into owned Cows and all values into 'static Values
@@ -157,6 +215,117 @@ impl<'value> Value<'value> {
             Value::Static(s) => Value::Static(*s),
         }
     }
+
+    /// Looks up a value by a JSON pointer
+    /// ([RFC 6901](https://www.rfc-editor.org/rfc/rfc6901)), returns `None`
+    /// if the pointer is malformed or there is no value at it.
+    ///
+    /// ```rust
+    /// use simd_json::{BorrowedValue, json_typed};
+    /// let v: BorrowedValue = json_typed!(borrowed, {"a": {"b": ["x", "y"]}, "m/n": 1});
+    /// assert_eq!(v.pointer("/a/b/1"), Some(&BorrowedValue::from("y")));
+    /// assert_eq!(v.pointer("/m~1n"), Some(&BorrowedValue::from(1)));
+    /// assert_eq!(v.pointer(""), Some(&v));
+    /// assert_eq!(v.pointer("/a/c"), None);
+    /// ```
+    #[must_use]
+    pub fn pointer(&self, pointer: &str) -> Option<&Self> {
+        super::pointer::get(self, pointer)
+    }
+
+    /// Looks up a value by a JSON pointer and returns a mutable reference to
+    /// it, see [`pointer`](Self::pointer)
+    #[must_use]
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut Self> {
+        super::pointer::get_mut(self, pointer)
+    }
+
+    /// Inserts a value at a JSON pointer, the array or object it points into
+    /// has to exist. Like the `add` operation of JSON Patch a key that is
+    /// already present gets replaced, array elements are shifted to make
+    /// room and `-` as the last token appends to an array. The empty pointer
+    /// replaces the value itself.
+    ///
+    /// Returns the value that got replaced, if any.
+    ///
+    /// ```rust
+    /// use simd_json::{BorrowedValue, json_typed};
+    /// let mut v: BorrowedValue = json_typed!(borrowed, {"a": [1, 3]});
+    /// v.pointer_insert("/a/1", 2)?;
+    /// v.pointer_insert("/a/-", 4)?;
+    /// assert_eq!(v.pointer_insert("/b", true)?, None);
+    /// assert_eq!(v, json_typed!(borrowed, {"a": [1, 2, 3, 4], "b": true}));
+    /// assert!(v.pointer_insert("/c/d", 5).is_err());
+    /// # Ok::<(), simd_json::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the pointer is malformed, doesn't point into an
+    /// array or object or the array index is out of bounds.
+    pub fn pointer_insert<V>(&mut self, pointer: &str, value: V) -> Result<Option<Self>>
+    where
+        V: Into<Self>,
+    {
+        super::pointer::insert(self, pointer, value.into())
+    }
+
+    /// Removes the value at a JSON pointer from the array or object holding
+    /// it and returns it, later array elements are shifted down
+    pub fn pointer_remove(&mut self, pointer: &str) -> Option<Self> {
+        super::pointer::remove(self, pointer)
+    }
+}
+
+impl super::pointer::Pointee for Value<'_> {
+    fn child(&self, token: &str) -> Option<&Self> {
+        match self {
+            Self::Array(a) => a.get(super::pointer::index(token)?),
+            Self::Object(o) => o.get(token),
+            _ => None,
+        }
+    }
+
+    fn child_mut(&mut self, token: &str) -> Option<&mut Self> {
+        match self {
+            Self::Array(a) => a.get_mut(super::pointer::index(token)?),
+            Self::Object(o) => o.get_mut(token),
+            _ => None,
+        }
+    }
+
+    fn insert_child(
+        &mut self,
+        token: std::borrow::Cow<'_, str>,
+        value: Self,
+    ) -> std::result::Result<Option<Self>, Self> {
+        match self {
+            Self::Array(a) if token == "-" => {
+                a.push(value);
+                Ok(None)
+            }
+            Self::Array(a) => match super::pointer::index(&token) {
+                Some(idx) if idx <= a.len() => {
+                    a.insert(idx, value);
+                    Ok(None)
+                }
+                _ => Err(value),
+            },
+            Self::Object(o) => Ok(o.insert(Cow::from(token.into_owned()), value)),
+            _ => Err(value),
+        }
+    }
+
+    fn remove_child(&mut self, token: &str) -> Option<Self> {
+        match self {
+            Self::Array(a) => {
+                let idx = super::pointer::index(token)?;
+                (idx < a.len()).then(|| a.remove(idx))
+            }
+            Self::Object(o) => o.remove(token),
+            _ => None,
+        }
+    }
 }
 
 impl<'value> ValueBuilder<'value> for Value<'value> {
@@ -222,6 +391,7 @@ impl TypedValue for Value<'_> {
         match self {
             Self::Static(s) => s.value_type(),
             Self::String(_) => ValueType::String,
+            Self::Number(_, kind) => kind.value_type(),
             Self::Array(_) => ValueType::Array,
             Self::Object(_) => ValueType::Object,
         }
@@ -339,7 +509,7 @@ impl fmt::Display for Value<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Self::Static(s) => write!(f, "{s}"),
-            Self::String(s) => write!(f, "{s}"),
+            Self::String(s) | Self::Number(s, _) => write!(f, "{s}"),
             Self::Array(a) => write!(f, "{a:?}"),
             Self::Object(o) => write!(f, "{o:?}"),
         }
@@ -383,7 +553,7 @@ impl Default for Value<'_> {
     }
 }
 
-pub(super) struct BorrowDeserializer<'de>(Deserializer<'de>);
+pub(crate) struct BorrowDeserializer<'de>(Deserializer<'de>);
 
 impl<'de> BorrowDeserializer<'de> {
     pub fn from_deserializer(de: Deserializer<'de>) -> Self {
@@ -395,6 +565,7 @@ impl<'de> BorrowDeserializer<'de> {
         match unsafe { self.0.next_() } {
             Node::Static(s) => Value::Static(s),
             Node::String(s) => Value::from(s),
+            Node::Number(n, kind) => Value::Number(Cow::from(n), kind),
             Node::Array { len, count: _ } => self.parse_array(len),
             Node::Object { len, count: _ } => self.parse_map(len),
         }
@@ -424,12 +595,11 @@ impl<'de> BorrowDeserializer<'de> {
         // element so we eat this
         for _ in 0..len {
             if let Node::String(key) = unsafe { self.0.next_() } {
-                #[cfg(not(feature = "value-no-dup-keys"))]
-                unsafe {
-                    res.insert_nocheck(key.into(), self.parse());
-                };
-                #[cfg(feature = "value-no-dup-keys")]
-                res.insert(key.into(), self.parse());
+                let value = self.parse();
+                self.0
+                    .duplicate_keys
+                    .unwrap_or_default()
+                    .insert(&mut res, key.into(), value);
             } else {
                 unreachable!("parse_map: key not a string");
             }
@@ -437,6 +607,89 @@ impl<'de> BorrowDeserializer<'de> {
         Value::from(res)
     }
 }
+/// The string or number `s` of a tape built over a copy of `input` that
+/// starts at `base`, borrowed from `input` if it is the same there. Strings
+/// are unescaped in place and numbers are never changed, so they start at the
+/// same offset in both.
+#[cfg_attr(not(feature = "no-inline"), inline)]
+pub(crate) fn reborrow<'input>(input: &'input [u8], base: usize, s: &str) -> Option<&'input str> {
+    let start = (s.as_ptr() as usize).checked_sub(base)?;
+    let raw = input.get(start..start + s.len())?;
+    // Safety: the input holds the same valid UTF-8 as `s` at this offset
+    (raw == s.as_bytes()).then(|| unsafe { std::str::from_utf8_unchecked(raw) })
+}
+
+/// Builds a value from a tape over a copy of `input`, re-borrowing strings
+/// from `input` when they were not changed by unescaping.
+struct BorrowBytesDeserializer<'tape, 'scratch, 'input> {
+    tape: &'tape [Node<'scratch>],
+    idx: usize,
+    input: &'input [u8],
+    base: usize,
+    duplicate_keys: DuplicateKeyPolicy,
+}
+
+impl<'tape, 'scratch, 'input> BorrowBytesDeserializer<'tape, 'scratch, 'input> {
+    /// `base` is the address of the copy of the input the tape points into
+    fn new(
+        input: &'input [u8],
+        base: usize,
+        tape: &'tape [Node<'scratch>],
+        duplicate_keys: DuplicateKeyPolicy,
+    ) -> Self {
+        Self {
+            tape,
+            idx: 0,
+            input,
+            base,
+            duplicate_keys,
+        }
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn next_(&mut self) -> Node<'scratch> {
+        let r = self.tape[self.idx];
+        self.idx += 1;
+        r
+    }
+
+    /// Borrows the string or number `s` from the input if it wasn't
+    /// changed by unescaping
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn string(&self, s: &str) -> Cow<'input, str> {
+        reborrow(self.input, self.base, s).map_or_else(|| Cow::from(s.to_owned()), Cow::from)
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn parse(&mut self) -> Value<'input> {
+        match self.next_() {
+            Node::Static(s) => Value::Static(s),
+            Node::String(s) => Value::String(self.string(s)),
+            Node::Number(n, kind) => Value::Number(self.string(n), kind),
+            Node::Array { len, count: _ } => {
+                let mut res = Vec::with_capacity(len);
+                for _ in 0..len {
+                    res.push(self.parse());
+                }
+                Value::Array(Box::new(res))
+            }
+            Node::Object { len, count: _ } => {
+                let mut res = Object::with_capacity_and_hasher(len, ObjectHasher::default());
+                for _ in 0..len {
+                    if let Node::String(key) = self.next_() {
+                        let key = self.string(key);
+                        let value = self.parse();
+                        self.duplicate_keys.insert(&mut res, key, value);
+                    } else {
+                        unreachable!("parse_map: key not a string");
+                    }
+                }
+                Value::from(res)
+            }
+        }
+    }
+}
+
 pub(super) struct BorrowSliceDeserializer<'tape, 'de> {
     tape: &'tape [Node<'de>],
     idx: usize,
@@ -457,6 +710,7 @@ impl<'tape, 'de> BorrowSliceDeserializer<'tape, 'de> {
         match unsafe { self.next_() } {
             Node::Static(s) => Value::Static(s),
             Node::String(s) => Value::from(s),
+            Node::Number(n, kind) => Value::Number(Cow::from(n), kind),
             Node::Array { len, count: _ } => self.parse_array(len),
             Node::Object { len, count: _ } => self.parse_map(len),
         }
@@ -486,12 +740,9 @@ impl<'tape, 'de> BorrowSliceDeserializer<'tape, 'de> {
         // element so we eat this
         for _ in 0..len {
             if let Node::String(key) = unsafe { self.next_() } {
-                #[cfg(not(feature = "value-no-dup-keys"))]
-                unsafe {
-                    res.insert_nocheck(key.into(), self.parse());
-                };
-                #[cfg(feature = "value-no-dup-keys")]
-                res.insert(key.into(), self.parse());
+                // a tape keeps every entry, it has no policy of its own
+                let value = self.parse();
+                DuplicateKeyPolicy::default().insert(&mut res, key.into(), value);
             } else {
                 unreachable!("parse_map: key needs to be a string");
             }
@@ -506,6 +757,30 @@ mod test {
     #![allow(clippy::cognitive_complexity)]
     use super::*;
 
+    #[test]
+    fn from_bytes() {
+        let input = r#"{"plain": ["abc", "", "é"], "a\"b": "c\u00e9\n", "n": [1, null]}"#;
+        let mut buffers = Buffers::default();
+        for _ in 0..2 {
+            let v = to_value_from_bytes_with_buffers(input.as_bytes(), &mut buffers)
+                .expect("valid json");
+            assert_eq!(
+                v,
+                json!({"plain": ["abc", "", "é"], "a\"b": "cé\n", "n": [1, null]})
+            );
+            let borrowed = |s: &str| input.as_bytes().as_ptr_range().contains(&s.as_ptr());
+            let plain = v.get_array("plain").expect("array");
+            assert!(borrowed(plain[0].as_str().expect("string")));
+            assert!(borrowed(plain[2].as_str().expect("string")));
+            assert!(!borrowed(v.get_str("a\"b").expect("string")));
+            let keys: Vec<_> = v.as_object().expect("object").keys().collect();
+            assert!(keys.iter().any(|k| k.as_ref() == "a\"b" && !borrowed(k)));
+            assert!(keys.iter().any(|k| k.as_ref() == "n" && borrowed(k)));
+        }
+        assert!(to_value_from_bytes(b"[1, 2").is_err());
+        assert_eq!(to_value_from_bytes(b"42"), Ok(Value::from(42)));
+    }
+
     #[test]
     fn object_access() {
         let mut v = Value::null();
@@ -1119,4 +1394,18 @@ mod test {
     // fn size() {
     //     assert_eq!(std::mem::size_of::<Value>(), 24);
     // }
+
+    #[test]
+    fn pointer() -> crate::Result<()> {
+        let mut input = br#"{"a": {"b~": [1, "x"]}}"#.to_vec();
+        let mut v = crate::to_borrowed_value(&mut input)?;
+        assert_eq!(v.pointer("/a/b~0/1"), Some(&Value::from("x")));
+        assert_eq!(v.pointer("/a/b~1/1"), None);
+        assert_eq!(v.pointer_insert("/a/c~1d", "y")?, None);
+        assert_eq!(v.pointer("/a/c~1d"), Some(&Value::from("y")));
+        *v.pointer_mut("/a/b~0/0").expect("element") = Value::from(2);
+        assert_eq!(v.pointer_remove("/a/b~0/0"), Some(Value::from(2)));
+        assert_eq!(v.encode(), r#"{"a":{"b~":["x"],"c/d":"y"}}"#);
+        Ok(())
+    }
 }
@@ -8,7 +8,9 @@ impl PartialEq for Value<'_> {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (Self::Static(s1), Self::Static(s2)) => s1 == s2,
-            (Self::String(v1), Self::String(v2)) => v1.eq(v2),
+            (Self::String(v1), Self::String(v2)) | (Self::Number(v1, _), Self::Number(v2, _)) => {
+                v1.eq(v2)
+            }
             (Self::Array(v1), Self::Array(v2)) => v1.eq(v2),
             (Self::Object(v1), Self::Object(v2)) => v1.eq(v2),
             _ => false,
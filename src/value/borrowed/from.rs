@@ -9,6 +9,7 @@ impl From<OwnedValue> for Value<'_> {
         match b {
             OwnedValue::Static(s) => Value::from(s),
             OwnedValue::String(s) => Value::from(s),
+            OwnedValue::Number(n, kind) => Value::Number(n.into(), kind),
             OwnedValue::Array(a) => a.into_iter().collect(),
             OwnedValue::Object(m) => m.into_iter().collect(),
         }
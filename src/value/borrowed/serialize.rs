@@ -5,6 +5,7 @@
 // https://github.com/maciejhirsz/json-rust/blob/master/src/codegen.rs
 
 use super::{Object, Value};
+use crate::NonFinitePolicy;
 use crate::prelude::*;
 use std::io;
 use std::io::Write;
@@ -18,14 +19,14 @@ impl Writable for Value<'_> {
     #[cfg_attr(not(feature = "no-inline"), inline)]
     fn encode(&self) -> String {
         let mut g = DumpGenerator::new();
-        let _r = g.write_json(self);
+        let _r = g.write_json(self, NonFinitePolicy::default());
         g.consume()
     }
 
     #[cfg_attr(not(feature = "no-inline"), inline)]
     fn encode_pp(&self) -> String {
         let mut g = PrettyGenerator::new(2);
-        let _r = g.write_json(self);
+        let _r = g.write_json(self, NonFinitePolicy::default());
         g.consume()
     }
 
@@ -35,7 +36,7 @@ impl Writable for Value<'_> {
         W: 'writer + Write,
     {
         let mut g = WriterGenerator::new(w);
-        g.write_json(self)
+        g.write_json(self, NonFinitePolicy::default())
     }
 
     #[cfg_attr(not(feature = "no-inline"), inline)]
@@ -44,7 +45,39 @@ impl Writable for Value<'_> {
         W: 'writer + Write,
     {
         let mut g = PrettyWriterGenerator::new(w, 2);
-        g.write_json(self)
+        g.write_json(self, NonFinitePolicy::default())
+    }
+}
+
+impl Value<'_> {
+    /// Writes the value to `w` like [`Writable::write`], with `non_finite`
+    /// deciding how `NaN` and infinite floats are written
+    /// # Errors
+    /// if the write fails or a non-finite float is rejected
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    pub fn write_with_non_finite<W>(&self, w: &mut W, non_finite: NonFinitePolicy) -> io::Result<()>
+    where
+        W: Write,
+    {
+        let mut g = WriterGenerator::new(w);
+        g.write_json(self, non_finite)
+    }
+
+    /// Writes the value to `w` like [`Writable::write_pp`], with `non_finite`
+    /// deciding how `NaN` and infinite floats are written
+    /// # Errors
+    /// if the write fails or a non-finite float is rejected
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    pub fn write_pp_with_non_finite<W>(
+        &self,
+        w: &mut W,
+        non_finite: NonFinitePolicy,
+    ) -> io::Result<()>
+    where
+        W: Write,
+    {
+        let mut g = PrettyWriterGenerator::new(w, 2);
+        g.write_json(self, non_finite)
     }
 }
 
@@ -52,7 +85,7 @@ trait Generator: BaseGenerator {
     type T: Write;
 
     #[cfg_attr(not(feature = "no-inline"), inline)]
-    fn write_object(&mut self, object: &Object) -> io::Result<()> {
+    fn write_object(&mut self, object: &Object, non_finite: NonFinitePolicy) -> io::Result<()> {
         if object.is_empty() {
             self.write(b"{}")
         } else {
@@ -68,14 +101,14 @@ trait Generator: BaseGenerator {
             stry!(self.new_line());
             stry!(self.write_simple_string(key));
             stry!(self.write_min(b": ", b':'));
-            stry!(self.write_json(value));
+            stry!(self.write_json(value, non_finite));
 
             for (key, value) in iter {
                 stry!(self.write(b","));
                 stry!(self.new_line());
                 stry!(self.write_simple_string(key));
                 stry!(self.write_min(b": ", b':'));
-                stry!(self.write_json(value));
+                stry!(self.write_json(value, non_finite));
             }
             self.dedent();
             stry!(self.new_line());
@@ -84,7 +117,7 @@ trait Generator: BaseGenerator {
     }
 
     #[cfg_attr(not(feature = "no-inline"), inline)]
-    fn write_json(&mut self, json: &Value) -> io::Result<()> {
+    fn write_json(&mut self, json: &Value, non_finite: NonFinitePolicy) -> io::Result<()> {
         match *json {
             Value::Static(StaticNode::Null) => self.write(b"null"),
             Value::Static(StaticNode::I64(number)) => self.write_int(number),
@@ -94,10 +127,11 @@ trait Generator: BaseGenerator {
             #[cfg(feature = "128bit")]
             Value::Static(StaticNode::U128(number)) => self.write_int(number),
             #[allow(clippy::useless_conversion)] // .into() required by ordered-float
-            Value::Static(StaticNode::F64(number)) => self.write_float(number.into()),
+            Value::Static(StaticNode::F64(number)) => non_finite.write_f64(self, number.into()),
             Value::Static(StaticNode::Bool(true)) => self.write(b"true"),
             Value::Static(StaticNode::Bool(false)) => self.write(b"false"),
             Value::String(ref string) => self.write_string(string),
+            Value::Number(ref number, _) => self.write(number.as_bytes()),
             Value::Array(ref array) => {
                 if array.is_empty() {
                     self.write(b"[]")
@@ -113,19 +147,19 @@ trait Generator: BaseGenerator {
                     self.indent();
 
                     stry!(self.new_line());
-                    stry!(self.write_json(item));
+                    stry!(self.write_json(item, non_finite));
 
                     for item in iter {
                         stry!(self.write(b","));
                         stry!(self.new_line());
-                        stry!(self.write_json(item));
+                        stry!(self.write_json(item, non_finite));
                     }
                     self.dedent();
                     stry!(self.new_line());
                     self.write(b"]")
                 }
             }
-            Value::Object(ref object) => self.write_object(object),
+            Value::Object(ref object) => self.write_object(object, non_finite),
         }
     }
 }
@@ -134,7 +168,7 @@ trait FastGenerator: BaseGenerator {
     type T: Write;
 
     #[cfg_attr(not(feature = "no-inline"), inline)]
-    fn write_object(&mut self, object: &Object) -> io::Result<()> {
+    fn write_object(&mut self, object: &Object, non_finite: NonFinitePolicy) -> io::Result<()> {
         if object.is_empty() {
             self.write(b"{}")
         } else {
@@ -148,20 +182,20 @@ trait FastGenerator: BaseGenerator {
             };
             stry!(self.write_simple_str_content(key));
             stry!(self.write(b"\":"));
-            stry!(self.write_json(value));
+            stry!(self.write_json(value, non_finite));
 
             for (key, value) in iter {
                 stry!(self.write(b",\""));
                 stry!(self.write_simple_str_content(key));
                 stry!(self.write(b"\":"));
-                stry!(self.write_json(value));
+                stry!(self.write_json(value, non_finite));
             }
             self.write(b"}")
         }
     }
 
     #[cfg_attr(not(feature = "no-inline"), inline)]
-    fn write_json(&mut self, json: &Value) -> io::Result<()> {
+    fn write_json(&mut self, json: &Value, non_finite: NonFinitePolicy) -> io::Result<()> {
         match *json {
             Value::Static(StaticNode::Null) => self.write(b"null"),
             Value::Static(StaticNode::I64(number)) => self.write_int(number),
@@ -171,10 +205,11 @@ trait FastGenerator: BaseGenerator {
             #[cfg(feature = "128bit")]
             Value::Static(StaticNode::U128(number)) => self.write_int(number),
             #[allow(clippy::useless_conversion)] // .into() required by ordered-float
-            Value::Static(StaticNode::F64(number)) => self.write_float(number.into()),
+            Value::Static(StaticNode::F64(number)) => non_finite.write_f64(self, number.into()),
             Value::Static(StaticNode::Bool(true)) => self.write(b"true"),
             Value::Static(StaticNode::Bool(false)) => self.write(b"false"),
             Value::String(ref string) => self.write_string(string),
+            Value::Number(ref number, _) => self.write(number.as_bytes()),
             Value::Array(ref array) => {
                 if array.is_empty() {
                     self.write(b"[]")
@@ -187,16 +222,16 @@ trait FastGenerator: BaseGenerator {
                     };
 
                     stry!(self.write(b"["));
-                    stry!(self.write_json(item));
+                    stry!(self.write_json(item, non_finite));
 
                     for item in iter {
                         stry!(self.write(b","));
-                        stry!(self.write_json(item));
+                        stry!(self.write_json(item, non_finite));
                     }
                     self.write(b"]")
                 }
             }
-            Value::Object(ref object) => self.write_object(object),
+            Value::Object(ref object) => self.write_object(object, non_finite),
         }
     }
 }
@@ -270,4 +305,23 @@ mod test {
             r#""this is a test a \\\"long\\\" test that should span the 32 byte boundary""#,
         );
     }
+
+    #[test]
+    fn non_finite() {
+        use crate::NonFinitePolicy;
+        let v = Value::from(vec![Value::from(f64::NAN), Value::from(f64::NEG_INFINITY)]);
+        assert_eq!(v.encode(), "[null,null]");
+        let mut out = Vec::new();
+        v.write_with_non_finite(&mut out, NonFinitePolicy::Literal)
+            .expect("literal");
+        assert_eq!(out, b"[NaN,-Infinity]");
+        let mut out = Vec::new();
+        v.write_pp_with_non_finite(&mut out, NonFinitePolicy::Literal)
+            .expect("literal");
+        assert_eq!(out, b"[\n  NaN,\n  -Infinity\n]");
+        assert!(
+            v.write_with_non_finite(&mut Vec::new(), NonFinitePolicy::Error)
+                .is_err()
+        );
+    }
 }
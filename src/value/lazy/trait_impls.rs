@@ -350,6 +350,52 @@ impl<'tape, 'input> Value<'_, 'tape, 'input> {
     }
 }
 
+// JSON pointer
+impl<'tape, 'input> Value<'_, 'tape, 'input> {
+    /// Looks up a value by a JSON pointer
+    /// ([RFC 6901](https://www.rfc-editor.org/rfc/rfc6901)), returns `None`
+    /// if the pointer is malformed or there is no value at it. This keeps
+    /// the value on the tape.
+    #[must_use]
+    pub fn pointer(&self, pointer: &str) -> Option<Value<'_, 'tape, 'input>> {
+        match self {
+            Value::Tape(tape) => tape.pointer(pointer).map(Value::Tape),
+            Value::Value(value) => value.pointer(pointer).map(Cow::Borrowed).map(Value::Value),
+        }
+    }
+
+    /// Looks up a value by a JSON pointer and returns a mutable reference to
+    /// it, see [`borrowed::Value::pointer_mut`]. This upgrades the value.
+    #[must_use]
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut borrowed::Value<'input>> {
+        self.as_mut().pointer_mut(pointer)
+    }
+
+    /// Inserts a value at a JSON pointer, see
+    /// [`borrowed::Value::pointer_insert`]. This upgrades the value.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the pointer is malformed, doesn't point into an
+    /// array or object or the array index is out of bounds.
+    pub fn pointer_insert<V>(
+        &mut self,
+        pointer: &str,
+        value: V,
+    ) -> crate::Result<Option<borrowed::Value<'input>>>
+    where
+        V: Into<borrowed::Value<'input>>,
+    {
+        self.as_mut().pointer_insert(pointer, value)
+    }
+
+    /// Removes the value at a JSON pointer, see
+    /// [`borrowed::Value::pointer_remove`]. This upgrades the value.
+    pub fn pointer_remove(&mut self, pointer: &str) -> Option<borrowed::Value<'input>> {
+        self.as_mut().pointer_remove(pointer)
+    }
+}
+
 // impl<'tape, 'input> ValueObjectAccessAsScalar for Value<'tape, 'input>
 impl<'tape, 'input> Value<'_, 'tape, 'input>
 where
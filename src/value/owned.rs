@@ -23,7 +23,7 @@ mod cmp;
 mod from;
 mod serialize;
 
-use super::ObjectHasher;
+use super::{NumberKind, ObjectHasher};
 use crate::{Buffers, prelude::*};
 use crate::{Deserializer, Node, Result};
 use halfbrown::HashMap;
@@ -80,6 +80,9 @@ pub enum Value {
     Static(StaticNode),
     /// string type
     String(String),
+    /// a number kept as its source text and what it converts to, see
+    /// [`Buffers::with_arbitrary_precision`]
+    Number(String, NumberKind),
     /// array type
     Array(Box<Vec<Value>>),
     /// object type
@@ -90,6 +93,118 @@ impl Value {
     fn as_static(&self) -> Option<StaticNode> {
         match self {
             Value::Static(s) => Some(*s),
+            Value::Number(n, kind) => Some(super::number_to_static(n, *kind)),
+            _ => None,
+        }
+    }
+
+    /// Looks up a value by a JSON pointer
+    /// ([RFC 6901](https://www.rfc-editor.org/rfc/rfc6901)), returns `None`
+    /// if the pointer is malformed or there is no value at it.
+    ///
+    /// ```rust
+    /// use simd_json::{OwnedValue, json};
+    /// let v = json!({"a": {"b": ["x", "y"]}, "m/n": 1});
+    /// assert_eq!(v.pointer("/a/b/1"), Some(&OwnedValue::from("y")));
+    /// assert_eq!(v.pointer("/m~1n"), Some(&OwnedValue::from(1)));
+    /// assert_eq!(v.pointer(""), Some(&v));
+    /// assert_eq!(v.pointer("/a/c"), None);
+    /// ```
+    #[must_use]
+    pub fn pointer(&self, pointer: &str) -> Option<&Self> {
+        super::pointer::get(self, pointer)
+    }
+
+    /// Looks up a value by a JSON pointer and returns a mutable reference to
+    /// it, see [`pointer`](Self::pointer)
+    #[must_use]
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut Self> {
+        super::pointer::get_mut(self, pointer)
+    }
+
+    /// Inserts a value at a JSON pointer, the array or object it points into
+    /// has to exist. Like the `add` operation of JSON Patch a key that is
+    /// already present gets replaced, array elements are shifted to make
+    /// room and `-` as the last token appends to an array. The empty pointer
+    /// replaces the value itself.
+    ///
+    /// Returns the value that got replaced, if any.
+    ///
+    /// ```rust
+    /// use simd_json::{OwnedValue, json};
+    /// let mut v = json!({"a": [1, 3]});
+    /// v.pointer_insert("/a/1", 2)?;
+    /// v.pointer_insert("/a/-", 4)?;
+    /// assert_eq!(v.pointer_insert("/b", true)?, None);
+    /// assert_eq!(v, json!({"a": [1, 2, 3, 4], "b": true}));
+    /// assert!(v.pointer_insert("/c/d", 5).is_err());
+    /// # Ok::<(), simd_json::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the pointer is malformed, doesn't point into an
+    /// array or object or the array index is out of bounds.
+    pub fn pointer_insert<V>(&mut self, pointer: &str, value: V) -> Result<Option<Self>>
+    where
+        V: Into<Self>,
+    {
+        super::pointer::insert(self, pointer, value.into())
+    }
+
+    /// Removes the value at a JSON pointer from the array or object holding
+    /// it and returns it, later array elements are shifted down
+    pub fn pointer_remove(&mut self, pointer: &str) -> Option<Self> {
+        super::pointer::remove(self, pointer)
+    }
+}
+
+impl super::pointer::Pointee for Value {
+    fn child(&self, token: &str) -> Option<&Self> {
+        match self {
+            Self::Array(a) => a.get(super::pointer::index(token)?),
+            Self::Object(o) => o.get(token),
+            _ => None,
+        }
+    }
+
+    fn child_mut(&mut self, token: &str) -> Option<&mut Self> {
+        match self {
+            Self::Array(a) => a.get_mut(super::pointer::index(token)?),
+            Self::Object(o) => o.get_mut(token),
+            _ => None,
+        }
+    }
+
+    fn insert_child(
+        &mut self,
+        token: std::borrow::Cow<'_, str>,
+        value: Self,
+    ) -> std::result::Result<Option<Self>, Self> {
+        match self {
+            Self::Array(a) if token == "-" => {
+                a.push(value);
+                Ok(None)
+            }
+            Self::Array(a) => match super::pointer::index(&token) {
+                Some(idx) if idx <= a.len() => {
+                    a.insert(idx, value);
+                    Ok(None)
+                }
+                _ => Err(value),
+            },
+            Self::Object(o) => Ok(o.insert(token.into_owned(), value)),
+            _ => Err(value),
+        }
+    }
+
+    fn remove_child(&mut self, token: &str) -> Option<Self> {
+        match self {
+            Self::Array(a) => {
+                let idx = super::pointer::index(token)?;
+                (idx < a.len()).then(|| a.remove(idx))
+            }
+            Self::Object(o) => o.remove(token),
             _ => None,
         }
     }
@@ -140,6 +255,7 @@ impl TypedValue for Value {
         match self {
             Self::Static(s) => s.value_type(),
             Self::String(_) => ValueType::String,
+            Self::Number(_, kind) => kind.value_type(),
             Self::Array(_) => ValueType::Array,
             Self::Object(_) => ValueType::Object,
         }
@@ -251,7 +367,7 @@ impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Self::Static(s) => s.fmt(f),
-            Self::String(s) => write!(f, "{s}"),
+            Self::String(s) | Self::Number(s, _) => write!(f, "{s}"),
             Self::Array(a) => write!(f, "{a:?}"),
             Self::Object(o) => write!(f, "{o:?}"),
         }
@@ -308,6 +424,7 @@ impl<'de> OwnedDeserializer<'de> {
         match unsafe { self.de.next_() } {
             Node::Static(s) => Value::Static(s),
             Node::String(s) => Value::from(s),
+            Node::Number(n, kind) => Value::Number(n.to_owned(), kind),
             Node::Array { len, count: _ } => self.parse_array(len),
             Node::Object { len, count: _ } => self.parse_map(len),
         }
@@ -335,12 +452,11 @@ impl<'de> OwnedDeserializer<'de> {
 
         for _ in 0..len {
             if let Node::String(key) = unsafe { self.de.next_() } {
-                #[cfg(not(feature = "value-no-dup-keys"))]
-                unsafe {
-                    res.insert_nocheck(key.into(), self.parse());
-                };
-                #[cfg(feature = "value-no-dup-keys")]
-                res.insert(key.into(), self.parse());
+                let value = self.parse();
+                self.de
+                    .duplicate_keys
+                    .unwrap_or_default()
+                    .insert(&mut res, key.into(), value);
             } else {
                 unreachable!("parse_map: key needs to be a string");
             }
@@ -947,4 +1063,54 @@ mod test {
         let v: Value = v.into();
         assert_eq!(v, 42);
     }
+
+    #[test]
+    fn pointer() -> crate::Result<()> {
+        let mut v = json!({"a": {"b": ["x", {"c": 1}]}, "m~n": 2, "": 3, "a/b": 4});
+        assert_eq!(v.pointer(""), Some(&v));
+        assert_eq!(v.pointer("/a/b/0"), Some(&Value::from("x")));
+        assert_eq!(v.pointer("/a/b/1/c"), Some(&Value::from(1)));
+        assert_eq!(v.pointer("/m~0n"), Some(&Value::from(2)));
+        assert_eq!(v.pointer("/"), Some(&Value::from(3)));
+        assert_eq!(v.pointer("/a~1b"), Some(&Value::from(4)));
+        assert_eq!(v.pointer("/a/b/01"), None);
+        assert_eq!(v.pointer("/a/b/2"), None);
+        assert_eq!(v.pointer("/a/b/0/c"), None);
+        assert_eq!(v.pointer("a"), None);
+        assert_eq!(v.pointer("/m~2n"), None);
+
+        *v.pointer_mut("/a/b/1/c").expect("c") = Value::from(5);
+        assert_eq!(v.pointer("/a/b/1/c"), Some(&Value::from(5)));
+        assert!(v.pointer_mut("/a/x").is_none());
+
+        assert_eq!(v.pointer_insert("/a/b/0", "w")?, None);
+        assert_eq!(v.pointer_insert("/a/b/-", "z")?, None);
+        assert_eq!(v.pointer_insert("/a/b/4", "end")?, None);
+        assert_eq!(v.pointer_insert("/m~0n", 6)?, Some(Value::from(2)));
+        assert_eq!(v.pointer_insert("/new", ())?, None);
+        assert!(v.pointer_insert("/a/b/9", 0).is_err());
+        assert!(v.pointer_insert("/a/b/x", 0).is_err());
+        assert!(v.pointer_insert("/missing/key", 0).is_err());
+        assert!(v.pointer_insert("/m~0n/key", 0).is_err());
+        assert_eq!(
+            v.pointer("/a/b"),
+            Some(&json!(["w", "x", {"c": 5}, "z", "end"]))
+        );
+
+        assert_eq!(v.pointer_remove("/a/b/1"), Some(Value::from("x")));
+        assert_eq!(v.pointer_remove("/a/b/4"), None);
+        assert_eq!(v.pointer_remove("/a/b/2/c"), None);
+        assert_eq!(v.pointer_remove("/a/b/1/c"), Some(Value::from(5)));
+        assert_eq!(v.pointer_remove("/new"), Some(Value::null()));
+        assert_eq!(v.pointer_remove(""), None);
+        assert_eq!(
+            v,
+            json!({"a": {"b": ["w", {}, "z", "end"]}, "m~n": 6, "": 3, "a/b": 4})
+        );
+
+        let before = v.clone();
+        assert_eq!(v.pointer_insert("", 1)?, Some(before));
+        assert_eq!(v, 1);
+        Ok(())
+    }
 }
@@ -7,7 +7,8 @@ impl PartialEq<BorrowedValue<'_>> for Value {
     fn eq(&self, other: &BorrowedValue<'_>) -> bool {
         match (self, other) {
             (Self::Static(s1), BorrowedValue::Static(s2)) => s1 == s2,
-            (Self::String(v1), BorrowedValue::String(v2)) => v1.eq(v2),
+            (Self::String(v1), BorrowedValue::String(v2))
+            | (Self::Number(v1, _), BorrowedValue::Number(v2, _)) => v1.eq(v2),
             (Self::Array(v1), BorrowedValue::Array(v2)) => v1.as_ref().eq(v2.as_ref()),
             (Self::Object(v1), BorrowedValue::Object(v2)) => {
                 if v1.len() != v2.len() {
@@ -27,7 +28,9 @@ impl PartialEq for Value {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (Self::Static(s1), Self::Static(s2)) => s1.eq(s2),
-            (Self::String(v1), Self::String(v2)) => v1.eq(v2),
+            (Self::String(v1), Self::String(v2)) | (Self::Number(v1, _), Self::Number(v2, _)) => {
+                v1.eq(v2)
+            }
             (Self::Array(v1), Self::Array(v2)) => v1.eq(v2),
             (Self::Object(v1), Self::Object(v2)) => v1.eq(v2),
             _ => false,
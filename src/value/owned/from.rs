@@ -7,6 +7,7 @@ impl From<crate::BorrowedValue<'_>> for Value {
         match b {
             BorrowedValue::Static(s) => Self::from(s),
             BorrowedValue::String(s) => Self::from(s.to_string()),
+            BorrowedValue::Number(n, kind) => Self::Number(n.to_string(), kind),
             BorrowedValue::Array(a) => a.into_iter().collect(),
             BorrowedValue::Object(m) => m.into_iter().collect(),
         }
@@ -0,0 +1,144 @@
+//! JSON Pointer ([RFC 6901](https://www.rfc-editor.org/rfc/rfc6901)) support
+//! shared by the value types.
+//!
+//! A pointer is either empty, pointing at the value itself, or a list of
+//! reference tokens each prefixed with `/`. In a token `~1` stands for `/`
+//! and `~0` for `~`. Tokens select an object key or, for arrays, an index
+//! without leading zeros.
+
+use crate::{Error, ErrorType, Result};
+use std::borrow::Cow;
+
+/// Unescapes a reference token, `None` if it contains a `~` that isn't
+/// followed by `0` or `1`
+fn unescape(token: &str) -> Option<Cow<'_, str>> {
+    if !token.contains('~') {
+        return Some(Cow::Borrowed(token));
+    }
+    let mut unescaped = String::with_capacity(token.len());
+    let mut chars = token.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '~' => match chars.next()? {
+                '0' => unescaped.push('~'),
+                '1' => unescaped.push('/'),
+                _ => return None,
+            },
+            c => unescaped.push(c),
+        }
+    }
+    Some(Cow::Owned(unescaped))
+}
+
+/// The reference tokens of `pointer`, `None` if it isn't a valid pointer.
+/// A token is `None` if it has an invalid escape.
+pub(crate) fn tokens(pointer: &str) -> Option<impl Iterator<Item = Option<Cow<'_, str>>>> {
+    let tokens = if pointer.is_empty() {
+        None
+    } else {
+        Some(pointer.strip_prefix('/')?)
+    };
+    Some(tokens.into_iter().flat_map(|t| t.split('/')).map(unescape))
+}
+
+/// Splits `pointer` into the pointer to the parent and the last reference
+/// token, `None` for the empty pointer or an invalid last token
+fn split_last(pointer: &str) -> Option<(&str, Cow<'_, str>)> {
+    let (parent, last) = pointer.rsplit_once('/')?;
+    Some((parent, unescape(last)?))
+}
+
+/// The array index a reference token selects, leading zeros and signs are
+/// not allowed
+pub(crate) fn index(token: &str) -> Option<usize> {
+    if token.starts_with('+') || (token.starts_with('0') && token.len() != 1) {
+        return None;
+    }
+    token.parse().ok()
+}
+
+/// A value that can be navigated with a JSON pointer
+pub(crate) trait Pointee: Sized {
+    /// The element of an array or object selected by `token`
+    fn child(&self, token: &str) -> Option<&Self>;
+    /// The element of an array or object selected by `token`, mutably
+    fn child_mut(&mut self, token: &str) -> Option<&mut Self>;
+    /// Inserts `value` into an array or object as the element `token`
+    /// selects, for arrays `-` appends it. Gives back `value` if this is no
+    /// container or the index is out of bounds.
+    fn insert_child(
+        &mut self,
+        token: Cow<'_, str>,
+        value: Self,
+    ) -> std::result::Result<Option<Self>, Self>;
+    /// Removes the element of an array or object selected by `token`
+    fn remove_child(&mut self, token: &str) -> Option<Self>;
+}
+
+/// The value `pointer` points to
+pub(crate) fn get<'v, V: Pointee>(value: &'v V, pointer: &str) -> Option<&'v V> {
+    let mut value = value;
+    for token in tokens(pointer)? {
+        value = value.child(&token?)?;
+    }
+    Some(value)
+}
+
+/// The value `pointer` points to, mutably
+pub(crate) fn get_mut<'v, V: Pointee>(value: &'v mut V, pointer: &str) -> Option<&'v mut V> {
+    let mut value = value;
+    for token in tokens(pointer)? {
+        value = value.child_mut(&token?)?;
+    }
+    Some(value)
+}
+
+/// Inserts `new` at `pointer`, see [`Pointee::insert_child`]. The empty
+/// pointer replaces the value itself.
+pub(crate) fn insert<V: Pointee>(value: &mut V, pointer: &str, new: V) -> Result<Option<V>> {
+    if pointer.is_empty() {
+        return Ok(Some(std::mem::replace(value, new)));
+    }
+    let invalid = || Error::generic(ErrorType::InvalidPointer);
+    let (parent, last) = split_last(pointer).ok_or_else(invalid)?;
+    get_mut(value, parent)
+        .ok_or_else(invalid)?
+        .insert_child(last, new)
+        .map_err(|_| invalid())
+}
+
+/// Removes the value at `pointer` from its parent
+pub(crate) fn remove<V: Pointee>(value: &mut V, pointer: &str) -> Option<V> {
+    let (parent, last) = split_last(pointer)?;
+    get_mut(value, parent)?.remove_child(&last)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn tokens_and_indexes() {
+        let collect = |p| tokens(p).map(Iterator::collect::<Option<Vec<_>>>);
+        assert_eq!(collect(""), Some(Some(vec![])));
+        assert_eq!(collect("/"), Some(Some(vec![Cow::from("")])));
+        assert_eq!(
+            collect("/a~1b/m~0n/~01"),
+            Some(Some(vec![
+                Cow::from("a/b"),
+                Cow::from("m~n"),
+                Cow::from("~1")
+            ]))
+        );
+        assert_eq!(collect("/a~2"), Some(None));
+        assert_eq!(collect("/a~"), Some(None));
+        assert!(collect("a").is_none());
+
+        assert_eq!(index("0"), Some(0));
+        assert_eq!(index("10"), Some(10));
+        assert_eq!(index("01"), None);
+        assert_eq!(index("+1"), None);
+        assert_eq!(index("-"), None);
+        assert_eq!(index(""), None);
+    }
+}
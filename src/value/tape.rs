@@ -1,8 +1,11 @@
+use super::NumberKind;
 /// A tape of a parsed json, all values are extracted and validated and
 /// can be used without further computation.
+use std::ops::Range;
 use value_trait::{StaticNode, TryTypeError, ValueType, base::TypedValue as _};
 
 pub(super) mod array;
+mod binary;
 mod cmp;
 pub(super) mod object;
 mod trait_impls;
@@ -47,6 +50,12 @@ impl<'input> Tape<'input> {
         let mut deserializer = Deserializer {
             tape: self.0,
             idx: 0,
+            duplicate_keys: None,
+            #[cfg(feature = "serde_impl")]
+            raw_source: None,
+            #[cfg(feature = "serde_impl")]
+            transient: 0..0,
+            _input: std::marker::PhantomData,
         };
 
         T::deserialize(&mut deserializer)
@@ -60,6 +69,89 @@ pub struct Value<'tape, 'input>(pub(crate) &'tape [Node<'input>])
 where
     'input: 'tape;
 
+impl Value<'_, '_> {
+    /// The index of the first node of `value` among the nodes of this value,
+    /// if `value` is a part of it. For the value of a whole tape this is the
+    /// index of `value` on the tape.
+    #[must_use]
+    pub fn index_of(&self, value: Value<'_, '_>) -> Option<usize> {
+        let offset = value
+            .0
+            .as_ptr()
+            .addr()
+            .checked_sub(self.0.as_ptr().addr())?;
+        let index = offset / std::mem::size_of::<Node>();
+        (offset % std::mem::size_of::<Node>() == 0 && index + value.0.len() <= self.0.len())
+            .then_some(index)
+    }
+}
+
+/// The byte range in the input every node of a tape was parsed from, a side
+/// table to the tape looked up by node index. Recorded when parsing with
+/// [`Buffers::with_spans`](crate::Buffers::with_spans) and available through
+/// [`Buffers::spans`](crate::Buffers::spans).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Spans(pub(crate) Vec<Range<usize>>);
+
+impl Spans {
+    /// The span of the node at index `node` of the tape
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    #[must_use]
+    pub fn get(&self, node: usize) -> Option<Range<usize>> {
+        self.0.get(node).cloned()
+    }
+
+    /// The span of `value`, a part of `tape`, the value of the tape the spans
+    /// were recorded for
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    #[must_use]
+    pub fn of(&self, tape: Value<'_, '_>, value: Value<'_, '_>) -> Option<Range<usize>> {
+        self.get(tape.index_of(value)?)
+    }
+
+    /// The spans of all nodes, in tape order
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    #[must_use]
+    pub fn as_slice(&self) -> &[Range<usize>] {
+        &self.0
+    }
+}
+
+#[cfg(feature = "serde_impl")]
+impl<'tape> Value<'tape, '_> {
+    /// Deserializes the value into a type that implements `serde::Deserialize`,
+    /// strings are borrowed from the tape. This allows to decode a part of a
+    /// document after inspecting the rest of it.
+    ///
+    /// ```rust
+    /// use simd_json::prelude::*;
+    ///
+    /// #[derive(serde::Deserialize)]
+    /// struct User<'a> {
+    ///     name: &'a str,
+    /// }
+    ///
+    /// let mut json = br#"{"type": "user", "data": {"name": "snot"}}"#.to_vec();
+    /// let tape = simd_json::to_tape(&mut json)?;
+    /// let value = tape.as_value();
+    /// if value.get_str("type") == Some("user") {
+    ///     let data = value.get("data").expect("data");
+    ///     let user: User = data.deserialize()?;
+    ///     assert_eq!(user.name, "snot");
+    /// }
+    /// # Ok::<(), simd_json::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if the deserialization fails
+    pub fn deserialize<T>(self) -> crate::Result<T>
+    where
+        T: serde::Deserialize<'tape>,
+    {
+        T::deserialize(self)
+    }
+}
+
 impl Value<'static, 'static> {
     const NULL_TAPE: [Node<'static>; 1] = [Node::Static(StaticNode::Null)];
     /// A static null value
@@ -74,9 +166,14 @@ impl Value<'static, 'static> {
 #[allow(clippy::derive_partial_eq_without_eq)]
 /// Tape `Node`
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
 pub enum Node<'input> {
     /// A string, located inside the input slice
     String(&'input str),
+    /// A number kept as its source text, located inside the input slice,
+    /// and what it converts to. Only produced when parsing with
+    /// [`Buffers::with_arbitrary_precision`](crate::Buffers::with_arbitrary_precision).
+    Number(&'input str, NumberKind),
     /// An `Object` with the given `size` starts here.
     /// the following values are keys and values, alternating
     /// however values can be nested and have a length themselves.
@@ -113,6 +210,7 @@ impl<'input> Node<'input> {
     pub fn value_type(&self) -> ValueType {
         match self {
             Node::String(_) => ValueType::String,
+            Node::Number(_, kind) => kind.value_type(),
             Node::Object { .. } => ValueType::Object,
             Node::Array { .. } => ValueType::Array,
             Node::Static(v) => v.value_type(),
@@ -168,7 +266,7 @@ impl<'input> Node<'input> {
     }
 
     // returns the count of elements in this node, including the node itself (n for nested, 1 for the rest)
-    fn count(&self) -> usize {
+    pub(crate) fn count(&self) -> usize {
         match self {
             // We add 1 as we need to include the header itself
             Node::Object { count, .. } | Node::Array { count, .. } => *count + 1,
@@ -644,4 +742,135 @@ mod test {
         let v: Value = false.into();
         assert_eq!(v, false);
     }
+
+    #[test]
+    fn from_bytes() -> crate::Result<()> {
+        use crate::{Buffers, Node, to_tape_from_bytes, to_tape_from_bytes_with_buffers};
+
+        let input = br#"{"plain": ["abc", -1.5], "a\"b": "c\u00e9\n"}"#;
+        let borrowed = |s: &str| input.as_ptr_range().contains(&s.as_ptr());
+        let mut copy = Vec::new();
+        let mut buffers = Buffers::default().with_arbitrary_precision(true);
+        for _ in 0..2 {
+            let tape = to_tape_from_bytes_with_buffers(input, &mut copy, &mut buffers)?;
+            let [
+                _,
+                Node::String(plain),
+                _,
+                Node::String(abc),
+                Node::Number(number, _),
+                Node::String(key),
+                Node::String(value),
+            ] = tape.0[..]
+            else {
+                panic!("unexpected tape {:?}", tape.0);
+            };
+            assert_eq!((plain, abc, number), ("plain", "abc", "-1.5"));
+            assert!(borrowed(plain) && borrowed(abc) && borrowed(number));
+            assert_eq!((key, value), ("a\"b", "c\u{e9}\n"));
+            assert!(!borrowed(key) && !borrowed(value));
+        }
+        // the input is left as it was
+        assert_eq!(
+            &input[..],
+            br#"{"plain": ["abc", -1.5], "a\"b": "c\u00e9\n"}"#
+        );
+        assert!(to_tape_from_bytes(b"[1, 2", &mut copy).is_err());
+        assert_eq!(
+            to_tape_from_bytes(b"42", &mut copy)?.as_value().as_u8(),
+            Some(42)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn spans() -> crate::Result<()> {
+        use crate::{Buffers, fill_tape, to_tape_with_buffers};
+
+        let json = br#" {"a\"b": [1, "x\ny" , {}], "c" :-1.5e3 , "d":[ ],"e":null} "#;
+        let text = std::str::from_utf8(json).expect("utf8");
+        let mut buffers = Buffers::default().with_spans(true);
+        let mut input = json.to_vec();
+        let tape = to_tape_with_buffers(&mut input, &mut buffers)?;
+        let spans = buffers.spans().expect("recorded");
+        assert_eq!(spans.as_slice().len(), tape.0.len());
+        let v = tape.as_value();
+        let span = |value: super::Value| spans.of(v, value).map(|s| &text[s]);
+        assert_eq!(span(v), Some(text.trim()));
+        let o = v.as_object().expect("object");
+        let a = o.get("a\"b").expect("array");
+        assert_eq!(v.index_of(a), Some(2));
+        assert_eq!(span(a), Some(r#"[1, "x\ny" , {}]"#));
+        let items: Vec<_> = a.as_array().expect("array").iter().map(span).collect();
+        assert_eq!(items, [Some("1"), Some(r#""x\ny""#), Some("{}")]);
+        let values: Vec<_> = o.iter().map(|(_, v)| span(v)).collect();
+        assert_eq!(
+            values,
+            [
+                Some(r#"[1, "x\ny" , {}]"#),
+                Some("-1.5e3"),
+                Some("[ ]"),
+                Some("null")
+            ]
+        );
+        assert_eq!(o.values().nth(1).and_then(span), Some("-1.5e3"));
+        // values of other tapes are not found
+        assert_eq!(a.index_of(v), None);
+        assert_eq!(spans.of(v, super::Value::NULL), None);
+
+        let mut input = b"  \"top\"  ".to_vec();
+        let tape = to_tape_with_buffers(&mut input, &mut buffers)?;
+        assert_eq!(buffers.spans().and_then(|s| s.get(0)), Some(2..7));
+        let mut tape = tape.reset();
+        let mut input = b"[true]".to_vec();
+        fill_tape(&mut input, &mut buffers, &mut tape)?;
+        assert_eq!(
+            buffers.spans().map(super::Spans::as_slice),
+            Some(&[0..6, 1..5][..])
+        );
+
+        // not recorded by default
+        assert!(Buffers::default().spans().is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn pointer() -> crate::Result<()> {
+        use crate::value::lazy;
+
+        let mut input = br#"{"s": [{"x": [1, 2]}, {"y": {}}], "a/b": {"m~n": "v"}}"#.to_vec();
+        let tape = crate::to_tape(&mut input)?;
+        let v = tape.as_value();
+        assert_eq!(v.pointer("").map(|v| v.0.len()), Some(tape.0.len()));
+        assert_eq!(
+            v.pointer("/s/1/y")
+                .and_then(|v| v.as_object())
+                .map(|o| o.len()),
+            Some(0)
+        );
+        assert_eq!(v.pointer("/s/0/x/1").and_then(|v| v.as_u8()), Some(2));
+        assert_eq!(
+            v.pointer("/a~1b/m~0n")
+                .and_then(ValueIntoString::into_string),
+            Some("v")
+        );
+        assert!(v.pointer("/s/2").is_none());
+        assert!(v.pointer("/s/00").is_none());
+        assert!(v.pointer("/s/0/x/1/z").is_none());
+        assert!(v.pointer("/a~1b/m~n").is_none());
+
+        let mut lazy = lazy::Value::from_tape(v);
+        assert_eq!(lazy.pointer("/s/0/x/0").and_then(|v| v.as_u8()), Some(1));
+        assert!(lazy.is_tape());
+        assert_eq!(lazy.pointer_insert("/s/0/x/-", 3)?, None);
+        assert!(lazy.is_value());
+        assert_eq!(lazy.pointer("/s/0/x/2").and_then(|v| v.as_u8()), Some(3));
+        assert_eq!(
+            lazy.pointer_remove("/a~1b").map(|v| v.encode()),
+            Some(r#"{"m~n":"v"}"#.to_string())
+        );
+        *lazy.pointer_mut("/s/1").expect("element") = 4.into();
+        assert_eq!(lazy.encode(), r#"{"s":[{"x":[1,2,3]},4]}"#);
+        Ok(())
+    }
 }
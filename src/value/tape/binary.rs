@@ -0,0 +1,431 @@
+//! A binary format for tapes, so a parsed document can be stored and loaded
+//! again without parsing it.
+//!
+//! All integers are little endian. A blob is laid out as:
+//!
+//! * the header: the magic bytes `SJTP`, the format version as `u16`, the
+//!   flags as `u16` (bit 0 is set if spans are included), the number of
+//!   nodes as `u64` and the length of the string data as `u64`
+//! * the string data, every string and arbitrary precision number of the
+//!   tape once
+//! * the nodes, a tag byte each followed by its payload: nothing for `null`,
+//!   `false` and `true`, the value for numbers, the offset and length into
+//!   the string data for strings, the `len` and `count` for arrays and
+//!   objects, all as `u64`
+//! * if the flag is set, the start and end of every node's span as `u64`
+
+use super::{Node, Spans, Tape};
+use crate::stage2::is_number;
+use crate::{Error, ErrorType, NumberKind, Result};
+use std::collections::HashMap;
+use value_trait::StaticNode;
+
+const MAGIC: &[u8; 4] = b"SJTP";
+const VERSION: u16 = 1;
+const HEADER_LEN: usize = 24;
+const FLAG_SPANS: u16 = 1;
+
+const TAG_NULL: u8 = 0;
+const TAG_FALSE: u8 = 1;
+const TAG_TRUE: u8 = 2;
+const TAG_I64: u8 = 3;
+const TAG_U64: u8 = 4;
+const TAG_F64: u8 = 5;
+#[cfg_attr(not(feature = "128bit"), allow(dead_code))]
+const TAG_I128: u8 = 6;
+#[cfg_attr(not(feature = "128bit"), allow(dead_code))]
+const TAG_U128: u8 = 7;
+const TAG_STRING: u8 = 8;
+const TAG_NUMBER: u8 = 9;
+const TAG_ARRAY: u8 = 10;
+const TAG_OBJECT: u8 = 11;
+
+impl<'input> Tape<'input> {
+    /// Encodes the tape into a versioned binary blob that
+    /// [`Tape::from_binary`] loads without parsing the document again.
+    /// Strings that occur more than once are stored once.
+    #[must_use]
+    pub fn to_binary(&self) -> Vec<u8> {
+        self.encode(None)
+    }
+
+    /// Encodes the tape like [`Tape::to_binary`] together with the spans
+    /// recorded for it, [`Tape::from_binary_with_spans`] loads both.
+    #[must_use]
+    pub fn to_binary_with_spans(&self, spans: &Spans) -> Vec<u8> {
+        self.encode(Some(spans).filter(|spans| spans.0.len() == self.0.len()))
+    }
+
+    fn encode(&self, spans: Option<&Spans>) -> Vec<u8> {
+        let mut data: Vec<u8> = Vec::new();
+        let mut offsets: HashMap<&str, u64> = HashMap::new();
+        let mut nodes = Vec::with_capacity(self.0.len() * 9);
+        let mut string = |nodes: &mut Vec<u8>, tag: u8, s: &'input str| {
+            let offset = *offsets.entry(s).or_insert_with(|| {
+                let offset = data.len() as u64;
+                data.extend_from_slice(s.as_bytes());
+                offset
+            });
+            nodes.push(tag);
+            nodes.extend_from_slice(&offset.to_le_bytes());
+            nodes.extend_from_slice(&(s.len() as u64).to_le_bytes());
+        };
+        for node in &self.0 {
+            match *node {
+                Node::Static(StaticNode::Null) => nodes.push(TAG_NULL),
+                Node::Static(StaticNode::Bool(false)) => nodes.push(TAG_FALSE),
+                Node::Static(StaticNode::Bool(true)) => nodes.push(TAG_TRUE),
+                Node::Static(StaticNode::I64(n)) => {
+                    nodes.push(TAG_I64);
+                    nodes.extend_from_slice(&n.to_le_bytes());
+                }
+                Node::Static(StaticNode::U64(n)) => {
+                    nodes.push(TAG_U64);
+                    nodes.extend_from_slice(&n.to_le_bytes());
+                }
+                #[allow(clippy::useless_conversion)] // .into() required by ordered-float
+                Node::Static(StaticNode::F64(n)) => {
+                    nodes.push(TAG_F64);
+                    nodes.extend_from_slice(&f64::from(n).to_bits().to_le_bytes());
+                }
+                #[cfg(feature = "128bit")]
+                Node::Static(StaticNode::I128(n)) => {
+                    nodes.push(TAG_I128);
+                    nodes.extend_from_slice(&n.to_le_bytes());
+                }
+                #[cfg(feature = "128bit")]
+                Node::Static(StaticNode::U128(n)) => {
+                    nodes.push(TAG_U128);
+                    nodes.extend_from_slice(&n.to_le_bytes());
+                }
+                Node::String(s) => string(&mut nodes, TAG_STRING, s),
+                Node::Number(n, _) => string(&mut nodes, TAG_NUMBER, n),
+                Node::Array { len, count } | Node::Object { len, count } => {
+                    let tag = if matches!(node, Node::Array { .. }) {
+                        TAG_ARRAY
+                    } else {
+                        TAG_OBJECT
+                    };
+                    nodes.push(tag);
+                    nodes.extend_from_slice(&(len as u64).to_le_bytes());
+                    nodes.extend_from_slice(&(count as u64).to_le_bytes());
+                }
+            }
+        }
+        let mut blob = Vec::with_capacity(
+            HEADER_LEN + data.len() + nodes.len() + spans.map_or(0, |spans| spans.0.len() * 16),
+        );
+        blob.extend_from_slice(MAGIC);
+        blob.extend_from_slice(&VERSION.to_le_bytes());
+        blob.extend_from_slice(&(if spans.is_some() { FLAG_SPANS } else { 0 }).to_le_bytes());
+        blob.extend_from_slice(&(self.0.len() as u64).to_le_bytes());
+        blob.extend_from_slice(&(data.len() as u64).to_le_bytes());
+        blob.extend_from_slice(&data);
+        blob.extend_from_slice(&nodes);
+        if let Some(spans) = spans {
+            for span in &spans.0 {
+                blob.extend_from_slice(&(span.start as u64).to_le_bytes());
+                blob.extend_from_slice(&(span.end as u64).to_le_bytes());
+            }
+        }
+        blob
+    }
+
+    /// Loads a tape written by [`Tape::to_binary`], strings are borrowed
+    /// from `blob`. Every offset, string and container is checked so that
+    /// the tape is well formed. Spans stored with the tape are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if `blob` is not a binary tape of a supported
+    /// version or is corrupt.
+    pub fn from_binary(blob: &'input [u8]) -> Result<Self> {
+        Self::from_binary_with_spans(blob).map(|(tape, _)| tape)
+    }
+
+    /// Loads a tape like [`Tape::from_binary`] together with the spans
+    /// stored by [`Tape::to_binary_with_spans`], they are empty if the blob
+    /// has none.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if `blob` is not a binary tape of a supported
+    /// version or is corrupt.
+    pub fn from_binary_with_spans(blob: &'input [u8]) -> Result<(Self, Spans)> {
+        let mut r = Reader(blob);
+        if r.bytes(MAGIC.len())? != MAGIC {
+            return Err(corrupt());
+        }
+        let version = r.u16()?;
+        if version != VERSION {
+            return Err(Error::generic(ErrorType::UnsupportedBinaryTape(version)));
+        }
+        let flags = r.u16()?;
+        if flags & !FLAG_SPANS != 0 {
+            return Err(corrupt());
+        }
+        let node_count = r.usize()?;
+        let data_len = r.usize()?;
+        let data = r.bytes(data_len)?;
+
+        // every node takes at least one byte
+        let mut nodes = Vec::with_capacity(node_count.min(r.0.len()));
+        for _ in 0..node_count {
+            let node = match r.u8()? {
+                TAG_NULL => Node::Static(StaticNode::Null),
+                TAG_FALSE => Node::Static(StaticNode::Bool(false)),
+                TAG_TRUE => Node::Static(StaticNode::Bool(true)),
+                TAG_I64 => Node::Static(StaticNode::I64(i64::from_le_bytes(r.array()?))),
+                TAG_U64 => Node::Static(StaticNode::U64(u64::from_le_bytes(r.array()?))),
+                #[allow(clippy::useless_conversion)] // .into() required by ordered-float
+                TAG_F64 => Node::Static(StaticNode::F64(
+                    f64::from_bits(u64::from_le_bytes(r.array()?)).into(),
+                )),
+                #[cfg(feature = "128bit")]
+                TAG_I128 => Node::Static(StaticNode::I128(i128::from_le_bytes(r.array()?))),
+                #[cfg(feature = "128bit")]
+                TAG_U128 => Node::Static(StaticNode::U128(u128::from_le_bytes(r.array()?))),
+                TAG_STRING => Node::String(r.string(data)?),
+                TAG_NUMBER => {
+                    let n = r.string(data)?;
+                    if !is_number(n) {
+                        return Err(corrupt());
+                    }
+                    Node::Number(n, NumberKind::of(n))
+                }
+                TAG_ARRAY => Node::Array {
+                    len: r.usize()?,
+                    count: r.usize()?,
+                },
+                TAG_OBJECT => Node::Object {
+                    len: r.usize()?,
+                    count: r.usize()?,
+                },
+                _ => return Err(corrupt()),
+            };
+            nodes.push(node);
+        }
+        let mut spans = Vec::new();
+        if flags & FLAG_SPANS != 0 {
+            spans.reserve(node_count);
+            for _ in 0..node_count {
+                let span = r.usize()?..r.usize()?;
+                if span.start > span.end {
+                    return Err(corrupt());
+                }
+                spans.push(span);
+            }
+        }
+        if !r.0.is_empty() || !is_well_formed(&nodes) {
+            return Err(corrupt());
+        }
+        Ok((Tape(nodes), Spans(spans)))
+    }
+}
+
+#[cold]
+fn corrupt() -> Error {
+    Error::generic(ErrorType::InvalidBinaryTape)
+}
+
+/// Checks that `nodes` hold exactly one value, that every container holds
+/// `len` members spanning exactly `count` nodes and that object keys are
+/// strings
+fn is_well_formed(nodes: &[Node]) -> bool {
+    // the end and remaining members of the open containers, the document
+    // itself is a container with a single member
+    let mut open: Vec<(usize, usize, bool)> = vec![(nodes.len(), 1, false)];
+    let mut idx = 0;
+    while let Some((end, members, object)) = open.last_mut() {
+        if *members == 0 {
+            if idx != *end {
+                return false;
+            }
+            open.pop();
+            continue;
+        }
+        *members -= 1;
+        let end = *end;
+        if *object {
+            if !matches!(nodes.get(idx), Some(Node::String(_))) {
+                return false;
+            }
+            idx += 1;
+        }
+        match nodes.get(idx) {
+            None => return false,
+            Some(node @ (Node::Array { len, count } | Node::Object { len, count })) => {
+                let object = matches!(node, Node::Object { .. });
+                match count
+                    .checked_add(1)
+                    .and_then(|count| idx.checked_add(count))
+                {
+                    Some(container_end) if container_end <= end => {
+                        open.push((container_end, *len, object));
+                    }
+                    _ => return false,
+                }
+            }
+            Some(_) => (),
+        }
+        idx += 1;
+    }
+    true
+}
+
+/// Reads the parts of a blob, running out of input is an error
+struct Reader<'input>(&'input [u8]);
+
+impl<'input> Reader<'input> {
+    fn bytes(&mut self, len: usize) -> Result<&'input [u8]> {
+        if len > self.0.len() {
+            return Err(corrupt());
+        }
+        let (head, tail) = self.0.split_at(len);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        self.bytes(N)?.try_into().map_err(|_| corrupt())
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        self.array::<1>().map(|[b]| b)
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        self.array().map(u16::from_le_bytes)
+    }
+
+    fn usize(&mut self) -> Result<usize> {
+        usize::try_from(u64::from_le_bytes(self.array()?)).map_err(|_| corrupt())
+    }
+
+    /// A string stored as offset and length into `data`
+    fn string(&mut self, data: &'input [u8]) -> Result<&'input str> {
+        let offset = self.usize()?;
+        let len = self.usize()?;
+        let bytes = offset
+            .checked_add(len)
+            .and_then(|end| data.get(offset..end))
+            .ok_or_else(corrupt)?;
+        std::str::from_utf8(bytes).map_err(|_| corrupt())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Buffers, prelude::*, to_tape, to_tape_with_buffers};
+
+    #[test]
+    fn round_trip() -> Result<()> {
+        let json = br#"{"a":[1,-2,3.5,"x\ny",true,false,null,{}],"b":{"a":"x\ny"},"c":[]}"#;
+        let mut input = json.to_vec();
+        let tape = to_tape(&mut input)?;
+        let blob = tape.to_binary();
+        assert_eq!(&blob[..4], MAGIC);
+        assert_eq!(
+            u64::from_le_bytes(blob[8..16].try_into().expect("8 bytes")),
+            tape.0.len() as u64
+        );
+        let loaded = Tape::from_binary(&blob)?;
+        assert_eq!(loaded.0, tape.0);
+        assert_eq!(loaded.as_value(), tape.as_value());
+        let (_, spans) = Tape::from_binary_with_spans(&blob)?;
+        assert!(spans.as_slice().is_empty());
+
+        let mut buffers = Buffers::default()
+            .with_spans(true)
+            .with_arbitrary_precision(true);
+        let mut input = b"[12345678901234567890123, \"s\"]".to_vec();
+        let tape = to_tape_with_buffers(&mut input, &mut buffers)?;
+        let spans = buffers.spans().expect("recorded");
+        let blob = tape.to_binary_with_spans(spans);
+        let (loaded, loaded_spans) = Tape::from_binary_with_spans(&blob)?;
+        assert_eq!(loaded.0, tape.0);
+        assert_eq!(&loaded_spans, spans);
+        assert_eq!(Tape::from_binary(&blob)?.0, tape.0);
+        Ok(())
+    }
+
+    #[test]
+    fn corrupt_blobs() -> Result<()> {
+        let mut input = br#"{"key":["value",1]}"#.to_vec();
+        let blob = to_tape(&mut input)?.to_binary();
+        let invalid = |blob: &[u8]| {
+            Tape::from_binary(blob)
+                .map(|_| ())
+                .map_err(|e| e.error().to_string())
+        };
+        let corrupt = Err(ErrorType::InvalidBinaryTape.to_string());
+
+        // every truncation and trailing data
+        for len in 0..blob.len() {
+            assert_eq!(invalid(&blob[..len]), corrupt);
+        }
+        let mut longer = blob.clone();
+        longer.push(0);
+        assert_eq!(invalid(&longer), corrupt);
+
+        let mut version = blob.clone();
+        version[4] = 2;
+        assert_eq!(
+            invalid(&version),
+            Err(ErrorType::UnsupportedBinaryTape(2).to_string())
+        );
+
+        // flipping any single byte must never load something broken
+        for idx in 0..blob.len() {
+            for bit in 0..8 {
+                let mut flipped = blob.clone();
+                flipped[idx] ^= 1 << bit;
+                if let Ok(tape) = Tape::from_binary(&flipped) {
+                    assert!(is_well_formed(&tape.0));
+                    let _ = tape.as_value().encode();
+                }
+            }
+        }
+
+        // arbitrary precision numbers that are no numbers and inverted spans
+        let mut buffers = Buffers::default()
+            .with_spans(true)
+            .with_arbitrary_precision(true);
+        let mut input = b"[12345678901234567890123]".to_vec();
+        let tape = to_tape_with_buffers(&mut input, &mut buffers)?;
+        let blob = tape.to_binary_with_spans(buffers.spans().expect("recorded"));
+        let number = HEADER_LEN..HEADER_LEN + 23;
+        for text in [
+            &b"1234567890123456789012x"[..],
+            b"-12345678901234567890.e",
+            b"12345678901234567890123",
+        ] {
+            let mut changed = blob.clone();
+            changed[number.clone()].copy_from_slice(text);
+            let valid = text.iter().all(u8::is_ascii_digit);
+            assert_eq!(invalid(&changed).is_ok(), valid);
+        }
+        let mut inverted = blob.clone();
+        let end = inverted.len() - 8;
+        inverted[end..].copy_from_slice(&0_u64.to_le_bytes());
+        assert_eq!(invalid(&inverted), corrupt);
+
+        // containers that claim more nodes than they hold
+        let nodes = [Node::Array { len: 2, count: 1 }, Node::String("a")];
+        assert!(!is_well_formed(&nodes));
+        let nodes = [
+            Node::Object { len: 1, count: 2 },
+            Node::Static(StaticNode::Null),
+            Node::Static(StaticNode::Null),
+        ];
+        assert!(!is_well_formed(&nodes));
+        let nodes = [
+            Node::Array { len: 1, count: 1 },
+            Node::Array { len: 0, count: 5 },
+        ];
+        assert!(!is_well_formed(&nodes));
+        assert!(!is_well_formed(&[]));
+        Ok(())
+    }
+}
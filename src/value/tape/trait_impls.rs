@@ -25,6 +25,7 @@ impl Value<'_, '_> {
     fn as_static(&self) -> Option<StaticNode> {
         match self.0.first()? {
             Node::Static(s) => Some(*s),
+            Node::Number(n, kind) => Some(crate::value::number_to_static(n, *kind)),
             _ => None,
         }
     }
@@ -131,6 +132,7 @@ impl TypedValue for Value<'_, '_> {
             Node::Static(StaticNode::U128(_)) => ValueType::U128,
             Node::Static(StaticNode::F64(_)) => ValueType::F64,
             Node::String(_) => ValueType::String,
+            Node::Number(_, kind) => kind.value_type(),
             Node::Array { .. } => ValueType::Array,
             Node::Object { .. } => ValueType::Object,
         }
@@ -253,6 +255,39 @@ impl<'tape, 'input> Value<'tape, 'input> {
     }
 }
 
+// JSON pointer
+impl<'tape, 'input> Value<'tape, 'input> {
+    /// Looks up a value by a JSON pointer
+    /// ([RFC 6901](https://www.rfc-editor.org/rfc/rfc6901)), returns `None`
+    /// if the pointer is malformed or there is no value at it. Entries that
+    /// are passed over are skipped by their node count, they are not visited.
+    ///
+    /// ```rust
+    /// use simd_json::prelude::*;
+    ///
+    /// let mut json = br#"{"a": {"b": ["x", "y"]}, "m/n": 1}"#.to_vec();
+    /// let tape = simd_json::to_tape(&mut json)?;
+    /// let value = tape.as_value();
+    /// assert_eq!(value.pointer("/a/b/1").and_then(|v| v.into_string()), Some("y"));
+    /// assert!(value.pointer("/m~1n").is_some());
+    /// assert!(value.pointer("/a/c").is_none());
+    /// # Ok::<(), simd_json::Error>(())
+    /// ```
+    #[must_use]
+    pub fn pointer(&self, pointer: &str) -> Option<Value<'tape, 'input>> {
+        let mut value = *self;
+        for token in crate::value::pointer::tokens(pointer)? {
+            let token = token?;
+            value = match value.0.first()? {
+                Node::Array { .. } => value.get_idx(crate::value::pointer::index(&token)?)?,
+                Node::Object { .. } => value.get(&*token)?,
+                _ => return None,
+            };
+        }
+        Some(value)
+    }
+}
+
 impl<'tape, 'input> ValueObjectAccessAsScalar for Value<'tape, 'input>
 where
     'input: 'tape,
@@ -749,6 +784,7 @@ trait Generator: BaseGenerator {
             Node::Static(StaticNode::Bool(true)) => self.write(b"true"),
             Node::Static(StaticNode::Bool(false)) => self.write(b"false"),
             Node::String(string) => self.write_string(string),
+            Node::Number(number, _) => self.write(number.as_bytes()),
             Node::Array { len, count } => {
                 if len == 0 {
                     self.write(b"[]")
@@ -827,6 +863,7 @@ trait FastGenerator: BaseGenerator {
             Node::Static(StaticNode::Bool(true)) => self.write(b"true"),
             Node::Static(StaticNode::Bool(false)) => self.write(b"false"),
             Node::String(string) => self.write_string(string),
+            Node::Number(number, _) => self.write(number.as_bytes()),
             Node::Array { len, count } => {
                 if len == 0 {
                     self.write(b"[]")
@@ -0,0 +1,35 @@
+// Switching the implementation changes it for the whole process, so this
+// lives in its own test binary where no other test can observe it.
+
+use simd_json::{Deserializer, ErrorType, Implementation};
+
+#[test]
+fn set_implementation() {
+    let available = Deserializer::available_implementations();
+    assert!(available.contains(&Deserializer::algorithm()));
+    assert!(available.contains(&Implementation::default()) || available.len() == 1);
+
+    let input = r#"{"snot": ["badger", 1, 2.5, {"a\"b": "é😀"}], "empty": []}"#;
+    let expected = simd_json::to_owned_value(&mut input.as_bytes().to_vec()).expect("valid");
+    for &implementation in &available {
+        Deserializer::set_implementation(implementation).expect("available");
+        assert_eq!(Deserializer::algorithm(), implementation);
+        let value = simd_json::to_owned_value(&mut input.as_bytes().to_vec());
+        assert_eq!(value.as_ref(), Ok(&expected), "{implementation}");
+        let e =
+            simd_json::to_owned_value(&mut br#"{"a": [1, "\x"]}"#.to_vec()).expect_err("escape");
+        assert_eq!(e.index(), 11, "{implementation}");
+    }
+
+    let unsupported = [
+        Implementation::NEON,
+        Implementation::AVX2,
+        Implementation::SIMD128,
+    ]
+    .into_iter()
+    .find(|i| !available.contains(i));
+    if let Some(unsupported) = unsupported {
+        let e = Deserializer::set_implementation(unsupported).expect_err("unsupported");
+        assert_eq!(e.error(), &ErrorType::SimdUnsupported);
+    }
+}